@@ -1,5 +1,6 @@
 use crate::config::button_face::ButtonFaceConfig;
 use crate::config::event_handler::EventHandlerConfig;
+use crate::config::foreground_window_condition::ForegroundWindowConditionConfig;
 use serde::Deserialize;
 
 /// Configuration of a button that must have a name
@@ -11,6 +12,80 @@ pub struct ButtonConfigWithName {
     pub down_face: Option<ButtonFaceConfig>,
     pub up_handler: Option<EventHandlerConfig>,
     pub down_handler: Option<EventHandlerConfig>,
+    /// Handlers executed in order after the [up_handler]
+    pub up_handlers: Option<Vec<EventHandlerConfig>>,
+    /// Handlers executed in order after the [down_handler]
+    pub down_handlers: Option<Vec<EventHandlerConfig>>,
+    pub load_page: Option<String>,
+    /// Disabled buttons are drawn dimmed and execute no handlers (default: true)
+    pub enabled: Option<bool>,
+    /// Up face while the layer modifier button is held down
+    pub layer_face: Option<ButtonFaceConfig>,
+    /// Handlers while the layer modifier button is held down
+    pub layer_handlers: Option<LayerHandlersConfig>,
+    /// Handler executed on the edge given by [trigger]
+    pub action: Option<EventHandlerConfig>,
+    /// Whether the [action] is executed when the button goes down or up (default: down)
+    pub trigger: Option<TriggerConfig>,
+    /// Swap the down and up handlers, so that they are executed on release and press (for example for foot pedals)
+    pub invert_press: Option<bool>,
+    /// Makes the button latching, every press toggles between the normal and the latched state
+    pub latch: Option<LatchConfig>,
+    /// Name of a group, of which only the last pressed button is selected (shows its down face)
+    pub group: Option<String>,
+    /// Faces replacing the up face, while their condition matches the foreground window
+    pub on_app_faces: Option<Vec<ConditionalFaceConfig>>,
+    /// Faces replacing the up face, while their variable is true
+    pub when_faces: Option<Vec<VariableFaceConfig>>,
+    /// Faces replacing the up face one after the other, switched by `state.cycle_button_face`
+    pub cycle_faces: Option<Vec<ButtonFaceConfig>>,
+    /// Free text describing the button, not used by the controller
+    pub description: Option<String>,
+    /// Free tags of the button, not used by the controller
+    pub tags: Option<Vec<String>>,
+}
+
+/// Handlers of a button, used while the layer modifier button is held down.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LayerHandlersConfig {
+    pub up_handler: Option<EventHandlerConfig>,
+    pub down_handler: Option<EventHandlerConfig>,
+}
+
+/// Face and handler of the latched state of a latching button.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LatchConfig {
+    /// Up face while the button is latched
+    pub face: Option<ButtonFaceConfig>,
+    /// Executed instead of the down handler, on the press that latches the button
+    pub down_handler: Option<EventHandlerConfig>,
+}
+
+/// The edge of a button press, on which the action of a button is executed.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TriggerConfig {
+    Down,
+    Up,
+}
+
+/// A face used while the condition matches the foreground window.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ConditionalFaceConfig {
+    pub condition: ForegroundWindowConditionConfig,
+    pub face: ButtonFaceConfig,
+}
+
+/// A face used while a variable (set by the scripts with `state.set_var`) is true.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VariableFaceConfig {
+    /// Name of the variable
+    pub when: String,
+    pub face: ButtonFaceConfig,
 }
 
 /// Configuration of a button that may have no name
@@ -22,6 +97,31 @@ pub struct ButtonConfigOptionalName {
     pub down_face: Option<ButtonFaceConfig>,
     pub up_handler: Option<EventHandlerConfig>,
     pub down_handler: Option<EventHandlerConfig>,
+    /// Handlers executed in order after the [up_handler]
+    pub up_handlers: Option<Vec<EventHandlerConfig>>,
+    /// Handlers executed in order after the [down_handler]
+    pub down_handlers: Option<Vec<EventHandlerConfig>>,
+    pub load_page: Option<String>,
+    /// Disabled buttons are drawn dimmed and execute no handlers (default: true)
+    pub enabled: Option<bool>,
+    /// Up face while the layer modifier button is held down
+    pub layer_face: Option<ButtonFaceConfig>,
+    /// Handlers while the layer modifier button is held down
+    pub layer_handlers: Option<LayerHandlersConfig>,
+    /// Handler executed on the edge given by [trigger]
+    pub action: Option<EventHandlerConfig>,
+    /// Whether the [action] is executed when the button goes down or up (default: down)
+    pub trigger: Option<TriggerConfig>,
+    /// Swap the down and up handlers, so that they are executed on release and press (for example for foot pedals)
+    pub invert_press: Option<bool>,
+    /// Makes the button latching, every press toggles between the normal and the latched state
+    pub latch: Option<LatchConfig>,
+    /// Name of a group, of which only the last pressed button is selected (shows its down face)
+    pub group: Option<String>,
+    /// Free text describing the button, not used by the controller
+    pub description: Option<String>,
+    /// Free tags of the button, not used by the controller
+    pub tags: Option<Vec<String>>,
 }
 
 /// Configuration of a button or just the name of a button
@@ -64,7 +164,15 @@ down_handler:
                 file: None,
                 label: None,
                 sublabel: None,
-                superlabel: None
+                superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
             })
         );
         assert_eq!(
@@ -75,6 +183,14 @@ down_handler:
                 label: Some(LabelConfig::JustText(String::from("Hello"))),
                 sublabel: None,
                 superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
             })
         );
         assert_eq!(
@@ -86,7 +202,8 @@ down_handler:
         assert_eq!(
             deserialize.down_handler,
             Some(EventHandlerConfig::AsFile {
-                file: String::from("handler.py")
+                file: String::from("handler.py"),
+                pythonpath: None
             })
         );
     }
@@ -118,7 +235,15 @@ down_handler:
                 file: None,
                 label: None,
                 sublabel: None,
-                superlabel: None
+                superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
             })
         );
         assert_eq!(
@@ -129,6 +254,14 @@ down_handler:
                 label: Some(LabelConfig::JustText(String::from("Hello"))),
                 sublabel: None,
                 superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
             })
         );
         assert_eq!(
@@ -140,7 +273,8 @@ down_handler:
         assert_eq!(
             deserialize.down_handler,
             Some(EventHandlerConfig::AsFile {
-                file: String::from("handler.py")
+                file: String::from("handler.py"),
+                pythonpath: None
             })
         );
     }
@@ -188,6 +322,22 @@ down_handler:
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn button_loading_a_page() {
+        // Setup
+        let yaml = "\
+name: button
+load_page: page1
+";
+
+        // Act
+        let deserialize: ButtonConfigWithName = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(deserialize.load_page, Some(String::from("page1")));
+        assert_eq!(deserialize.down_handler, None);
+    }
+
     #[test]
     fn just_button_name() {
         // Setup
@@ -203,4 +353,151 @@ down_handler:
             ButtonOrButtonName::ButtonName(String::from(button_name))
         );
     }
+
+    #[test]
+    fn button_with_on_app_faces() {
+        // Setup
+        let yaml = "\
+name: button
+on_app_faces:
+  - condition:
+      executable: '.*firefox.*'
+    face:
+      label: Web
+";
+
+        // Act
+        let deserialize: ButtonConfigWithName = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.on_app_faces,
+            Some(vec![ConditionalFaceConfig {
+                condition: ForegroundWindowConditionConfig {
+                    title: None,
+                    executable: Some(String::from(".*firefox.*")),
+                    class_name: None,
+                    workspace: None,
+                    title_equals: None,
+                    title_starts_with: None,
+                    title_contains: None,
+                },
+                face: ButtonFaceConfig {
+                    color: None,
+                    file: None,
+                    label: Some(LabelConfig::JustText(String::from("Web"))),
+                    sublabel: None,
+                    superlabel: None,
+                    overlay: None,
+                    sprite: None,
+                    lines: None,
+                    pixelated: None,
+                    padding: None,
+                    background: None,
+                    color_key: None,
+                    color_key_tolerance: None,
+                },
+            }])
+        );
+    }
+
+    #[test]
+    fn button_with_action_and_trigger() {
+        // Setup
+        let yaml = "\
+action:
+  code: print
+trigger: up
+";
+
+        // Act
+        let deserialize: ButtonConfigOptionalName = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.action,
+            Some(EventHandlerConfig::AsCode {
+                code: String::from("print")
+            })
+        );
+        assert_eq!(deserialize.trigger, Some(TriggerConfig::Up));
+    }
+
+    #[test]
+    fn button_with_description_and_tags() {
+        // Setup
+        let yaml = "\
+name: button
+description: Mutes the microphone
+tags:
+  - audio
+  - meeting
+";
+
+        // Act
+        let deserialize: ButtonConfigWithName = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.description,
+            Some(String::from("Mutes the microphone"))
+        );
+        assert_eq!(
+            deserialize.tags,
+            Some(vec![String::from("audio"), String::from("meeting")])
+        );
+    }
+
+    #[test]
+    fn button_with_when_faces() {
+        // Setup
+        let yaml = "\
+name: button
+when_faces:
+  - when: muted
+    face:
+      label: Muted
+";
+
+        // Act
+        let deserialize: ButtonConfigWithName = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        let when_faces = deserialize.when_faces.unwrap();
+        assert_eq!(when_faces.len(), 1);
+        assert_eq!(when_faces[0].when, String::from("muted"));
+        assert_eq!(
+            when_faces[0].face.label,
+            Some(LabelConfig::JustText(String::from("Muted")))
+        );
+    }
+
+    #[test]
+    fn button_with_handler_chain() {
+        // Setup
+        let yaml = "\
+name: button
+down_handlers:
+  - code: first
+  - file: second.py
+";
+
+        // Act
+        let deserialize: ButtonConfigWithName = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.down_handlers,
+            Some(vec![
+                EventHandlerConfig::AsCode {
+                    code: String::from("first")
+                },
+                EventHandlerConfig::AsFile {
+                    file: String::from("second.py"),
+                    pythonpath: None
+                }
+            ])
+        );
+        assert_eq!(deserialize.up_handlers, None);
+    }
 }
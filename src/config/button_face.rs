@@ -1,16 +1,70 @@
-use crate::config::color::ColorConfig;
-use crate::config::label::LabelConfig;
+use crate::config::color::{ColorConfig, ColorConfigRGB};
+use crate::config::label::{LabelConfig, LineConfig};
 use serde::Deserialize;
 
 /// The face of a button (what is displayed on a button) from the config.
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct ButtonFaceConfig {
+    /// Either a color or an image file, replaces [color] and [file]
+    pub background: Option<BackgroundConfig>,
+    /// Deprecated, use [background]
     pub color: Option<ColorConfig>,
+    /// Deprecated, use [background]
     pub file: Option<String>,
     pub label: Option<LabelConfig>,
     pub sublabel: Option<LabelConfig>,
     pub superlabel: Option<LabelConfig>,
+    /// Additional lines of text, drawn in order after the labels
+    pub lines: Option<Vec<LineConfig>>,
+    pub overlay: Option<OverlayConfig>,
+    pub sprite: Option<SpriteConfig>,
+    /// Scale the image with nearest-neighbor filtering, to keep pixel art sharp
+    pub pixelated: Option<bool>,
+    /// Space between the border of the face and the image
+    pub padding: Option<PaddingConfig>,
+    /// Pixels of the image with this color are transparent
+    pub color_key: Option<ColorConfig>,
+    /// Maximal difference of each color channel to the [color_key], for a pixel to be transparent
+    pub color_key_tolerance: Option<u8>,
+}
+
+/// Background of a face, either a color or an image file.
+///
+/// Strings starting with "#" are hex colors, all other strings are image files.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum BackgroundConfig {
+    RGB(ColorConfigRGB),
+    ColorOrFile(String),
+}
+
+/// A cell of a sprite sheet (an image containing a grid of sub-images) drawn on the face.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SpriteConfig {
+    pub file: String,
+    pub cols: u32,
+    pub rows: u32,
+    /// Index of the cell, counted row by row from the top left.
+    pub index: u32,
+}
+
+/// Padding around the image of a face, in pixels (a number) or percent (a string like "25%").
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum PaddingConfig {
+    Pixels(u32),
+    Percent(String),
+}
+
+/// A color blended over the whole face (after the image, before the labels).
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OverlayConfig {
+    pub color: ColorConfig,
+    /// Opacity of the overlay, between 0.0 (invisible) and 1.0 (opaque).
+    pub opacity: f32,
 }
 
 #[cfg(test)]
@@ -31,6 +85,100 @@ mod tests {
         assert_eq!(deserialize.label, None);
         assert_eq!(deserialize.sublabel, None);
         assert_eq!(deserialize.superlabel, None);
+        assert_eq!(deserialize.overlay, None);
+        assert_eq!(deserialize.lines, None);
+    }
+
+    #[test]
+    fn test_with_lines() {
+        // Setup
+        let yaml = "\
+lines:
+  - text: first
+    y: 0.1
+  - text: second
+    color: '#FF0000'
+    y: 0.9";
+
+        // Act
+        let deserialize: ButtonFaceConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.lines,
+            Some(vec![
+                LineConfig {
+                    text: String::from("first"),
+                    color: None,
+                    y: 0.1
+                },
+                LineConfig {
+                    text: String::from("second"),
+                    color: Some(ColorConfig::HEXString(String::from("#FF0000"))),
+                    y: 0.9
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_with_overlay() {
+        // Setup
+        let yaml = "\
+overlay:
+  color: '#FF0000'
+  opacity: 0.5";
+
+        // Act
+        let deserialize: ButtonFaceConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.overlay,
+            Some(OverlayConfig {
+                color: ColorConfig::HEXString(String::from("#FF0000")),
+                opacity: 0.5
+            })
+        );
+    }
+
+    #[test]
+    fn test_with_sprite() {
+        // Setup
+        let yaml = "\
+sprite:
+  file: sheet.png
+  cols: 4
+  rows: 2
+  index: 5";
+
+        // Act
+        let deserialize: ButtonFaceConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.sprite,
+            Some(SpriteConfig {
+                file: String::from("sheet.png"),
+                cols: 4,
+                rows: 2,
+                index: 5
+            })
+        );
+    }
+
+    #[test]
+    fn fails_with_overlay_without_opacity() {
+        // Setup
+        let yaml = "\
+overlay:
+  color: '#FF0000'";
+
+        // Act
+        let deserialize: Result<ButtonFaceConfig, serde_yaml::Error> = serde_yaml::from_str(&yaml);
+
+        // Test
+        assert!(deserialize.is_err());
     }
 
     #[test]
@@ -92,14 +240,20 @@ superlabel:
             deserialize.label,
             Some(LabelConfig::WithColor(LabelConfigWithColor {
                 text: String::from(label_value),
-                color: Some(ColorConfig::HEXString(String::from(label_color_value)))
+                color: Some(ColorConfig::HEXString(String::from(label_color_value))),
+                size: None,
+                at: None,
+                font: None,
             }))
         );
         assert_eq!(
             deserialize.sublabel,
             Some(LabelConfig::WithColor(LabelConfigWithColor {
                 text: String::from(sub_label_value),
-                color: Some(ColorConfig::HEXString(String::from(sub_label_color_value)))
+                color: Some(ColorConfig::HEXString(String::from(sub_label_color_value))),
+                size: None,
+                at: None,
+                font: None,
             }))
         );
         assert_eq!(
@@ -108,8 +262,115 @@ superlabel:
                 text: String::from(super_label_value),
                 color: Some(ColorConfig::HEXString(String::from(
                     super_label_color_value
-                )))
+                ))),
+                size: None,
+                at: None,
+                font: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_with_pixelated() {
+        // Setup
+        let yaml = "\
+file: icon.png
+pixelated: true";
+
+        // Act
+        let deserialize: ButtonFaceConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(deserialize.pixelated, Some(true));
+    }
+
+    #[test]
+    fn test_with_padding() {
+        // Setup
+        let pixels = "padding: 4";
+        let percent = "padding: 25%";
+
+        // Act
+        let pixels: ButtonFaceConfig = serde_yaml::from_str(&pixels).unwrap();
+        let percent: ButtonFaceConfig = serde_yaml::from_str(&percent).unwrap();
+
+        // Test
+        assert_eq!(pixels.padding, Some(PaddingConfig::Pixels(4)));
+        assert_eq!(
+            percent.padding,
+            Some(PaddingConfig::Percent(String::from("25%")))
+        );
+    }
+
+    #[test]
+    fn test_with_background_color() {
+        // Setup
+        let yaml = "background: '#FF0000'";
+
+        // Act
+        let deserialize: ButtonFaceConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.background,
+            Some(BackgroundConfig::ColorOrFile(String::from("#FF0000")))
+        );
+    }
+
+    #[test]
+    fn test_with_background_file() {
+        // Setup
+        let yaml = "background: icon.png";
+
+        // Act
+        let deserialize: ButtonFaceConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.background,
+            Some(BackgroundConfig::ColorOrFile(String::from("icon.png")))
+        );
+    }
+
+    #[test]
+    fn test_with_background_rgb() {
+        // Setup
+        let yaml = "\
+background:
+  red: 255
+  green: 0
+  blue: 0";
+
+        // Act
+        let deserialize: ButtonFaceConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.background,
+            Some(BackgroundConfig::RGB(ColorConfigRGB {
+                red: 255,
+                green: 0,
+                blue: 0
             }))
         );
     }
+
+    #[test]
+    fn test_with_color_key() {
+        // Setup
+        let yaml = "\
+file: icon.png
+color_key: '#00FF00'
+color_key_tolerance: 8";
+
+        // Act
+        let deserialize: ButtonFaceConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.color_key,
+            Some(ColorConfig::HEXString(String::from("#00FF00")))
+        );
+        assert_eq!(deserialize.color_key_tolerance, Some(8));
+    }
 }
@@ -1,11 +1,15 @@
 use serde::Deserialize;
 
 /// Button positions can be given as tuples ar os objects!
+///
+/// The string form is either a tuple like "(0, 1)" or a grid label like "A1".
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(untagged)]
 pub enum ButtonPositionConfig {
     ButtonPositionTupleConfig(String),
     ButtonPositionObjectConfig(ButtonPositionObject),
+    ButtonPositionRelativeConfig(ButtonPositionRelative),
+    ButtonPositionRangeConfig(ButtonPositionRange),
 }
 
 /// Position of a button on a page.
@@ -18,6 +22,37 @@ pub struct ButtonPositionObject {
     pub col: i32,
 }
 
+/// Position of a button, relative to the position of another button on the same page.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonPositionRelative {
+    /// Name of the button, the position is relative to
+    pub relative_to: String,
+    /// Rows below the other button (negative for above)
+    pub drow: i32,
+    /// Columns after the other button (in the direction in which `col` is counted)
+    pub dcol: i32,
+}
+
+/// Range of positions, the same button is placed on every position in it.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ButtonPositionRange {
+    pub rows: PositionRangeConfig,
+    pub cols: PositionRangeConfig,
+}
+
+/// Rows or columns of a [ButtonPositionRange].
+///
+/// Either a single index, or an inclusive range like "0..4". Negative indexes are counted from
+/// right or below, like in [ButtonPositionObject].
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum PositionRangeConfig {
+    Index(i32),
+    Range(String),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -69,4 +104,56 @@ mod tests {
         // Test
         assert!(result.is_err());
     }
+
+    #[test]
+    fn grid_label_position() {
+        // Setup
+        let yaml = "A1\n";
+
+        // Act
+        let deserialize: ButtonPositionConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize,
+            ButtonPositionConfig::ButtonPositionTupleConfig(String::from("A1"))
+        );
+    }
+
+    #[test]
+    fn relative_position() {
+        // Setup
+        let yaml = "relative_to: anchor\ndrow: 1\ndcol: -2\n";
+
+        // Act
+        let deserialize: ButtonPositionConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize,
+            ButtonPositionConfig::ButtonPositionRelativeConfig(ButtonPositionRelative {
+                relative_to: String::from("anchor"),
+                drow: 1,
+                dcol: -2
+            })
+        );
+    }
+
+    #[test]
+    fn range_position() {
+        // Setup
+        let yaml = "rows: 1\ncols: 0..-2\n";
+
+        // Act
+        let deserialize: ButtonPositionConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize,
+            ButtonPositionConfig::ButtonPositionRangeConfig(ButtonPositionRange {
+                rows: PositionRangeConfig::Index(1),
+                cols: PositionRangeConfig::Range(String::from("0..-2"))
+            })
+        );
+    }
 }
@@ -38,6 +38,12 @@ pub fn hex_string_to_rgba_color(hex: &String) -> Result<image::Rgba<u8>, error::
 }
 
 impl ColorConfig {
+    /// Whether this is the special `auto` color, which is chosen
+    /// when drawing (for contrast to the background).
+    pub fn is_auto(&self) -> bool {
+        matches!(self, ColorConfig::HEXString(s) if s == "auto")
+    }
+
     /// Convert to an image color.
     pub fn to_image_rgba_color(&self) -> Result<image::Rgba<u8>, error::Error> {
         match self {
@@ -89,6 +95,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_auto_color() {
+        // Setup
+        let yaml = "auto";
+
+        // Act
+        let deserialize: ColorConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert!(deserialize.is_auto());
+        assert!(!ColorConfig::HEXString(String::from("#FF0000")).is_auto());
+        assert!(deserialize.to_image_rgba_color().is_err());
+    }
+
     #[test]
     fn hex_to_rgba() {
         // Setup
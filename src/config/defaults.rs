@@ -9,6 +9,31 @@ pub struct DefaultsConfig {
     pub label_color: Option<ColorConfig>,
     pub superlabel_color: Option<ColorConfig>,
     pub sublabel_color: Option<ColorConfig>,
+    pub column_order: Option<ColumnOrderConfig>,
+    /// Smallest font size (in pixels) of labels, longer texts are truncated
+    pub min_font_scale: Option<f32>,
+    /// Largest font size (in pixels) of labels
+    pub max_font_scale: Option<f32>,
+    /// Font files used for characters missing in the bundled font (for example CJK characters)
+    pub fallback_fonts: Option<Vec<String>>,
+    /// Color emoji font file with bitmap glyphs (for example Noto Color Emoji), used for emoji in labels
+    pub emoji_font: Option<String>,
+    /// Factor for all font sizes, derived from the button size of the device if not given
+    pub font_scale: Option<f32>,
+    /// Draw faces at this multiple (2, 3 or 4) of the button size and downsample them
+    pub supersample: Option<u32>,
+    /// Show the up face shrunk to this size (for example 0.9) while a button without down face is pressed
+    pub press_scale: Option<f32>,
+}
+
+/// Direction in which the columns of button positions are counted.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnOrderConfig {
+    /// Column 0 is the leftmost column
+    Ltr,
+    /// Column 0 is the rightmost column
+    Rtl,
 }
 
 #[cfg(test)]
@@ -30,6 +55,36 @@ mod tests {
         assert_eq!(deserialize.label_color, None);
         assert_eq!(deserialize.superlabel_color, None);
         assert_eq!(deserialize.sublabel_color, None);
+        assert_eq!(deserialize.column_order, None);
+        assert_eq!(deserialize.min_font_scale, None);
+        assert_eq!(deserialize.max_font_scale, None);
+        assert_eq!(deserialize.fallback_fonts, None);
+        assert_eq!(deserialize.emoji_font, None);
+        assert_eq!(deserialize.font_scale, None);
+    }
+
+    #[test]
+    fn test_column_order() {
+        // Setup
+        let yaml = "column_order: ltr";
+
+        // Act
+        let deserialize: DefaultsConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(deserialize.column_order, Some(ColumnOrderConfig::Ltr));
+    }
+
+    #[test]
+    fn fails_with_unknown_column_order() {
+        // Setup
+        let yaml = "column_order: up";
+
+        // Act
+        let deserialize: Result<DefaultsConfig, serde_yaml::Error> = serde_yaml::from_str(&yaml);
+
+        // Test
+        assert!(deserialize.is_err());
     }
 
     #[test]
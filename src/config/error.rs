@@ -1,4 +1,48 @@
+use std::fmt;
+
 #[derive(Debug)]
 pub enum Error {
     InvalidColorHexString(String),
+    /// The log level is not one of "error", "warn", "info", "debug" or "trace"
+    InvalidLogLevel(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidColorHexString(hex) => write!(f, "invalid color hex string: {}", hex),
+            Error::InvalidLogLevel(level) => write!(f, "invalid log level: {}", level),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_color_hex_string_message_contains_the_string() {
+        // Setup
+        let error = Error::InvalidColorHexString(String::from("#XYZ"));
+
+        // Act
+        let message = error.to_string();
+
+        // Test
+        assert!(message.contains("#XYZ"));
+    }
+
+    #[test]
+    fn invalid_log_level_message_contains_the_level() {
+        // Setup
+        let error = Error::InvalidLogLevel(String::from("verbose"));
+
+        // Act
+        let message = error.to_string();
+
+        // Test
+        assert!(message.contains("verbose"));
+    }
 }
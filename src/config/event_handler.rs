@@ -6,7 +6,12 @@ use serde::Deserialize;
 #[serde(deny_unknown_fields)]
 pub enum EventHandlerConfig {
     AsCode { code: String },
-    AsFile { file: String },
+    AsFile {
+        file: String,
+        /// Directories prepended to the python path while the handler runs
+        #[serde(default)]
+        pythonpath: Option<Vec<String>>,
+    },
 }
 
 #[cfg(test)]
@@ -58,7 +63,26 @@ mod tests {
         assert_eq!(
             deserialize,
             EventHandlerConfig::AsFile {
-                file: String::from(file_value)
+                file: String::from(file_value),
+                pythonpath: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_file_with_pythonpath() {
+        // Setup
+        let yaml = "file: handler.py\npythonpath:\n  - lib\n  - /opt/scripts";
+
+        // Act
+        let deserialize: EventHandlerConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize,
+            EventHandlerConfig::AsFile {
+                file: String::from("handler.py"),
+                pythonpath: Some(vec![String::from("lib"), String::from("/opt/scripts")])
             }
         );
     }
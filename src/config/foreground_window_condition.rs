@@ -4,9 +4,40 @@ use serde::Deserialize;
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct ForegroundWindowConditionConfig {
+    /// Regex matching the title
     pub title: Option<String>,
+    /// The title, matched literally (instead of the `title` regex)
+    pub title_equals: Option<String>,
+    /// Start of the title, matched literally
+    pub title_starts_with: Option<String>,
+    /// Part of the title, matched literally
+    pub title_contains: Option<String>,
     pub executable: Option<String>,
     pub class_name: Option<String>,
+    pub workspace: Option<u32>,
+}
+
+/// Condition of a page, either a single window condition or a group of conditions.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum PageConditionConfig {
+    /// Matches, if all conditions of the group match
+    All(AllConditionsConfig),
+    /// Matches, if any condition of the group matches
+    Any(AnyConditionConfig),
+    Window(ForegroundWindowConditionConfig),
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AllConditionsConfig {
+    pub all: Vec<PageConditionConfig>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AnyConditionConfig {
+    pub any: Vec<PageConditionConfig>,
 }
 
 #[cfg(test)]
@@ -61,6 +92,19 @@ mod tests {
         assert_eq!(deserialize.executable, Some(exec_value.to_string()));
     }
 
+    #[test]
+    fn test_with_only_workspace() {
+        // Setup
+        let yaml = "workspace: 2\n";
+
+        // Act
+        let deserialize: ForegroundWindowConditionConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(deserialize.title, None);
+        assert_eq!(deserialize.workspace, Some(2));
+    }
+
     #[test]
     fn test_with_only_class_name() {
         // Setup
@@ -74,4 +118,29 @@ mod tests {
         assert_eq!(deserialize.title, None);
         assert_eq!(deserialize.class_name, Some(class_name_value.to_string()));
     }
+
+    #[test]
+    fn nested_condition_groups() {
+        // Setup
+        let yaml = "
+- all:
+  - executable: firefox
+  - title: .*GitHub.*
+- any:
+  - executable: code
+  - workspace: 2
+- title: .*Terminal.*
+";
+
+        // Act
+        let deserialize: Vec<PageConditionConfig> = serde_yaml::from_str(yaml).unwrap();
+
+        // Test
+        assert_eq!(deserialize.len(), 3);
+        assert!(matches!(&deserialize[0], PageConditionConfig::All(group) if group.all.len() == 2));
+        assert!(matches!(&deserialize[1], PageConditionConfig::Any(group) if group.any.len() == 2));
+        assert!(
+            matches!(&deserialize[2], PageConditionConfig::Window(c) if c.title == Some(String::from(".*Terminal.*")))
+        );
+    }
 }
@@ -0,0 +1,39 @@
+use serde::Deserialize;
+
+/// Flipping of the button images before they are sent to the device.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFlipConfig {
+    /// Mirror horizontally (left and right are swapped)
+    H,
+    /// Mirror vertically (top and bottom are swapped)
+    V,
+    /// Mirror horizontally and vertically (rotated by 180 degrees)
+    HV,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flips_are_parsed() {
+        // Act
+        let flips: Vec<ImageFlipConfig> = serde_yaml::from_str("[h, v, hv]").unwrap();
+
+        // Test
+        assert_eq!(
+            flips,
+            vec![ImageFlipConfig::H, ImageFlipConfig::V, ImageFlipConfig::HV]
+        );
+    }
+
+    #[test]
+    fn fails_with_unknown_flip() {
+        // Act
+        let result: Result<ImageFlipConfig, serde_yaml::Error> = serde_yaml::from_str("x");
+
+        // Test
+        assert!(result.is_err());
+    }
+}
@@ -15,6 +15,50 @@ pub enum LabelConfig {
 pub struct LabelConfigWithColor {
     pub color: Option<ColorConfig>,
     pub text: String,
+    /// Font size in pixels, the text is still shrunk if it does not fit the button
+    pub size: Option<f32>,
+    /// Place the text at these coordinates, instead of the default position of the label
+    pub at: Option<LabelAtConfig>,
+    /// Font file (TrueType or OpenType) of the text, instead of the bundled font
+    pub font: Option<String>,
+}
+
+/// Explicit position of a label on a button.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LabelAtConfig {
+    /// Pixels from the left of the button
+    pub x: i32,
+    /// Pixels from the top of the button
+    pub y: i32,
+    /// Point of the text, that is placed at the coordinates (default: center)
+    pub anchor: Option<LabelAnchorConfig>,
+}
+
+/// Point of a text, used to place it.
+#[derive(Debug, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelAnchorConfig {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// A line of text at a free vertical position on a button.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LineConfig {
+    pub text: String,
+    pub color: Option<ColorConfig>,
+    /// Vertical position of the center of the line,
+    /// between 0.0 (top) and 1.0 (bottom) of the button.
+    pub y: f32,
 }
 
 #[cfg(test)]
@@ -51,7 +95,10 @@ mod tests {
             deserialize,
             LabelConfig::WithColor(LabelConfigWithColor {
                 color: None,
-                text: String::from(label_value)
+                text: String::from(label_value),
+                size: None,
+                at: None,
+                font: None,
             })
         );
     }
@@ -71,7 +118,87 @@ mod tests {
             deserialize,
             LabelConfig::WithColor(LabelConfigWithColor {
                 color: Some(ColorConfig::HEXString(String::from(color_value))),
-                text: String::from(label_value)
+                text: String::from(label_value),
+                size: None,
+                at: None,
+                font: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_with_size() {
+        // Setup
+        let yaml = "text: label\nsize: 24";
+
+        // Act
+        let deserialize: LabelConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize,
+            LabelConfig::WithColor(LabelConfigWithColor {
+                color: None,
+                text: String::from("label"),
+                size: Some(24.0),
+                at: None,
+                font: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_line() {
+        // Setup
+        let yaml = "text: line\ncolor: '#FF0000'\ny: 0.25";
+
+        // Act
+        let deserialize: LineConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize,
+            LineConfig {
+                text: String::from("line"),
+                color: Some(ColorConfig::HEXString(String::from("#FF0000"))),
+                y: 0.25
+            }
+        );
+    }
+
+    #[test]
+    fn fails_with_line_without_y() {
+        // Setup
+        let yaml = "text: line";
+
+        // Act
+        let deserialize: Result<LineConfig, serde_yaml::Error> = serde_yaml::from_str(&yaml);
+
+        // Test
+        assert!(deserialize.is_err());
+    }
+
+    #[test]
+    fn test_with_position() {
+        // Setup
+        let yaml = "text: '12'\nat:\n  x: 36\n  y: 4\n  anchor: top";
+
+        // Act
+        let deserialize: LabelConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize,
+            LabelConfig::WithColor(LabelConfigWithColor {
+                color: None,
+                text: String::from("12"),
+                size: None,
+                at: Some(LabelAtConfig {
+                    x: 36,
+                    y: 4,
+                    anchor: Some(LabelAnchorConfig::Top),
+                }),
+                font: None,
             })
         );
     }
@@ -0,0 +1,50 @@
+use crate::config::Error;
+use log::LevelFilter;
+
+/// Get the level filter of the logger from its name in the config.
+///
+/// # Arguments
+///
+/// name - The name of the level ("error", "warn", "info", "debug" or "trace"), case insensitive.
+///
+/// # Return
+///
+/// The level filter, or an error if the name is unknown.
+pub fn parse_log_level(name: &str) -> Result<LevelFilter, Error> {
+    match name.to_ascii_lowercase().as_str() {
+        "error" => Ok(LevelFilter::Error),
+        "warn" => Ok(LevelFilter::Warn),
+        "info" => Ok(LevelFilter::Info),
+        "debug" => Ok(LevelFilter::Debug),
+        "trace" => Ok(LevelFilter::Trace),
+        _ => Err(Error::InvalidLogLevel(name.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_map_to_levels() {
+        assert_eq!(parse_log_level("error").unwrap(), LevelFilter::Error);
+        assert_eq!(parse_log_level("warn").unwrap(), LevelFilter::Warn);
+        assert_eq!(parse_log_level("info").unwrap(), LevelFilter::Info);
+        assert_eq!(parse_log_level("debug").unwrap(), LevelFilter::Debug);
+        assert_eq!(parse_log_level("trace").unwrap(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn names_are_case_insensitive() {
+        assert_eq!(parse_log_level("DEBUG").unwrap(), LevelFilter::Debug);
+    }
+
+    #[test]
+    fn unknown_name_is_an_error() {
+        // Act
+        let result = parse_log_level("verbose");
+
+        // Test
+        assert!(matches!(result, Err(Error::InvalidLogLevel(name)) if name == "verbose"));
+    }
+}
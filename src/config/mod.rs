@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 
 mod button;
 pub use button::*;
@@ -20,8 +21,16 @@ pub use label::*;
 mod error;
 pub use error::*;
 mod foreground_window_condition;
+mod image_flip;
+pub use image_flip::*;
+mod log_level;
+pub use log_level::*;
 mod foreground_window_handler;
 mod page;
+mod profile;
+pub use profile::*;
+mod touch_point;
+pub use touch_point::*;
 
 pub use foreground_window_condition::*;
 
@@ -37,7 +46,56 @@ pub struct Config {
     pub pages: Vec<page::PageConfig>,
     pub default_pages: Option<Vec<String>>,
     pub init_script: Option<EventHandlerConfig>,
+    /// Executed when the program exits (after Ctrl-C).
+    pub shutdown_script: Option<EventHandlerConfig>,
     pub on_app: Option<Vec<ForegroundWindowHandlerConfig>>,
+    /// Executed for every press and release of any button.
+    pub on_any_button: Option<EventHandlerConfig>,
+    /// Executed when a button without setup (showing the "empty" button) is pressed.
+    pub default_button_handler: Option<EventHandlerConfig>,
+    /// Index of the button, that activates the layer of the other buttons while held down.
+    pub layer_modifier: Option<usize>,
+    /// Do not create the "empty" button automatically, and fail on unresolved button names.
+    pub strict_empty: Option<bool>,
+    /// Re-draw faces when their image files change.
+    pub watch_images: Option<bool>,
+    /// Type of the Stream Deck, used when no device is connected.
+    pub device_type: Option<String>,
+    /// Maximal number of faces uploaded to the device at once, before input events are handled again.
+    pub max_faces_per_frame: Option<usize>,
+    /// Check the foreground window every this many milliseconds, in addition to reacting to window events.
+    pub poll_interval_ms: Option<u64>,
+    /// Level of the log messages ("error", "warn", "info", "debug" or "trace"), RUST_LOG takes precedence.
+    pub log_level: Option<String>,
+    /// Handlers of the touch points next to the buttons (of the Stream Deck Neo), by index.
+    pub touch_points: Option<Vec<TouchPointConfig>>,
+    /// Count the `col` of button positions from the left (column 0 is the leftmost button).
+    pub positions_left_to_right: Option<bool>,
+    /// Flip the button images before sending them to the device, for devices showing them mirrored.
+    pub image_flip: Option<ImageFlipConfig>,
+    /// Clear the buttons of the device at startup (default: true).
+    pub reset_on_start: Option<bool>,
+    /// Address (like "127.0.0.1:9000") on which OSC messages are received.
+    pub osc_address: Option<String>,
+    /// Ignore foreground window events identical to the previous one within this many milliseconds (default: 100).
+    pub window_dedup_ms: Option<u64>,
+    /// Render the faces at most this many times per second, changes in between are rendered together.
+    pub max_render_hz: Option<u32>,
+    /// Address (like "127.0.0.1:9100"), on which the metrics are served for Prometheus.
+    pub metrics_address: Option<String>,
+    /// Show the background color of faces with images, while the images are loaded in the background.
+    pub loading_placeholder: Option<bool>,
+    /// Named sets of default pages, that can be activated by scripts.
+    pub profiles: Option<HashMap<String, ProfileConfig>>,
+}
+
+impl Config {
+    /// Whether the buttons of the device are cleared at startup.
+    ///
+    /// Without the reset, the images of the previous run stay until the first render overwrites them.
+    pub fn reset_on_start(&self) -> bool {
+        self.reset_on_start.unwrap_or(true)
+    }
 }
 
 #[cfg(test)]
@@ -56,6 +114,18 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn config_with_device_type() {
+        // Setup
+        let yaml = "device_type: xl\npages: []";
+
+        // Act
+        let result: Config = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(result.device_type, Some(String::from("xl")));
+    }
+
     #[test]
     fn fail_on_config_with_unkown_fields() {
         // Setup
@@ -67,4 +137,29 @@ mod tests {
         // Test
         assert!(result.is_err());
     }
+
+    #[test]
+    fn reset_on_start_defaults_to_true() {
+        // Setup
+        let yaml = "pages: []";
+
+        // Act
+        let result: Config = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(result.reset_on_start, None);
+        assert!(result.reset_on_start());
+    }
+
+    #[test]
+    fn reset_on_start_can_be_disabled() {
+        // Setup
+        let yaml = "reset_on_start: false\npages: []";
+
+        // Act
+        let result: Config = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert!(!result.reset_on_start());
+    }
 }
@@ -1,6 +1,6 @@
 use crate::config::button::ButtonOrButtonName;
 use crate::config::button_position::ButtonPositionConfig;
-use crate::config::ForegroundWindowConditionConfig;
+use crate::config::{EventHandlerConfig, PageConditionConfig};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -9,6 +9,17 @@ pub struct PageConfig {
     pub name: String,
     pub on_app: Option<PageLoadConditions>,
     pub buttons: Vec<PageButtonConfig>,
+    pub on_load: Option<EventHandlerConfig>,
+    pub on_unload: Option<EventHandlerConfig>,
+    /// Executed for every press and release of a button of the page
+    pub on_any_button: Option<EventHandlerConfig>,
+    /// Free text describing the page, not used by the controller
+    pub description: Option<String>,
+    /// Free tags of the page, not used by the controller
+    pub tags: Option<Vec<String>>,
+    /// Give the page its own copies of the named buttons it shows, so that changes of the copies
+    /// by scripts do not affect other pages.
+    pub isolate_named_buttons: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -21,7 +32,8 @@ pub struct PageButtonConfig {
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct PageLoadConditions {
-    pub conditions: Vec<ForegroundWindowConditionConfig>,
+    /// The page is loaded if any of the conditions matches
+    pub conditions: Vec<PageConditionConfig>,
     pub remove: Option<bool>,
 }
 
@@ -30,6 +42,7 @@ mod tests {
     use super::*;
     use crate::config::button::ButtonOrButtonName;
     use crate::config::ButtonPositionObject;
+    use crate::config::ForegroundWindowConditionConfig;
 
     #[test]
     fn page_button_config() {
@@ -99,11 +112,48 @@ buttons:
                         ButtonPositionObject { row: 0, col: 1 }
                     ),
                     button: ButtonOrButtonName::ButtonName(String::from("button1"))
-                }])
+                }]),
+                on_load: None,
+                on_unload: None,
+                on_any_button: None,
+                description: None,
+                tags: None,
+                isolate_named_buttons: None,
             }
         );
     }
 
+    #[test]
+    fn page_config_with_load_and_unload_handlers() {
+        // Setup
+        let yaml = "\
+name: page1
+buttons: []
+on_load:
+  code: print('loaded')
+on_unload:
+  file: unloaded.py
+";
+
+        // Act
+        let deserialize: PageConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.on_load,
+            Some(EventHandlerConfig::AsCode {
+                code: String::from("print('loaded')")
+            })
+        );
+        assert_eq!(
+            deserialize.on_unload,
+            Some(EventHandlerConfig::AsFile {
+                file: String::from("unloaded.py"),
+                pythonpath: None
+            })
+        );
+    }
+
     #[test]
     fn page_config_with_on_app() {
         // Setup
@@ -129,11 +179,17 @@ buttons:
             PageConfig {
                 name: String::from("page1"),
                 on_app: Some(PageLoadConditions {
-                    conditions: vec![ForegroundWindowConditionConfig {
-                        title: Some(".*title.*".to_string()),
-                        executable: Some(".*exec.*".to_string()),
-                        class_name: None,
-                    }],
+                    conditions: vec![PageConditionConfig::Window(
+                        ForegroundWindowConditionConfig {
+                            title: Some(".*title.*".to_string()),
+                            executable: Some(".*exec.*".to_string()),
+                            class_name: None,
+                            workspace: None,
+                            title_equals: None,
+                            title_starts_with: None,
+                            title_contains: None,
+                        }
+                    )],
                     remove: None
                 }),
                 buttons: Vec::from([PageButtonConfig {
@@ -141,8 +197,35 @@ buttons:
                         ButtonPositionObject { row: 0, col: 1 }
                     ),
                     button: ButtonOrButtonName::ButtonName(String::from("button1"))
-                }])
+                }]),
+                on_load: None,
+                on_unload: None,
+                on_any_button: None,
+                description: None,
+                tags: None,
+                isolate_named_buttons: None,
             }
         );
     }
+
+    #[test]
+    fn page_with_description_and_tags() {
+        // Setup
+        let yaml = "\
+name: page1
+description: Controls for video calls
+tags: [meeting]
+buttons: []
+";
+
+        // Act
+        let deserialize: PageConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.description,
+            Some(String::from("Controls for video calls"))
+        );
+        assert_eq!(deserialize.tags, Some(vec![String::from("meeting")]));
+    }
 }
@@ -0,0 +1,46 @@
+use serde::Deserialize;
+
+/// A named set of pages, that can be activated instead of the loaded pages.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// The pages loaded when the profile is activated, from the bottom to the top of the stack
+    pub default_pages: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn profiles_are_parsed_by_name() {
+        // Setup
+        let yaml = "\
+work:
+  default_pages: [mail, calendar]
+gaming:
+  default_pages: [obs]
+";
+
+        // Act
+        let profiles: HashMap<String, ProfileConfig> = serde_yaml::from_str(yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            profiles["work"].default_pages,
+            vec![String::from("mail"), String::from("calendar")]
+        );
+        assert_eq!(profiles["gaming"].default_pages, vec![String::from("obs")]);
+    }
+
+    #[test]
+    fn fails_with_unknown_field() {
+        // Act
+        let result: Result<ProfileConfig, serde_yaml::Error> =
+            serde_yaml::from_str("default_pages: []\npages: []");
+
+        // Test
+        assert!(result.is_err());
+    }
+}
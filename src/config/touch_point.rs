@@ -0,0 +1,36 @@
+use super::event_handler::EventHandlerConfig;
+use serde::Deserialize;
+
+/// Handlers of a touch point next to the buttons (like the two touch points of the Stream Deck Neo).
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TouchPointConfig {
+    pub down_handler: Option<EventHandlerConfig>,
+    pub up_handler: Option<EventHandlerConfig>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn touch_point_with_down_handler() {
+        // Setup
+        let yaml = "\
+down_handler:
+  code: print('touched')
+";
+
+        // Act
+        let deserialize: TouchPointConfig = serde_yaml::from_str(&yaml).unwrap();
+
+        // Test
+        assert_eq!(
+            deserialize.down_handler,
+            Some(EventHandlerConfig::AsCode {
+                code: String::from("print('touched')")
+            })
+        );
+        assert_eq!(deserialize.up_handler, None);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::input_event::InputEvent;
+use crate::script_engine::PythonEngine;
+use crate::state::{AppState, EventContext, EventHandler};
+use log::{debug, warn};
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+
+/// Handle an input event: update the state and run the resulting event handlers.
+///
+/// The state is only locked while it is updated, not while the handlers run. The scripts
+/// of the handlers lock the state themselves (for example to load a page), holding the
+/// lock while they run would deadlock.
+///
+/// # Arguments
+///
+/// app_state - The state, shared with the script engine.
+/// engine - The engine running the handlers.
+/// event - The event to handle.
+///
+/// # Return
+///
+/// () if all went ok, the error of the state or a handler otherwise.
+pub fn handle_input_event(
+    app_state: &Arc<RwLock<AppState>>,
+    engine: &PythonEngine,
+    event: InputEvent,
+) -> Result<(), Box<dyn Error>> {
+    let (handlers, context) = {
+        let mut app_state = app_state.write().unwrap();
+        let mut context = EventContext::default();
+        let handlers: Vec<EventHandler> = match event {
+            InputEvent::ButtonDownEvent(button_id) => {
+                let button_id = button_id as usize;
+                if let Some(button_name) = app_state.button_name(button_id) {
+                    context = EventContext::for_button(button_id, button_name, "down");
+                }
+                app_state.on_button_pressed(button_id).into_iter().cloned().collect()
+            }
+            InputEvent::ButtonUpEvent(button_id) => {
+                let button_id = button_id as usize;
+                if let Some(button_name) = app_state.button_name(button_id) {
+                    context = EventContext::for_button(button_id, button_name, "up");
+                }
+                app_state.on_button_released(button_id).into_iter().cloned().collect()
+            }
+            InputEvent::TouchPointDownEvent(touch_point) => {
+                context.event = Some("down");
+                app_state.on_touch_point_pressed(touch_point as usize).into_iter().cloned().collect()
+            }
+            InputEvent::TouchPointUpEvent(touch_point) => {
+                context.event = Some("up");
+                app_state.on_touch_point_released(touch_point as usize).into_iter().cloned().collect()
+            }
+            InputEvent::ForegroundWindow(info) => {
+                debug!(
+                    "new foreground window: title={}, executable={}, class_name={}, workspace={:?}",
+                    info.title, info.executable, info.class_name, info.workspace
+                );
+                app_state.on_foreground_window(&info)?;
+                Vec::new()
+            }
+            InputEvent::FileChanged(path) => {
+                debug!("image file changed: {}", path.display());
+                if let Err(e) = app_state.on_file_changed(&path) {
+                    warn!("unable to re-draw faces for {}: {}", path.display(), e);
+                }
+                Vec::new()
+            }
+            InputEvent::LoadPage(page_name) => match app_state.load_page(&page_name) {
+                Ok(on_load) => on_load.iter().map(|handler| handler.as_ref().clone()).collect(),
+                Err(e) => {
+                    warn!("unable to load page {}: {}", page_name, e);
+                    Vec::new()
+                }
+            },
+            InputEvent::UnloadPage(page_name) => match app_state.unload_page(&page_name) {
+                Ok(on_unload) => on_unload.iter().map(|handler| handler.as_ref().clone()).collect(),
+                Err(e) => {
+                    warn!("unable to unload page {}: {}", page_name, e);
+                    Vec::new()
+                }
+            },
+            // The faces are rendered by the main loop
+            InputEvent::FacesLoaded => Vec::new(),
+            // Handled by the main loop, which exits
+            InputEvent::Shutdown => Vec::new(),
+        };
+        (handlers, context)
+    };
+
+    for event_handler in &handlers {
+        engine.run_event_handler(event_handler, &context)?;
+    }
+
+    // Run handlers queued by the state (for example of loaded pages),
+    // before the next rendering
+    run_queued_handlers(app_state, engine)
+}
+
+/// Run the handlers queued by the state, without holding the lock of the state.
+pub fn run_queued_handlers(
+    app_state: &Arc<RwLock<AppState>>,
+    engine: &PythonEngine,
+) -> Result<(), Box<dyn Error>> {
+    let queued_handlers = app_state.write().unwrap().take_queued_handlers();
+    for queued_handler in queued_handlers {
+        engine.run_event_handler(&queued_handler, &EventContext::default())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use streamdeck_hid_rs::StreamDeckType;
+
+    /// Config with a button at index 4, which loads a second page from its down handler.
+    fn get_config() -> crate::config::Config {
+        serde_yaml::from_str(
+            "\
+pages:
+- name: page1
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button:
+      name: load_button
+      down_handler:
+        code: state.load_page('page2')
+- name: page2
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button:
+      name: page2_button
+default_pages:
+- page1
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn script_loading_a_page_does_not_deadlock_rendering() {
+        // Setup
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let app_state = Arc::new(RwLock::new(
+                AppState::from_config(&StreamDeckType::Orig, &get_config()).unwrap(),
+            ));
+            let engine = PythonEngine::new(&app_state).unwrap();
+
+            // Act
+            handle_input_event(&app_state, &engine, InputEvent::ButtonDownEvent(4)).unwrap();
+            let rendered = crate::render::render_faces(
+                &mut app_state.write().unwrap(),
+                &mut crate::render::RenderBudget::new(None),
+                &crate::render::ImageFlip::default(),
+                &crate::render::MockRenderTarget::default(),
+            );
+            let visible = app_state.read().unwrap().visible_buttons();
+            sender.send((rendered, visible)).unwrap();
+        });
+
+        // Test
+        let (failed, visible) = receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("handling the event did not finish");
+        assert!(failed.is_empty());
+        assert_eq!(visible[4], (4, String::from("page2_button")));
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[cfg(target_os = "linux")]
 use x11rb::errors::{ConnectError, ConnectionError, ReplyError};
 
@@ -24,3 +26,87 @@ pub enum Error {
     WMError(Win32Error),
     AlreadyStarted,
 }
+
+#[cfg(target_os = "linux")]
+impl fmt::Display for X11Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            X11Error::ConnectError(e) => write!(f, "unable to connect to the X server: {}", e),
+            X11Error::ConnectionError(e) => write!(f, "X server connection failed: {}", e),
+            X11Error::ReplyError(e) => write!(f, "X server request failed: {}", e),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl std::error::Error for X11Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            X11Error::ConnectError(e) => Some(e),
+            X11Error::ConnectionError(e) => Some(e),
+            X11Error::ReplyError(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl fmt::Display for Win32Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Win32Error::UnspecificError => write!(f, "unspecific win32 error"),
+            Win32Error::CoreError(e) => write!(f, "win32 call failed: {}", e),
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl std::error::Error for Win32Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Win32Error::UnspecificError => None,
+            Win32Error::CoreError(e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::WMError(e) => write!(f, "window manager error: {}", e),
+            Error::AlreadyStarted => write!(f, "foreground window observer is already started"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::WMError(e) => Some(e),
+            Error::AlreadyStarted => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn already_started_message_is_not_empty() {
+        assert!(!Error::AlreadyStarted.to_string().is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn wm_error_message_contains_the_wrapped_error() {
+        // Setup
+        let wrapped = ConnectionError::UnknownError;
+        let wrapped_message = wrapped.to_string();
+
+        // Act
+        let message = Error::WMError(X11Error::ConnectionError(wrapped)).to_string();
+
+        // Test
+        assert!(message.contains(&wrapped_message));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::foreground_window::{Error, WindowInformation, X11Error};
 use log::warn;
 use std::fs;
+use std::time::Duration;
 use x11rb::connection::Connection;
 use x11rb::protocol::xproto::{
     Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask, GetPropertyReply, Window,
@@ -12,6 +13,7 @@ use x11rb::rust_connection::RustConnection;
 
 struct Atoms {
     pub net_active_window: Atom,
+    pub net_current_desktop: Atom,
     pub net_wm_name: Atom,
     pub wm_class: Atom,
     pub net_wm_pid: Atom,
@@ -29,7 +31,38 @@ struct WindowData {
     pub command: String,
 }
 
-pub fn foreground_window_observer<F>(cb: F) -> Result<(), Error>
+/// Remembers the last active window (and workspace), so that no event is sent twice.
+///
+/// Polling finds the same window again and again, and the window manager may report
+/// property changes without an actual change of the active window.
+#[derive(Default)]
+struct ActiveWindowTracker {
+    last: Option<(Window, Option<u32>)>,
+}
+
+impl ActiveWindowTracker {
+    /// Remember the active window.
+    ///
+    /// # Return
+    ///
+    /// true, if the window or the workspace differs from the last active one.
+    fn update(&mut self, window: Window, workspace: Option<u32>) -> bool {
+        if self.last == Some((window, workspace)) {
+            return false;
+        }
+        self.last = Some((window, workspace));
+        true
+    }
+}
+
+/// Observe the foreground window, calling the callback whenever it changes.
+///
+/// # Arguments
+///
+/// cb - Called with the new foreground window.
+/// poll_interval - If given, the active window is also checked in this interval,
+///                 for window managers not reliably reporting changes by events.
+pub fn foreground_window_observer<F>(cb: F, poll_interval: Option<Duration>) -> Result<(), Error>
 where
     F: Fn(WindowInformation),
     F: 'static,
@@ -43,6 +76,7 @@ where
     // Get the atoms
     let atoms = Atoms {
         net_active_window: get_or_intern_atom(&conn, b"_NET_ACTIVE_WINDOW"),
+        net_current_desktop: get_or_intern_atom(&conn, b"_NET_CURRENT_DESKTOP"),
         net_wm_name: get_or_intern_atom(&conn, b"_NET_WM_NAME"),
         net_wm_pid: get_or_intern_atom(&conn, b"_NET_WM_PID"),
         utf8_string: get_or_intern_atom(&conn, b"UTF8_STRING"),
@@ -60,18 +94,32 @@ where
     conn.flush()
         .map_err(|e| Error::WMError(X11Error::ConnectionError(e)))?;
 
-    // Remember last active window, so we don't double send events!
-    let mut last_active_window: Window = 0;
+    // Remember last active window (and workspace), so we don't double send events!
+    let mut last_active_window = ActiveWindowTracker::default();
 
     // Send initial window
     send_active_window_information(&cb, &conn, root, &atoms, &mut last_active_window)?;
 
     loop {
-        let event = conn
-            .wait_for_event()
-            .map_err(|e| Error::WMError(X11Error::ConnectionError(e)))?;
-        if let Event::PropertyNotify(e) = event {
-            if e.atom == atoms.net_active_window {
+        let event = match poll_interval {
+            None => Some(
+                conn.wait_for_event()
+                    .map_err(|e| Error::WMError(X11Error::ConnectionError(e)))?,
+            ),
+            Some(poll_interval) => {
+                let event = conn
+                    .poll_for_event()
+                    .map_err(|e| Error::WMError(X11Error::ConnectionError(e)))?;
+                if event.is_none() {
+                    // No pending events, check the active window after waiting
+                    std::thread::sleep(poll_interval);
+                    send_active_window_information(&cb, &conn, root, &atoms, &mut last_active_window)?;
+                }
+                event
+            }
+        };
+        if let Some(Event::PropertyNotify(e)) = event {
+            if e.atom == atoms.net_active_window || e.atom == atoms.net_current_desktop {
                 // Grab the server
                 // conn.grab_server().map_err(|e| Error::WMError(X11Error::ConnectionError(e)))?;
                 send_active_window_information(&cb, &conn, root, &atoms, &mut last_active_window)?;
@@ -85,7 +133,7 @@ fn send_active_window_information<F>(
     conn: &RustConnection,
     root: Window,
     atoms: &Atoms,
-    last_active_window: &mut Window,
+    last_active_window: &mut ActiveWindowTracker,
 ) -> Result<(), Error>
 where
     F: Fn(WindowInformation),
@@ -99,10 +147,11 @@ where
         Some(x) => x,
     };
 
-    if *last_active_window == active_window_data.window {
+    let workspace = get_current_desktop(&conn, root, &atoms);
+
+    if !last_active_window.update(active_window_data.window, workspace) {
         return Ok(()); // Already known focused!
     }
-    *last_active_window = active_window_data.window;
 
     // Ungrap the server
     // conn.ungrab_server().map_err(|e| Error::WMError(X11Error::ConnectionError(e)))?;
@@ -110,6 +159,7 @@ where
         title: active_window_data.window_name,
         executable: active_window_data.command,
         class_name: active_window_data.class,
+        workspace,
     });
     Ok(())
 }
@@ -154,6 +204,18 @@ fn get_active_window_data(
     })
 }
 
+/// Get the current virtual desktop from the root window (`_NET_CURRENT_DESKTOP`).
+///
+/// Returns None, if the window manager does not support it.
+fn get_current_desktop(conn: &RustConnection, root: Window, atoms: &Atoms) -> Option<u32> {
+    let desktop = get_property(conn, root, atoms.net_current_desktop, atoms.cardinal)?;
+    if desktop.format == 32 && desktop.length == 1 {
+        desktop.value32()?.next()
+    } else {
+        None
+    }
+}
+
 fn get_property(
     conn: &RustConnection,
     window: Window,
@@ -243,3 +305,50 @@ fn parse_wm_class(property: &GetPropertyReply) -> (String, String) {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_window_is_new() {
+        // Setup
+        let mut tracker = ActiveWindowTracker::default();
+
+        // Act
+        let is_new = tracker.update(0, None);
+
+        // Test
+        assert!(is_new);
+    }
+
+    #[test]
+    fn same_window_polled_again_is_not_new() {
+        // Setup
+        let mut tracker = ActiveWindowTracker::default();
+        tracker.update(42, Some(1));
+
+        // Act
+        let is_new = tracker.update(42, Some(1));
+
+        // Test
+        assert!(!is_new);
+    }
+
+    #[test]
+    fn other_window_or_workspace_is_new() {
+        // Setup
+        let mut tracker = ActiveWindowTracker::default();
+        tracker.update(42, Some(1));
+
+        // Act
+        let other_workspace = tracker.update(42, Some(2));
+        let other_window = tracker.update(43, Some(2));
+        let back_to_first = tracker.update(42, Some(1));
+
+        // Test
+        assert!(other_workspace);
+        assert!(other_window);
+        assert!(back_to_first);
+    }
+}
@@ -11,9 +11,11 @@ mod error;
 pub use error::*;
 
 /// Information about a window just getting into foreground
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct WindowInformation {
     pub title: String,
     pub executable: String,
     pub class_name: String,
+    /// The current virtual desktop (workspace), if the platform reports it
+    pub workspace: Option<u32>,
 }
@@ -76,7 +76,13 @@ fn get_window_executable_name(hwnd: &HWND) -> Result<String, Error> {
 /// # Arguments
 ///
 /// cb - The Callback function to be called when a new window gets focus.
-pub fn foreground_window_observer<F>(cb: F) -> Result<(), Error>
+/// Observe the foreground window, calling the callback whenever it changes.
+///
+/// # Arguments
+///
+/// cb - Called with the new foreground window.
+/// poll_interval - Not used, the event hook of windows reports every change of the foreground window.
+pub fn foreground_window_observer<F>(cb: F, _poll_interval: Option<std::time::Duration>) -> Result<(), Error>
 where
     F: Fn(WindowInformation),
     F: 'static,
@@ -93,6 +99,7 @@ where
                 title,
                 executable,
                 class_name,
+                workspace: None,
             });
         }));
 
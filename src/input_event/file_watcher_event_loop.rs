@@ -0,0 +1,54 @@
+use crate::InputEvent;
+use log::warn;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::thread;
+
+/// Starts a thread watching the given files and sending an event
+/// via the [sender] object, whenever one of them changes.
+///
+/// The parent directories are watched (and not the files themselves), so that
+/// files replaced by editors (instead of being written in place) are still noticed.
+pub fn run_file_watcher_thread(
+    files: Vec<PathBuf>,
+    sender: std::sync::mpsc::Sender<InputEvent>,
+) -> Result<(), notify::Error> {
+    // Resolve all paths, because notify reports absolute paths
+    let files: Vec<PathBuf> = files
+        .iter()
+        .filter_map(|f| std::fs::canonicalize(f).ok())
+        .collect();
+
+    let (notify_sender, notify_receiver) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(notify_sender)?;
+    let mut watched_dirs: Vec<PathBuf> = Vec::new();
+    for file in &files {
+        if let Some(dir) = file.parent() {
+            if !watched_dirs.iter().any(|d| d == dir) {
+                watcher.watch(dir, RecursiveMode::NonRecursive)?;
+                watched_dirs.push(dir.to_path_buf());
+            }
+        }
+    }
+
+    let _watcher_thread = thread::spawn(move || {
+        // The watcher must live as long as the thread
+        let _watcher = watcher;
+        for result in notify_receiver {
+            match result {
+                Ok(event) => {
+                    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                        continue;
+                    }
+                    for path in event.paths {
+                        if files.contains(&path) {
+                            sender.send(InputEvent::FileChanged(path)).unwrap();
+                        }
+                    }
+                }
+                Err(e) => warn!("error watching image files: {:?}", e),
+            }
+        }
+    });
+    Ok(())
+}
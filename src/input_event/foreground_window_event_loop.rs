@@ -1,17 +1,140 @@
-use crate::foreground_window::foreground_window_observer;
+use crate::foreground_window::{foreground_window_observer, WindowInformation};
 use crate::InputEvent;
+use std::cell::RefCell;
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// Suppresses identical foreground window events received in quick succession.
+///
+/// Some apps refocus their own windows rapidly, and the Windows backend reports every one of them.
+struct WindowEventDedup {
+    /// Identical events received within this duration after the previous one are suppressed
+    window: Duration,
+    last: Option<(WindowInformation, Instant)>,
+}
+
+impl WindowEventDedup {
+    fn new(window: Duration) -> WindowEventDedup {
+        WindowEventDedup { window, last: None }
+    }
+
+    /// Remember a received window event.
+    ///
+    /// # Arguments
+    ///
+    /// info - The information of the event.
+    /// now - The time the event was received.
+    ///
+    /// # Return
+    ///
+    /// true, if the event should be sent (it differs from the previous one, or came later than the window).
+    fn update(&mut self, info: &WindowInformation, now: Instant) -> bool {
+        let is_duplicate = match &self.last {
+            Some((last_info, last_time)) => {
+                last_info == info && now.saturating_duration_since(*last_time) < self.window
+            }
+            None => false,
+        };
+        self.last = Some((info.clone(), now));
+        !is_duplicate
+    }
+}
 
 /// Starts a thread getting input events about the forground window
 /// and sending them via the [sender] object.
+///
+/// If a [poll_interval] is given, the foreground window is also checked in that interval.
+/// Events identical to the previous one are not sent, if they are received within the [dedup_window].
 pub fn run_foreground_window_event_loop_thread(
     sender: std::sync::mpsc::Sender<InputEvent>,
+    poll_interval: Option<Duration>,
+    dedup_window: Duration,
 ) -> Result<(), crate::foreground_window::Error> {
     let _wm_thread = thread::spawn(move || {
-        foreground_window_observer(move |e| {
-            sender.send(InputEvent::ForegroundWindow(e)).unwrap();
-        })
+        let dedup = RefCell::new(WindowEventDedup::new(dedup_window));
+        foreground_window_observer(
+            move |e| {
+                if dedup.borrow_mut().update(&e, Instant::now()) {
+                    sender.send(InputEvent::ForegroundWindow(e)).unwrap();
+                }
+            },
+            poll_interval,
+        )
         .unwrap();
     });
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Window information with the given title
+    fn window(title: &str) -> WindowInformation {
+        WindowInformation {
+            title: String::from(title),
+            executable: String::from("app"),
+            class_name: String::from("app_class"),
+            workspace: None,
+        }
+    }
+
+    #[test]
+    fn identical_event_within_the_window_is_suppressed() {
+        // Setup
+        let mut dedup = WindowEventDedup::new(Duration::from_millis(100));
+        let start = Instant::now();
+        let first = dedup.update(&window("editor"), start);
+
+        // Act
+        let second = dedup.update(&window("editor"), start + Duration::from_millis(50));
+
+        // Test
+        assert!(first);
+        assert!(!second);
+    }
+
+    #[test]
+    fn distinct_event_within_the_window_is_sent() {
+        // Setup
+        let mut dedup = WindowEventDedup::new(Duration::from_millis(100));
+        let start = Instant::now();
+        dedup.update(&window("editor"), start);
+
+        // Act
+        let other = dedup.update(&window("browser"), start + Duration::from_millis(10));
+        let back = dedup.update(&window("editor"), start + Duration::from_millis(20));
+
+        // Test
+        assert!(other);
+        assert!(back);
+    }
+
+    #[test]
+    fn identical_event_after_the_window_is_sent() {
+        // Setup
+        let mut dedup = WindowEventDedup::new(Duration::from_millis(100));
+        let start = Instant::now();
+        dedup.update(&window("editor"), start);
+
+        // Act
+        let later = dedup.update(&window("editor"), start + Duration::from_millis(150));
+
+        // Test
+        assert!(later);
+    }
+
+    #[test]
+    fn zero_window_sends_every_event() {
+        // Setup
+        let mut dedup = WindowEventDedup::new(Duration::ZERO);
+        let start = Instant::now();
+        dedup.update(&window("editor"), start);
+
+        // Act
+        let again = dedup.update(&window("editor"), start);
+
+        // Test
+        assert!(again);
+    }
+}
@@ -1,13 +1,32 @@
+mod file_watcher_event_loop;
 mod foreground_window_event_loop;
+mod osc_event_loop;
+mod shutdown_event_loop;
 mod stream_deck_event_loop;
 
 use crate::foreground_window::WindowInformation;
+pub use file_watcher_event_loop::*;
 pub use foreground_window_event_loop::*;
+pub use osc_event_loop::*;
+pub use shutdown_event_loop::*;
 pub use stream_deck_event_loop::*;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum InputEvent {
     ButtonDownEvent(u32),
     ButtonUpEvent(u32),
+    /// A touch point next to the buttons (of the Stream Deck Neo) was touched
+    TouchPointDownEvent(u32),
+    TouchPointUpEvent(u32),
     ForegroundWindow(WindowInformation),
+    FileChanged(PathBuf),
+    /// Load the page with the given name (requested via OSC)
+    LoadPage(String),
+    /// Unload the page with the given name (requested via OSC)
+    UnloadPage(String),
+    /// Faces drawn in the background replaced their loading placeholders
+    FacesLoaded,
+    /// The program should exit (for example after Ctrl-C)
+    Shutdown,
 }
@@ -0,0 +1,363 @@
+use crate::InputEvent;
+use log::warn;
+use std::net::UdpSocket;
+use std::thread;
+
+/// Largest OSC datagram, that is received.
+const MAX_DATAGRAM_SIZE: usize = 65536;
+
+/// Start of the content of an OSC bundle.
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+
+/// Argument of an OSC message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscType {
+    Int(i32),
+    Float(f32),
+    String(String),
+    Blob(Vec<u8>),
+}
+
+/// An OSC message, the address it is send to and its arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub addr: String,
+    pub args: Vec<OscType>,
+}
+
+/// An OSC packet, either a single message or a bundle of packets.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscPacket {
+    Message(OscMessage),
+    Bundle(Vec<OscPacket>),
+}
+
+/// Starts a thread receiving OSC messages on the given address and sending the
+/// resulting events via the [sender] object.
+///
+/// # Arguments
+///
+/// address - Address (like "127.0.0.1:9000") of the UDP socket, the messages are received on.
+/// sender - Sender of the input events.
+///
+/// # Return
+///
+/// An error if the socket can not be bound.
+pub fn run_osc_event_loop_thread(
+    address: &str,
+    sender: std::sync::mpsc::Sender<InputEvent>,
+) -> Result<(), std::io::Error> {
+    let socket = UdpSocket::bind(address)?;
+    let _osc_thread = thread::spawn(move || {
+        let mut buffer = [0u8; MAX_DATAGRAM_SIZE];
+        loop {
+            let size = match socket.recv_from(&mut buffer) {
+                Ok((size, _)) => size,
+                Err(e) => {
+                    warn!("unable to receive osc message: {}", e);
+                    continue;
+                }
+            };
+            match input_events_from_datagram(&buffer[..size]) {
+                Ok(events) => {
+                    for event in events {
+                        sender.send(event).unwrap();
+                    }
+                }
+                Err(e) => warn!("ignoring osc message: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Parse an OSC datagram and convert the messages in it to input events.
+///
+/// # Arguments
+///
+/// datagram - The received datagram, containing a message or a bundle of messages.
+///
+/// # Return
+///
+/// The events of the messages (in the order of the messages), or an error if the datagram can
+/// not be parsed or contains an unknown message.
+pub fn input_events_from_datagram(datagram: &[u8]) -> Result<Vec<InputEvent>, String> {
+    let packet = decode_packet(datagram).map_err(|e| format!("invalid osc packet: {}", e))?;
+    let mut events = Vec::new();
+    add_input_events(&packet, &mut events)?;
+    Ok(events)
+}
+
+/// Convert the messages of a packet (and of nested bundles) to input events.
+fn add_input_events(packet: &OscPacket, events: &mut Vec<InputEvent>) -> Result<(), String> {
+    match packet {
+        OscPacket::Message(message) => events.push(input_event_from_message(message)?),
+        OscPacket::Bundle(content) => {
+            for packet in content {
+                add_input_events(packet, events)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode an OSC 1.0 packet.
+///
+/// Messages may have int (`i`), float (`f`), string (`s`) and blob (`b`) arguments, the time tag
+/// of bundles is ignored.
+///
+/// # Arguments
+///
+/// data - The bytes of the packet.
+///
+/// # Return
+///
+/// The packet, or an error if the bytes are not a valid packet.
+pub fn decode_packet(data: &[u8]) -> Result<OscPacket, String> {
+    if data.starts_with(BUNDLE_TAG) {
+        // skip the tag and the 8 byte time tag
+        let mut rest = data
+            .get(BUNDLE_TAG.len() + 8..)
+            .ok_or("bundle without time tag")?;
+        let mut content = Vec::new();
+        while !rest.is_empty() {
+            let (size, after_size) = read_i32(rest)?;
+            let size = usize::try_from(size).map_err(|_| "negative bundle element size")?;
+            if after_size.len() < size {
+                return Err(String::from("bundle element exceeds the bundle"));
+            }
+            content.push(decode_packet(&after_size[..size])?);
+            rest = &after_size[size..];
+        }
+        return Ok(OscPacket::Bundle(content));
+    }
+
+    let (addr, rest) = read_string(data)?;
+    if !addr.starts_with('/') {
+        return Err(format!("invalid address {}", addr));
+    }
+    // Type tags are optional in old implementations, no tags means no arguments
+    if rest.is_empty() {
+        return Ok(OscPacket::Message(OscMessage { addr, args: vec![] }));
+    }
+    let (type_tags, mut rest) = read_string(rest)?;
+    let type_tags = type_tags
+        .strip_prefix(',')
+        .ok_or_else(|| format!("invalid type tags {}", type_tags))?;
+    let mut args = Vec::new();
+    for tag in type_tags.chars() {
+        let (arg, after_arg) = match tag {
+            'i' => read_i32(rest).map(|(v, r)| (OscType::Int(v), r))?,
+            'f' => read_i32(rest).map(|(v, r)| (OscType::Float(f32::from_bits(v as u32)), r))?,
+            's' => read_string(rest).map(|(v, r)| (OscType::String(v), r))?,
+            'b' => read_blob(rest).map(|(v, r)| (OscType::Blob(v), r))?,
+            _ => return Err(format!("unsupported argument type {}", tag)),
+        };
+        args.push(arg);
+        rest = after_arg;
+    }
+    Ok(OscPacket::Message(OscMessage { addr, args }))
+}
+
+/// Number of bytes a field of the given length occupies, OSC pads all fields to 4 bytes.
+fn padded_len(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+/// Read a big endian int from the start of the data, return it and the rest of the data.
+fn read_i32(data: &[u8]) -> Result<(i32, &[u8]), String> {
+    let bytes = data.get(..4).ok_or("truncated int")?;
+    Ok((
+        i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        &data[4..],
+    ))
+}
+
+/// Read a null terminated and padded string from the start of the data, return it and the rest
+/// of the data.
+fn read_string(data: &[u8]) -> Result<(String, &[u8]), String> {
+    let len = data
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or("unterminated string")?;
+    let string = std::str::from_utf8(&data[..len]).map_err(|e| e.to_string())?;
+    let rest = data.get(padded_len(len + 1)..).ok_or("truncated string")?;
+    Ok((String::from(string), rest))
+}
+
+/// Read a size prefixed and padded blob from the start of the data, return it and the rest of
+/// the data.
+fn read_blob(data: &[u8]) -> Result<(Vec<u8>, &[u8]), String> {
+    let (len, rest) = read_i32(data)?;
+    let len = usize::try_from(len).map_err(|_| "negative blob size")?;
+    let blob = rest.get(..len).ok_or("truncated blob")?;
+    let rest = rest.get(padded_len(len)..).ok_or("truncated blob")?;
+    Ok((blob.to_vec(), rest))
+}
+
+/// Convert an OSC message to the input event it stands for.
+///
+/// The known addresses are:
+/// * `/deck/page/load` and `/deck/page/unload` with the name of the page as string.
+/// * `/deck/button/down` and `/deck/button/up` with the index of the button as int.
+///
+/// # Return
+///
+/// The event, or an error if the address is unknown or the arguments do not match it.
+pub fn input_event_from_message(message: &OscMessage) -> Result<InputEvent, String> {
+    match (message.addr.as_str(), message.args.as_slice()) {
+        ("/deck/page/load", [OscType::String(page_name)]) => {
+            Ok(InputEvent::LoadPage(page_name.clone()))
+        }
+        ("/deck/page/unload", [OscType::String(page_name)]) => {
+            Ok(InputEvent::UnloadPage(page_name.clone()))
+        }
+        ("/deck/button/down", [OscType::Int(button_id)]) if *button_id >= 0 => {
+            Ok(InputEvent::ButtonDownEvent(*button_id as u32))
+        }
+        ("/deck/button/up", [OscType::Int(button_id)]) if *button_id >= 0 => {
+            Ok(InputEvent::ButtonUpEvent(*button_id as u32))
+        }
+        (addr, args) => Err(format!("unknown osc message {} {:?}", addr, args)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An OSC message with the given address and arguments
+    fn message(addr: &str, args: Vec<OscType>) -> OscMessage {
+        OscMessage {
+            addr: String::from(addr),
+            args,
+        }
+    }
+
+    #[test]
+    fn page_load_message_loads_the_page() {
+        // Setup
+        let message = message(
+            "/deck/page/load",
+            vec![OscType::String(String::from("media"))],
+        );
+
+        // Act
+        let event = input_event_from_message(&message);
+
+        // Test
+        assert!(matches!(event, Ok(InputEvent::LoadPage(name)) if name == "media"));
+    }
+
+    #[test]
+    fn page_unload_message_unloads_the_page() {
+        // Setup
+        let message = message(
+            "/deck/page/unload",
+            vec![OscType::String(String::from("media"))],
+        );
+
+        // Act
+        let event = input_event_from_message(&message);
+
+        // Test
+        assert!(matches!(event, Ok(InputEvent::UnloadPage(name)) if name == "media"));
+    }
+
+    #[test]
+    fn button_messages_press_and_release_the_button() {
+        // Act
+        let down = input_event_from_message(&message("/deck/button/down", vec![OscType::Int(3)]));
+        let up = input_event_from_message(&message("/deck/button/up", vec![OscType::Int(3)]));
+
+        // Test
+        assert!(matches!(down, Ok(InputEvent::ButtonDownEvent(3))));
+        assert!(matches!(up, Ok(InputEvent::ButtonUpEvent(3))));
+    }
+
+    #[test]
+    fn messages_with_wrong_arguments_are_rejected() {
+        // Act
+        let missing = input_event_from_message(&message("/deck/page/load", vec![]));
+        let negative =
+            input_event_from_message(&message("/deck/button/down", vec![OscType::Int(-1)]));
+        let unknown = input_event_from_message(&message("/deck/other", vec![]));
+
+        // Test
+        assert!(missing.is_err());
+        assert!(negative.is_err());
+        assert!(unknown.is_err());
+    }
+
+    /// The bytes of a string in an OSC packet
+    fn osc_string(value: &str) -> Vec<u8> {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.resize(padded_len(value.len() + 1), 0);
+        bytes
+    }
+
+    #[test]
+    fn messages_are_decoded() {
+        // Setup
+        let mut datagram = osc_string("/deck/test");
+        datagram.extend(osc_string(",isf"));
+        datagram.extend(7i32.to_be_bytes());
+        datagram.extend(osc_string("media"));
+        datagram.extend(1.5f32.to_be_bytes());
+
+        // Act
+        let packet = decode_packet(&datagram);
+
+        // Test
+        assert_eq!(
+            packet,
+            Ok(OscPacket::Message(message(
+                "/deck/test",
+                vec![
+                    OscType::Int(7),
+                    OscType::String(String::from("media")),
+                    OscType::Float(1.5)
+                ]
+            )))
+        );
+    }
+
+    #[test]
+    fn truncated_messages_are_rejected() {
+        // Setup
+        let mut datagram = osc_string("/deck/button/down");
+        datagram.extend(osc_string(",i"));
+        datagram.extend([0u8, 0]);
+
+        // Act
+        let packet = decode_packet(&datagram);
+
+        // Test
+        assert!(packet.is_err());
+    }
+
+    #[test]
+    fn bundles_are_converted_in_order() {
+        // Setup
+        let mut load = osc_string("/deck/page/load");
+        load.extend(osc_string(",s"));
+        load.extend(osc_string("media"));
+        let mut down = osc_string("/deck/button/down");
+        down.extend(osc_string(",i"));
+        down.extend(0i32.to_be_bytes());
+        let mut datagram = osc_string("#bundle");
+        datagram.extend([0u8, 0, 0, 0, 0, 0, 0, 1]);
+        for element in [load, down] {
+            datagram.extend((element.len() as i32).to_be_bytes());
+            datagram.extend(element);
+        }
+
+        // Act
+        let events = input_events_from_datagram(&datagram).unwrap();
+
+        // Test
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], InputEvent::LoadPage(name) if name == "media"));
+        assert!(matches!(events[1], InputEvent::ButtonDownEvent(0)));
+    }
+}
@@ -0,0 +1,12 @@
+use crate::InputEvent;
+
+/// Sends a [InputEvent::Shutdown] via the [sender] object,
+/// when the program is interrupted (for example with Ctrl-C).
+pub fn run_shutdown_handler(
+    sender: std::sync::mpsc::Sender<InputEvent>,
+) -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(move || {
+        // The main loop may already be gone, nothing left to shut down then
+        let _ = sender.send(InputEvent::Shutdown);
+    })
+}
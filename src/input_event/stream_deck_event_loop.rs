@@ -1,25 +1,274 @@
 use crate::InputEvent;
+use std::fmt::Debug;
 use std::sync::Arc;
 use std::thread;
 use streamdeck_hid_rs::{ButtonState, StreamDeckDevice};
 
+/// Callback, that is called with the id and the new state of a button.
+pub type ButtonEventCallback = Box<dyn Fn(u32, ButtonState) + Send>;
+
+/// Source of button events, usually the streamdeck device.
+///
+/// Allows the input loop to be driven by something else than the hardware (for example in tests).
+pub trait ButtonEventSource: Send + Sync + 'static {
+    type Error: Debug;
+
+    /// Call the callback for every button event.
+    ///
+    /// Blocks until the source is closed or fails.
+    ///
+    /// # Arguments
+    ///
+    /// callback - Called with the id and the new state of the button.
+    fn on_button_events(&self, callback: ButtonEventCallback) -> Result<(), Self::Error>;
+}
+
+impl ButtonEventSource for StreamDeckDevice<hidapi::HidApi> {
+    type Error = streamdeck_hid_rs::Error;
+
+    fn on_button_events(&self, callback: ButtonEventCallback) -> Result<(), Self::Error> {
+        StreamDeckDevice::on_button_events(self, move |event| {
+            callback(event.button_id, event.state)
+        })
+    }
+}
+
 /// Starts a thread getting input events from the device
 /// and sending them via the [sender] object.
-pub fn run_input_loop_thread(
-    device: Arc<StreamDeckDevice<hidapi::HidApi>>,
+///
+/// Ids after the buttons are touch points (like the two touch points of the Stream Deck Neo),
+/// they are sent as touch point events, counted from 0.
+///
+/// # Arguments
+///
+/// source - The source of the button events.
+/// sender - Sender for the input events.
+/// num_buttons - Number of buttons of the device.
+pub fn run_input_loop_thread<S: ButtonEventSource>(
+    source: Arc<S>,
     sender: std::sync::mpsc::Sender<InputEvent>,
-) -> Result<(), streamdeck_hid_rs::Error> {
+    num_buttons: u32,
+) -> Result<(), S::Error> {
     let _button_thread = thread::spawn(move || {
-        device
-            .on_button_events(move |event| match event.state {
-                ButtonState::Down => sender
-                    .send(InputEvent::ButtonDownEvent(event.button_id))
-                    .unwrap(),
-                ButtonState::Up => sender
-                    .send(InputEvent::ButtonUpEvent(event.button_id))
-                    .unwrap(),
-            })
+        source
+            .on_button_events(Box::new(move |id, state| {
+                let event = match (id.checked_sub(num_buttons), state) {
+                    (None, ButtonState::Down) => InputEvent::ButtonDownEvent(id),
+                    (None, ButtonState::Up) => InputEvent::ButtonUpEvent(id),
+                    (Some(touch_point), ButtonState::Down) => {
+                        InputEvent::TouchPointDownEvent(touch_point)
+                    }
+                    (Some(touch_point), ButtonState::Up) => InputEvent::TouchPointUpEvent(touch_point),
+                };
+                sender.send(event).unwrap()
+            }))
             .unwrap();
     });
     Ok(())
 }
+
+/// Button event source for tests, the events are pushed programmatically.
+#[cfg(test)]
+pub struct MockButtonEventSource {
+    sender: std::sync::Mutex<Option<std::sync::mpsc::Sender<(u32, ButtonState)>>>,
+    receiver: std::sync::Mutex<std::sync::mpsc::Receiver<(u32, ButtonState)>>,
+}
+
+#[cfg(test)]
+impl MockButtonEventSource {
+    pub fn new() -> MockButtonEventSource {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        MockButtonEventSource {
+            sender: std::sync::Mutex::new(Some(sender)),
+            receiver: std::sync::Mutex::new(receiver),
+        }
+    }
+
+    /// Push a button down event.
+    pub fn press(&self, button_id: u32) {
+        self.push(button_id, ButtonState::Down);
+    }
+
+    /// Push a button up event.
+    pub fn release(&self, button_id: u32) {
+        self.push(button_id, ButtonState::Up);
+    }
+
+    /// Close the source, [on_button_events] returns after delivering the pushed events.
+    pub fn close(&self) {
+        self.sender.lock().unwrap().take();
+    }
+
+    fn push(&self, button_id: u32, state: ButtonState) {
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            sender.send((button_id, state)).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+impl ButtonEventSource for MockButtonEventSource {
+    type Error = ();
+
+    fn on_button_events(&self, callback: ButtonEventCallback) -> Result<(), Self::Error> {
+        for (button_id, state) in self.receiver.lock().unwrap().iter() {
+            callback(button_id, state);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AppState;
+    use std::time::Duration;
+    use streamdeck_hid_rs::StreamDeckType;
+
+    /// Config with a single page, that has a button with down and up handlers at index 4.
+    fn get_config() -> crate::config::Config {
+        serde_yaml::from_str(
+            "\
+pages:
+- name: page1
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button:
+      down_handler:
+        code: down
+      up_handler:
+        code: up
+default_pages:
+- page1
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn mock_events_are_sent_as_input_events() {
+        // Setup
+        let source = Arc::new(MockButtonEventSource::new());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        run_input_loop_thread(source.clone(), sender, 15).unwrap();
+
+        // Act
+        source.press(3);
+        source.release(3);
+        source.close();
+
+        // Test
+        let events: Vec<InputEvent> = receiver.iter().collect();
+        assert!(matches!(
+            events.as_slice(),
+            [InputEvent::ButtonDownEvent(3), InputEvent::ButtonUpEvent(3)]
+        ));
+    }
+
+    #[test]
+    fn mock_presses_dispatch_the_button_handlers() {
+        // Setup
+        let mut app_state = AppState::from_config(&StreamDeckType::Orig, &get_config()).unwrap();
+        let source = Arc::new(MockButtonEventSource::new());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        run_input_loop_thread(source.clone(), sender, 15).unwrap();
+
+        // Act
+        source.press(4);
+        source.release(4);
+        let mut scripts = Vec::new();
+        for _ in 0..2 {
+            let handlers = match receiver.recv_timeout(Duration::from_secs(5)).unwrap() {
+                InputEvent::ButtonDownEvent(id) => app_state.on_button_pressed(id as usize),
+                InputEvent::ButtonUpEvent(id) => app_state.on_button_released(id as usize),
+                _ => Vec::new(),
+            };
+            scripts.extend(handlers.iter().map(|h| h.script.clone()));
+        }
+
+        // Test
+        assert_eq!(scripts, vec![String::from("down"), String::from("up")]);
+    }
+
+    #[test]
+    fn ids_after_the_buttons_are_touch_points() {
+        // Setup
+        let source = Arc::new(MockButtonEventSource::new());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        run_input_loop_thread(source.clone(), sender, 8).unwrap();
+
+        // Act
+        source.press(7);
+        source.press(9);
+        source.release(9);
+        source.close();
+
+        // Test
+        let events: Vec<InputEvent> = receiver.iter().collect();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                InputEvent::ButtonDownEvent(7),
+                InputEvent::TouchPointDownEvent(1),
+                InputEvent::TouchPointUpEvent(1)
+            ]
+        ));
+    }
+
+    #[test]
+    fn touch_events_dispatch_the_touch_point_handlers() {
+        // Setup
+        let mut config = get_config();
+        config.touch_points = Some(
+            serde_yaml::from_str(
+                "\
+- down_handler:
+    code: first down
+- down_handler:
+    code: second down
+  up_handler:
+    code: second up
+",
+            )
+            .unwrap(),
+        );
+        let app_state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let source = Arc::new(MockButtonEventSource::new());
+        let (sender, receiver) = std::sync::mpsc::channel();
+        run_input_loop_thread(source.clone(), sender, 15).unwrap();
+
+        // Act
+        source.press(16);
+        source.release(16);
+        let mut scripts = Vec::new();
+        for _ in 0..2 {
+            let handler = match receiver.recv_timeout(Duration::from_secs(5)).unwrap() {
+                InputEvent::TouchPointDownEvent(id) => app_state.on_touch_point_pressed(id as usize),
+                InputEvent::TouchPointUpEvent(id) => app_state.on_touch_point_released(id as usize),
+                _ => None,
+            };
+            scripts.extend(handler.map(|h| h.script.clone()));
+        }
+
+        // Test
+        assert_eq!(
+            scripts,
+            vec![String::from("second down"), String::from("second up")]
+        );
+    }
+
+    #[test]
+    fn more_than_two_touch_points_are_an_error() {
+        // Setup
+        let mut config = get_config();
+        config.touch_points = Some(serde_yaml::from_str("[{}, {}, {}]").unwrap());
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(result.is_err());
+    }
+}
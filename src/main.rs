@@ -1,19 +1,30 @@
 extern crate core;
 
 mod config;
+mod event_loop;
 mod foreground_window;
 mod input_event;
+mod metrics;
+mod render;
 mod script_engine;
 mod state;
 
 use crate::input_event::{
-    run_foreground_window_event_loop_thread, run_input_loop_thread, InputEvent,
+    run_file_watcher_thread, run_foreground_window_event_loop_thread, run_input_loop_thread,
+    run_osc_event_loop_thread, run_shutdown_handler, InputEvent,
 };
-use crate::state::AppState;
+use crate::render::RenderTarget;
+use crate::state::{AppState, EventContext};
 use clap::Parser;
-use log::{debug, info};
+use log::{debug, error, info, warn};
+use std::error::Error;
 use std::fs::File;
+use std::sync::mpsc::{RecvTimeoutError, TryRecvError};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Identical foreground window events within this many milliseconds are ignored, if not configured.
+const DEFAULT_WINDOW_DEDUP_MS: u64 = 100;
 
 /// Command line arguments.
 ///
@@ -24,29 +35,80 @@ struct Cli {
     pub config: std::path::PathBuf,
 }
 
-fn main() {
-    // Start the logger
-    simple_logger::SimpleLogger::new().env().init().unwrap();
-
+fn main() -> Result<(), Box<dyn Error>> {
     // Parse input arguments
     let args = Cli::parse();
 
-    // Load the config
-    let config: config::Config =
-        { serde_yaml::from_reader(File::open(&args.config).unwrap()).unwrap() };
+    // Load the config, errors are handled when the device is known (safe mode)
+    let config: Result<config::Config, String> = File::open(&args.config)
+        .map_err(|e| format!("{}: {}", args.config.display(), e))
+        .and_then(|file| {
+            serde_yaml::from_reader(file).map_err(|e| format!("{}: {}", args.config.display(), e))
+        });
 
-    // Detect and open the streamdeck device!
-    let hid = hidapi::HidApi::new().unwrap();
-    let device = Arc::new(streamdeck_hid_rs::StreamDeckDevice::open_first_device(&hid).unwrap());
-    device.reset().unwrap();
+    // Start the logger, the RUST_LOG environment variable takes precedence over the configured level
+    let mut logger = simple_logger::SimpleLogger::new();
+    let mut log_level_error = None;
+    if let Some(log_level) = config.as_ref().ok().and_then(|config| config.log_level.as_ref()) {
+        match config::parse_log_level(log_level) {
+            Ok(level) => logger = logger.with_level(level),
+            Err(e) => log_level_error = Some(e),
+        }
+    }
+    logger.env().init()?;
+    if let Some(e) = log_level_error {
+        error!("invalid log_level: {}", e);
+    }
 
-    // Initialize the app state
     // Change to the directory of the config
-    let config_dir = args.config.as_path().parent().unwrap();
-    std::env::set_current_dir(&config_dir).unwrap();
-    let app_state = Arc::new(RwLock::new(
-        AppState::from_config(&device.device_type, &config).unwrap(),
-    ));
+    let config_dir = args
+        .config
+        .as_path()
+        .parent()
+        .ok_or("config file has no parent directory")?;
+    std::env::set_current_dir(&config_dir)?;
+
+    // Detect and open the streamdeck device!
+    let hid = hidapi::HidApi::new()?;
+    let device = match streamdeck_hid_rs::StreamDeckDevice::open_first_device(&hid) {
+        Ok(device) => Arc::new(device),
+        Err(e) => {
+            // Without a device, we can only check the config for the configured device type
+            let config = config?;
+            let device_type_name = config
+                .device_type
+                .as_ref()
+                .ok_or_else(|| format!("unable to open streamdeck device: {:?}", e))?;
+            let device_type = state::device_type_from_name(device_type_name)?;
+            let app_state = AppState::from_config(&device_type, &config)?;
+            for name in app_state.unreferenced_named_buttons() {
+                warn!("named button {} is not used by any page", name);
+            }
+            info!(
+                "no device connected, config checked for device type {}",
+                device_type_name
+            );
+            return Ok(());
+        }
+    };
+
+    // Initialize the app state, with a broken config the safe mode shows an error on the device
+    let (config, app_state) = state::app_state_or_safe_mode(&device.device_type, config);
+
+    // Without the reset, the first render overwrites the images of the previous run
+    if config.reset_on_start() {
+        RenderTarget::reset(device.as_ref())
+            .map_err(|e| format!("unable to reset streamdeck device: {}", e))?;
+    }
+    let app_state = Arc::new(RwLock::new(app_state));
+
+    // The faces are drawn for the connected device, warn if the config expects another one
+    if let Err(e) = state::check_device_type(&config.device_type, &device.device_type) {
+        warn!("config does not match the connected device: {}", e);
+    }
+    if let Err(e) = state::check_touch_points(&config.touch_points, &device.device_type) {
+        warn!("config does not match the connected device: {}", e);
+    }
 
     // Create the channels for communication
     let (sender, receiver): (
@@ -55,57 +117,148 @@ fn main() {
     ) = std::sync::mpsc::channel();
 
     // Run streamdeck input event thread
-    run_input_loop_thread(device.clone(), sender.clone()).unwrap();
+    run_input_loop_thread(
+        device.clone(),
+        sender.clone(),
+        device.device_type.total_num_buttons() as u32,
+    )
+    .map_err(|e| format!("unable to read streamdeck input: {:?}", e))?;
 
     // Run foreground window event thread
-    run_foreground_window_event_loop_thread(sender.clone()).unwrap();
+    run_foreground_window_event_loop_thread(
+        sender.clone(),
+        config.poll_interval_ms.map(Duration::from_millis),
+        Duration::from_millis(config.window_dedup_ms.unwrap_or(DEFAULT_WINDOW_DEDUP_MS)),
+    )?;
+
+    // Receive OSC messages
+    if let Some(osc_address) = &config.osc_address {
+        run_osc_event_loop_thread(osc_address, sender.clone())
+            .map_err(|e| format!("unable to receive osc messages on {}: {}", osc_address, e))?;
+    }
+
+    // Serve the metrics
+    if let Some(metrics_address) = &config.metrics_address {
+        let metrics = app_state.read().unwrap().metrics();
+        metrics::run_metrics_server_thread(metrics_address, metrics)
+            .map_err(|e| format!("unable to serve metrics on {}: {}", metrics_address, e))?;
+    }
+
+    // Exit the main loop cleanly on Ctrl-C
+    run_shutdown_handler(sender.clone())?;
+
+    // Run the image file watcher thread
+    if config.watch_images == Some(true) {
+        let image_files = app_state.read().unwrap().image_files();
+        run_file_watcher_thread(image_files, sender.clone())?;
+    }
 
     // The script engine!
-    let engine = crate::script_engine::PythonEngine::new(&app_state).unwrap();
+    let engine = crate::script_engine::PythonEngine::new(&app_state)?;
 
-    // Run init script
-    {
-        if let Some(init_handler) = app_state.read().unwrap().get_init_handler() {
-            debug!("running init script");
-            engine.run_event_handler(&init_handler).unwrap();
-        }
+    // Run init script (the lock of the state must not be held, while scripts run)
+    let init_handler = app_state.read().unwrap().get_init_handler();
+    if let Some(init_handler) = init_handler {
+        debug!("running init script");
+        engine.run_event_handler(&init_handler, &EventContext::default())?;
     }
+    // And the handlers of the default pages
+    event_loop::run_queued_handlers(&app_state, &engine)?;
+
+    // Pedals have no displays, only their handlers are used
+    let has_display = app_state.read().unwrap().has_display();
+    if !has_display {
+        info!("device has no display, faces are not rendered");
+    }
+
+    // Draw the faces of the other pages in the background, so that they load fast
+    let face_warmer = state::FaceWarmer::start(&app_state);
 
     // Receive events!
+    let mut render_budget = render::RenderBudget::new(config.max_faces_per_frame);
+    let image_flip = render::ImageFlip::from_config(&config.image_flip);
+    let mut render_rate_limit = render::RenderRateLimit::from_config(config.max_render_hz)?;
     loop {
-        let mut app_state = app_state.write().unwrap();
-        let faces = {
-            app_state
-                .set_rendered_and_get_rendering_faces()
+        // Remove expired toasts, before the buttons are rendered
+        let next_toast_expiry = {
+            let mut app_state = app_state.write().unwrap();
+            app_state.expire_toasts(Instant::now());
+            app_state.next_toast_expiry()
+        };
+
+        // Draw faces shown with a loading placeholder in the background, render them when they are done
+        let faces_sender = sender.clone();
+        state::load_pending_faces(&app_state, move || {
+            let _ = faces_sender.send(InputEvent::FacesLoaded);
+        });
+
+        // Changes within the minimal time between renders are collected and rendered together
+        let render_delay = render_rate_limit.delay(Instant::now());
+        let (failed_buttons, pending_buttons) = if has_display {
+            let mut app_state = app_state.write().unwrap();
+            let failed_buttons =
+                if render_delay.is_none() && !app_state.buttons_needing_rendering().is_empty() {
+                    render_rate_limit.rendered(Instant::now());
+                    render::render_faces(
+                        &mut app_state,
+                        &mut render_budget,
+                        &image_flip,
+                        device.as_ref(),
+                    )
+                } else {
+                    Vec::new()
+                };
+            (failed_buttons, app_state.buttons_needing_rendering())
+        } else {
+            (Vec::new(), Vec::new())
         };
-        for (button_id, face) in faces {
-            device.set_button_image(button_id, &face.face).unwrap();
-        }
 
         info!("Waiting for input events");
-        let e = receiver.recv().unwrap();
-        let handler = match e {
-            InputEvent::ButtonDownEvent(button_id) => app_state
-                .on_button_pressed(button_id as usize),
-            InputEvent::ButtonUpEvent(button_id) => app_state
-                .on_button_released(button_id as usize),
-            InputEvent::ForegroundWindow(info) => {
-                // So something
-                debug!(
-                    "new foreground window: title={}, executable={}, class_name={}",
-                    info.title, info.executable, info.class_name
-                );
-                app_state
-                    .on_foreground_window(&info)
-                    .unwrap();
-                None
+        let e = if !failed_buttons.is_empty() {
+            // Retry the failed buttons soon, even if no event occurs
+            match receiver.recv_timeout(Duration::from_millis(500)) {
+                Ok(e) => e,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(e) => return Err(e.into()),
+            }
+        } else if let Some(render_delay) = render_delay.filter(|_| !pending_buttons.is_empty()) {
+            // Render the collected changes, when the next render is allowed
+            match receiver.recv_timeout(render_delay) {
+                Ok(e) => e,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(e) => return Err(e.into()),
+            }
+        } else if !pending_buttons.is_empty() {
+            // Faces left over by the render budget, handle waiting events first
+            match receiver.try_recv() {
+                Ok(e) => e,
+                Err(TryRecvError::Empty) => continue,
+                Err(e) => return Err(e.into()),
+            }
+        } else if let Some(next_toast_expiry) = next_toast_expiry {
+            // Wake up, when the next toast expires
+            match receiver.recv_timeout(next_toast_expiry.saturating_duration_since(Instant::now())) {
+                Ok(e) => e,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(e) => return Err(e.into()),
             }
+        } else {
+            receiver.recv()?
         };
-
-        if let Some(event_handler) = handler {
-            engine
-                .run_event_handler(&event_handler)
-                .unwrap();
+        if let InputEvent::Shutdown = e {
+            break;
         }
+        event_loop::handle_input_event(&app_state, &engine, e)?;
+    }
+
+    face_warmer.stop();
+
+    // Run the shutdown script
+    let shutdown_handler = app_state.read().unwrap().get_shutdown_handler();
+    if let Some(shutdown_handler) = shutdown_handler {
+        debug!("running shutdown script");
+        engine.run_event_handler(&shutdown_handler, &EventContext::default())?;
     }
+    info!("shut down");
+    Ok(())
 }
@@ -0,0 +1,167 @@
+use log::warn;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Counters about the usage of the deck, exported in the Prometheus text format.
+#[derive(Default)]
+pub struct Metrics {
+    /// Number of presses by button id
+    button_presses: Mutex<BTreeMap<usize, u64>>,
+    page_loads: AtomicU64,
+    page_unloads: AtomicU64,
+    /// Number of faces uploaded to the device
+    faces_rendered: AtomicU64,
+    script_errors: AtomicU64,
+}
+
+impl Metrics {
+    pub fn button_pressed(&self, button_id: usize) {
+        *self
+            .button_presses
+            .lock()
+            .unwrap()
+            .entry(button_id)
+            .or_insert(0) += 1;
+    }
+
+    pub fn page_loaded(&self) {
+        self.page_loads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn page_unloaded(&self) {
+        self.page_unloads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn faces_rendered(&self, count: usize) {
+        self.faces_rendered.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn script_failed(&self) {
+        self.script_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The number of presses of a button.
+    #[cfg(test)]
+    pub fn button_presses(&self, button_id: usize) -> u64 {
+        self.button_presses
+            .lock()
+            .unwrap()
+            .get(&button_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The counters in the Prometheus text exposition format.
+    pub fn exposition(&self) -> String {
+        let mut result = String::new();
+        result.push_str("# HELP streamdeck_button_presses_total Presses of the buttons.\n");
+        result.push_str("# TYPE streamdeck_button_presses_total counter\n");
+        for (button_id, presses) in self.button_presses.lock().unwrap().iter() {
+            result.push_str(&format!(
+                "streamdeck_button_presses_total{{button=\"{}\"}} {}\n",
+                button_id, presses
+            ));
+        }
+        for (name, help, counter) in [
+            ("streamdeck_page_loads_total", "Pages loaded.", &self.page_loads),
+            ("streamdeck_page_unloads_total", "Pages unloaded.", &self.page_unloads),
+            (
+                "streamdeck_faces_rendered_total",
+                "Faces uploaded to the device.",
+                &self.faces_rendered,
+            ),
+            ("streamdeck_script_errors_total", "Failed scripts.", &self.script_errors),
+        ] {
+            result.push_str(&format!("# HELP {} {}\n", name, help));
+            result.push_str(&format!("# TYPE {} counter\n", name));
+            result.push_str(&format!("{} {}\n", name, counter.load(Ordering::Relaxed)));
+        }
+        result
+    }
+}
+
+/// Starts a thread serving the metrics via HTTP on the given address (at `/metrics`).
+///
+/// # Arguments
+///
+/// address - Address (like "127.0.0.1:9100") of the TCP socket, the metrics are served on.
+/// metrics - The metrics to serve.
+///
+/// # Return
+///
+/// An error if the socket can not be bound.
+pub fn run_metrics_server_thread(address: &str, metrics: Arc<Metrics>) -> Result<(), std::io::Error> {
+    let listener = TcpListener::bind(address)?;
+    let _metrics_thread = thread::spawn(move || {
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| respond(stream, &metrics));
+            if let Err(e) = result {
+                warn!("unable to serve metrics: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Answer a single HTTP request with the metrics.
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> Result<(), std::io::Error> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let response = match request_line.split_whitespace().nth(1) {
+        Some("/metrics") => {
+            let body = metrics.exposition();
+            format!(
+                "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+        }
+        _ => String::from("HTTP/1.0 404 Not Found\r\nContent-Length: 0\r\n\r\n"),
+    };
+    stream.write_all(response.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presses_are_counted_per_button() {
+        // Setup
+        let metrics = Metrics::default();
+
+        // Act
+        metrics.button_pressed(3);
+        metrics.button_pressed(3);
+        metrics.button_pressed(5);
+
+        // Test
+        assert_eq!(metrics.button_presses(3), 2);
+        assert_eq!(metrics.button_presses(5), 1);
+        assert_eq!(metrics.button_presses(0), 0);
+    }
+
+    #[test]
+    fn exposition_contains_all_counters() {
+        // Setup
+        let metrics = Metrics::default();
+        metrics.button_pressed(3);
+        metrics.page_loaded();
+        metrics.faces_rendered(15);
+
+        // Act
+        let exposition = metrics.exposition();
+
+        // Test
+        assert!(exposition.contains("# TYPE streamdeck_button_presses_total counter\n"));
+        assert!(exposition.contains("streamdeck_button_presses_total{button=\"3\"} 1\n"));
+        assert!(exposition.contains("streamdeck_page_loads_total 1\n"));
+        assert!(exposition.contains("streamdeck_page_unloads_total 0\n"));
+        assert!(exposition.contains("streamdeck_faces_rendered_total 15\n"));
+        assert!(exposition.contains("streamdeck_script_errors_total 0\n"));
+    }
+}
@@ -0,0 +1,565 @@
+use crate::config;
+use crate::state::AppState;
+use log::warn;
+use std::borrow::Cow;
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+use streamdeck_hid_rs::StreamDeckDevice;
+
+/// Target, the faces are rendered to, usually the streamdeck device.
+///
+/// Allows the rendering to be checked without the hardware (for example in tests).
+pub trait RenderTarget {
+    type Error: Debug;
+
+    /// Show an image on a button.
+    ///
+    /// # Arguments
+    ///
+    /// button_id - The id of the button.
+    /// image - The image, in the size of the button images of the device.
+    fn set_button_image(&self, button_id: u8, image: &image::RgbImage) -> Result<(), Self::Error>;
+
+    /// Clear all buttons.
+    fn reset(&self) -> Result<(), Self::Error>;
+}
+
+impl RenderTarget for StreamDeckDevice<hidapi::HidApi> {
+    type Error = String;
+
+    fn set_button_image(&self, button_id: u8, image: &image::RgbImage) -> Result<(), Self::Error> {
+        StreamDeckDevice::set_button_image(self, button_id, image)
+            .map(|_| ())
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    fn reset(&self) -> Result<(), Self::Error> {
+        StreamDeckDevice::reset(self).map_err(|e| format!("{:?}", e))
+    }
+}
+
+/// Render target recording the uploaded images, instead of showing them.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MockRenderTarget {
+    /// The uploaded images with the ids of their buttons, in the order of the uploads
+    pub uploads: std::cell::RefCell<Vec<(u8, image::RgbImage)>>,
+    /// Uploads to this button fail
+    pub failing_button: Option<u8>,
+}
+
+#[cfg(test)]
+impl MockRenderTarget {
+    /// The ids of the buttons of all uploads, in the order of the uploads.
+    pub fn uploaded_button_ids(&self) -> Vec<u8> {
+        self.uploads.borrow().iter().map(|(id, _)| *id).collect()
+    }
+}
+
+#[cfg(test)]
+impl RenderTarget for MockRenderTarget {
+    type Error = String;
+
+    fn set_button_image(&self, button_id: u8, image: &image::RgbImage) -> Result<(), Self::Error> {
+        if self.failing_button == Some(button_id) {
+            return Err(String::from("usb error"));
+        }
+        self.uploads.borrow_mut().push((button_id, image.clone()));
+        Ok(())
+    }
+
+    fn reset(&self) -> Result<(), Self::Error> {
+        self.uploads.borrow_mut().clear();
+        Ok(())
+    }
+}
+
+/// Flipping of the face images, right before they are uploaded.
+///
+/// An escape hatch for devices showing the images mirrored.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ImageFlip {
+    pub horizontal: bool,
+    pub vertical: bool,
+}
+
+impl ImageFlip {
+    pub fn from_config(config: &Option<config::ImageFlipConfig>) -> ImageFlip {
+        match config {
+            None => ImageFlip::default(),
+            Some(config::ImageFlipConfig::H) => ImageFlip {
+                horizontal: true,
+                vertical: false,
+            },
+            Some(config::ImageFlipConfig::V) => ImageFlip {
+                horizontal: false,
+                vertical: true,
+            },
+            Some(config::ImageFlipConfig::HV) => ImageFlip {
+                horizontal: true,
+                vertical: true,
+            },
+        }
+    }
+
+    /// The flipped image (the image itself, if nothing is flipped).
+    pub fn apply<'a>(&self, image: &'a image::RgbImage) -> Cow<'a, image::RgbImage> {
+        let mut result = Cow::Borrowed(image);
+        if self.horizontal {
+            result = Cow::Owned(image::imageops::flip_horizontal(result.as_ref()));
+        }
+        if self.vertical {
+            result = Cow::Owned(image::imageops::flip_vertical(result.as_ref()));
+        }
+        result
+    }
+}
+
+/// Limits the number of faces rendered at once.
+///
+/// The buttons are selected round-robin, so that every button needing rendering
+/// is rendered eventually, even if others keep needing rendering.
+pub struct RenderBudget {
+    max_faces: Option<usize>,
+    /// The button, from which the next selection starts
+    next_button: usize,
+}
+
+impl RenderBudget {
+    /// Create the budget.
+    ///
+    /// # Arguments
+    ///
+    /// max_faces - Maximal number of faces rendered at once, None for no limit.
+    pub fn new(max_faces: Option<usize>) -> RenderBudget {
+        RenderBudget {
+            max_faces,
+            next_button: 0,
+        }
+    }
+
+    /// Select the buttons to render now.
+    ///
+    /// # Arguments
+    ///
+    /// button_ids - The ids of the buttons needing rendering (in ascending order).
+    ///
+    /// # Return
+    ///
+    /// At most `max_faces` of the ids, starting at the button after the last selected one.
+    pub fn select(&mut self, button_ids: &[usize]) -> Vec<usize> {
+        let max_faces = match self.max_faces {
+            None => return button_ids.to_vec(),
+            Some(max_faces) => max_faces,
+        };
+        let (after, before): (Vec<usize>, Vec<usize>) =
+            button_ids.iter().partition(|id| **id >= self.next_button);
+        let selected: Vec<usize> = after.into_iter().chain(before).take(max_faces).collect();
+        if let Some(last) = selected.last() {
+            self.next_button = last + 1;
+        }
+        selected
+    }
+}
+
+/// Limits how often faces are rendered.
+///
+/// Buttons needing rendering in between are not rendered on their own, they are
+/// collected and rendered together when the next render is allowed.
+pub struct RenderRateLimit {
+    /// Minimal time between two renders, None for no limit
+    min_interval: Option<Duration>,
+    last_render: Option<Instant>,
+}
+
+impl RenderRateLimit {
+    /// Create the limit from the config.
+    ///
+    /// # Arguments
+    ///
+    /// max_render_hz - Maximal number of renders per second, None for no limit.
+    ///
+    /// # Return
+    ///
+    /// The limit, or an error if the number of renders is 0.
+    pub fn from_config(max_render_hz: Option<u32>) -> Result<RenderRateLimit, String> {
+        let min_interval = match max_render_hz {
+            None => None,
+            Some(0) => return Err(String::from("max_render_hz must be greater than 0")),
+            Some(hz) => Some(Duration::from_secs(1) / hz),
+        };
+        Ok(RenderRateLimit {
+            min_interval,
+            last_render: None,
+        })
+    }
+
+    /// The time left until the next render is allowed.
+    ///
+    /// # Arguments
+    ///
+    /// now - The current time.
+    ///
+    /// # Return
+    ///
+    /// None if rendering is allowed now, otherwise the time to wait.
+    pub fn delay(&self, now: Instant) -> Option<Duration> {
+        match (self.min_interval, self.last_render) {
+            (Some(min_interval), Some(last_render)) => {
+                let next_render = last_render + min_interval;
+                if now < next_render {
+                    Some(next_render - now)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Remember that faces were rendered.
+    ///
+    /// # Arguments
+    ///
+    /// now - The time of the render.
+    pub fn rendered(&mut self, now: Instant) {
+        self.last_render = Some(now);
+    }
+}
+
+/// Render the faces of the buttons that need rendering (as many as the budget allows).
+///
+/// Failed uploads do not abort the rendering of the other buttons. The buttons
+/// are marked as needing rendering again, so that they are retried next time.
+///
+/// # Arguments
+///
+/// app_state - The state providing the faces.
+/// budget - Selects the buttons to render, the others keep needing rendering.
+/// flip - Flipping of the images, applied right before they are uploaded.
+/// target - The target, the images of the faces are uploaded to.
+///
+/// # Return
+///
+/// The ids of the buttons, for which the upload failed.
+pub fn render_faces<T: RenderTarget>(
+    app_state: &mut AppState,
+    budget: &mut RenderBudget,
+    flip: &ImageFlip,
+    target: &T,
+) -> Vec<u8> {
+    let button_ids = budget.select(&app_state.buttons_needing_rendering());
+    let mut failed = Vec::new();
+    let mut rendered = 0;
+    for (button_id, face) in app_state.set_rendered_and_get_rendering_faces_of(&button_ids) {
+        match target.set_button_image(button_id, &flip.apply(&face.face)) {
+            Ok(()) => rendered += 1,
+            Err(e) => {
+                warn!("unable to set image of button {}: {:?}", button_id, e);
+                failed.push(button_id);
+            }
+        }
+    }
+    app_state.metrics().faces_rendered(rendered);
+    for button_id in &failed {
+        app_state.set_button_needs_rendering(*button_id as usize);
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use streamdeck_hid_rs::StreamDeckType;
+
+    /// App state with only empty buttons
+    fn create_state() -> AppState {
+        let config: crate::config::Config = serde_yaml::from_str("pages: []").unwrap();
+        AppState::from_config(&StreamDeckType::Orig, &config).unwrap()
+    }
+
+    #[test]
+    fn all_faces_are_uploaded() {
+        // Setup
+        let mut state = create_state();
+        let target = MockRenderTarget::default();
+
+        // Act
+        let failed = render_faces(
+            &mut state,
+            &mut RenderBudget::new(None),
+            &ImageFlip::default(),
+            &target,
+        );
+
+        // Test
+        assert!(failed.is_empty());
+        assert_eq!(target.uploaded_button_ids().len(), StreamDeckType::Orig.total_num_buttons());
+        assert!(state.set_rendered_and_get_rendering_faces().is_empty());
+        assert!(state.metrics().exposition().contains(&format!(
+            "streamdeck_faces_rendered_total {}\n",
+            StreamDeckType::Orig.total_num_buttons()
+        )));
+    }
+
+    #[test]
+    fn failed_buttons_are_rendered_again() {
+        // Setup
+        let mut state = create_state();
+        let target = MockRenderTarget {
+            failing_button: Some(3),
+            ..MockRenderTarget::default()
+        };
+
+        // Act
+        let failed = render_faces(
+            &mut state,
+            &mut RenderBudget::new(None),
+            &ImageFlip::default(),
+            &target,
+        );
+
+        // Test
+        assert_eq!(failed, vec![3]);
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        assert_eq!(rendering_faces.len(), 1);
+        assert_eq!(rendering_faces[0].0, 3);
+    }
+
+    #[test]
+    fn budget_selects_at_most_max_faces_round_robin() {
+        // Setup
+        let mut budget = RenderBudget::new(Some(4));
+        let button_ids: Vec<usize> = (0..10).collect();
+
+        // Act
+        let first = budget.select(&button_ids);
+        let second = budget.select(&button_ids);
+        let third = budget.select(&button_ids);
+
+        // Test
+        assert_eq!(first, vec![0, 1, 2, 3]);
+        assert_eq!(second, vec![4, 5, 6, 7]);
+        assert_eq!(third, vec![8, 9, 0, 1]);
+    }
+
+    #[test]
+    fn budget_continues_after_the_last_selected_button() {
+        // Setup
+        let mut budget = RenderBudget::new(Some(2));
+        budget.select(&[5, 6]);
+
+        // Act
+        let selected = budget.select(&[1, 6, 7, 9]);
+
+        // Test
+        assert_eq!(selected, vec![7, 9]);
+    }
+
+    #[test]
+    fn budget_renders_the_remaining_faces_in_the_next_call() {
+        // Setup
+        let mut state = create_state();
+        let mut budget = RenderBudget::new(Some(10));
+        let target = MockRenderTarget::default();
+
+        // Act
+        for _ in 0..2 {
+            render_faces(&mut state, &mut budget, &ImageFlip::default(), &target);
+        }
+
+        // Test
+        assert_eq!(target.uploaded_button_ids().len(), StreamDeckType::Orig.total_num_buttons());
+        assert!(state.buttons_needing_rendering().is_empty());
+    }
+
+    /// A 2x2 image with the pixel values 1 (top left), 2 (top right), 3 (bottom left) and 4 (bottom right).
+    fn known_image() -> image::RgbImage {
+        image::RgbImage::from_fn(2, 2, |x, y| {
+            let value = (1 + x + 2 * y) as u8;
+            image::Rgb([value, value, value])
+        })
+    }
+
+    /// The pixel values of a 2x2 image, row by row.
+    fn pixel_values(image: &image::RgbImage) -> Vec<u8> {
+        image.pixels().map(|p| p[0]).collect()
+    }
+
+    #[test]
+    fn no_flip_keeps_the_image() {
+        // Setup
+        let image = known_image();
+
+        // Act
+        let flipped = ImageFlip::from_config(&None).apply(&image);
+
+        // Test
+        assert!(matches!(flipped, Cow::Borrowed(_)));
+        assert_eq!(pixel_values(&flipped), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn horizontal_flip_swaps_left_and_right() {
+        // Act
+        let flipped =
+            ImageFlip::from_config(&Some(config::ImageFlipConfig::H)).apply(&known_image()).into_owned();
+
+        // Test
+        assert_eq!(pixel_values(&flipped), vec![2, 1, 4, 3]);
+    }
+
+    #[test]
+    fn vertical_flip_swaps_top_and_bottom() {
+        // Act
+        let flipped =
+            ImageFlip::from_config(&Some(config::ImageFlipConfig::V)).apply(&known_image()).into_owned();
+
+        // Test
+        assert_eq!(pixel_values(&flipped), vec![3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn flipping_both_rotates_by_180_degrees() {
+        // Act
+        let flipped =
+            ImageFlip::from_config(&Some(config::ImageFlipConfig::HV)).apply(&known_image()).into_owned();
+
+        // Test
+        assert_eq!(pixel_values(&flipped), vec![4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn loading_a_page_uploads_its_buttons() {
+        // Setup
+        let config: crate::config::Config = serde_yaml::from_str(
+            "\
+pages:
+- name: main
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button:
+      up_face:
+        color: '#FF0000'
+- name: overlay
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button:
+      up_face:
+        color: '#00FF00'
+  - position:
+      row: 0
+      col: 1
+    button:
+      up_face:
+        color: '#0000FF'
+default_pages:
+- main
+",
+        )
+        .unwrap();
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let mut budget = RenderBudget::new(None);
+        render_faces(
+            &mut state,
+            &mut budget,
+            &ImageFlip::default(),
+            &MockRenderTarget::default(),
+        );
+        let target = MockRenderTarget::default();
+
+        // Act
+        state.load_page(&String::from("overlay")).unwrap();
+        let failed = render_faces(&mut state, &mut budget, &ImageFlip::default(), &target);
+
+        // Test
+        assert!(failed.is_empty());
+        let mut button_ids = target.uploaded_button_ids();
+        button_ids.sort();
+        assert_eq!(button_ids, vec![3, 4]);
+        let uploads = target.uploads.borrow();
+        let overlay_face = &uploads.iter().find(|(id, _)| *id == 4).unwrap().1;
+        let center = overlay_face.width() / 2;
+        assert_eq!(overlay_face.get_pixel(center, center), &image::Rgb([0, 255, 0]));
+    }
+
+    #[test]
+    fn rate_limit_allows_the_first_render() {
+        // Setup
+        let limit = RenderRateLimit::from_config(Some(10)).unwrap();
+
+        // Act
+        let delay = limit.delay(Instant::now());
+
+        // Test
+        assert_eq!(delay, None);
+    }
+
+    #[test]
+    fn rate_limit_delays_renders_within_the_interval() {
+        // Setup
+        let mut limit = RenderRateLimit::from_config(Some(10)).unwrap();
+        let start = Instant::now();
+        limit.rendered(start);
+
+        // Act
+        let within = limit.delay(start + Duration::from_millis(30));
+        let after = limit.delay(start + Duration::from_millis(100));
+
+        // Test
+        assert_eq!(within, Some(Duration::from_millis(70)));
+        assert_eq!(after, None);
+    }
+
+    #[test]
+    fn rate_limit_is_validated() {
+        // Act
+        let unlimited = RenderRateLimit::from_config(None).unwrap();
+        let zero = RenderRateLimit::from_config(Some(0));
+
+        // Test
+        assert_eq!(unlimited.min_interval, None);
+        assert!(zero.is_err());
+    }
+
+    #[test]
+    fn changes_within_a_frame_are_uploaded_together() {
+        // Setup
+        let mut state = create_state();
+        let mut limit = RenderRateLimit::from_config(Some(10)).unwrap();
+        let target = MockRenderTarget::default();
+        let start = Instant::now();
+        // Render the initial faces, like the main loop does
+        let mut render = |state: &mut AppState, now: Instant| {
+            if limit.delay(now).is_none() {
+                limit.rendered(now);
+                render_faces(
+                    state,
+                    &mut RenderBudget::new(None),
+                    &ImageFlip::default(),
+                            &target,
+                );
+            }
+        };
+        render(&mut state, start);
+        let initial_uploads = target.uploads.borrow().len();
+
+        // Act
+        state.set_button_needs_rendering(1);
+        render(&mut state, start + Duration::from_millis(10));
+        state.set_button_needs_rendering(2);
+        render(&mut state, start + Duration::from_millis(20));
+        state.set_button_needs_rendering(1);
+        render(&mut state, start + Duration::from_millis(50));
+        let uploads_within_frame = target.uploads.borrow().len() - initial_uploads;
+        render(&mut state, start + Duration::from_millis(100));
+
+        // Test
+        assert_eq!(uploads_within_frame, 0);
+        assert_eq!(target.uploaded_button_ids()[initial_uploads..], [1, 2]);
+    }
+}
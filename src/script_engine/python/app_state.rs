@@ -1,7 +1,10 @@
 use std::collections::HashMap;
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use crate::config::hex_string_to_rgba_color;
+use crate::state::{FaceValues, PressState};
 
 /// Class for wrapping the app state to be used from python
 #[pyclass]
@@ -17,10 +20,240 @@ impl AppState {
     }
 }
 
+/// A button given by its index, or all buttons showing a named button
+#[derive(FromPyObject)]
+pub enum ButtonIndexOrName {
+    Index(usize),
+    Name(String),
+}
+
+/// Value of a variable set by the scripts
+#[derive(FromPyObject)]
+pub enum VariableValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+impl VariableValue {
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            VariableValue::Bool(b) => serde_json::json!(b),
+            VariableValue::Int(i) => serde_json::json!(i),
+            VariableValue::Float(f) => serde_json::json!(f),
+            VariableValue::Text(s) => serde_json::json!(s),
+        }
+    }
+}
+
 #[pymethods]
 impl AppState {
     pub fn load_page(&self, page_name: String) {
-        self.state.write().unwrap().load_page(&page_name).unwrap();
+        let mut state = self.state.write().unwrap();
+        let on_load = state.load_page(&page_name).unwrap();
+        state.queue_handler(on_load);
+    }
+
+    /// The current state as JSON string (see [crate::state::AppState::to_status_json]).
+    pub fn status(&self) -> String {
+        self.state.read().unwrap().to_status_json().to_string()
+    }
+
+    /// Names of all named buttons.
+    pub fn named_button_names(&self) -> Vec<String> {
+        self.state.read().unwrap().named_button_names()
+    }
+
+    /// Write the up face of every named button as `<name>.png` into the directory.
+    pub fn export_faces(&self, dir: String) -> PyResult<()> {
+        self.state
+            .write()
+            .unwrap()
+            .export_faces(std::path::Path::new(&dir))
+            .map(|_| ())
+            .map_err(to_py_err)
+    }
+
+    /// Width, height and font size of a label on the buttons of the device,
+    /// the font size defaults to the one of labels in the center.
+    pub fn measure_label(&self, text: String, base_scale: Option<f32>) -> (u32, u32, f32) {
+        let state = self.state.read().unwrap();
+        let (width, height) = state.button_image_size();
+        crate::state::measure_label(
+            &text,
+            state.defaults(),
+            width,
+            base_scale.unwrap_or(height as f32 / 1.1),
+        )
+    }
+
+    /// Tuples of the button index and the name of the named button shown on it.
+    pub fn visible_buttons(&self) -> Vec<(usize, String)> {
+        self.state.read().unwrap().visible_buttons()
+    }
+
+    /// Undo the last loading or unloading of a page, returns false if there is nothing to undo.
+    pub fn undo_page_operation(&self) -> bool {
+        self.state.write().unwrap().undo_page_operation()
+    }
+
+    /// Stop (true) or resume (false) loading and unloading pages for the foreground window.
+    pub fn freeze_pages(&self, frozen: bool) {
+        self.state
+            .write()
+            .unwrap()
+            .set_window_switching_enabled(!frozen);
+    }
+
+    /// The loaded pages, from the bottom to the top of the stack.
+    pub fn get_page_stack(&self) -> Vec<String> {
+        self.state.read().unwrap().get_page_stack()
+    }
+
+    /// Replace all loaded pages with the given pages (from the bottom to the top of the stack).
+    pub fn set_page_stack(&self, page_names: Vec<String>) -> PyResult<()> {
+        self.state
+            .write()
+            .unwrap()
+            .set_page_stack(page_names)
+            .map_err(to_py_err)
+    }
+
+    /// Replace all loaded pages with the default pages of a profile.
+    pub fn activate_profile(&self, profile_name: String) -> PyResult<()> {
+        self.state
+            .write()
+            .unwrap()
+            .activate_profile(&profile_name)
+            .map_err(to_py_err)
+    }
+
+    /// Enable or disable a named button.
+    pub fn set_button_enabled(&self, button_name: String, enabled: bool) -> PyResult<()> {
+        self.state
+            .write()
+            .unwrap()
+            .set_button_enabled(&button_name, enabled)
+            .map_err(to_py_err)
+    }
+
+    /// Replace the up face of a named button with an image of rgb bytes.
+    pub fn set_button_image(
+        &self,
+        button_name: String,
+        width: u32,
+        height: u32,
+        rgb_bytes: Vec<u8>,
+    ) -> PyResult<()> {
+        self.state
+            .write()
+            .unwrap()
+            .set_named_button_image(&button_name, width, height, rgb_bytes)
+            .map_err(to_py_err)
+    }
+
+    /// Whether a latching named button is latched.
+    pub fn get_button_latch(&self, button_name: String) -> bool {
+        self.state.read().unwrap().button_latch(&button_name)
+    }
+
+    /// Latch or unlatch a named button.
+    pub fn set_button_latch(&self, button_name: String, latched: bool) -> PyResult<()> {
+        self.state
+            .write()
+            .unwrap()
+            .set_button_latch(&button_name, latched)
+            .map_err(to_py_err)
+    }
+
+    /// Select a named button in its group, deselecting the other buttons of the group.
+    pub fn select_in_group(&self, group: String, button_name: String) -> PyResult<()> {
+        self.state
+            .write()
+            .unwrap()
+            .select_in_group(&group, &button_name)
+            .map_err(to_py_err)
+    }
+
+    /// The selected named button of a group, None if no button of the group has been selected.
+    pub fn get_selected_in_group(&self, group: String) -> Option<String> {
+        self.state.read().unwrap().selected_in_group(&group).cloned()
+    }
+
+    /// Show a named button on a button (by index), regardless of the loaded pages.
+    pub fn override_button(&self, button_index: usize, button_name: String) -> PyResult<()> {
+        self.state
+            .write()
+            .unwrap()
+            .override_button(button_index, &button_name)
+            .map_err(to_py_err)
+    }
+
+    /// Show the button of the loaded pages again, false if the button was not overridden.
+    pub fn clear_override(&self, button_index: usize) -> bool {
+        self.state.write().unwrap().clear_override(button_index)
+    }
+
+    /// Show a text (and optionally a color) on a button for the given milliseconds.
+    pub fn toast(
+        &self,
+        button: ButtonIndexOrName,
+        text: String,
+        ms: u64,
+        color: Option<String>,
+    ) -> PyResult<()> {
+        let color = match color {
+            None => None,
+            Some(c) => Some(hex_string_to_rgba_color(&c).map_err(to_py_err)?),
+        };
+        let mut state = self.state.write().unwrap();
+        let button_indexes = match button {
+            ButtonIndexOrName::Index(button_index) => vec![button_index],
+            ButtonIndexOrName::Name(button_name) => state.buttons_showing(&button_name),
+        };
+        let now = Instant::now();
+        for button_index in button_indexes {
+            state
+                .toast(button_index, &text, color, Duration::from_millis(ms), now)
+                .map_err(to_py_err)?;
+        }
+        Ok(())
+    }
+
+    /// Set a variable (bool, int, float, str or None), named buttons with a face for it are drawn again.
+    pub fn set_var(&self, name: String, value: Option<VariableValue>) {
+        let value = value.map_or(serde_json::Value::Null, |v| v.to_json());
+        self.state.write().unwrap().set_var(&name, value);
+    }
+
+    /// Value of a variable set with set_var, None if it is not set.
+    pub fn get_var(&self, py: Python, name: String) -> PyObject {
+        match self.state.read().unwrap().get_var(&name) {
+            None | Some(serde_json::Value::Null) => py.None(),
+            Some(serde_json::Value::Bool(b)) => b.to_object(py),
+            Some(serde_json::Value::Number(n)) => match n.as_i64() {
+                Some(i) => i.to_object(py),
+                None => n.as_f64().to_object(py),
+            },
+            Some(serde_json::Value::String(s)) => s.to_object(py),
+            Some(value) => value.to_string().to_object(py),
+        }
+    }
+
+    /// Value of an environment variable, None if it is not set (or not valid unicode).
+    pub fn env(&self, key: String) -> Option<String> {
+        std::env::var(key).ok()
+    }
+
+    /// Name of the operating system (for example "linux", "windows" or "macos").
+    pub fn platform(&self) -> String {
+        String::from(std::env::consts::OS)
+    }
+
+    /// Re-render all buttons (for example after external changes to image files).
+    pub fn refresh(&self) {
+        self.state.write().unwrap().invalidate_all();
     }
 
     pub fn set_named_button_up_face(&self, button_name: String, properties: HashMap<String, String>) {
@@ -47,4 +280,86 @@ impl AppState {
                 Some(c) => Some(hex_string_to_rgba_color(c).unwrap()),
             });
     }
+
+    /// Show the next of the cycle faces of a named button, returns the index of the face shown now.
+    pub fn cycle_button_face(&self, button_name: String) -> PyResult<usize> {
+        self.state
+            .write()
+            .unwrap()
+            .cycle_button_face(&button_name)
+            .map_err(to_py_err)
+    }
+
+    /// The name of the named button on a button, and the source and script of every handler,
+    /// that pressing ("down") or releasing ("up") the button would execute.
+    pub fn peek_button_handler(
+        &self,
+        button_index: usize,
+        edge: String,
+    ) -> PyResult<Option<(String, Vec<(String, String)>)>> {
+        let edge = match edge.as_str() {
+            "down" => PressState::Down,
+            "up" => PressState::Up,
+            _ => {
+                return Err(PyValueError::new_err(format!(
+                    "invalid edge {}, must be \"down\" or \"up\"",
+                    edge
+                )))
+            }
+        };
+        let info = self.state.read().unwrap().peek_button_handler(button_index, &edge);
+        Ok(info.map(|info| {
+            (
+                info.button_name,
+                info.handlers
+                    .into_iter()
+                    .map(|handler| (handler.source.to_string(), handler.script))
+                    .collect(),
+            )
+        }))
+    }
+
+    /// Change the up and the down face of a named button at once,
+    /// without (or with empty) down properties only the up face is changed.
+    pub fn set_named_button_faces(
+        &self,
+        button_name: String,
+        up_properties: HashMap<String, String>,
+        down_properties: Option<HashMap<String, String>>,
+    ) -> PyResult<()> {
+        let up = face_values(&up_properties)?;
+        let down = match down_properties.filter(|properties| !properties.is_empty()) {
+            Some(properties) => Some(face_values(&properties)?),
+            None => None,
+        };
+        self.state
+            .write()
+            .unwrap()
+            .set_named_button_faces(&button_name, up, down)
+            .map_err(to_py_err)
+    }
+}
+
+/// A python exception for an error of the app state, raised instead of panicking
+/// while the state is locked (which would poison the lock).
+fn to_py_err(error: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// The face values given as properties (like `color` or `label`) by a script.
+fn face_values(properties: &HashMap<String, String>) -> PyResult<FaceValues> {
+    let color = |key: &str| match properties.get(key) {
+        None => Ok(None),
+        Some(c) => hex_string_to_rgba_color(c).map(Some).map_err(to_py_err),
+    };
+    Ok(FaceValues {
+        color: color("color")?,
+        file: properties.get("file").cloned(),
+        label: properties.get("label").cloned(),
+        labelcolor: color("labelcolor")?,
+        sublabel: properties.get("sublabel").cloned(),
+        sublabelcolor: color("sublabelcolor")?,
+        superlabel: properties.get("superlabel").cloned(),
+        superlabelcolor: color("superlabelcolor")?,
+    })
 }
@@ -1,47 +1,311 @@
 use super::stdout::LoggingStdout;
+use crate::state::{EventContext, ScriptLanguage};
+use crate::metrics::Metrics;
 use crate::AppState;
 use log::{error, info};
 use pyo3::prelude::*;
-use pyo3::types::{IntoPyDict, PyDict};
+use pyo3::types::{IntoPyDict, PyDict, PyModule};
 use pyo3::Python;
 use std::sync::{Arc, RwLock};
 
+/// Name of the module, in whose namespace all scripts run.
+const SCRIPTS_MODULE_NAME: &str = "streamdeck_controller_scripts";
+
 pub struct PythonEngine {
-    locals: Py<PyDict>,
+    /// Globals of all scripts, the namespace of a module persisting as long as the engine.
+    globals: Py<PyDict>,
+    /// Counters about the usage, failed scripts are counted
+    metrics: Arc<Metrics>,
 }
 
 impl PythonEngine {
+    /// Create the engine.
+    ///
+    /// All scripts run in the namespace of the same module, so functions, imports and variables
+    /// defined by one script (for example the init script) can be used by all later scripts.
+    /// Scripts are not isolated from each other, they can also overwrite each others definitions.
     pub fn new(app_state: &Arc<RwLock<AppState>>) -> PyResult<PythonEngine> {
-        let locals = Python::with_gil(|py| {
-            let locals = PyDict::new(py);
-            locals.set_item("state", Py::new(py, super::app_state::AppState::new(app_state)).unwrap());
-            locals.into_py(py)
-        });
-        Ok(PythonEngine {
-            locals
-        })
+        let globals = Python::with_gil(|py| -> PyResult<Py<PyDict>> {
+            let module = PyModule::new(py, SCRIPTS_MODULE_NAME)?;
+            let globals = module.dict();
+            globals.set_item("__builtins__", py.import("builtins")?)?;
+            globals.set_item("state", Py::new(py, super::app_state::AppState::new(app_state))?)?;
+            Ok(globals.into_py(py))
+        })?;
+        let metrics = app_state.read().unwrap().metrics();
+        Ok(PythonEngine { globals, metrics })
     }
 
+    /// Run an event handler.
+    ///
+    /// The values of the context are available to the script as the variables
+    /// `button_id`, `button_name` and `event` (None, if not given by the context).
+    ///
+    /// The python path of the handler is prepended to `sys.path` while the script runs.
+    /// Afterwards `sys.path` is reset.
+    ///
+    /// Scripts selecting another language with their shebang line are not run, there is no engine
+    /// for other languages yet.
     pub fn run_event_handler(
         &self,
-        event_handler: &crate::state::EventHandler
+        event_handler: &crate::state::EventHandler,
+        context: &EventContext,
     ) -> Result<(), PyErr> {
+        if event_handler.language != ScriptLanguage::Python {
+            error!(
+                "{} script ({}) not run, there is no engine for {} scripts",
+                event_handler.language, event_handler.source, event_handler.language
+            );
+            return Ok(());
+        }
         match Python::with_gil(|py| -> Result<(), PyErr> {
             let sys = py.import("sys")?;
             sys.setattr("stdout", LoggingStdout.into_py(py))?;
 
-            py.run(event_handler.script.as_str(), Some(self.locals.as_ref(py)), None)?;
-            Ok(())
+            let globals = self.globals.as_ref(py);
+            globals.set_item("button_id", context.button_id)?;
+            globals.set_item("button_name", context.button_name.clone())?;
+            globals.set_item("event", context.event)?;
+
+            let original_path: Vec<String> = sys.getattr("path")?.extract()?;
+            if !event_handler.pythonpath.is_empty() {
+                let path: Vec<String> = event_handler
+                    .pythonpath
+                    .iter()
+                    .map(|dir| dir.to_string_lossy().to_string())
+                    .chain(original_path.iter().cloned())
+                    .collect();
+                sys.setattr("path", path)?;
+            }
+
+            let result = py.run(event_handler.script.as_str(), Some(globals), None);
+            sys.setattr("path", original_path)?;
+            result
         }) {
             Ok(_) => {
-                info!("python script finished successfully")
+                info!("python script ({}) finished successfully", event_handler.source)
             }
             Err(e) => {
+                self.metrics.script_failed();
                 Python::with_gil(|py| {
-                    error!("python script failed: {}", e.value(py));
+                    error!("python script ({}) failed: {}", event_handler.source, e.value(py));
                 });
             }
         };
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{EventHandler, HandlerSource, ScriptLanguage};
+    use streamdeck_hid_rs::StreamDeckType;
+
+    /// Python engine with an empty app state
+    fn create_engine() -> PythonEngine {
+        let config: crate::config::Config = serde_yaml::from_str("pages: []").unwrap();
+        let app_state = Arc::new(RwLock::new(
+            AppState::from_config(&StreamDeckType::Orig, &config).unwrap(),
+        ));
+        PythonEngine::new(&app_state).unwrap()
+    }
+
+    /// Get a variable from the globals of the engine
+    fn get_global<T: for<'a> FromPyObject<'a>>(engine: &PythonEngine, name: &str) -> T {
+        Python::with_gil(|py| {
+            engine
+                .globals
+                .as_ref(py)
+                .get_item(name)
+                .unwrap()
+                .extract()
+                .unwrap()
+        })
+    }
+
+    #[test]
+    fn script_sees_button_context() {
+        // Setup
+        let engine = create_engine();
+        let handler = EventHandler {
+            script: String::from("seen = (button_id, button_name, event)"),
+            pythonpath: Vec::new(),
+            source: HandlerSource::Inline,
+            language: ScriptLanguage::Python,
+        };
+
+        // Act
+        engine
+            .run_event_handler(
+                &handler,
+                &EventContext::for_button(3, String::from("button3"), "down"),
+            )
+            .unwrap();
+
+        // Test
+        let seen: (usize, String, String) = get_global(&engine, "seen");
+        assert_eq!(seen, (3, String::from("button3"), String::from("down")));
+    }
+
+    #[test]
+    fn script_without_context_sees_none() {
+        // Setup
+        let engine = create_engine();
+        let handler = EventHandler {
+            script: String::from("seen = button_id is None and event is None"),
+            pythonpath: Vec::new(),
+            source: HandlerSource::Inline,
+            language: ScriptLanguage::Python,
+        };
+
+        // Act
+        engine
+            .run_event_handler(
+                &EventHandler {
+                    script: String::from("pass"),
+                    pythonpath: Vec::new(),
+                    source: HandlerSource::Inline,
+                    language: ScriptLanguage::Python,
+                },
+                &EventContext::for_button(3, String::from("button3"), "down"),
+            )
+            .unwrap();
+        engine
+            .run_event_handler(&handler, &EventContext::default())
+            .unwrap();
+
+        // Test
+        let seen: bool = get_global(&engine, "seen");
+        assert!(seen);
+    }
+
+    #[test]
+    fn script_can_import_module_from_pythonpath() {
+        // Setup
+        let engine = create_engine();
+        let module_dir = std::env::temp_dir().join("streamdeck_controller_pythonpath_test");
+        std::fs::create_dir_all(&module_dir).unwrap();
+        std::fs::write(module_dir.join("pythonpath_test_helper.py"), "VALUE = 42\n").unwrap();
+        let handler = EventHandler {
+            script: String::from("import pythonpath_test_helper\nseen = pythonpath_test_helper.VALUE"),
+            pythonpath: vec![module_dir.clone()],
+            source: HandlerSource::Inline,
+            language: ScriptLanguage::Python,
+        };
+
+        // Act
+        engine
+            .run_event_handler(&handler, &EventContext::default())
+            .unwrap();
+
+        // Test
+        let seen: i32 = get_global(&engine, "seen");
+        assert_eq!(seen, 42);
+        let sys_path: Vec<String> =
+            Python::with_gil(|py| py.import("sys").unwrap().getattr("path").unwrap().extract().unwrap());
+        assert!(!sys_path.contains(&module_dir.to_string_lossy().to_string()));
+    }
+
+    #[test]
+    fn script_reads_environment_variables() {
+        // Setup
+        let engine = create_engine();
+        std::env::set_var("STREAMDECK_CONTROLLER_ENV_TEST", "value");
+        std::env::remove_var("STREAMDECK_CONTROLLER_ENV_TEST_UNSET");
+        let handler = EventHandler {
+            script: String::from(
+                "seen = (state.env('STREAMDECK_CONTROLLER_ENV_TEST'), state.env('STREAMDECK_CONTROLLER_ENV_TEST_UNSET'))",
+            ),
+            pythonpath: Vec::new(),
+            source: HandlerSource::Inline,
+            language: ScriptLanguage::Python,
+        };
+
+        // Act
+        engine
+            .run_event_handler(&handler, &EventContext::default())
+            .unwrap();
+
+        // Test
+        let seen: (Option<String>, Option<String>) = get_global(&engine, "seen");
+        assert_eq!(seen, (Some(String::from("value")), None));
+    }
+
+    #[test]
+    fn script_reads_the_platform() {
+        // Setup
+        let engine = create_engine();
+        let handler = EventHandler {
+            script: String::from("seen = state.platform()"),
+            pythonpath: Vec::new(),
+            source: HandlerSource::Inline,
+            language: ScriptLanguage::Python,
+        };
+
+        // Act
+        engine
+            .run_event_handler(&handler, &EventContext::default())
+            .unwrap();
+
+        // Test
+        let seen: String = get_global(&engine, "seen");
+        assert_eq!(seen, std::env::consts::OS);
+    }
+
+    #[test]
+    fn functions_defined_in_init_script_are_callable_from_later_handlers() {
+        // Setup
+        let engine = create_engine();
+        let init_script = EventHandler {
+            script: String::from(
+                "import math\nOFFSET = 1\ndef helper(x):\n    return math.floor(x) + OFFSET",
+            ),
+            pythonpath: Vec::new(),
+            source: HandlerSource::Inline,
+            language: ScriptLanguage::Python,
+        };
+        let handler = EventHandler {
+            script: String::from("seen = (helper(41.5), helper.__module__)"),
+            pythonpath: Vec::new(),
+            source: HandlerSource::Inline,
+            language: ScriptLanguage::Python,
+        };
+
+        // Act
+        engine
+            .run_event_handler(&init_script, &EventContext::default())
+            .unwrap();
+        engine
+            .run_event_handler(
+                &handler,
+                &EventContext::for_button(3, String::from("button3"), "down"),
+            )
+            .unwrap();
+
+        // Test
+        let seen: (i32, String) = get_global(&engine, "seen");
+        assert_eq!(seen, (42, String::from(SCRIPTS_MODULE_NAME)));
+    }
+
+    #[test]
+    fn lua_script_is_not_run_by_python() {
+        // Setup
+        let engine = create_engine();
+        let handler = EventHandler {
+            script: String::from("#!lua\nran = 1"),
+            pythonpath: Vec::new(),
+            source: HandlerSource::Inline,
+            language: ScriptLanguage::Lua,
+        };
+
+        // Act
+        engine
+            .run_event_handler(&handler, &EventContext::default())
+            .unwrap();
+
+        // Test
+        let ran = Python::with_gil(|py| engine.globals.as_ref(py).get_item("ran").is_some());
+        assert!(!ran);
+    }
+}
@@ -1,16 +1,23 @@
 use super::button::ButtonSetup;
 use super::button::ButtonState;
-use super::button_face::ButtonFace;
+use super::button::PressState;
+use super::button_face::{forget_downloaded_images, ButtonFace, FaceCache};
+use super::button_position::ColumnOrder;
 use super::defaults::Defaults;
 use super::error::Error;
 use super::event_handler::EventHandler;
 use super::page::Page;
+use super::touch_point::TouchPoint;
 use crate::config;
 use crate::config::{ButtonConfigWithName, ButtonFaceConfig, ColorConfig};
 use crate::foreground_window::WindowInformation;
-use log::debug;
-use std::collections::HashMap;
+use crate::metrics::Metrics;
+use log::{debug, warn};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use image::Rgba;
 use streamdeck_hid_rs::StreamDeckType;
 
@@ -28,12 +35,77 @@ pub struct AppState {
     loaded_pages: Vec<String>,
     /// The device type this is for!
     device_type: StreamDeckType,
+    /// False for devices without displays (like pedals), no faces are rendered for them
+    has_display: bool,
     /// Init event handler
     init_handler: Option<Arc<EventHandler>>,
+    /// Handler executed when the program exits
+    shutdown_handler: Option<Arc<EventHandler>>,
+    /// Handler executed for every press and release of any button
+    on_any_button: Option<Arc<EventHandler>>,
+    /// Handler executed when a button without setup is pressed
+    default_button_handler: Option<Arc<EventHandler>>,
+    /// Index of the button, that activates the layer of the other buttons while held down
+    layer_modifier: Option<usize>,
     /// The current foreground window
     foreground_window: Option<WindowInformation>,
+    /// Handlers, that should be executed by the script engine
+    /// (for example because a page has been loaded).
+    queued_handlers: Vec<Arc<EventHandler>>,
+    /// The stacks of loaded pages before the last page operations, for undoing them
+    page_history: Vec<Vec<String>>,
+    /// Names of the latched named buttons, kept while they are not shown
+    latched_buttons: HashSet<String>,
+    /// The selected named button of each button group
+    selected_in_groups: HashMap<String, String>,
+    /// Named buttons shown on buttons (by index) instead of the buttons of the loaded pages
+    overrides: HashMap<usize, String>,
+    /// Toasts currently shown on buttons (by index)
+    toasts: HashMap<usize, Toast>,
+    /// Handlers of the touch points next to the buttons, by index
+    touch_points: Vec<TouchPoint>,
+    /// Variables set by the scripts, selecting the variable faces of named buttons
+    variables: HashMap<String, serde_json::Value>,
+    /// False while pages are not loaded and unloaded for the foreground window
+    window_switching_enabled: bool,
+    /// Show placeholders for faces loading images, while they are drawn in the background
+    loading_placeholders: bool,
+    /// Faces shown with a placeholder, that still have to be drawn
+    pending_faces: Vec<PendingFace>,
+    /// Counters about the usage, shared with the metrics server and the script engine
+    metrics: Arc<Metrics>,
+    /// Names of the named buttons copied for the loaded pages isolating their named buttons, by page
+    page_copies: HashMap<String, Vec<String>>,
+    /// The default pages of the profiles, by the name of the profile
+    profiles: HashMap<String, Vec<String>>,
 }
 
+/// A face, that is shown with a placeholder until it is drawn in the background.
+pub struct PendingFace {
+    /// The placeholder, shown by the buttons instead of the face
+    pub placeholder: Arc<ButtonFace>,
+    /// The face, that has not been drawn yet
+    pub face: Arc<ButtonFace>,
+}
+
+/// A toast shown on a button, until it expires.
+///
+/// The toast is shown as an override with its own named button.
+struct Toast {
+    expires: Instant,
+    /// The override of the button before the toast, restored when it expires
+    previous_override: Option<String>,
+}
+
+/// Prefix of the names of the named buttons created for toasts
+const TOAST_BUTTON_PREFIX: &str = "toast:";
+
+/// Prefix of the names of the copies of named buttons made for pages, followed by "<page>:<button>"
+const PAGE_COPY_PREFIX: &str = "page:";
+
+/// Number of page operations, that can be undone
+const PAGE_HISTORY_LENGTH: usize = 32;
+
 impl AppState {
     /// Create an app state from configuration
     ///
@@ -50,7 +122,11 @@ impl AppState {
         device_type: &StreamDeckType,
         config: &config::Config,
     ) -> Result<AppState, Error> {
-        let defaults = Defaults::from_config(&config.defaults)?;
+        let mut defaults = Defaults::from_config(&config.defaults, device_type)?;
+        if config.positions_left_to_right == Some(true) {
+            defaults.column_order = ColumnOrder::LeftToRight;
+        }
+        let mut face_cache = FaceCache::new();
 
         let mut named_buttons: HashMap<String, ButtonSetup> = HashMap::new();
 
@@ -61,14 +137,21 @@ impl AppState {
                 }
                 named_buttons.insert(
                     button_config.name.clone(),
-                        ButtonSetup::from_config_with_name(&device_type, &button_config, &defaults)
-                            .unwrap(),
+                    ButtonSetup::from_config_with_name(
+                        &device_type,
+                        &button_config,
+                        &defaults,
+                        &mut face_cache,
+                    )?,
                 );
             }
         }
 
+        // In strict mode, the "empty" button must be defined by the config
+        let strict_empty = config.strict_empty.unwrap_or(false);
+
         // Create a special empty named button (that can be overwritten)
-        if !named_buttons.contains_key("empty") {
+        if !strict_empty && !named_buttons.contains_key("empty") {
             named_buttons.insert(
                 "empty".to_string(),
                 ButtonSetup::from_config_with_name(
@@ -81,22 +164,54 @@ impl AppState {
                             label: None,
                             sublabel: None,
                             superlabel: None,
+                            overlay: None,
+                            sprite: None,
+                            lines: None,
+                            pixelated: None,
+                            padding: None,
+                            background: None,
+                            color_key: None,
+                            color_key_tolerance: None,
                         }),
                         down_face: None,
                         up_handler: None,
                         down_handler: None,
+                        load_page: None,
+                        on_app_faces: None,
+                        enabled: None,
+                        layer_face: None,
+                        layer_handlers: None,
+                        action: None,
+                        trigger: None,
+                        latch: None,
+                        group: None,
+                        description: None,
+                        tags: None,
+                        when_faces: None,
+                        up_handlers: None,
+                        down_handlers: None,
+                        cycle_faces: None,
+                        invert_press: None,
                     },
                     &defaults,
-                )
-                    .unwrap(),
+                    &mut face_cache,
+                )?,
             );
         }
 
         let mut pages: HashMap<String, Arc<Page>> = HashMap::new();
 
+        // Only the faces of the default pages are drawn now, the others when their page is loaded
+        let default_pages = config.default_pages.as_deref().unwrap_or_default();
         for page_config in &config.pages {
+            face_cache.set_deferred(!default_pages.contains(&page_config.name));
             let (page, more_named_buttons) =
-                Page::from_config_with_named_buttons(device_type, &page_config, &defaults)?;
+                Page::from_config_with_named_buttons(
+                    device_type,
+                    &page_config,
+                    &defaults,
+                    &mut face_cache,
+                )?;
             pages.insert(page_config.name.clone(), Arc::new(page));
             for (name, new_named_button) in more_named_buttons {
                 if named_buttons.contains_key(&name) {
@@ -106,6 +221,62 @@ impl AppState {
             }
         }
 
+        // The prefixes of internal named buttons can not be used in the config
+        if let Some(name) = named_buttons
+            .keys()
+            .find(|name| name.starts_with(TOAST_BUTTON_PREFIX) || name.starts_with(PAGE_COPY_PREFIX))
+        {
+            return Err(Error::ReservedButtonName(name.clone()));
+        }
+
+        // All default pages must exist, before any of them is loaded
+        let missing_pages: Vec<String> = default_pages
+            .iter()
+            .filter(|page_name| !pages.contains_key(*page_name))
+            .cloned()
+            .collect();
+        if !missing_pages.is_empty() {
+            return Err(Error::PagesNotFound(missing_pages));
+        }
+
+        // And so must the pages of the profiles
+        let mut profiles = HashMap::new();
+        for (profile_name, profile) in config.profiles.iter().flatten() {
+            let missing_pages: Vec<String> = profile
+                .default_pages
+                .iter()
+                .filter(|page_name| !pages.contains_key(*page_name))
+                .cloned()
+                .collect();
+            if !missing_pages.is_empty() {
+                return Err(Error::PagesNotFound(missing_pages));
+            }
+            profiles.insert(profile_name.clone(), profile.default_pages.clone());
+        }
+
+        // In strict mode, every referenced button must exist
+        if strict_empty {
+            if !named_buttons.contains_key("empty") {
+                return Err(Error::ButtonNotFound("empty".to_string()));
+            }
+            for page in pages.values() {
+                for button in &page.buttons {
+                    if !named_buttons.contains_key(&button.button_name) {
+                        return Err(Error::ButtonNotFound(button.button_name.clone()));
+                    }
+                }
+            }
+        }
+
+        // Buttons can only load existing pages
+        for named_button in named_buttons.values() {
+            if let Some(page_name) = &named_button.load_page {
+                if !pages.contains_key(page_name) {
+                    return Err(Error::PageNotFound(page_name.clone()));
+                }
+            }
+        }
+
         let mut buttons = Vec::new();
         for _ in 0..device_type.total_num_buttons() {
             buttons.push(ButtonState::empty());
@@ -116,6 +287,34 @@ impl AppState {
         } else {
             None
         };
+        let shutdown_handler = match &config.shutdown_script {
+            None => None,
+            Some(c) => Some(Arc::new(EventHandler::from_config(c)?)),
+        };
+
+        if let Some(layer_modifier) = config.layer_modifier {
+            if layer_modifier >= device_type.total_num_buttons() {
+                return Err(Error::ConfigParserError(format!(
+                    "layer_modifier {} is not a button of the device",
+                    layer_modifier
+                )));
+            }
+        }
+
+        if config.poll_interval_ms == Some(0) {
+            return Err(Error::ConfigParserError(String::from(
+                "poll_interval_ms must be greater than 0",
+            )));
+        }
+
+        let on_any_button = match &config.on_any_button {
+            None => None,
+            Some(c) => Some(Arc::new(EventHandler::from_config(c)?)),
+        };
+        let default_button_handler = match &config.default_button_handler {
+            None => None,
+            Some(c) => Some(Arc::new(EventHandler::from_config(c)?)),
+        };
 
         let mut result = AppState {
             defaults,
@@ -123,16 +322,38 @@ impl AppState {
             pages,
             buttons,
             init_handler,
+            shutdown_handler,
+            on_any_button,
+            default_button_handler,
+            layer_modifier: config.layer_modifier,
             device_type: device_type.clone(),
+            has_display: super::has_display(device_type),
             loaded_pages: Vec::new(),
             foreground_window: None,
+            queued_handlers: Vec::new(),
+            page_history: Vec::new(),
+            latched_buttons: HashSet::new(),
+            selected_in_groups: HashMap::new(),
+            overrides: HashMap::new(),
+            toasts: HashMap::new(),
+            touch_points: TouchPoint::all_from_config(&config.touch_points)?,
+            variables: HashMap::new(),
+            window_switching_enabled: true,
+            loading_placeholders: config.loading_placeholder == Some(true),
+            pending_faces: Vec::new(),
+            metrics: Arc::new(Metrics::default()),
+            page_copies: HashMap::new(),
+            profiles,
         };
 
         if let Some(page_names) = &config.default_pages {
             for page_name in page_names {
-                result.load_page(page_name)?;
+                let on_load = result.load_page(page_name)?;
+                result.queue_handler(on_load);
             }
         }
+        // Loading the default pages can not be undone
+        result.page_history.clear();
         Ok(result)
     }
 
@@ -141,6 +362,208 @@ impl AppState {
         self.init_handler.clone()
     }
 
+    /// Returns the handler to be executed by the script engine, when the program exits
+    pub fn get_shutdown_handler(&self) -> Option<Arc<EventHandler>> {
+        self.shutdown_handler.clone()
+    }
+
+    /// Queues a handler, to be executed later by the script engine.
+    pub fn queue_handler(&mut self, handler: Option<Arc<EventHandler>>) {
+        if let Some(handler) = handler {
+            self.queued_handlers.push(handler);
+        }
+    }
+
+    /// Returns (and removes) all queued handlers, in the order they have been queued.
+    pub fn take_queued_handlers(&mut self) -> Vec<Arc<EventHandler>> {
+        std::mem::take(&mut self.queued_handlers)
+    }
+
+    /// The name of the named button currently on the button with the given id
+    pub fn button_name(&self, button_id: usize) -> Option<String> {
+        self.buttons.get(button_id).map(|b| b.button_name().clone())
+    }
+
+    /// Names of all named buttons (without the ones of toasts and the copies of pages), sorted alphabetically.
+    pub fn named_button_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .named_buttons
+            .keys()
+            .filter(|name| !name.starts_with(TOAST_BUTTON_PREFIX) && !name.starts_with(PAGE_COPY_PREFIX))
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Names of the named buttons, that no button of any page refers to, sorted alphabetically.
+    ///
+    /// The "empty" button and the buttons of toasts are not included. Buttons, that are only
+    /// set by scripts, are included.
+    pub fn unreferenced_named_buttons(&self) -> Vec<String> {
+        let referenced: HashSet<&String> = self
+            .pages
+            .values()
+            .flat_map(|page| page.buttons.iter().map(|button| &button.button_name))
+            .collect();
+        self.named_button_names()
+            .into_iter()
+            .filter(|name| name != "empty" && !referenced.contains(name))
+            .collect()
+    }
+
+    /// Writes the up face of every named button as `<name>.png` into a directory,
+    /// for example for cheat sheets of the buttons.
+    ///
+    /// Named buttons without up face (and all buttons of devices without display) are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// dir - The directory to write the images to, it is created if it does not exist.
+    ///
+    /// # Return
+    ///
+    /// The paths of the written images, or an error if a face could not be drawn or written.
+    pub fn export_faces(&mut self, dir: &Path) -> Result<Vec<PathBuf>, Error> {
+        let names = self.named_button_names();
+        self.draw_deferred_faces(names.iter())?;
+        std::fs::create_dir_all(dir).map_err(|e| {
+            Error::ImageSavingError(dir.display().to_string(), image::ImageError::IoError(e))
+        })?;
+
+        let mut paths = Vec::new();
+        for name in names {
+            let face = match self.named_buttons.get(&name).and_then(|s| s.up_face.as_ref()) {
+                Some(face) if face.face.width() > 0 && face.face.height() > 0 => face,
+                _ => continue,
+            };
+            let path = dir.join(format!("{}.png", name));
+            face.face
+                .save(&path)
+                .map_err(|e| Error::ImageSavingError(path.display().to_string(), e))?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// The named button currently shown on each button of the device.
+    ///
+    /// # Return
+    ///
+    /// Tuples of the button index and the name of the named button shown on it.
+    pub fn visible_buttons(&self) -> Vec<(usize, String)> {
+        self.buttons
+            .iter()
+            .enumerate()
+            .map(|(id, button)| (id, button.button_name().clone()))
+            .collect()
+    }
+
+    /// Activates or deactivates the layer of all buttons, except the modifier button itself.
+    fn set_layer_active(&mut self, modifier_id: usize, layer_active: bool) {
+        for (id, button) in self.buttons.iter_mut().enumerate() {
+            if id != modifier_id {
+                button.set_layer_active(layer_active);
+            }
+        }
+    }
+
+    /// Whether the button at the given position is enabled (buttons without setup are).
+    fn is_button_enabled(&self, button_id: usize) -> bool {
+        self.buttons
+            .get(button_id)
+            .and_then(|b| b.get_setup(&self.named_buttons))
+            .map_or(true, |s| s.enabled)
+    }
+
+    /// Enables or disables a named button.
+    ///
+    /// Disabled buttons are drawn dimmed and their handlers are not executed.
+    ///
+    /// # Arguments
+    ///
+    /// button_name - The name of the named button
+    /// enabled - Whether the button should be enabled
+    ///
+    /// # Return
+    ///
+    /// () if all went ok, Error if the button was not found.
+    pub fn set_button_enabled(&mut self, button_name: &String, enabled: bool) -> Result<(), Error> {
+        let button_name = &self.named_button_to_change(button_name);
+        let setup = self
+            .named_buttons
+            .get_mut(button_name)
+            .ok_or(Error::ButtonNotFound(button_name.clone()))?;
+        setup.set_enabled(enabled);
+        for button in self.buttons.iter_mut() {
+            if button.uses_button(button_name) {
+                button.set_needs_rendering();
+            }
+        }
+        Ok(())
+    }
+
+    /// The top most loaded page, that defines the button at the given position.
+    fn page_of_button(&self, button_id: usize) -> Option<&Page> {
+        self.loaded_pages
+            .iter()
+            .rev()
+            .filter_map(|page_name| self.pages.get(page_name))
+            .find(|page| {
+                page.get_button(&self.device_type, &self.defaults.column_order, button_id)
+                    .is_some()
+            })
+            .map(|page| page.as_ref())
+    }
+
+    /// The handlers for a button event, in the order in which they should be executed:
+    /// The handler of the button itself, the `on_any_button` handler of the page the
+    /// button belongs to and the global `on_any_button` handler.
+    fn button_event_handlers<'a>(
+        &'a self,
+        button_id: usize,
+        button_handlers: Vec<&'a EventHandler>,
+    ) -> Vec<&'a EventHandler> {
+        let page_handler = self
+            .page_of_button(button_id)
+            .and_then(|page| page.on_any_button.as_deref());
+        button_handlers
+            .into_iter()
+            .chain(page_handler)
+            .chain(self.on_any_button.as_deref())
+            .collect()
+    }
+
+    /// What pressing or releasing a button would execute, without changing any state.
+    ///
+    /// Loading the page of the button (and the handlers of that page) is not included.
+    ///
+    /// # Arguments
+    ///
+    /// button_id - The id of the button.
+    /// edge - Whether the button would be pressed ([PressState::Down]) or released ([PressState::Up]).
+    ///
+    /// # Return
+    ///
+    /// The name of the named button on the button and the handlers, that [AppState::on_button_pressed]
+    /// or [AppState::on_button_released] would return. None if there is no such button.
+    pub fn peek_button_handler(&self, button_id: usize, edge: &PressState) -> Option<HandlerInfo> {
+        let button = self.buttons.get(button_id)?;
+        let handlers = if self.is_button_enabled(button_id) {
+            let mut handlers = button.peek_handlers(&self.named_buttons, edge);
+            if *edge == PressState::Down && handlers.is_empty() && button.button_name() == "empty" {
+                handlers.extend(self.default_button_handler.as_deref());
+            }
+            self.button_event_handlers(button_id, handlers)
+        } else {
+            Vec::new()
+        };
+        Some(HandlerInfo {
+            button_name: button.button_name().clone(),
+            handlers: handlers.into_iter().cloned().collect(),
+        })
+    }
+
     /// Button gets pressed
     ///
     /// # Arguments
@@ -149,10 +572,59 @@ impl AppState {
     ///
     /// # Return
     ///
-    /// Event handler, that should be executed as a result of the button press.
-    pub fn on_button_pressed(&mut self, button_id: usize) -> Option<&EventHandler> {
-        let button = self.buttons.get_mut(button_id)?;
-        button.set_pressed(&self.named_buttons)
+    /// Event handlers, that should be executed (in order) as a result of the button press.
+    pub fn on_button_pressed(&mut self, button_id: usize) -> Vec<&EventHandler> {
+        self.metrics.button_pressed(button_id);
+        if self.layer_modifier == Some(button_id) {
+            self.set_layer_active(button_id, true);
+        }
+        if !self.is_button_enabled(button_id) {
+            return Vec::new();
+        }
+
+        // Select the button in its group
+        let group = self
+            .buttons
+            .get(button_id)
+            .and_then(|b| Some((b.get_setup(&self.named_buttons)?.group.clone()?, b.button_name().clone())));
+        if let Some((group, button_name)) = group {
+            let button_name = AppState::original_button_name(&self.page_copies, &button_name).clone();
+            self.selected_in_groups.insert(group, button_name);
+            self.update_highlights();
+        }
+
+        // Load the page this button switches to
+        let page_to_load = self
+            .buttons
+            .get(button_id)
+            .and_then(|b| b.get_setup(&self.named_buttons))
+            .and_then(|s| s.load_page.clone());
+        if let Some(page_name) = page_to_load {
+            match self.load_page(&page_name) {
+                Ok(on_load) => self.queue_handler(on_load),
+                Err(e) => warn!("unable to load page {}: {:?}", page_name, e),
+            }
+        }
+
+        let button_handlers = match self.buttons.get_mut(button_id) {
+            None => return Vec::new(),
+            Some(button) => {
+                let mut handlers = button.set_pressed(&self.named_buttons);
+                // Buttons without setup use the default handler, if they have no handler themselves
+                if handlers.is_empty() && button.button_name() == "empty" {
+                    handlers.extend(self.default_button_handler.as_deref());
+                }
+                // Remember the latched state, for when the named button is shown again
+                let button_name = AppState::original_button_name(&self.page_copies, button.button_name());
+                if button.is_latched() {
+                    self.latched_buttons.insert(button_name.clone());
+                } else {
+                    self.latched_buttons.remove(button_name);
+                }
+                handlers
+            }
+        };
+        self.button_event_handlers(button_id, button_handlers)
     }
 
     /// Button gets released
@@ -163,10 +635,59 @@ impl AppState {
     ///
     /// # Return
     ///
-    /// Event handler, that should be executed as a result of the button release.
-    pub fn on_button_released(&mut self, button_id: usize) -> Option<&EventHandler> {
-        let button = self.buttons.get_mut(button_id)?;
-        button.set_released(&self.named_buttons)
+    /// Event handlers, that should be executed (in order) as a result of the button release.
+    pub fn on_button_released(&mut self, button_id: usize) -> Vec<&EventHandler> {
+        if self.layer_modifier == Some(button_id) {
+            self.set_layer_active(button_id, false);
+        }
+        if !self.is_button_enabled(button_id) {
+            return Vec::new();
+        }
+        let button_handlers = match self.buttons.get_mut(button_id) {
+            None => return Vec::new(),
+            Some(button) => {
+                let handlers = button.set_released(&self.named_buttons);
+                // Buttons with inverted presses latch on release
+                let button_name = AppState::original_button_name(&self.page_copies, button.button_name());
+                if button.is_latched() {
+                    self.latched_buttons.insert(button_name.clone());
+                } else {
+                    self.latched_buttons.remove(button_name);
+                }
+                handlers
+            }
+        };
+        self.button_event_handlers(button_id, button_handlers)
+    }
+
+    /// A touch point (next to the buttons) has been touched.
+    ///
+    /// # Arguments
+    ///
+    /// touch_point - Index of the touch point.
+    ///
+    /// # Return
+    ///
+    /// The down handler of the touch point, if it is configured.
+    pub fn on_touch_point_pressed(&self, touch_point: usize) -> Option<&EventHandler> {
+        self.touch_points
+            .get(touch_point)
+            .and_then(|t| t.down_handler.as_ref())
+    }
+
+    /// A touch point (next to the buttons) has been released.
+    ///
+    /// # Arguments
+    ///
+    /// touch_point - Index of the touch point.
+    ///
+    /// # Return
+    ///
+    /// The up handler of the touch point, if it is configured.
+    pub fn on_touch_point_released(&self, touch_point: usize) -> Option<&EventHandler> {
+        self.touch_points
+            .get(touch_point)
+            .and_then(|t| t.up_handler.as_ref())
     }
 
     /// Get all faces, that need rendering. Also sets all buttons do being rendered.
@@ -176,18 +697,151 @@ impl AppState {
     /// # Return
     ///
     /// List of tuples with the id of the button to be rendered and the ButtonFace that
-    /// should be rendered on the button.
+    /// should be rendered on the button (empty for devices without display).
     pub fn set_rendered_and_get_rendering_faces(&mut self) -> Vec<(u8, &ButtonFace)> {
         let mut result = Vec::new();
+        if !self.has_display {
+            return result;
+        }
         for (id, button) in self.buttons.iter_mut().enumerate() {
             match button.set_rendered_and_get_face_for_rendering(&self.named_buttons) {
                 None => {}
-                Some(face) => result.push((id as u8, face.clone())),
+                Some(face) => result.push((id as u8, face)),
+            }
+        }
+        result
+    }
+
+    /// Ids of all buttons, that need rendering (none for devices without display).
+    pub fn buttons_needing_rendering(&self) -> Vec<usize> {
+        if !self.has_display {
+            return Vec::new();
+        }
+        self.buttons
+            .iter()
+            .enumerate()
+            .filter(|(_, button)| button.needs_rendering())
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Like [AppState::set_rendered_and_get_rendering_faces], but only for the given buttons.
+    ///
+    /// # Arguments
+    ///
+    /// button_ids - The ids of the buttons to render, the other buttons keep needing rendering.
+    ///
+    /// # Return
+    ///
+    /// List of tuples with the id of the button to be rendered and the ButtonFace that
+    /// should be rendered on the button.
+    pub fn set_rendered_and_get_rendering_faces_of(
+        &mut self,
+        button_ids: &[usize],
+    ) -> Vec<(u8, &ButtonFace)> {
+        let mut result = Vec::new();
+        if !self.has_display {
+            return result;
+        }
+        for (id, button) in self.buttons.iter_mut().enumerate() {
+            if !button_ids.contains(&id) {
+                continue;
+            }
+            if let Some(face) = button.set_rendered_and_get_face_for_rendering(&self.named_buttons) {
+                result.push((id as u8, face));
             }
         }
         result
     }
 
+    /// Width and height of the images of the buttons of the device.
+    pub fn button_image_size(&self) -> (u32, u32) {
+        self.device_type.button_image_size()
+    }
+
+    /// Whether the device has displays, that faces are rendered on.
+    pub fn has_display(&self) -> bool {
+        self.has_display
+    }
+
+    /// The current state as JSON, for example for external dashboards.
+    ///
+    /// # Return
+    ///
+    /// A JSON object with the `loaded_pages`, the `pages` (with `name`, `description` and `tags`
+    /// of the loaded pages), the `buttons` (with `id`, `name`, `description` and `tags` of the
+    /// named button, whether it is `pressed` and where the script of its `down_handler`
+    /// and `up_handler` comes from) and the `foreground_window` (or null).
+    pub fn to_status_json(&self) -> serde_json::Value {
+        let buttons: Vec<serde_json::Value> = self
+            .buttons
+            .iter()
+            .enumerate()
+            .map(|(id, button)| {
+                let setup = button.get_setup(&self.named_buttons);
+                let handler_source = |handler: Option<&EventHandler>| match handler {
+                    None => serde_json::Value::Null,
+                    Some(handler) => json!(handler.source.to_string()),
+                };
+                json!({
+                    "id": id,
+                    "name": button.button_name(),
+                    "pressed": button.is_pressed(),
+                    "down_handler": handler_source(setup.and_then(|s| s.down_handler.as_ref())),
+                    "up_handler": handler_source(setup.and_then(|s| s.up_handler.as_ref())),
+                    "description": setup.and_then(|s| s.description.clone()),
+                    "tags": setup.map_or_else(Vec::new, |s| s.tags.clone()),
+                })
+            })
+            .collect();
+        let pages: Vec<serde_json::Value> = self
+            .loaded_pages
+            .iter()
+            .filter_map(|page_name| Some((page_name, self.pages.get(page_name)?)))
+            .map(|(page_name, page)| {
+                json!({
+                    "name": page_name,
+                    "description": page.description,
+                    "tags": page.tags,
+                })
+            })
+            .collect();
+        let foreground_window = match &self.foreground_window {
+            None => serde_json::Value::Null,
+            Some(window) => json!({
+                "title": window.title,
+                "executable": window.executable,
+                "class_name": window.class_name,
+                "workspace": window.workspace,
+            }),
+        };
+        json!({
+            "loaded_pages": self.loaded_pages,
+            "pages": pages,
+            "buttons": buttons,
+            "foreground_window": foreground_window,
+        })
+    }
+
+    /// Sets all buttons to need rendering, so that the next call to
+    /// [AppState::set_rendered_and_get_rendering_faces] returns the faces of all buttons.
+    ///
+    /// Images downloaded from URLs are forgotten, so that they are downloaded again
+    /// the next time a face using them is drawn.
+    pub fn invalidate_all(&mut self) {
+        forget_downloaded_images();
+        for button in self.buttons.iter_mut() {
+            button.set_needs_rendering();
+        }
+    }
+
+    /// Sets a single button to need rendering, for example because rendering it failed.
+    pub fn set_button_needs_rendering(&mut self, button_id: usize) {
+        if let Some(button) = self.buttons.get_mut(button_id) {
+            button.set_needs_rendering();
+        }
+    }
+
     /// Updates the up face of a named button.
     ///
     /// # Arguments
@@ -209,7 +863,8 @@ impl AppState {
         superlabel: Option<String>,
         superlabelcolor: Option<Rgba<u8>>,
     ) -> Result<(), Error> {
-        // Find the button
+        // Find the button (the copy of the page, if a loaded page isolates it)
+        let button_name = &self.named_button_to_change(button_name);
         let mut button= self
             .named_buttons
             .get_mut(button_name)
@@ -217,12 +872,13 @@ impl AppState {
 
         // Update the button
         if let Some(uf) = &mut button.up_face {
-            uf.update_values(color, file, label, labelcolor, sublabel, sublabelcolor, superlabel, superlabelcolor, &self.defaults)?;
+            Arc::make_mut(uf).update_values(color, file, label, labelcolor, sublabel, sublabelcolor, superlabel, superlabelcolor, &self.defaults)?;
         } else {
             let mut uf = ButtonFace::empty(self.device_type.clone());
             uf.update_values(color, file, label, labelcolor, sublabel, sublabelcolor, superlabel, superlabelcolor, &self.defaults)?;
-            button.up_face = Some(uf);
+            button.up_face = Some(Arc::new(uf));
         }
+        button.update_derived_faces();
         // Set all buttons using this to re-render!
         for mut button in self.buttons.iter_mut() {
             if button.uses_button(button_name) {
@@ -233,473 +889,4048 @@ impl AppState {
         Ok(())
     }
 
-    /// Loads a page, setting all the buttons.
+    /// Updates the up and the down face of a named button at once.
+    ///
+    /// Both faces are updated before the buttons using the named button are marked for
+    /// rendering, so the button is never shown with only one of the faces changed.
     ///
     /// # Arguments
     ///
-    /// page_name - Name of the page to be loaded.
+    /// button_name - The name of the named button
+    /// up - The new values of the up face
+    /// down - The new values of the down face, None to keep the down face (or, without
+    ///        down face, showing the up face while the button is pressed)
     ///
     /// # Return
     ///
-    /// () if all went ok, Error if the page is not found.
-    pub fn load_page(&mut self, page_name: &String) -> Result<(), Error> {
-        // Find the page
-        let page = self
-            .pages
-            .get(page_name)
-            .ok_or(Error::PageNotFound(page_name.clone()))?;
-
-        // Add page to stack
-        self.loaded_pages.push(page_name.clone());
+    /// () if all went ok, Error if the button was not found or a face could not be drawn.
+    /// On error, none of the faces is changed.
+    pub fn set_named_button_faces(
+        &mut self,
+        button_name: &String,
+        up: FaceValues,
+        down: Option<FaceValues>,
+    ) -> Result<(), Error> {
+        let button_name = &self.named_button_to_change(button_name);
+        let button = self
+            .named_buttons
+            .get_mut(button_name)
+            .ok_or(Error::ButtonNotFound(button_name.clone()))?;
 
-        // Load all the buttons
-        for button in &page.buttons {
-            self.buttons[button.position.to_button_index(&self.device_type)]
-                .set_button(button.button_name.clone());
+        let up_face = up.applied_to(&button.up_face, &self.device_type, &self.defaults)?;
+        let down_face = match down {
+            Some(down) => Some(down.applied_to(
+                &button.down_face,
+                &self.device_type,
+                &self.defaults,
+            )?),
+            None => None,
+        };
+        button.up_face = Some(Arc::new(up_face));
+        if let Some(down_face) = down_face {
+            button.down_face = Some(Arc::new(down_face));
+        }
+        button.update_derived_faces();
+        for button in self.buttons.iter_mut() {
+            if button.uses_button(button_name) {
+                button.set_needs_rendering();
+            }
         }
-
-        // All went fine!
-        debug!("page {} loaded", page_name);
         Ok(())
     }
 
-    /// Unloads a page, setting all the buttons that originate from this page to be empty.
+    /// Replaces the up face of a named button with an image.
+    ///
+    /// The image is shown as it is, without background color, labels or other face values.
     ///
     /// # Arguments
     ///
-    /// page_name - Name of the page to be un-loaded.
+    /// button_name - The name of the named button
+    /// width - Width of the image, must be the width of the buttons of the device
+    /// height - Height of the image, must be the height of the buttons of the device
+    /// rgb_bytes - The pixels of the image, row by row with 3 bytes (red, green, blue) per pixel
     ///
     /// # Return
     ///
-    /// () if all went ok, Error if something went wrong
-    pub fn unload_page(&mut self, page_name: &String) -> Result<(), Error> {
-        // Find the page
-        let page = self
-            .pages
-            .get(page_name)
-            .ok_or(Error::PageNotFound(page_name.clone()))?;
-
-        // Remove the page from the stack
-        self.loaded_pages.retain(|i| i != page_name);
-
-        // Get through all the buttons
-        for button_index in 0..self.device_type.total_num_buttons() {
-            if page.get_button(&self.device_type, button_index).is_some() {
-                // Button needs to be removed, that means we have to find the correct button from the stack!
-                self.buttons[button_index].set_button("empty".to_string());
-                for stack_page_name in &self.loaded_pages {
-                    if let Some(button) = self
-                        .pages
-                        .get(stack_page_name.as_str())
-                        .and_then(|p| p.get_button(&self.device_type, button_index))
-                    {
-                        self.buttons[button_index].set_button(button.button_name.clone());
-                    }
-                }
+    /// () if all went ok, Error if the button was not found or the image does not fit.
+    pub fn set_named_button_image(
+        &mut self,
+        button_name: &String,
+        width: u32,
+        height: u32,
+        rgb_bytes: Vec<u8>,
+    ) -> Result<(), Error> {
+        let button_name = &self.named_button_to_change(button_name);
+        if (width, height) != self.device_type.button_image_size() {
+            return Err(Error::InvalidImageSize(width, height));
+        }
+        if rgb_bytes.len() != (width * height * 3) as usize {
+            return Err(Error::InvalidImageBufferLength(rgb_bytes.len()));
+        }
+        let button = self
+            .named_buttons
+            .get_mut(button_name)
+            .ok_or(Error::ButtonNotFound(button_name.clone()))?;
+        let image = image::RgbImage::from_raw(width, height, rgb_bytes)
+            .ok_or(Error::InvalidImageSize(width, height))?;
+        button.up_face = Some(Arc::new(ButtonFace::from_image(self.device_type.clone(), image)));
+        button.update_derived_faces();
+        for button in self.buttons.iter_mut() {
+            if button.uses_button(button_name) {
+                button.set_needs_rendering();
             }
         }
-
-        // All went fine!
-        debug!("page {} un-loaded", page_name);
         Ok(())
     }
 
-    /// React to a foreground window
-    pub fn on_foreground_window(&mut self, window_info: &WindowInformation) -> Result<(), Error> {
-        let mut pages_to_load = Vec::new();
-        let mut pages_to_unload: Vec<String> = Vec::new();
-
-        for (page_name, page) in &self.pages {
-            for condition in &page.on_foreground_window {
-                if condition.matches(window_info) {
-                    pages_to_load.push(page_name.clone());
-                } else if page.unload_if_not_loaded && self.loaded_pages.contains(page_name) {
-                    pages_to_unload.push(page_name.clone());
+    /// All image files drawn on the faces of the named buttons.
+    pub fn image_files(&self) -> Vec<PathBuf> {
+        let mut result: Vec<PathBuf> = Vec::new();
+        for setup in self.named_buttons.values() {
+            for face in setup.faces() {
+                for file in face.files() {
+                    let path = PathBuf::from(file);
+                    if !result.contains(&path) {
+                        result.push(path);
+                    }
                 }
             }
         }
+        result
+    }
 
-        self.foreground_window = Some(window_info.clone());
-
-        for page_name in pages_to_load {
-            self.load_page(&page_name)?;
-        }
+    /// Names of all named buttons, with a face drawing the given image file.
+    pub fn named_buttons_using_file(&self, path: &Path) -> Vec<String> {
+        self.named_buttons
+            .iter()
+            .filter(|(_, setup)| setup.faces().any(|face| face.uses_file(path)))
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
 
-        for page_name in pages_to_unload {
-            self.unload_page(&page_name)?;
-        }
+    /// An image file has changed.
+    ///
+    /// Re-draws all faces using the file and sets the buttons showing them to re-render.
+    ///
+    /// # Arguments
+    ///
+    /// path - Path of the changed file.
+    ///
+    /// # Return
+    ///
+    /// () if all went ok, Error if a face could not be re-drawn.
+    pub fn on_file_changed(&mut self, path: &Path) -> Result<(), Error> {
+        for button_name in self.named_buttons_using_file(path) {
+            if let Some(setup) = self.named_buttons.get_mut(&button_name) {
+                for face in setup.faces_mut() {
+                    if face.uses_file(path) {
+                        Arc::make_mut(face).redraw(&self.defaults)?;
+                    }
+                }
+                setup.update_derived_faces();
+            }
+            for button in self.buttons.iter_mut() {
+                if button.uses_button(&button_name) {
+                    button.set_needs_rendering();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads a page, setting all the buttons.
+    ///
+    /// # Arguments
+    ///
+    /// page_name - Name of the page to be loaded.
+    ///
+    /// # Return
+    ///
+    /// The `on_load` handler of the page if all went ok, Error if the page is not found.
+    /// The handler should be executed before the next rendering, so that it can change
+    /// the faces of the page before they are shown.
+    pub fn load_page(&mut self, page_name: &String) -> Result<Option<Arc<EventHandler>>, Error> {
+        // Find the page
+        let page = self
+            .pages
+            .get(page_name)
+            .ok_or(Error::PageNotFound(page_name.clone()))?;
+        let on_load = page.on_load.clone();
+        let page = page.clone();
+        self.draw_deferred_faces(page.buttons.iter().map(|b| &b.button_name))?;
+        self.copy_named_buttons_for_page(page_name);
+
+        // Add page to top of the stack (a page can only be once in the stack)
+        let previous_pages = self.loaded_pages.clone();
+        self.loaded_pages.retain(|i| i != page_name);
+        self.loaded_pages.push(page_name.clone());
+        self.record_page_operation(previous_pages);
 
+        // Load all the buttons
+        for button in &page.buttons {
+            let button_index = button
+                .position
+                .to_button_index(&self.device_type, &self.defaults.column_order);
+            if self.overrides.contains_key(&button_index) {
+                continue;
+            }
+            let button_name = self.named_button_of_page(page_name, &button.button_name);
+            self.buttons[button_index].set_button(button_name);
+        }
+
+        self.update_highlights();
+        self.update_latches();
+
+        // All went fine!
+        self.metrics.page_loaded();
+        debug!("page {} loaded", page_name);
+        Ok(on_load)
+    }
+
+    /// Draws the faces of named buttons (for example of a page), that have not been drawn yet.
+    ///
+    /// Faces shared by several buttons are only drawn once. With loading placeholders, faces loading
+    /// images are replaced by a placeholder instead, and queued to be drawn in the background.
+    fn draw_deferred_faces<'a>(
+        &mut self,
+        button_names: impl Iterator<Item = &'a String>,
+    ) -> Result<(), Error> {
+        let mut drawn_faces: HashMap<*const ButtonFace, Arc<ButtonFace>> = HashMap::new();
+        for button_name in button_names {
+            let setup = match self.named_buttons.get_mut(button_name) {
+                None => continue,
+                Some(setup) => setup,
+            };
+            if setup.faces().all(|face| face.is_drawn()) {
+                continue;
+            }
+            for face in setup.faces_mut() {
+                if face.is_drawn() {
+                    continue;
+                }
+                let drawn_face = match drawn_faces.get(&Arc::as_ptr(face)) {
+                    Some(drawn_face) => drawn_face.clone(),
+                    None if self.loading_placeholders && face.loads_image() => {
+                        let placeholder = Arc::new(face.placeholder(&self.defaults));
+                        drawn_faces.insert(Arc::as_ptr(face), placeholder.clone());
+                        self.pending_faces.push(PendingFace {
+                            placeholder: placeholder.clone(),
+                            face: face.clone(),
+                        });
+                        placeholder
+                    }
+                    None => {
+                        let mut drawn_face = ButtonFace::clone(face);
+                        drawn_face.redraw(&self.defaults)?;
+                        let drawn_face = Arc::new(drawn_face);
+                        drawn_faces.insert(Arc::as_ptr(face), drawn_face.clone());
+                        drawn_face
+                    }
+                };
+                *face = drawn_face;
+            }
+            setup.update_derived_faces();
+        }
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::{ForegroundWindowConditionConfig, PageLoadConditions};
-    use image::RgbImage;
-    use std::borrow::Borrow;
-    use std::collections::hash_map::RandomState;
-    use std::collections::HashSet;
+    /// The faces of named buttons, that have not been drawn yet (faces shared by several buttons only once).
+    ///
+    /// These are the faces of pages, that have not been loaded yet. They can be drawn in the
+    /// background (without holding the lock of the state) and installed with [AppState::install_drawn_face].
+    pub fn undrawn_faces(&self) -> Vec<Arc<ButtonFace>> {
+        let mut names: Vec<&String> = self.named_buttons.keys().collect();
+        names.sort();
+        let mut faces: Vec<Arc<ButtonFace>> = Vec::new();
+        for name in names {
+            for face in self.named_buttons[name].face_arcs() {
+                if !face.is_drawn() && !faces.iter().any(|f| Arc::ptr_eq(f, face)) {
+                    faces.push(face.clone());
+                }
+            }
+        }
+        faces
+    }
 
-    /// Returns a full config to be used in tests
+    /// Replaces an undrawn face (or its placeholder) by the same face drawn outside of the state.
     ///
-    /// The config contains 1 page with all buttons!
-    fn get_full_config(add_doubled_name_error: bool) -> config::Config {
-        let mut named_buttons = Vec::new();
-        for i in 0..5 {
-            named_buttons.push(config::ButtonConfigWithName {
-                name: format!("named_button{}", i),
-                up_face: Some(config::ButtonFaceConfig {
-                    color: Some(config::ColorConfig::HEXString("#FF0000".to_string())),
-                    file: None,
-                    label: None,
-                    sublabel: None,
-                    superlabel: None,
-                }),
-                down_face: None,
-                up_handler: Some(config::EventHandlerConfig::AsCode {
-                    code: format!("on_named_button{}_up", i),
-                }),
-                down_handler: Some(config::EventHandlerConfig::AsCode {
-                    code: format!("on_named_button{}_down", i),
-                }),
-            });
+    /// The buttons showing the face are rendered again.
+    ///
+    /// # Arguments
+    ///
+    /// undrawn - The face as returned by [AppState::undrawn_faces], or the placeholder of a pending face.
+    /// drawn - The drawn face.
+    ///
+    /// # Return
+    ///
+    /// The number of named buttons, whose face has been replaced. 0 if the face has been drawn or
+    /// replaced in the meantime (for example because its page has been loaded).
+    pub fn install_drawn_face(&mut self, undrawn: &Arc<ButtonFace>, drawn: Arc<ButtonFace>) -> usize {
+        let mut replaced = Vec::new();
+        for (name, setup) in self.named_buttons.iter_mut() {
+            let mut changed = false;
+            for face in setup.faces_mut() {
+                if Arc::ptr_eq(face, undrawn) {
+                    *face = drawn.clone();
+                    changed = true;
+                }
+            }
+            if changed {
+                setup.update_derived_faces();
+                replaced.push(name.clone());
+            }
         }
+        for button in self.buttons.iter_mut() {
+            if replaced.iter().any(|name| button.uses_button(name)) {
+                button.set_needs_rendering();
+            }
+        }
+        replaced.len()
+    }
 
-        let mut pages = Vec::new();
+    /// Returns (and removes) the faces shown with a placeholder, that have to be drawn in the background.
+    pub fn take_pending_faces(&mut self) -> Vec<PendingFace> {
+        std::mem::take(&mut self.pending_faces)
+    }
 
-        for page_id in 0..3 {
-            let mut page_buttons = Vec::new();
-            for button_id in 0..15 {
-                if add_doubled_name_error {}
+    /// The defaults, the faces are drawn with.
+    pub fn defaults(&self) -> &Defaults {
+        &self.defaults
+    }
 
-                page_buttons.push(config::PageButtonConfig {
-                    position: config::ButtonPositionConfig::ButtonPositionObjectConfig(
-                        config::ButtonPositionObject {
-                            row: button_id / 5,
-                            col: button_id % 5,
-                        },
-                    ),
-                    button: config::ButtonOrButtonName::Button(config::ButtonConfigOptionalName {
-                        name: Some(
-                            if add_doubled_name_error && button_id == 0 && page_id == 0 {
-                                format!("named_button0")
-                            } else {
-                                format!("page{}_button{}", page_id, button_id)
-                            },
-                        ),
-                        up_face: Some(config::ButtonFaceConfig {
-                            color: None,
-                            file: None,
-                            label: Some(config::LabelConfig::JustText(format!(
-                                "page{}_button{}",
-                                page_id, button_id
-                            ))),
-                            sublabel: None,
-                            superlabel: None,
-                        }),
-                        down_face: None,
-                        up_handler: Some(config::EventHandlerConfig::AsCode {
-                            code: format!("on_page{}_button{}_up", page_id, button_id),
-                        }),
-                        down_handler: Some(config::EventHandlerConfig::AsCode {
-                            code: format!("on_page{}_button{}_down", page_id, button_id),
-                        }),
-                    }),
-                });
+    /// The counters about the usage of the deck.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Unloads a page, setting all the buttons that originate from this page to be empty.
+    ///
+    /// # Arguments
+    ///
+    /// page_name - Name of the page to be un-loaded.
+    ///
+    /// # Return
+    ///
+    /// The `on_unload` handler of the page if all went ok, Error if something went wrong.
+    pub fn unload_page(&mut self, page_name: &String) -> Result<Option<Arc<EventHandler>>, Error> {
+        // Find the page
+        let page = self
+            .pages
+            .get(page_name)
+            .cloned()
+            .ok_or(Error::PageNotFound(page_name.clone()))?;
+        let on_unload = page.on_unload.clone();
+
+        // Remove the page from the stack
+        let previous_pages = self.loaded_pages.clone();
+        self.loaded_pages.retain(|i| i != page_name);
+        self.record_page_operation(previous_pages);
+
+        // Get through all the buttons
+        for button_index in 0..self.device_type.total_num_buttons() {
+            if self.overrides.contains_key(&button_index) {
+                continue;
+            }
+            if page.get_button(&self.device_type, &self.defaults.column_order, button_index).is_some() {
+                // Button needs to be removed, that means we have to find the correct button from the stack!
+                let button_name = self.page_button_name(button_index);
+                self.buttons[button_index].set_button(button_name);
             }
-            pages.push(config::PageConfig {
-                on_app: Some(PageLoadConditions {
-                    conditions: vec![ForegroundWindowConditionConfig {
-                        executable: Some(format!(".*page{}_exec.*", page_id)),
-                        title: Some(format!(".*page{}_title.*", page_id)),
-                        class_name: None,
-                    }],
-                    remove: None,
-                }),
-                name: format!("page{}", page_id),
-                buttons: page_buttons,
-            });
         }
+        self.remove_named_button_copies_of_page(page_name);
 
-        let on_app = None;
+        self.update_highlights();
+        self.update_latches();
 
-        config::Config {
-            defaults: None,
-            buttons: Some(named_buttons),
-            pages,
-            on_app,
-            init_script: None,
-            default_pages: Some(vec!["page0".to_string()]),
+        // All went fine!
+        self.metrics.page_unloaded();
+        debug!("page {} un-loaded", page_name);
+        Ok(on_unload)
+    }
+
+    /// Remember the stack of loaded pages before a page operation, if the operation changed it.
+    fn record_page_operation(&mut self, previous_pages: Vec<String>) {
+        if previous_pages == self.loaded_pages {
+            return;
+        }
+        if self.page_history.len() == PAGE_HISTORY_LENGTH {
+            self.page_history.remove(0);
+        }
+        self.page_history.push(previous_pages);
+    }
+
+    /// Reverses the last page operation (loading or unloading a page).
+    ///
+    /// The `on_load` handlers of pages loaded again and the `on_unload` handlers
+    /// of pages unloaded again are queued.
+    ///
+    /// # Return
+    ///
+    /// true if an operation has been undone, false if there was nothing to undo.
+    pub fn undo_page_operation(&mut self) -> bool {
+        let previous_pages = match self.page_history.pop() {
+            None => return false,
+            Some(previous_pages) => previous_pages,
+        };
+        self.replace_loaded_pages(previous_pages);
+        debug!("page operation undone");
+        true
+    }
+
+    /// The loaded pages, from the bottom to the top of the stack.
+    pub fn get_page_stack(&self) -> Vec<String> {
+        self.loaded_pages.clone()
+    }
+
+    /// Activates a profile: replaces all loaded pages by the default pages of the profile.
+    ///
+    /// Like with [AppState::set_page_stack], the handlers of the pages changing are queued
+    /// and the operation can be undone.
+    ///
+    /// # Arguments
+    ///
+    /// profile_name - The name of the profile.
+    ///
+    /// # Return
+    ///
+    /// () if all went ok, Error if the profile does not exist (the state is not changed then).
+    pub fn activate_profile(&mut self, profile_name: &String) -> Result<(), Error> {
+        let page_names = self
+            .profiles
+            .get(profile_name)
+            .ok_or_else(|| Error::ProfileNotFound(profile_name.clone()))?
+            .clone();
+        self.set_page_stack(page_names)?;
+        debug!("profile {} activated", profile_name);
+        Ok(())
+    }
+
+    /// Replaces all loaded pages at once, for example to restore a saved page stack.
+    ///
+    /// The result is the same as unloading all pages and loading the given pages in order,
+    /// but the buttons are only set once and it is a single operation for [AppState::undo_page_operation].
+    /// The `on_unload` handlers of the pages, that are no longer loaded, and the `on_load` handlers
+    /// of the pages, that were not loaded before, are queued.
+    ///
+    /// # Arguments
+    ///
+    /// page_names - The pages to load, from the bottom to the top of the stack.
+    ///
+    /// # Return
+    ///
+    /// () if all went ok, Error if any of the pages does not exist (the state is not changed then).
+    pub fn set_page_stack(&mut self, page_names: Vec<String>) -> Result<(), Error> {
+        let missing_pages: Vec<String> = page_names
+            .iter()
+            .filter(|page_name| !self.pages.contains_key(*page_name))
+            .cloned()
+            .collect();
+        if !missing_pages.is_empty() {
+            return Err(Error::PagesNotFound(missing_pages));
+        }
+
+        // A page can only be once in the stack, like with loading the pages in order
+        let mut new_pages: Vec<String> = Vec::new();
+        for page_name in page_names {
+            new_pages.retain(|i| *i != page_name);
+            new_pages.push(page_name);
+        }
+
+        let button_names: Vec<String> = new_pages
+            .iter()
+            .filter_map(|page_name| self.pages.get(page_name))
+            .flat_map(|page| page.buttons.iter().map(|b| b.button_name.clone()))
+            .collect();
+        self.draw_deferred_faces(button_names.iter())?;
+
+        let previous_pages = self.loaded_pages.clone();
+        self.replace_loaded_pages(new_pages);
+        self.record_page_operation(previous_pages);
+        debug!("page stack set to {:?}", self.loaded_pages);
+        Ok(())
+    }
+
+    /// Sets the loaded pages and the buttons shown by them, queuing the handlers of the pages that change.
+    fn replace_loaded_pages(&mut self, new_pages: Vec<String>) {
+        // Queue the handlers of the pages, that change
+        for page_name in &new_pages {
+            if !self.loaded_pages.contains(page_name) {
+                let on_load = self.pages.get(page_name).and_then(|p| p.on_load.clone());
+                self.queue_handler(on_load);
+            }
+        }
+        for page_name in &self.loaded_pages.clone() {
+            if !new_pages.contains(page_name) {
+                let on_unload = self.pages.get(page_name).and_then(|p| p.on_unload.clone());
+                self.queue_handler(on_unload);
+            }
+        }
+
+        for page_name in &self.loaded_pages.clone() {
+            if !new_pages.contains(page_name) {
+                self.remove_named_button_copies_of_page(page_name);
+            }
+        }
+        for page_name in &new_pages {
+            self.copy_named_buttons_for_page(page_name);
+        }
+        self.loaded_pages = new_pages;
+
+        // Set the buttons from the top most page defining them
+        for button_index in 0..self.device_type.total_num_buttons() {
+            if self.overrides.contains_key(&button_index) {
+                continue;
+            }
+            let button_name = self.page_button_name(button_index);
+            if *self.buttons[button_index].button_name() != button_name {
+                self.buttons[button_index].set_button(button_name);
+            }
+        }
+
+        self.update_highlights();
+        self.update_latches();
+    }
+
+    /// The name of the named button, the loaded pages set on a button ("empty" if no page sets it).
+    fn page_button_name(&self, button_index: usize) -> String {
+        self.loaded_pages
+            .iter()
+            .rev()
+            .find_map(|page_name| {
+                let button = self
+                    .pages
+                    .get(page_name)?
+                    .get_button(&self.device_type, &self.defaults.column_order, button_index)?;
+                Some(self.named_button_of_page(page_name, &button.button_name))
+            })
+            .unwrap_or_else(|| String::from("empty"))
+    }
+
+    /// The name of the copy of a named button made for a page.
+    fn page_copy_name(page_name: &str, button_name: &str) -> String {
+        format!("{}{}:{}", PAGE_COPY_PREFIX, page_name, button_name)
+    }
+
+    /// The named button, a page shows for one of its buttons (its copy, if the page has one).
+    fn named_button_of_page(&self, page_name: &String, button_name: &String) -> String {
+        match self.page_copies.get(page_name) {
+            Some(copied) if copied.contains(button_name) => AppState::page_copy_name(page_name, button_name),
+            _ => button_name.clone(),
+        }
+    }
+
+    /// The named button a copy for a page was made of (the name itself, if it is not a copy).
+    ///
+    /// Latches and selections in groups are kept for the original named button, so that the
+    /// copies and the original share them.
+    fn original_button_name<'a>(
+        page_copies: &'a HashMap<String, Vec<String>>,
+        button_name: &'a String,
+    ) -> &'a String {
+        page_copies
+            .iter()
+            .find_map(|(page_name, copied)| {
+                copied
+                    .iter()
+                    .find(|copied| AppState::page_copy_name(page_name, copied) == *button_name)
+            })
+            .unwrap_or(button_name)
+    }
+
+    /// The named button changed by scripts for a name.
+    ///
+    /// While a page isolating its named buttons is loaded, its copy is changed instead of the
+    /// named button shared with other pages (the copy of the top most page, if several have one).
+    fn named_button_to_change(&self, button_name: &String) -> String {
+        self.loaded_pages
+            .iter()
+            .rev()
+            .find(|page_name| {
+                self.page_copies
+                    .get(*page_name)
+                    .map_or(false, |copied| copied.contains(button_name))
+            })
+            .map_or_else(|| button_name.clone(), |page_name| AppState::page_copy_name(page_name, button_name))
+    }
+
+    /// Copies the named buttons of a page isolating its named buttons, if they are not copied yet.
+    ///
+    /// The copies are made when the page is loaded, changes made before to the named buttons are
+    /// part of the copies.
+    fn copy_named_buttons_for_page(&mut self, page_name: &String) {
+        let page = match self.pages.get(page_name) {
+            Some(page) if page.isolate_named_buttons => page.clone(),
+            _ => return,
+        };
+        if self.page_copies.contains_key(page_name) {
+            return;
         }
+        let mut copied = Vec::new();
+        for button in &page.buttons {
+            if copied.contains(&button.button_name) {
+                continue;
+            }
+            if let Some(setup) = self.named_buttons.get(&button.button_name) {
+                let copy = setup.clone();
+                self.named_buttons
+                    .insert(AppState::page_copy_name(page_name, &button.button_name), copy);
+                copied.push(button.button_name.clone());
+            }
+        }
+        self.page_copies.insert(page_name.clone(), copied);
+    }
+
+    /// Removes the copies of the named buttons of a page, for example because it has been unloaded.
+    ///
+    /// The buttons must not show the copies anymore.
+    fn remove_named_button_copies_of_page(&mut self, page_name: &String) {
+        for button_name in self.page_copies.remove(page_name).unwrap_or_default() {
+            self.named_buttons
+                .remove(&AppState::page_copy_name(page_name, &button_name));
+        }
+    }
+
+    /// Shows a named button on a button, regardless of the loaded pages.
+    ///
+    /// The override stays until [AppState::clear_override] is called, loading and unloading
+    /// pages does not change the button.
+    ///
+    /// # Arguments
+    ///
+    /// button_index - The index of the button on the device.
+    /// button_name - The name of the named button to show.
+    ///
+    /// # Return
+    ///
+    /// () if all went ok, Error if the button index or the named button does not exist.
+    pub fn override_button(&mut self, button_index: usize, button_name: &String) -> Result<(), Error> {
+        if button_index >= self.buttons.len() {
+            return Err(Error::InvalidButtonIndex(button_index));
+        }
+        if !self.named_buttons.contains_key(button_name) {
+            return Err(Error::ButtonNotFound(button_name.clone()));
+        }
+        self.draw_deferred_faces(std::iter::once(button_name))?;
+        self.remove_toast(button_index);
+        self.overrides.insert(button_index, button_name.clone());
+        self.buttons[button_index].set_button(button_name.clone());
+        self.update_highlights();
+        self.update_latches();
+        Ok(())
+    }
+
+    /// Removes the override of a button, showing the button of the loaded pages again.
+    ///
+    /// # Return
+    ///
+    /// true if the button was overridden, false otherwise.
+    pub fn clear_override(&mut self, button_index: usize) -> bool {
+        self.remove_toast(button_index);
+        if self.overrides.remove(&button_index).is_none() {
+            return false;
+        }
+        let button_name = self.page_button_name(button_index);
+        self.buttons[button_index].set_button(button_name);
+        self.update_highlights();
+        self.update_latches();
+        true
+    }
+
+    /// Indexes of the buttons, currently showing a named button.
+    pub fn buttons_showing(&self, button_name: &String) -> Vec<usize> {
+        self.buttons
+            .iter()
+            .enumerate()
+            .filter(|(_, button)| button.button_name() == button_name)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Shows a text on a button for a while, for example as feedback for an action.
+    ///
+    /// The face of the button is drawn with the text as label (and the color blended over it).
+    /// Pressing the button executes its handlers as usual. After the duration, the toast is
+    /// removed by [AppState::expire_toasts].
+    ///
+    /// # Arguments
+    ///
+    /// button_index - The index of the button on the device.
+    /// text - The text of the toast.
+    /// color - Color blended over the face, if given.
+    /// duration - How long the toast is shown.
+    /// now - The current time.
+    ///
+    /// # Return
+    ///
+    /// () if all went ok, Error if the button index does not exist or the face could not be drawn.
+    pub fn toast(
+        &mut self,
+        button_index: usize,
+        text: &str,
+        color: Option<Rgba<u8>>,
+        duration: Duration,
+        now: Instant,
+    ) -> Result<(), Error> {
+        if button_index >= self.buttons.len() {
+            return Err(Error::InvalidButtonIndex(button_index));
+        }
+        // A new toast replaces the old one, but not the override below it
+        let previous_override = match self.toasts.remove(&button_index) {
+            Some(toast) => toast.previous_override,
+            None => self.overrides.get(&button_index).cloned(),
+        };
+        let covered_name = previous_override
+            .clone()
+            .unwrap_or_else(|| self.page_button_name(button_index));
+        let covered = self.named_buttons.get(&covered_name);
+        let face = match covered.and_then(|setup| setup.active_up_face()) {
+            Some(face) => face.toasted(text, color, &self.defaults)?,
+            None => ButtonFace::empty(self.device_type.clone()).toasted(text, color, &self.defaults)?,
+        };
+        let setup = ButtonSetup::for_toast(face, covered);
+
+        let toast_name = format!("{}{}", TOAST_BUTTON_PREFIX, button_index);
+        self.named_buttons.insert(toast_name.clone(), setup);
+        self.toasts.insert(
+            button_index,
+            Toast {
+                expires: now + duration,
+                previous_override,
+            },
+        );
+        self.overrides.insert(button_index, toast_name.clone());
+        self.buttons[button_index].set_button(toast_name);
+        self.update_highlights();
+        self.update_latches();
+        Ok(())
+    }
+
+    /// Removes the expired toasts, showing the buttons below them again.
+    ///
+    /// # Arguments
+    ///
+    /// now - The current time.
+    pub fn expire_toasts(&mut self, now: Instant) {
+        let expired: Vec<usize> = self
+            .toasts
+            .iter()
+            .filter(|(_, toast)| toast.expires <= now)
+            .map(|(button_index, _)| *button_index)
+            .collect();
+        for button_index in expired {
+            let toast = self.remove_toast(button_index).unwrap();
+            self.clear_override(button_index);
+            if let Some(previous_override) = toast.previous_override {
+                if let Err(e) = self.override_button(button_index, &previous_override) {
+                    warn!("unable to restore override of button {}: {}", button_index, e);
+                }
+            }
+        }
+    }
+
+    /// Forgets the toast of a button and removes its named button (the override stays).
+    fn remove_toast(&mut self, button_index: usize) -> Option<Toast> {
+        let toast = self.toasts.remove(&button_index)?;
+        self.named_buttons
+            .remove(&format!("{}{}", TOAST_BUTTON_PREFIX, button_index));
+        Some(toast)
+    }
+
+    /// The time, the next toast expires (None if no toast is shown).
+    pub fn next_toast_expiry(&self) -> Option<Instant> {
+        self.toasts.values().map(|toast| toast.expires).min()
+    }
+
+    /// Highlight all buttons, which load a page that is currently loaded.
+    fn update_highlights(&mut self) {
+        for button in self.buttons.iter_mut() {
+            let setup = button.get_setup(&self.named_buttons);
+            let page_loaded = setup
+                .and_then(|s| s.load_page.as_ref())
+                .map_or(false, |p| self.loaded_pages.contains(p));
+            let selected = setup
+                .and_then(|s| s.group.as_ref())
+                .and_then(|g| self.selected_in_groups.get(g))
+                .map_or(false, |selected| {
+                    selected == AppState::original_button_name(&self.page_copies, button.button_name())
+                });
+            button.set_highlighted(page_loaded || selected);
+        }
+    }
+
+    /// The selected named button of a button group, if any button of the group has been selected.
+    pub fn selected_in_group(&self, group: &String) -> Option<&String> {
+        self.selected_in_groups.get(group)
+    }
+
+    /// Selects a named button in its group (it is highlighted), deselecting the other buttons of the group.
+    ///
+    /// # Arguments
+    ///
+    /// group - The name of the group.
+    /// button_name - The name of the named button to select.
+    ///
+    /// # Return
+    ///
+    /// () if all went ok, Error if the button is not found or does not belong to the group.
+    pub fn select_in_group(&mut self, group: &String, button_name: &String) -> Result<(), Error> {
+        let setup = self
+            .named_buttons
+            .get(&self.named_button_to_change(button_name))
+            .ok_or_else(|| Error::ButtonNotFound(button_name.clone()))?;
+        if setup.group.as_ref() != Some(group) {
+            return Err(Error::ButtonNotInGroup(button_name.clone(), group.clone()));
+        }
+        self.selected_in_groups
+            .insert(group.clone(), button_name.clone());
+        self.update_highlights();
+        Ok(())
+    }
+
+    /// Set the latched state of all buttons, from the state of the named button shown on them.
+    fn update_latches(&mut self) {
+        for button in self.buttons.iter_mut() {
+            let latched = self
+                .latched_buttons
+                .contains(AppState::original_button_name(&self.page_copies, button.button_name()));
+            button.set_latched(latched);
+        }
+    }
+
+    /// Whether a latching named button is latched.
+    pub fn button_latch(&self, button_name: &String) -> bool {
+        self.latched_buttons.contains(button_name)
+    }
+
+    /// Latches or unlatches a named button.
+    ///
+    /// # Arguments
+    ///
+    /// button_name - The name of the named button
+    /// latched - Whether the button should be latched
+    ///
+    /// # Return
+    ///
+    /// () if all went ok, Error if the button was not found.
+    pub fn set_button_latch(&mut self, button_name: &String, latched: bool) -> Result<(), Error> {
+        if !self.named_buttons.contains_key(&self.named_button_to_change(button_name)) {
+            return Err(Error::ButtonNotFound(button_name.clone()));
+        }
+        if latched {
+            self.latched_buttons.insert(button_name.clone());
+        } else {
+            self.latched_buttons.remove(button_name);
+        }
+        self.update_latches();
+        Ok(())
+    }
+
+    /// React to a foreground window
+    pub fn on_foreground_window(&mut self, window_info: &WindowInformation) -> Result<(), Error> {
+        let pages_to_load: Vec<String> = self
+            .pages
+            .iter()
+            .filter(|(_, page)| page.matches_window(window_info))
+            .map(|(page_name, _)| page_name.clone())
+            .collect();
+
+        self.foreground_window = Some(window_info.clone());
+
+        // Swap the conditional faces of the named buttons
+        for (button_name, setup) in self.named_buttons.iter_mut() {
+            if setup.on_foreground_window(window_info) {
+                for button in self.buttons.iter_mut() {
+                    if button.uses_button(button_name) {
+                        button.set_needs_rendering();
+                    }
+                }
+            }
+        }
+
+        // Keep the pages, while switching them is disabled
+        if !self.window_switching_enabled {
+            return Ok(());
+        }
+
+        // Pages already on top (for example after another event of the same window) are not
+        // loaded again, so that their on_load handlers do not run again
+        let top_pages = &self.loaded_pages[self.loaded_pages.len().saturating_sub(pages_to_load.len())..];
+        if !pages_to_load.iter().all(|page_name| top_pages.contains(page_name)) {
+            for page_name in pages_to_load {
+                let on_load = self.load_page(&page_name)?;
+                self.queue_handler(on_load);
+            }
+        }
+
+        self.unload_app_pages(window_info)
+    }
+
+    /// Enables or disables loading and unloading pages for the foreground window,
+    /// for example to keep the current pages during a presentation.
+    ///
+    /// While disabled, the foreground window is still recorded (and the faces for it are shown),
+    /// switching resumes with the next foreground window after enabling it again.
+    pub fn set_window_switching_enabled(&mut self, enabled: bool) {
+        self.window_switching_enabled = enabled;
+    }
+
+    /// Sets a variable for the scripts, named buttons with a face for the variable
+    /// switch to it (or back) and are rendered again.
+    ///
+    /// # Arguments
+    ///
+    /// name - The name of the variable.
+    /// value - The new value, faces are used while it is true (like in python).
+    pub fn set_var(&mut self, name: &str, value: serde_json::Value) {
+        self.variables.insert(name.to_string(), value);
+        for (button_name, setup) in self.named_buttons.iter_mut() {
+            if setup.on_variables_changed(&self.variables) {
+                for button in self.buttons.iter_mut() {
+                    if button.uses_button(button_name) {
+                        button.set_needs_rendering();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Show the next of the cycle faces of a named button (the first after the last).
+    ///
+    /// # Arguments
+    ///
+    /// button_name - The name of the named button.
+    ///
+    /// # Return
+    ///
+    /// The index of the face shown now, or an error if the button does not exist or has no cycle faces.
+    pub fn cycle_button_face(&mut self, button_name: &String) -> Result<usize, Error> {
+        let button_name = &self.named_button_to_change(button_name);
+        let setup = self
+            .named_buttons
+            .get_mut(button_name)
+            .ok_or(Error::ButtonNotFound(button_name.clone()))?;
+        let index = setup
+            .cycle_face()
+            .ok_or(Error::NoCycleFaces(button_name.clone()))?;
+        for button in self.buttons.iter_mut() {
+            if button.uses_button(button_name) {
+                button.set_needs_rendering();
+            }
+        }
+        Ok(index)
+    }
+
+    /// The value of a variable set with [AppState::set_var], None if it is not set.
+    pub fn get_var(&self, name: &str) -> Option<&serde_json::Value> {
+        self.variables.get(name)
+    }
+
+    /// Unloads all loaded pages, that should only be loaded while their app is in the foreground,
+    /// but none of their conditions matches the window anymore.
+    ///
+    /// # Arguments
+    ///
+    /// window_info - The new foreground window.
+    ///
+    /// # Return
+    ///
+    /// () if all went ok, Error if a page could not be unloaded.
+    pub fn unload_app_pages(&mut self, window_info: &WindowInformation) -> Result<(), Error> {
+        let pages_to_unload: Vec<String> = self
+            .pages
+            .iter()
+            .filter(|(page_name, page)| {
+                page.unload_if_not_loaded
+                    && self.loaded_pages.contains(page_name)
+                    && !page.matches_window(window_info)
+            })
+            .map(|(page_name, _)| page_name.clone())
+            .collect();
+
+        for page_name in pages_to_unload {
+            let on_unload = self.unload_page(&page_name)?;
+            self.queue_handler(on_unload);
+        }
+
+        Ok(())
+    }
+}
+
+/// What a button press or release would execute, see [AppState::peek_button_handler].
+#[derive(Debug, Clone)]
+pub struct HandlerInfo {
+    /// Name of the named button shown on the button
+    pub button_name: String,
+    /// The handlers, that would be executed (in order)
+    pub handlers: Vec<EventHandler>,
+}
+
+/// New values of a face, set by a script. Missing values keep the value of the face.
+#[derive(Debug, Default)]
+pub struct FaceValues {
+    pub color: Option<Rgba<u8>>,
+    pub file: Option<String>,
+    pub label: Option<String>,
+    pub labelcolor: Option<Rgba<u8>>,
+    pub sublabel: Option<String>,
+    pub sublabelcolor: Option<Rgba<u8>>,
+    pub superlabel: Option<String>,
+    pub superlabelcolor: Option<Rgba<u8>>,
+}
+
+impl FaceValues {
+    /// A copy of a face (or of an empty face, if there is none) with these values applied.
+    fn applied_to(
+        self,
+        face: &Option<Arc<ButtonFace>>,
+        device_type: &StreamDeckType,
+        defaults: &Defaults,
+    ) -> Result<ButtonFace, Error> {
+        let mut face = match face {
+            Some(face) => face.as_ref().clone(),
+            None => ButtonFace::empty(device_type.clone()),
+        };
+        face.update_values(
+            self.color,
+            self.file,
+            self.label,
+            self.labelcolor,
+            self.sublabel,
+            self.sublabelcolor,
+            self.superlabel,
+            self.superlabelcolor,
+            defaults,
+        )?;
+        Ok(face)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ForegroundWindowConditionConfig, PageLoadConditions};
+    use image::RgbImage;
+    use std::borrow::Borrow;
+    use std::collections::hash_map::RandomState;
+    use std::collections::HashSet;
+
+    /// Returns a full config to be used in tests
+    ///
+    /// The config contains 1 page with all buttons!
+    fn get_full_config(add_doubled_name_error: bool) -> config::Config {
+        let mut named_buttons = Vec::new();
+        for i in 0..5 {
+            named_buttons.push(config::ButtonConfigWithName {
+                name: format!("named_button{}", i),
+                up_face: Some(config::ButtonFaceConfig {
+                    color: Some(config::ColorConfig::HEXString("#FF0000".to_string())),
+                    file: None,
+                    label: None,
+                    sublabel: None,
+                    superlabel: None,
+                    overlay: None,
+                    sprite: None,
+                    lines: None,
+                    pixelated: None,
+                    padding: None,
+                    background: None,
+                    color_key: None,
+                    color_key_tolerance: None,
+                }),
+                down_face: None,
+                up_handler: Some(config::EventHandlerConfig::AsCode {
+                    code: format!("on_named_button{}_up", i),
+                }),
+                down_handler: Some(config::EventHandlerConfig::AsCode {
+                    code: format!("on_named_button{}_down", i),
+                }),
+                load_page: None,
+                on_app_faces: None,
+                enabled: None,
+                layer_face: None,
+                layer_handlers: None,
+                action: None,
+                trigger: None,
+                latch: None,
+                group: None,
+                description: None,
+                tags: None,
+                when_faces: None,
+                up_handlers: None,
+                down_handlers: None,
+                cycle_faces: None,
+                invert_press: None,
+            });
+        }
+
+        let mut pages = Vec::new();
+
+        for page_id in 0..3 {
+            let mut page_buttons = Vec::new();
+            for button_id in 0..15 {
+                if add_doubled_name_error {}
+
+                page_buttons.push(config::PageButtonConfig {
+                    position: config::ButtonPositionConfig::ButtonPositionObjectConfig(
+                        config::ButtonPositionObject {
+                            row: button_id / 5,
+                            col: button_id % 5,
+                        },
+                    ),
+                    button: config::ButtonOrButtonName::Button(config::ButtonConfigOptionalName {
+                        name: Some(
+                            if add_doubled_name_error && button_id == 0 && page_id == 0 {
+                                format!("named_button0")
+                            } else {
+                                format!("page{}_button{}", page_id, button_id)
+                            },
+                        ),
+                        up_face: Some(config::ButtonFaceConfig {
+                            color: None,
+                            file: None,
+                            label: Some(config::LabelConfig::JustText(format!(
+                                "page{}_button{}",
+                                page_id, button_id
+                            ))),
+                            sublabel: None,
+                            superlabel: None,
+                            overlay: None,
+                            sprite: None,
+                            lines: None,
+                            pixelated: None,
+                            padding: None,
+                            background: None,
+                            color_key: None,
+                            color_key_tolerance: None,
+                        }),
+                        down_face: None,
+                        up_handler: Some(config::EventHandlerConfig::AsCode {
+                            code: format!("on_page{}_button{}_up", page_id, button_id),
+                        }),
+                        down_handler: Some(config::EventHandlerConfig::AsCode {
+                            code: format!("on_page{}_button{}_down", page_id, button_id),
+                        }),
+                        load_page: None,
+                        enabled: None,
+                        layer_face: None,
+                        layer_handlers: None,
+                        action: None,
+                        trigger: None,
+                        latch: None,
+                        group: None,
+                        description: None,
+                        tags: None,
+                        up_handlers: None,
+                        down_handlers: None,
+                        invert_press: None,
+                    }),
+                });
+            }
+            pages.push(config::PageConfig {
+                on_app: Some(PageLoadConditions {
+                    conditions: vec![config::PageConditionConfig::Window(
+                        ForegroundWindowConditionConfig {
+                            executable: Some(format!(".*page{}_exec.*", page_id)),
+                            title: Some(format!(".*page{}_title.*", page_id)),
+                            class_name: None,
+                            workspace: None,
+                            title_equals: None,
+                            title_starts_with: None,
+                            title_contains: None,
+                        },
+                    )],
+                    remove: None,
+                }),
+                name: format!("page{}", page_id),
+                buttons: page_buttons,
+                on_load: None,
+                on_unload: None,
+                on_any_button: None,
+                description: None,
+                tags: None,
+                isolate_named_buttons: None,
+            });
+        }
+
+        let on_app = None;
+
+        config::Config {
+            defaults: None,
+            buttons: Some(named_buttons),
+            pages,
+            on_app,
+            init_script: None,
+            default_pages: Some(vec!["page0".to_string()]),
+            strict_empty: None,
+            watch_images: None,
+            device_type: None,
+            on_any_button: None,
+            layer_modifier: None,
+            max_faces_per_frame: None,
+            default_button_handler: None,
+            poll_interval_ms: None,
+            log_level: None,
+            shutdown_script: None,
+            touch_points: None,
+            positions_left_to_right: None,
+            image_flip: None,
+            reset_on_start: None,
+            osc_address: None,
+            window_dedup_ms: None,
+            loading_placeholder: None,
+            max_render_hz: None,
+            metrics_address: None,
+            profiles: None,
+        }
+    }
+
+    #[test]
+    fn named_buttons_must_be_unique() {
+        // Setup
+        let config = get_full_config(true);
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn named_buttons_are_loaded_from_config() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        //Test
+        for i in 0..5 {
+            assert!(state
+                .named_buttons
+                .get(&format!("named_button{}", i))
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn pages_are_loaded_from_config() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        //Test
+        assert!(state.pages.get(&String::from("page1")).is_some());
+    }
+
+    #[test]
+    fn named_buttons_of_page_appear_in_named_buttons() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        //Test
+        for i in 0..15 {
+            assert!(state
+                .named_buttons
+                .get(&format!("page1_button{}", i))
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn correct_button_press_and_release_events_are_returned() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        // Page0 is default and loaded!
+        let press_script = state.on_button_pressed(0)[0].script.clone();
+        let release_script = state.on_button_released(0)[0].script.clone();
+
+        //Test
+        assert_eq!(press_script, String::from("on_page0_button4_down"));
+        assert_eq!(release_script, String::from("on_page0_button4_up"));
+    }
+
+    #[test]
+    fn default_page_is_loaded_and_all_buttons_need_rendering() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Test
+        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 15);
+    }
+
+    #[test]
+    fn after_button_press_face_is_returned_for_rendering() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 15);
+        state.on_button_pressed(0);
+
+        // Test
+        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 1);
+    }
+
+    #[test]
+    fn after_button_release_face_is_returned_for_rendering() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.on_button_pressed(0);
+        state.set_rendered_and_get_rendering_faces();
+        state.on_button_released(0);
+
+        // Test
+        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 1);
+    }
+
+    #[test]
+    fn button_press_and_release_results_in_no_need_for_rendering() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        state.on_button_pressed(0);
+        state.on_button_released(0);
+
+        // Test
+        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 0);
+    }
+
+    // Get the md5 sum of an image
+    fn image_md5(i: &RgbImage) -> md5::Digest {
+        md5::compute(i.as_raw())
+    }
+
+    #[test]
+    fn page_loading_results_in_face_for_new_button_returned_for_rendering() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        state.load_page(&"page1".to_string()).unwrap();
+
+        // Test
+        let face_md5s = HashSet::<md5::Digest, RandomState>::from_iter(
+            state
+                .set_rendered_and_get_rendering_faces()
+                .iter()
+                .map(|f| image_md5(&f.1.face)),
+        );
+        assert_eq!(face_md5s.len(), 15);
+        for index in 0..15 {
+            assert!(face_md5s.contains(&image_md5(
+                &state
+                    .named_buttons
+                    .get(format!("page1_button{index}").as_str())
+                    .unwrap()
+                    .up_face
+                    .as_ref()
+                    .unwrap()
+                    .face
+            )))
+        }
+    }
+
+    #[test]
+    fn page_loading_and_unloading_results_in_face_for_empty_needing_rendering() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.unload_page(&"page0".to_string()).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        state.load_page(&"page1".to_string()).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        state.unload_page(&"page1".to_string()).unwrap();
+
+        // Test
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        let face_md5s = HashSet::<md5::Digest, RandomState>::from_iter(
+            rendering_faces.iter().map(|f| image_md5(&f.1.face)),
+        );
+        assert_eq!(rendering_faces.len(), 15);
+        assert_eq!(face_md5s.len(), 1);
+        assert!(face_md5s.contains(&image_md5(
+            &state
+                .named_buttons
+                .get("empty".to_string().as_str())
+                .unwrap()
+                .up_face
+                .as_ref()
+                .unwrap()
+                .face
+        )));
+    }
+
+    #[test]
+    fn page_unloading_over_other_page_results_in_other_page_visible() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        state.load_page(&"page1".to_string()).unwrap();
+        state.load_page(&"page2".to_string()).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        state.unload_page(&"page2".to_string()).unwrap();
+
+        // Test
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        let face_md5s = HashSet::<md5::Digest, RandomState>::from_iter(
+            rendering_faces.iter().map(|f| image_md5(&f.1.face)),
+        );
+        assert_eq!(rendering_faces.len(), 15);
+        assert_eq!(face_md5s.len(), 15);
+        for index in 0..15 {
+            assert!(face_md5s.contains(&image_md5(
+                &state
+                    .named_buttons
+                    .get(format!("page1_button{index}").as_str())
+                    .unwrap()
+                    .up_face
+                    .as_ref()
+                    .unwrap()
+                    .face
+            )))
+        }
+    }
+
+    #[test]
+    fn not_existing_page_loading_results_in_error() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let result = state.load_page(&String::from("unkown_page"));
+
+        // Test
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn load_page_on_window() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state
+            .on_foreground_window(&WindowInformation {
+                title: String::from("This is a title for loading page2_title page"),
+                executable: String::from("/usr/bin/page2_exec"),
+                class_name: String::from("Some class we don't care about"),
+                workspace: None,
+            })
+            .unwrap();
+
+        // Test
+        assert_eq!(
+            state.on_button_pressed(0)[0].script,
+            "on_page2_button4_down"
+        );
+    }
+
+    /// Adds a named button "page_switch" loading "page1" at index 4 of "page0"
+    /// and empties "page1", so that the switch stays visible.
+    fn add_page_switch_button(config: &mut config::Config) {
+        config
+            .buttons
+            .as_mut()
+            .unwrap()
+            .push(config::ButtonConfigWithName {
+                name: "page_switch".to_string(),
+                up_face: Some(config::ButtonFaceConfig {
+                    color: Some(config::ColorConfig::HEXString("#FF0000".to_string())),
+                    file: None,
+                    label: None,
+                    sublabel: None,
+                    superlabel: None,
+                    overlay: None,
+                    sprite: None,
+                    lines: None,
+                    pixelated: None,
+                    padding: None,
+                    background: None,
+                    color_key: None,
+                    color_key_tolerance: None,
+                }),
+                down_face: Some(config::ButtonFaceConfig {
+                    color: Some(config::ColorConfig::HEXString("#00FF00".to_string())),
+                    file: None,
+                    label: None,
+                    sublabel: None,
+                    superlabel: None,
+                    overlay: None,
+                    sprite: None,
+                    lines: None,
+                    pixelated: None,
+                    padding: None,
+                    background: None,
+                    color_key: None,
+                    color_key_tolerance: None,
+                }),
+                up_handler: None,
+                down_handler: None,
+                load_page: Some("page1".to_string()),
+                on_app_faces: None,
+                enabled: None,
+                layer_face: None,
+                layer_handlers: None,
+                action: None,
+                trigger: None,
+                latch: None,
+                group: None,
+                description: None,
+                tags: None,
+                when_faces: None,
+                up_handlers: None,
+                down_handlers: None,
+                cycle_faces: None,
+                invert_press: None,
+            });
+        config.pages[0].buttons[0].button =
+            config::ButtonOrButtonName::ButtonName("page_switch".to_string());
+        config.pages[1].buttons.clear();
+    }
+
+    #[test]
+    fn page_switch_button_is_highlighted_after_loading_its_page() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_page_switch_button(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state.on_button_pressed(4);
+        state.on_button_released(4);
+
+        // Test
+        assert!(state.loaded_pages.contains(&"page1".to_string()));
+        let down_face_md5 = image_md5(
+            &state
+                .named_buttons
+                .get("page_switch")
+                .unwrap()
+                .down_face
+                .as_ref()
+                .unwrap()
+                .face,
+        );
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        assert_eq!(rendering_faces.len(), 1);
+        assert_eq!(rendering_faces[0].0, 4);
+        assert_eq!(image_md5(&rendering_faces[0].1.face), down_face_md5);
+    }
+
+    #[test]
+    fn page_switch_button_is_not_highlighted_after_unloading_its_page() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_page_switch_button(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.on_button_pressed(4);
+        state.on_button_released(4);
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state.unload_page(&"page1".to_string()).unwrap();
+
+        // Test
+        let up_face_md5 = image_md5(
+            &state
+                .named_buttons
+                .get("page_switch")
+                .unwrap()
+                .up_face
+                .as_ref()
+                .unwrap()
+                .face,
+        );
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        assert_eq!(rendering_faces.len(), 1);
+        assert_eq!(image_md5(&rendering_faces[0].1.face), up_face_md5);
+    }
+
+    #[test]
+    fn invalid_named_button_is_an_error() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.buttons.as_mut().unwrap()[0].up_face.as_mut().unwrap().color =
+            Some(config::ColorConfig::HEXString("red".to_string()));
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn named_button_with_reserved_prefix_is_an_error() {
+        for name in ["toast:0", "page:page0:named_button0"] {
+            // Setup
+            let mut config = get_full_config(false);
+            config.buttons.as_mut().unwrap()[0].name = String::from(name);
+
+            // Act
+            let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+            // Test
+            assert!(matches!(result, Err(Error::ReservedButtonName(n)) if n == name));
+        }
+    }
+
+    #[test]
+    fn page_switch_button_to_unknown_page_is_an_error() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_page_switch_button(&mut config);
+        config.buttons.as_mut().unwrap().last_mut().unwrap().load_page =
+            Some("unknown_page".to_string());
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn empty_button_is_created_when_not_strict() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Test
+        assert!(state.named_buttons.contains_key("empty"));
+    }
+
+    #[test]
+    fn unresolved_button_is_allowed_when_not_strict() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.pages[1].buttons[0].button =
+            config::ButtonOrButtonName::ButtonName("missing_button".to_string());
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn strict_empty_requires_empty_button() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.strict_empty = Some(true);
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(matches!(result, Err(Error::ButtonNotFound(name)) if name == "empty"));
+    }
+
+    #[test]
+    fn strict_empty_uses_configured_empty_button() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.strict_empty = Some(true);
+        config
+            .buttons
+            .as_mut()
+            .unwrap()
+            .push(config::ButtonConfigWithName {
+                name: "empty".to_string(),
+                up_face: None,
+                down_face: None,
+                up_handler: Some(config::EventHandlerConfig::AsCode {
+                    code: "on_empty_up".to_string(),
+                }),
+                down_handler: None,
+                load_page: None,
+                on_app_faces: None,
+                enabled: None,
+                layer_face: None,
+                layer_handlers: None,
+                action: None,
+                trigger: None,
+                latch: None,
+                group: None,
+                description: None,
+                tags: None,
+                when_faces: None,
+                up_handlers: None,
+                down_handlers: None,
+                cycle_faces: None,
+                invert_press: None,
+            });
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.unload_page(&"page0".to_string()).unwrap();
+
+        // Test
+        assert_eq!(
+            state.on_button_released(0)[0].script,
+            "on_empty_up".to_string()
+        );
+    }
+
+    #[test]
+    fn strict_empty_fails_on_unresolved_button() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.strict_empty = Some(true);
+        config
+            .buttons
+            .as_mut()
+            .unwrap()
+            .push(config::ButtonConfigWithName {
+                name: "empty".to_string(),
+                up_face: None,
+                down_face: None,
+                up_handler: None,
+                down_handler: None,
+                load_page: None,
+                on_app_faces: None,
+                enabled: None,
+                layer_face: None,
+                layer_handlers: None,
+                action: None,
+                trigger: None,
+                latch: None,
+                group: None,
+                description: None,
+                tags: None,
+                when_faces: None,
+                up_handlers: None,
+                down_handlers: None,
+                cycle_faces: None,
+                invert_press: None,
+            });
+        config.pages[1].buttons[0].button =
+            config::ButtonOrButtonName::ButtonName("missing_button".to_string());
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(matches!(result, Err(Error::ButtonNotFound(name)) if name == "missing_button"));
+    }
+
+    /// Sets the up face of "named_button0" to draw the test image and
+    /// puts "named_button0" on index 4 of "page0".
+    fn use_test_image_in_named_button(config: &mut config::Config) {
+        config.buttons.as_mut().unwrap()[0].up_face = Some(config::ButtonFaceConfig {
+            color: None,
+            file: Some(String::from("./src/state/test_image_st_orig.png")),
+            label: None,
+            sublabel: None,
+            superlabel: None,
+            overlay: None,
+            sprite: None,
+            lines: None,
+            pixelated: None,
+            padding: None,
+            background: None,
+            color_key: None,
+            color_key_tolerance: None,
+        });
+        config.pages[0].buttons[0].button =
+            config::ButtonOrButtonName::ButtonName("named_button0".to_string());
+    }
+
+    #[test]
+    fn changed_image_file_maps_to_buttons_using_it() {
+        // Setup
+        let mut config = get_full_config(false);
+        use_test_image_in_named_button(&mut config);
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let using = state.named_buttons_using_file(Path::new("src/state/test_image_st_orig.png"));
+        let not_using = state.named_buttons_using_file(Path::new("src/state/other.png"));
+
+        // Test
+        assert_eq!(using, vec!["named_button0".to_string()]);
+        assert!(not_using.is_empty());
+        assert_eq!(
+            state.image_files(),
+            vec![PathBuf::from("./src/state/test_image_st_orig.png")]
+        );
+    }
+
+    #[test]
+    fn changed_image_file_sets_buttons_to_re_render() {
+        // Setup
+        let mut config = get_full_config(false);
+        use_test_image_in_named_button(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state
+            .on_file_changed(Path::new("src/state/test_image_st_orig.png"))
+            .unwrap();
+
+        // Test
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        assert_eq!(rendering_faces.len(), 1);
+        assert_eq!(rendering_faces[0].0, 4);
+    }
+
+    /// Adds load and unload handlers to "page1"
+    fn add_page_handlers(config: &mut config::Config) {
+        config.pages[1].on_load = Some(config::EventHandlerConfig::AsCode {
+            code: "on_page1_load".to_string(),
+        });
+        config.pages[1].on_unload = Some(config::EventHandlerConfig::AsCode {
+            code: "on_page1_unload".to_string(),
+        });
+    }
+
+    #[test]
+    fn loading_page_returns_on_load_handler() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_page_handlers(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let handler = state.load_page(&"page1".to_string()).unwrap();
+
+        // Test
+        assert_eq!(handler.unwrap().script, "on_page1_load");
+    }
+
+    #[test]
+    fn unloading_page_returns_on_unload_handler() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_page_handlers(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.load_page(&"page1".to_string()).unwrap();
+
+        // Act
+        let handler = state.unload_page(&"page1".to_string()).unwrap();
+
+        // Test
+        assert_eq!(handler.unwrap().script, "on_page1_unload");
+    }
+
+    #[test]
+    fn page_without_handlers_returns_none() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let load_handler = state.load_page(&"page1".to_string()).unwrap();
+        let unload_handler = state.unload_page(&"page1".to_string()).unwrap();
+
+        // Test
+        assert!(load_handler.is_none());
+        assert!(unload_handler.is_none());
+    }
+
+    #[test]
+    fn page_loaded_on_window_queues_on_load_handler() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_page_handlers(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        state
+            .on_foreground_window(&WindowInformation {
+                title: String::from("page1_title"),
+                executable: String::from("/usr/bin/page1_exec"),
+                class_name: String::from("Some class we don't care about"),
+                workspace: None,
+            })
+            .unwrap();
+
+        // Test
+        let handlers = state.take_queued_handlers();
+        assert_eq!(handlers.len(), 1);
+        assert_eq!(handlers[0].script, "on_page1_load");
+        assert!(state.take_queued_handlers().is_empty());
+    }
+
+    #[test]
+    fn repeated_window_event_does_not_queue_on_load_handler_again() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_page_handlers(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let window = WindowInformation {
+            title: String::from("page1_title"),
+            executable: String::from("/usr/bin/page1_exec"),
+            class_name: String::from("Some class we don't care about"),
+            workspace: None,
+        };
+        state.on_foreground_window(&window).unwrap();
+        state.take_queued_handlers();
+
+        // Act
+        state.on_foreground_window(&window).unwrap();
+
+        // Test
+        assert!(state.take_queued_handlers().is_empty());
+        assert!(state
+            .metrics()
+            .exposition()
+            .contains("streamdeck_page_loads_total 2\n"));
+    }
+
+    #[test]
+    fn button_name_of_loaded_page_is_returned() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Test
+        assert_eq!(state.button_name(0), Some("page0_button4".to_string()));
+        assert_eq!(state.button_name(100), None);
+    }
+
+    #[test]
+    fn after_invalidate_all_all_faces_are_returned_for_rendering() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state.invalidate_all();
+
+        // Test
+        assert_eq!(
+            state.set_rendered_and_get_rendering_faces().len(),
+            StreamDeckType::Orig.total_num_buttons()
+        );
+    }
+
+    /// Adds a named button "app_icon" at index 4 of "page0",
+    /// which is blue while an editor is the foreground window and red otherwise.
+    fn add_conditional_face_button(config: &mut config::Config) {
+        config
+            .buttons
+            .as_mut()
+            .unwrap()
+            .push(config::ButtonConfigWithName {
+                name: "app_icon".to_string(),
+                up_face: Some(config::ButtonFaceConfig {
+                    color: Some(config::ColorConfig::HEXString("#FF0000".to_string())),
+                    file: None,
+                    label: None,
+                    sublabel: None,
+                    superlabel: None,
+                    overlay: None,
+                    sprite: None,
+                    lines: None,
+                    pixelated: None,
+                    padding: None,
+                    background: None,
+                    color_key: None,
+                    color_key_tolerance: None,
+                }),
+                down_face: None,
+                up_handler: None,
+                down_handler: None,
+                load_page: None,
+                on_app_faces: Some(vec![config::ConditionalFaceConfig {
+                    condition: ForegroundWindowConditionConfig {
+                        title: None,
+                        executable: Some(".*editor.*".to_string()),
+                        class_name: None,
+                        workspace: None,
+                        title_equals: None,
+                        title_starts_with: None,
+                        title_contains: None,
+                    },
+                    face: config::ButtonFaceConfig {
+                        color: Some(config::ColorConfig::HEXString("#0000FF".to_string())),
+                        file: None,
+                        label: None,
+                        sublabel: None,
+                        superlabel: None,
+                        overlay: None,
+                        sprite: None,
+                        lines: None,
+                        pixelated: None,
+                        padding: None,
+                        background: None,
+                        color_key: None,
+                        color_key_tolerance: None,
+                    },
+                }]),
+                enabled: None,
+                layer_face: None,
+                layer_handlers: None,
+                action: None,
+                trigger: None,
+                latch: None,
+                group: None,
+                description: None,
+                tags: None,
+                when_faces: None,
+                up_handlers: None,
+                down_handlers: None,
+                cycle_faces: None,
+                invert_press: None,
+            });
+        config.pages[0].buttons[0].button =
+            config::ButtonOrButtonName::ButtonName("app_icon".to_string());
+    }
+
+    /// Adds the named button "mute" with a blue face while the variable "muted" is true,
+    /// shown at index 4 by "page0".
+    fn add_variable_face_button(config: &mut config::Config) {
+        config.buttons.as_mut().unwrap().push(
+            serde_yaml::from_str(
+                "\
+name: mute
+up_face:
+  color: '#FF0000'
+when_faces:
+  - when: muted
+    face:
+      color: '#0000FF'
+",
+            )
+            .unwrap(),
+        );
+        config.pages[0].buttons[0].button =
+            config::ButtonOrButtonName::ButtonName("mute".to_string());
+    }
+
+    #[test]
+    fn flipping_a_variable_swaps_the_rendered_face() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_variable_face_button(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        let setup = state.named_buttons.get("mute").unwrap();
+        let up_face_md5 = image_md5(&setup.up_face.as_ref().unwrap().face);
+        let variable_face_md5 = image_md5(&setup.variable_faces[0].face.face);
+
+        // Act
+        state.set_var("muted", json!(true));
+        let muted_faces: Vec<(u8, md5::Digest)> = state
+            .set_rendered_and_get_rendering_faces()
+            .iter()
+            .map(|(id, face)| (*id, image_md5(&face.face)))
+            .collect();
+        state.set_var("muted", json!(false));
+        let unmuted_faces: Vec<(u8, md5::Digest)> = state
+            .set_rendered_and_get_rendering_faces()
+            .iter()
+            .map(|(id, face)| (*id, image_md5(&face.face)))
+            .collect();
+
+        // Test
+        assert_eq!(muted_faces, vec![(4, variable_face_md5)]);
+        assert_eq!(unmuted_faces, vec![(4, up_face_md5)]);
+        assert_eq!(state.get_var("muted"), Some(&json!(false)));
+    }
+
+    #[test]
+    fn setting_an_unrelated_variable_does_not_re_render() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_variable_face_button(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state.set_var("volume", json!(11));
+
+        // Test
+        assert!(state.set_rendered_and_get_rendering_faces().is_empty());
+    }
+
+    /// Window information of a foreground window with the given executable
+    fn window_with_executable(executable: &str) -> WindowInformation {
+        WindowInformation {
+            title: String::from("Some title"),
+            executable: String::from(executable),
+            class_name: String::from("Some class"),
+            workspace: None,
+        }
+    }
+
+    #[test]
+    fn conditional_face_is_shown_for_matching_window() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_conditional_face_button(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        let conditional_face_md5 = image_md5(
+            &state.named_buttons.get("app_icon").unwrap().conditional_faces[0]
+                .face
+                .face,
+        );
+
+        // Act
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/editor"))
+            .unwrap();
+
+        // Test
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        assert_eq!(rendering_faces.len(), 1);
+        assert_eq!(rendering_faces[0].0, 4);
+        assert_eq!(image_md5(&rendering_faces[0].1.face), conditional_face_md5);
+    }
+
+    #[test]
+    fn up_face_is_shown_again_after_switching_to_other_window() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_conditional_face_button(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/editor"))
+            .unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        let up_face_md5 = image_md5(
+            &state
+                .named_buttons
+                .get("app_icon")
+                .unwrap()
+                .up_face
+                .as_ref()
+                .unwrap()
+                .face,
+        );
+
+        // Act
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/terminal"))
+            .unwrap();
+
+        // Test
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        assert_eq!(rendering_faces.len(), 1);
+        assert_eq!(rendering_faces[0].0, 4);
+        assert_eq!(image_md5(&rendering_faces[0].1.face), up_face_md5);
+    }
+
+    #[test]
+    fn non_matching_window_does_not_re_render_conditional_button() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_conditional_face_button(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/terminal"))
+            .unwrap();
+
+        // Test
+        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 0);
+    }
+
+    /// Window information matching the conditions of the page with the given id
+    fn window_of_page(page_id: usize) -> WindowInformation {
+        WindowInformation {
+            title: format!("Window for page{}_title", page_id),
+            executable: format!("/usr/bin/page{}_exec", page_id),
+            class_name: String::from("Some class we don't care about"),
+            workspace: None,
+        }
+    }
+
+    #[test]
+    fn tracked_page_is_unloaded_when_app_loses_focus() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.pages[1].on_app.as_mut().unwrap().remove = Some(true);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.on_foreground_window(&window_of_page(1)).unwrap();
+        assert!(state.loaded_pages.contains(&"page1".to_string()));
+
+        // Act
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/terminal"))
+            .unwrap();
+
+        // Test
+        assert!(!state.loaded_pages.contains(&"page1".to_string()));
+        assert!(state.loaded_pages.contains(&"page0".to_string()));
+    }
+
+    #[test]
+    fn matching_window_does_not_load_pages_while_switching_is_disabled() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_window_switching_enabled(false);
+
+        // Act
+        state.on_foreground_window(&window_of_page(1)).unwrap();
+
+        // Test
+        assert_eq!(state.loaded_pages, vec!["page0".to_string()]);
+        assert_eq!(
+            state.foreground_window.as_ref().map(|w| w.executable.clone()),
+            Some(String::from("/usr/bin/page1_exec"))
+        );
+    }
+
+    #[test]
+    fn re_enabling_window_switching_resumes_loading_and_unloading() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.pages[1].on_app.as_mut().unwrap().remove = Some(true);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.on_foreground_window(&window_of_page(1)).unwrap();
+        state.set_window_switching_enabled(false);
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/terminal"))
+            .unwrap();
+        let pages_while_disabled = state.loaded_pages.clone();
+
+        // Act
+        state.set_window_switching_enabled(true);
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/terminal"))
+            .unwrap();
+
+        // Test
+        assert_eq!(
+            pages_while_disabled,
+            vec!["page0".to_string(), "page1".to_string()]
+        );
+        assert_eq!(state.loaded_pages, vec!["page0".to_string()]);
+    }
+
+    #[test]
+    fn untracked_page_stays_loaded_when_app_loses_focus() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.on_foreground_window(&window_of_page(1)).unwrap();
+
+        // Act
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/terminal"))
+            .unwrap();
+
+        // Test
+        assert!(state.loaded_pages.contains(&"page1".to_string()));
+    }
+
+    #[test]
+    fn tracked_page_with_one_matching_condition_stays_loaded() {
+        // Setup
+        let mut config = get_full_config(false);
+        let on_app = config.pages[1].on_app.as_mut().unwrap();
+        on_app.remove = Some(true);
+        on_app
+            .conditions
+            .push(config::PageConditionConfig::Window(
+                ForegroundWindowConditionConfig {
+                    title: None,
+                    executable: Some(String::from(".*editor.*")),
+                    class_name: None,
+                    workspace: None,
+                    title_equals: None,
+                    title_starts_with: None,
+                    title_contains: None,
+                },
+            ));
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/editor"))
+            .unwrap();
+
+        // Test
+        assert!(state.loaded_pages.contains(&"page1".to_string()));
+    }
+
+    #[test]
+    fn status_json_contains_pages_buttons_and_window() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        state.load_page(&String::from("page1")).unwrap();
+        state.on_button_pressed(0);
+        state
+            .on_foreground_window(&window_with_executable("/usr/bin/terminal"))
+            .unwrap();
+        let status = state.to_status_json();
+
+        // Test
+        assert_eq!(status["loaded_pages"], json!(["page0", "page1"]));
+        let buttons = status["buttons"].as_array().unwrap();
+        assert_eq!(buttons.len(), StreamDeckType::Orig.total_num_buttons());
+        assert_eq!(buttons[0]["id"], json!(0));
+        assert_eq!(buttons[0]["name"], json!("page1_button4"));
+        assert_eq!(buttons[0]["pressed"], json!(true));
+        assert_eq!(buttons[1]["pressed"], json!(false));
+        assert_eq!(buttons[0]["down_handler"], json!("inline"));
+        assert_eq!(
+            status["foreground_window"]["executable"],
+            json!("/usr/bin/terminal")
+        );
+    }
+
+    #[test]
+    fn status_json_contains_descriptions_and_tags() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.pages[1].description = Some(String::from("The second page"));
+        config.pages[1].tags = Some(vec![String::from("second")]);
+        if let config::ButtonOrButtonName::Button(button) = &mut config.pages[1].buttons[4].button {
+            button.description = Some(String::from("The first button"));
+            button.tags = Some(vec![String::from("first")]);
+        }
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        state.load_page(&String::from("page1")).unwrap();
+        let status = state.to_status_json();
+
+        // Test
+        assert_eq!(status["pages"][1]["name"], json!("page1"));
+        assert_eq!(status["pages"][1]["description"], json!("The second page"));
+        assert_eq!(status["pages"][1]["tags"], json!(["second"]));
+        assert_eq!(status["pages"][0]["description"], json!(null));
+        assert_eq!(status["buttons"][0]["description"], json!("The first button"));
+        assert_eq!(status["buttons"][0]["tags"], json!(["first"]));
+        assert_eq!(status["buttons"][1]["tags"], json!([]));
+    }
+
+    /// Adds `on_any_button` handlers to "page0" and globally
+    /// and removes the handlers of the button at index 0 of "page0".
+    fn add_any_button_handlers(config: &mut config::Config) {
+        config.on_any_button = Some(config::EventHandlerConfig::AsCode {
+            code: "on_any_button".to_string(),
+        });
+        config.pages[0].on_any_button = Some(config::EventHandlerConfig::AsCode {
+            code: "on_page0_any_button".to_string(),
+        });
+        if let config::ButtonOrButtonName::Button(button) = &mut config.pages[0].buttons[4].button {
+            button.up_handler = None;
+            button.down_handler = None;
+        }
+    }
+
+    /// The scripts of the handlers
+    fn scripts(handlers: Vec<&EventHandler>) -> Vec<String> {
+        handlers.iter().map(|h| h.script.clone()).collect()
+    }
+
+    #[test]
+    fn press_without_button_handler_invokes_any_button_handlers() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_any_button_handlers(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let handlers = scripts(state.on_button_pressed(0));
+
+        // Test
+        assert_eq!(handlers, vec!["on_page0_any_button", "on_any_button"]);
+    }
+
+    #[test]
+    fn button_handler_is_executed_before_any_button_handlers() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_any_button_handlers(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let press_handlers = scripts(state.on_button_pressed(1));
+        let release_handlers = scripts(state.on_button_released(1));
+
+        // Test
+        assert_eq!(
+            press_handlers,
+            vec!["on_page0_button3_down", "on_page0_any_button", "on_any_button"]
+        );
+        assert_eq!(
+            release_handlers,
+            vec!["on_page0_button3_up", "on_page0_any_button", "on_any_button"]
+        );
+    }
+
+    #[test]
+    fn any_button_handler_of_top_most_page_is_used() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_any_button_handlers(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+
+        // Act
+        let handlers = scripts(state.on_button_pressed(0));
+
+        // Test
+        assert_eq!(handlers, vec!["on_page1_button4_down", "on_any_button"]);
+    }
+
+    #[test]
+    fn disabled_button_returns_no_handlers() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_any_button_handlers(&mut config);
+        if let config::ButtonOrButtonName::Button(button) = &mut config.pages[0].buttons[3].button {
+            button.enabled = Some(false);
+        }
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let press_handlers = state.on_button_pressed(1).len();
+        let release_handlers = state.on_button_released(1).len();
+
+        // Test
+        assert_eq!(press_handlers, 0);
+        assert_eq!(release_handlers, 0);
+    }
+
+    #[test]
+    fn disabled_button_is_rendered_dimmed() {
+        // Setup
+        let mut config = get_full_config(false);
+        if let config::ButtonOrButtonName::Button(button) = &mut config.pages[0].buttons[3].button {
+            button.enabled = Some(false);
+        }
+
+        // Act
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Test
+        let setup = state.named_buttons.get("page0_button3").unwrap();
+        let up_face_md5 = image_md5(&setup.up_face.as_ref().unwrap().face);
+        let dimmed_face_md5 = image_md5(&setup.up_face.as_ref().unwrap().dimmed().face);
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        let (_, face) = rendering_faces.iter().find(|(id, _)| *id == 1).unwrap();
+        assert_eq!(image_md5(&face.face), dimmed_face_md5);
+        assert_ne!(image_md5(&face.face), up_face_md5);
+    }
+
+    #[test]
+    fn button_disabled_at_runtime_is_re_rendered_and_suppresses_handlers() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state
+            .set_button_enabled(&String::from("page0_button3"), false)
+            .unwrap();
+
+        // Test
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        assert_eq!(rendering_faces.len(), 1);
+        assert_eq!(rendering_faces[0].0, 1);
+        assert!(state.on_button_pressed(1).is_empty());
+    }
+
+    #[test]
+    fn button_enabled_again_executes_handlers() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state
+            .set_button_enabled(&String::from("page0_button3"), false)
+            .unwrap();
+
+        // Act
+        state
+            .set_button_enabled(&String::from("page0_button3"), true)
+            .unwrap();
+
+        // Test
+        assert_eq!(
+            scripts(state.on_button_pressed(1)),
+            vec!["on_page0_button3_down"]
+        );
+    }
+
+    /// Uses the button at index 14 as layer modifier and adds a layer down handler
+    /// to the button at index 1.
+    fn add_layer(config: &mut config::Config) {
+        config.layer_modifier = Some(14);
+        if let config::ButtonOrButtonName::Button(button) = &mut config.pages[0].buttons[3].button {
+            button.layer_handlers = Some(config::LayerHandlersConfig {
+                up_handler: None,
+                down_handler: Some(config::EventHandlerConfig::AsCode {
+                    code: "on_layer_down".to_string(),
+                }),
+            });
+        }
+    }
+
+    #[test]
+    fn button_returns_layer_handler_while_modifier_is_held() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_layer(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let without_modifier = scripts(state.on_button_pressed(1));
+        state.on_button_released(1);
+        state.on_button_pressed(14);
+        let with_modifier = scripts(state.on_button_pressed(1));
+        let release_with_modifier = scripts(state.on_button_released(1));
+
+        // Test
+        assert_eq!(without_modifier, vec!["on_page0_button3_down"]);
+        assert_eq!(with_modifier, vec!["on_layer_down"]);
+        // No layer up handler, so the normal one is used
+        assert_eq!(release_with_modifier, vec!["on_page0_button3_up"]);
+    }
+
+    #[test]
+    fn button_returns_normal_handler_after_modifier_is_released() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_layer(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.on_button_pressed(14);
+        state.on_button_released(14);
+
+        // Act
+        let handlers = scripts(state.on_button_pressed(1));
+
+        // Test
+        assert_eq!(handlers, vec!["on_page0_button3_down"]);
+    }
+
+    #[test]
+    fn layer_modifier_outside_of_device_is_an_error() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.layer_modifier = Some(15);
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn zero_poll_interval_is_an_error() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.poll_interval_ms = Some(0);
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(matches!(result, Err(Error::ConfigParserError(message)) if message.contains("poll_interval_ms")));
+    }
+
+    #[test]
+    fn layer_face_is_shown_while_modifier_is_held() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_layer(&mut config);
+        if let config::ButtonOrButtonName::Button(button) = &mut config.pages[0].buttons[3].button {
+            button.layer_face = Some(config::ButtonFaceConfig {
+                color: Some(config::ColorConfig::HEXString("#0000FF".to_string())),
+                file: None,
+                label: None,
+                sublabel: None,
+                superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
+            });
+        }
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        let layer_face_md5 = image_md5(
+            &state
+                .named_buttons
+                .get("page0_button3")
+                .unwrap()
+                .layer_face
+                .as_ref()
+                .unwrap()
+                .face,
+        );
+
+        // Act
+        state.on_button_pressed(14);
+
+        // Test
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        let (_, face) = rendering_faces.iter().find(|(id, _)| *id == 1).unwrap();
+        assert_eq!(image_md5(&face.face), layer_face_md5);
+    }
+
+    #[test]
+    fn undo_without_page_operation_does_nothing() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let undone = state.undo_page_operation();
+
+        // Test
+        assert!(!undone);
+        assert_eq!(state.loaded_pages, vec!["page0".to_string()]);
+    }
+
+    #[test]
+    fn page_stack_round_trips() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.load_page(&String::from("page2")).unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+        let saved_stack = state.get_page_stack();
+
+        // Act
+        state.set_page_stack(vec![String::from("page0")]).unwrap();
+        let stack_in_between = state.get_page_stack();
+        let name_in_between = state.button_name(0);
+        state.set_page_stack(saved_stack.clone()).unwrap();
+
+        // Test
+        assert_eq!(stack_in_between, vec![String::from("page0")]);
+        assert_eq!(name_in_between, Some(String::from("page0_button4")));
+        assert_eq!(
+            state.get_page_stack(),
+            vec![
+                String::from("page0"),
+                String::from("page2"),
+                String::from("page1")
+            ]
+        );
+        assert_eq!(state.get_page_stack(), saved_stack);
+        assert_eq!(state.button_name(0), Some(String::from("page1_button4")));
+    }
+
+    #[test]
+    fn invalid_page_stack_does_not_change_the_state() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+
+        // Act
+        let result = state.set_page_stack(vec![String::from("page2"), String::from("missing")]);
+
+        // Test
+        assert!(matches!(
+            result,
+            Err(Error::PagesNotFound(names)) if names == vec![String::from("missing")]
+        ));
+        assert_eq!(
+            state.get_page_stack(),
+            vec![String::from("page0"), String::from("page1")]
+        );
+        assert_eq!(state.button_name(0), Some(String::from("page1_button4")));
+    }
+
+    #[test]
+    fn setting_the_page_stack_is_undone_at_once() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        state
+            .set_page_stack(vec![String::from("page1"), String::from("page2")])
+            .unwrap();
+        let undone = state.undo_page_operation();
+
+        // Test
+        assert!(undone);
+        assert_eq!(state.get_page_stack(), vec![String::from("page0")]);
+    }
+
+    #[test]
+    fn undo_after_load_unloads_the_page() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+
+        // Act
+        let undone = state.undo_page_operation();
+
+        // Test
+        assert!(undone);
+        assert_eq!(state.loaded_pages, vec!["page0".to_string()]);
+        assert_eq!(state.button_name(0), Some(String::from("page0_button4")));
+    }
+
+    #[test]
+    fn undo_after_unload_loads_the_page_again() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+        state.unload_page(&String::from("page1")).unwrap();
+
+        // Act
+        let undone = state.undo_page_operation();
+
+        // Test
+        assert!(undone);
+        assert_eq!(
+            state.loaded_pages,
+            vec!["page0".to_string(), "page1".to_string()]
+        );
+        assert_eq!(state.button_name(0), Some(String::from("page1_button4")));
+    }
+
+    #[test]
+    fn undo_queues_page_handlers() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_page_handlers(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+        state.take_queued_handlers();
+
+        // Act
+        state.undo_page_operation();
+
+        // Test
+        let handlers = state.take_queued_handlers();
+        assert_eq!(handlers.len(), 1);
+        assert_eq!(handlers[0].script, "on_page1_unload");
+    }
+
+    #[test]
+    fn page_history_is_bounded() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        for i in 0..PAGE_HISTORY_LENGTH + 5 {
+            state
+                .load_page(&format!("page{}", 1 + i % 2))
+                .unwrap();
+        }
+
+        // Act
+        let mut undone = 0;
+        while state.undo_page_operation() {
+            undone += 1;
+        }
+
+        // Test
+        assert_eq!(undone, PAGE_HISTORY_LENGTH);
+    }
+
+    #[test]
+    fn button_image_replaces_the_up_face() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        let (width, height) = StreamDeckType::Orig.button_image_size();
+        let rgb_bytes = [0u8, 128, 255].repeat((width * height) as usize);
+
+        // Act
+        state
+            .set_named_button_image(&String::from("page0_button3"), width, height, rgb_bytes)
+            .unwrap();
+
+        // Test
+        let expected = image::RgbImage::from_pixel(width, height, image::Rgb([0, 128, 255]));
+        let setup = state.named_buttons.get("page0_button3").unwrap();
+        assert_eq!(image_md5(&setup.up_face.as_ref().unwrap().face), image_md5(&expected));
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        assert_eq!(rendering_faces.len(), 1);
+        assert_eq!(rendering_faces[0].0, 1);
+    }
+
+    #[test]
+    fn button_image_with_wrong_buffer_length_is_an_error() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let (width, height) = StreamDeckType::Orig.button_image_size();
+
+        // Act
+        let result = state.set_named_button_image(
+            &String::from("page0_button3"),
+            width,
+            height,
+            vec![0; 10],
+        );
+
+        // Test
+        assert!(matches!(result, Err(Error::InvalidImageBufferLength(10))));
+    }
+
+    #[test]
+    fn button_image_with_wrong_size_is_an_error() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let result = state.set_named_button_image(
+            &String::from("page0_button3"),
+            10,
+            10,
+            vec![0; 300],
+        );
+
+        // Test
+        assert!(matches!(result, Err(Error::InvalidImageSize(10, 10))));
+    }
+
+    #[test]
+    fn named_button_names_contain_global_and_page_buttons() {
+        // Setup
+        let config = get_full_config(false);
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let names = state.named_button_names();
+
+        // Test
+        assert!(names.contains(&String::from("named_button0")));
+        assert!(names.contains(&String::from("page2_button14")));
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+    }
+
+    #[test]
+    fn exporting_faces_writes_one_png_per_named_button() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let dir = std::env::temp_dir().join("streamdeck_controller_export_faces_test");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        // Act
+        let paths = state.export_faces(&dir).unwrap();
+
+        // Test
+        let names = state.named_button_names();
+        assert_eq!(paths.len(), names.len());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), names.len());
+        for name in names {
+            let image = image::open(dir.join(format!("{}.png", name))).unwrap();
+            assert_eq!(
+                (image.width(), image.height()),
+                StreamDeckType::Orig.button_image_size()
+            );
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn visible_buttons_follow_the_loaded_page() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let before = state.visible_buttons();
+        state.load_page(&String::from("page1")).unwrap();
+        let after = state.visible_buttons();
+
+        // Test
+        assert_eq!(before.len(), StreamDeckType::Orig.total_num_buttons());
+        assert_eq!(before[0], (0, String::from("page0_button4")));
+        assert_eq!(before[1], (1, String::from("page0_button3")));
+        assert_eq!(after[0], (0, String::from("page1_button4")));
+        assert_eq!(after[14], (14, String::from("page1_button10")));
+    }
+
+    /// Makes page0_button3 (at index 1) a latching button with a latch face and handler
+    fn add_latch(config: &mut config::Config) {
+        if let config::ButtonOrButtonName::Button(button) = &mut config.pages[0].buttons[3].button {
+            button.latch = Some(config::LatchConfig {
+                face: Some(config::ButtonFaceConfig {
+                    color: Some(config::ColorConfig::HEXString(String::from("#00FF00"))),
+                    file: None,
+                    label: None,
+                    sublabel: None,
+                    superlabel: None,
+                    overlay: None,
+                    sprite: None,
+                    lines: None,
+                    pixelated: None,
+                    padding: None,
+                    background: None,
+                    color_key: None,
+                    color_key_tolerance: None,
+                }),
+                down_handler: Some(config::EventHandlerConfig::AsCode {
+                    code: String::from("latch_down"),
+                }),
+            });
+        }
+    }
+
+    #[test]
+    fn alternating_presses_toggle_the_latch() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_latch(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let name = String::from("page0_button3");
+
+        // Act
+        let first_press = scripts(state.on_button_pressed(1));
+        let first_release = scripts(state.on_button_released(1));
+        let latched_after_first = state.button_latch(&name);
+        let second_press = scripts(state.on_button_pressed(1));
+        state.on_button_released(1);
+        let latched_after_second = state.button_latch(&name);
+
+        // Test
+        assert_eq!(first_press, vec!["latch_down"]);
+        assert_eq!(first_release, vec!["on_page0_button3_up"]);
+        assert!(latched_after_first);
+        assert_eq!(second_press, vec!["on_page0_button3_down"]);
+        assert!(!latched_after_second);
+    }
+
+    #[test]
+    fn latched_button_shows_the_latch_face() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_latch(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state.on_button_pressed(1);
+        state.on_button_released(1);
+
+        // Test
+        let setup = state.named_buttons.get("page0_button3").unwrap();
+        let latch_face_md5 = image_md5(&setup.latch.as_ref().unwrap().face.as_ref().unwrap().face);
+        let rendering_faces = state.set_rendered_and_get_rendering_faces();
+        let (_, face) = rendering_faces.iter().find(|(id, _)| *id == 1).unwrap();
+        assert_eq!(image_md5(&face.face), latch_face_md5);
+    }
+
+    #[test]
+    fn latch_is_kept_while_the_button_is_hidden() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_latch(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.on_button_pressed(1);
+        state.on_button_released(1);
+
+        // Act
+        state.load_page(&String::from("page1")).unwrap();
+        let hidden_latched = state.buttons[1].is_latched();
+        state.unload_page(&String::from("page1")).unwrap();
+
+        // Test
+        assert!(!hidden_latched);
+        assert!(state.buttons[1].is_latched());
+        assert_eq!(
+            scripts(state.on_button_pressed(1)),
+            vec!["on_page0_button3_down"]
+        );
+    }
+
+    #[test]
+    fn latch_can_be_set_by_name() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_latch(&mut config);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        state
+            .set_button_latch(&String::from("page0_button3"), true)
+            .unwrap();
+
+        // Test
+        assert!(state.buttons[1].is_latched());
+        assert!(state
+            .set_button_latch(&String::from("unknown"), true)
+            .is_err());
+    }
+
+    #[test]
+    fn identical_face_configs_share_one_face() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Test
+        let face0 = state.named_buttons["named_button0"].up_face.as_ref().unwrap();
+        let face1 = state.named_buttons["named_button1"].up_face.as_ref().unwrap();
+        let other_face = state.named_buttons["page0_button0"].up_face.as_ref().unwrap();
+        assert!(Arc::ptr_eq(face0, face1));
+        assert!(!Arc::ptr_eq(face0, other_face));
+    }
+
+    #[test]
+    fn changing_a_shared_face_does_not_change_the_other_buttons() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let face1_md5 = image_md5(&state.named_buttons["named_button1"].up_face.as_ref().unwrap().face);
+
+        // Act
+        state
+            .set_named_button_up_face(
+                &String::from("named_button0"),
+                Some(Rgba([0, 0, 255, 255])),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+
+        // Test
+        let face0 = state.named_buttons["named_button0"].up_face.as_ref().unwrap();
+        let face1 = state.named_buttons["named_button1"].up_face.as_ref().unwrap();
+        assert!(!Arc::ptr_eq(face0, face1));
+        assert_eq!(image_md5(&face1.face), face1_md5);
+        assert_ne!(image_md5(&face0.face), face1_md5);
+    }
+
+    #[test]
+    fn device_without_display_renders_no_faces_but_fires_handlers() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        // Streamdeck types without screens (like pedals) are not known yet, so pretend one
+        state.has_display = false;
+
+        // Act
+        let rendering_faces = state.set_rendered_and_get_rendering_faces().len();
+        let handlers = scripts(state.on_button_pressed(1));
+
+        // Test
+        assert_eq!(rendering_faces, 0);
+        assert!(state.buttons_needing_rendering().is_empty());
+        assert!(state.set_rendered_and_get_rendering_faces_of(&[0, 1]).is_empty());
+        assert_eq!(handlers, vec!["on_page0_button3_down"]);
+    }
+
+    /// Full config with a default button handler, page0 has no button at index 10
+    fn config_with_default_button_handler() -> config::Config {
+        let mut config = get_full_config(false);
+        config.default_button_handler = Some(config::EventHandlerConfig::AsCode {
+            code: String::from("default_button"),
+        });
+        // Removes page0_button14 (at row 2, col 4) from page0
+        config.pages[0].buttons.truncate(14);
+        config
+    }
+
+    #[test]
+    fn pressing_an_unconfigured_button_returns_the_default_handler() {
+        // Setup
+        let config = config_with_default_button_handler();
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let handlers = scripts(state.on_button_pressed(10));
+
+        // Test
+        assert_eq!(handlers, vec!["default_button"]);
+        assert!(scripts(state.on_button_released(10)).is_empty());
+    }
+
+    #[test]
+    fn pressing_a_configured_button_does_not_return_the_default_handler() {
+        // Setup
+        let config = config_with_default_button_handler();
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let handlers = scripts(state.on_button_pressed(1));
+
+        // Test
+        assert_eq!(handlers, vec!["on_page0_button3_down"]);
+    }
+
+    /// Full config with page0_button3 (at index 1) and page0_button4 (at index 0) in the group "radio"
+    fn config_with_group() -> config::Config {
+        let mut config = get_full_config(false);
+        for button in &mut config.pages[0].buttons[3..5] {
+            if let config::ButtonOrButtonName::Button(button) = &mut button.button {
+                button.group = Some(String::from("radio"));
+            }
+        }
+        config
+    }
+
+    #[test]
+    fn pressing_a_group_member_selects_it() {
+        // Setup
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config_with_group()).unwrap();
+
+        // Act
+        state.on_button_pressed(1);
+        state.on_button_released(1);
+
+        // Test
+        assert!(state.buttons[1].is_highlighted());
+        assert!(!state.buttons[0].is_highlighted());
+        assert_eq!(
+            state.selected_in_group(&String::from("radio")),
+            Some(&String::from("page0_button3"))
+        );
+    }
+
+    #[test]
+    fn selecting_a_second_member_deselects_the_first() {
+        // Setup
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config_with_group()).unwrap();
+        state.on_button_pressed(1);
+        state.on_button_released(1);
+
+        // Act
+        state.on_button_pressed(0);
+        state.on_button_released(0);
+
+        // Test
+        assert!(state.buttons[0].is_highlighted());
+        assert!(!state.buttons[1].is_highlighted());
+    }
+
+    #[test]
+    fn group_member_can_be_selected_by_name() {
+        // Setup
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config_with_group()).unwrap();
+        let group = String::from("radio");
+
+        // Act
+        state
+            .select_in_group(&group, &String::from("page0_button4"))
+            .unwrap();
+
+        // Test
+        assert!(state.buttons[0].is_highlighted());
+        assert!(!state.buttons[1].is_highlighted());
+        assert!(matches!(
+            state.select_in_group(&group, &String::from("page0_button5")),
+            Err(Error::ButtonNotInGroup(_, _))
+        ));
+        assert!(matches!(
+            state.select_in_group(&group, &String::from("unknown")),
+            Err(Error::ButtonNotFound(_))
+        ));
+    }
+
+    /// Number of buttons of a page, whose faces have all been drawn
+    fn drawn_buttons_of_page(state: &AppState, page_id: usize) -> usize {
+        (0..15)
+            .filter(|button_id| {
+                state.named_buttons[&format!("page{}_button{}", page_id, button_id)]
+                    .faces()
+                    .all(|face| face.is_drawn())
+            })
+            .count()
+    }
+
+    #[test]
+    fn faces_of_pages_are_drawn_when_the_page_is_loaded() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let drawn_default_page_buttons = drawn_buttons_of_page(&state, 0);
+        let drawn_buttons_before_loading = drawn_buttons_of_page(&state, 1);
+
+        // Act
+        state.load_page(&String::from("page1")).unwrap();
+
+        // Test
+        assert_eq!(drawn_default_page_buttons, 15);
+        assert_eq!(drawn_buttons_before_loading, 0);
+        assert_eq!(drawn_buttons_of_page(&state, 1), 15);
+        assert_eq!(drawn_buttons_of_page(&state, 2), 0);
+    }
+
+    #[test]
+    fn errors_of_deferred_faces_are_returned_when_loading_the_page() {
+        // Setup
+        let mut config = get_full_config(false);
+        if let config::ButtonOrButtonName::Button(button) = &mut config.pages[1].buttons[0].button {
+            button.up_face.as_mut().unwrap().file = Some(String::from("./does_not_exist.png"));
+        }
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let result = state.load_page(&String::from("page1"));
+
+        // Test
+        assert!(result.is_err());
+        assert_eq!(state.loaded_pages, vec![String::from("page0")]);
+    }
+
+    /// Replaces the conditions of "page1" with
+    /// (executable firefox AND title GitHub) OR (executable code AND workspace 2)
+    fn config_with_condition_groups() -> config::Config {
+        let condition = |title: Option<&str>, executable: Option<&str>, workspace: Option<u32>| {
+            config::PageConditionConfig::Window(ForegroundWindowConditionConfig {
+                title: title.map(String::from),
+                executable: executable.map(String::from),
+                class_name: None,
+                workspace,
+                title_equals: None,
+                title_starts_with: None,
+                title_contains: None,
+            })
+        };
+        let mut config = get_full_config(false);
+        config.pages[1].on_app = Some(PageLoadConditions {
+            conditions: vec![
+                config::PageConditionConfig::All(config::AllConditionsConfig {
+                    all: vec![
+                        condition(None, Some(".*firefox.*"), None),
+                        condition(Some(".*GitHub.*"), None, None),
+                    ],
+                }),
+                config::PageConditionConfig::All(config::AllConditionsConfig {
+                    all: vec![
+                        condition(None, Some(".*code.*"), None),
+                        condition(None, None, Some(2)),
+                    ],
+                }),
+            ],
+            remove: None,
+        });
+        config
+    }
+
+    /// Whether "page1" is loaded, after the window got the focus
+    fn page1_loaded_on_window(title: &str, executable: &str, workspace: Option<u32>) -> bool {
+        let mut state =
+            AppState::from_config(&StreamDeckType::Orig, &config_with_condition_groups()).unwrap();
+        state
+            .on_foreground_window(&WindowInformation {
+                title: String::from(title),
+                executable: String::from(executable),
+                class_name: String::from("class"),
+                workspace,
+            })
+            .unwrap();
+        state.loaded_pages.contains(&String::from("page1"))
+    }
+
+    #[test]
+    fn any_matching_condition_group_loads_the_page() {
+        assert!(page1_loaded_on_window("GitHub - Mozilla", "/usr/bin/firefox", None));
+        assert!(page1_loaded_on_window("main.rs", "/usr/bin/code", Some(2)));
+    }
+
+    #[test]
+    fn partially_matching_condition_groups_do_not_load_the_page() {
+        assert!(!page1_loaded_on_window("News - Mozilla", "/usr/bin/firefox", None));
+        assert!(!page1_loaded_on_window("main.rs", "/usr/bin/code", Some(1)));
+        assert!(!page1_loaded_on_window("GitHub", "/usr/bin/code", None));
+    }
+
+    /// The md5 of the up face of a named button
+    fn up_face_md5(state: &AppState, button_name: &str) -> md5::Digest {
+        image_md5(&state.named_buttons[button_name].up_face.as_ref().unwrap().face)
+    }
+
+    #[test]
+    fn overridden_button_shows_the_override_face() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state
+            .override_button(0, &String::from("named_button0"))
+            .unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+        state.unload_page(&String::from("page1")).unwrap();
+
+        // Test
+        assert_eq!(state.button_name(0), Some(String::from("named_button0")));
+        let faces = state.set_rendered_and_get_rendering_faces();
+        let face = faces.iter().find(|(id, _)| *id == 0).unwrap().1;
+        assert_eq!(image_md5(&face.face), up_face_md5(&state, "named_button0"));
+    }
+
+    #[test]
+    fn clearing_an_override_restores_the_page_face() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state
+            .override_button(0, &String::from("named_button0"))
+            .unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        let cleared = state.clear_override(0);
+
+        // Test
+        assert!(cleared);
+        assert_eq!(state.button_name(0), Some(String::from("page1_button4")));
+        let faces = state.set_rendered_and_get_rendering_faces();
+        let face = faces.iter().find(|(id, _)| *id == 0).unwrap().1;
+        assert_eq!(image_md5(&face.face), up_face_md5(&state, "page1_button4"));
+        assert!(!state.clear_override(0));
+    }
+
+    #[test]
+    fn override_of_unknown_button_index_is_an_error() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let result = state.override_button(15, &String::from("named_button0"));
+
+        // Test
+        assert!(matches!(result, Err(Error::InvalidButtonIndex(15))));
+    }
+
+    #[test]
+    fn toast_is_shown_until_it_expires() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let now = Instant::now();
+        state
+            .toast(0, "Saved!", Some(Rgba([0, 255, 0, 255])), Duration::from_millis(500), now)
+            .unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state.expire_toasts(now + Duration::from_millis(499));
+
+        // Test
+        assert_eq!(state.button_name(0), Some(String::from("toast:0")));
+        assert_ne!(
+            up_face_md5(&state, "toast:0"),
+            up_face_md5(&state, "page0_button4")
+        );
+        assert!(state.set_rendered_and_get_rendering_faces().is_empty());
+        assert_eq!(state.next_toast_expiry(), Some(now + Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn expired_toast_restores_the_page_face() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let now = Instant::now();
+        state
+            .toast(0, "Saved!", None, Duration::from_millis(500), now)
+            .unwrap();
+        state.set_rendered_and_get_rendering_faces();
+
+        // Act
+        state.expire_toasts(now + Duration::from_millis(500));
+
+        // Test
+        assert_eq!(state.button_name(0), Some(String::from("page0_button4")));
+        let faces = state.set_rendered_and_get_rendering_faces();
+        let face = faces.iter().find(|(id, _)| *id == 0).unwrap().1;
+        assert_eq!(image_md5(&face.face), up_face_md5(&state, "page0_button4"));
+        assert!(!state.named_buttons.contains_key("toast:0"));
+        assert_eq!(state.next_toast_expiry(), None);
+    }
+
+    #[test]
+    fn expired_toast_restores_the_override_below_it() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let now = Instant::now();
+        state
+            .override_button(0, &String::from("named_button0"))
+            .unwrap();
+        state
+            .toast(0, "Saved!", None, Duration::from_millis(500), now)
+            .unwrap();
+
+        // Act
+        state.expire_toasts(now + Duration::from_secs(1));
+
+        // Test
+        assert_eq!(state.button_name(0), Some(String::from("named_button0")));
+    }
+
+    #[test]
+    fn toasted_button_keeps_its_handlers() {
+        // Setup
+        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state
+            .toast(0, "Saved!", None, Duration::from_millis(500), Instant::now())
+            .unwrap();
+
+        // Act
+        let handlers = state.on_button_pressed(0);
+
+        // Test
+        assert_eq!(handlers[0].script, "on_page0_button4_down");
+    }
+
+    #[test]
+    fn shutdown_handler_is_created_from_config() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.shutdown_script = Some(config::EventHandlerConfig::AsCode {
+            code: "on_shutdown".to_string(),
+        });
+
+        // Act
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Test
+        assert_eq!(state.get_shutdown_handler().unwrap().script, "on_shutdown");
+    }
+
+    #[test]
+    fn no_shutdown_handler_without_config() {
+        // Setup
+        let config = get_full_config(false);
+
+        // Act
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Test
+        assert!(state.get_shutdown_handler().is_none());
+    }
+
+    #[test]
+    fn missing_default_page_is_an_error() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.default_pages = Some(vec![String::from("page0"), String::from("missing")]);
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(matches!(
+            result,
+            Err(Error::PagesNotFound(names)) if names == vec![String::from("missing")]
+        ));
     }
 
     #[test]
-    fn named_buttons_must_be_unique() {
+    fn all_missing_default_pages_are_reported() {
         // Setup
-        let config = get_full_config(true);
+        let mut config = get_full_config(false);
+        config.default_pages = Some(vec![
+            String::from("missing1"),
+            String::from("page0"),
+            String::from("missing2"),
+        ]);
 
         // Act
         let result = AppState::from_config(&StreamDeckType::Orig, &config);
 
         // Test
-        assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(Error::PagesNotFound(names))
+                if names == vec![String::from("missing1"), String::from("missing2")]
+        ));
+    }
+
+    /// Name of the button of "page0" at a button index.
+    fn page0_button_at(state: &AppState, button_index: usize) -> Option<String> {
+        state.pages["page0"]
+            .get_button(&state.device_type, &state.defaults.column_order, button_index)
+            .map(|button| button.button_name.clone())
     }
 
     #[test]
-    fn named_buttons_are_loaded_from_config() {
+    fn columns_are_counted_from_the_right_by_default() {
         // Setup
         let config = get_full_config(false);
 
         // Act
-        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let orig = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let xl = AppState::from_config(&StreamDeckType::Xl, &config).unwrap();
 
-        //Test
-        for i in 0..5 {
-            assert!(state
-                .named_buttons
-                .get(&format!("named_button{}", i))
-                .is_some());
-        }
+        // Test
+        assert_eq!(page0_button_at(&orig, 4), Some(String::from("page0_button0")));
+        assert_eq!(page0_button_at(&xl, 7), Some(String::from("page0_button0")));
     }
 
     #[test]
-    fn pages_are_loaded_from_config() {
+    fn positions_left_to_right_counts_columns_from_the_left() {
         // Setup
-        let config = get_full_config(false);
+        let mut config = get_full_config(false);
+        config.positions_left_to_right = Some(true);
 
         // Act
-        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let orig = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let xl = AppState::from_config(&StreamDeckType::Xl, &config).unwrap();
 
-        //Test
-        assert!(state.pages.get(&String::from("page1")).is_some());
+        // Test
+        assert_eq!(page0_button_at(&orig, 0), Some(String::from("page0_button0")));
+        assert_eq!(page0_button_at(&xl, 0), Some(String::from("page0_button0")));
     }
 
     #[test]
-    fn named_buttons_of_page_appear_in_named_buttons() {
+    fn set_named_button_faces_updates_both_faces_and_renders_once() {
         // Setup
-        let config = get_full_config(false);
+        let mut config = get_full_config(false);
+        config.pages[0].buttons[0].button =
+            config::ButtonOrButtonName::ButtonName("named_button0".to_string());
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
+        let setup = state.named_buttons.get("named_button0").unwrap();
+        let old_up_md5 = image_md5(&setup.up_face.as_ref().unwrap().face);
+        let old_down_md5 = setup.down_face.as_ref().map(|face| image_md5(&face.face));
 
         // Act
-        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state
+            .set_named_button_faces(
+                &String::from("named_button0"),
+                FaceValues {
+                    label: Some(String::from("on")),
+                    ..FaceValues::default()
+                },
+                Some(FaceValues {
+                    color: Some(Rgba([255, 0, 0, 255])),
+                    label: Some(String::from("off")),
+                    ..FaceValues::default()
+                }),
+            )
+            .unwrap();
+        let rendering: Vec<(u8, md5::Digest)> = state
+            .set_rendered_and_get_rendering_faces()
+            .iter()
+            .map(|(id, face)| (*id, image_md5(&face.face)))
+            .collect();
 
-        //Test
-        for i in 0..15 {
-            assert!(state
-                .named_buttons
-                .get(&format!("page1_button{}", i))
-                .is_some());
-        }
+        // Test
+        let setup = state.named_buttons.get("named_button0").unwrap();
+        let up_md5 = image_md5(&setup.up_face.as_ref().unwrap().face);
+        let down_md5 = image_md5(&setup.down_face.as_ref().unwrap().face);
+        assert_ne!(up_md5, old_up_md5);
+        assert_ne!(Some(down_md5), old_down_md5);
+        assert_eq!(rendering, vec![(4, up_md5)]);
     }
 
     #[test]
-    fn correct_button_press_and_release_events_are_returned() {
+    fn set_named_button_faces_without_down_values_keeps_the_down_face() {
         // Setup
-        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &get_full_config(false)).unwrap();
+        let setup = state.named_buttons.get("named_button0").unwrap();
+        let old_up_md5 = image_md5(&setup.up_face.as_ref().unwrap().face);
 
         // Act
-        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
-        // Page0 is default and loaded!
-        let press_event = state.on_button_pressed(0).unwrap();
-        let release_event = state.on_button_released(0).unwrap();
+        state
+            .set_named_button_faces(
+                &String::from("named_button0"),
+                FaceValues {
+                    label: Some(String::from("on")),
+                    ..FaceValues::default()
+                },
+                None,
+            )
+            .unwrap();
 
-        //Test
-        assert_eq!(press_event.script, String::from("on_page0_button4_down"));
-        assert_eq!(release_event.script, String::from("on_page0_button4_up"));
+        // Test
+        let setup = state.named_buttons.get("named_button0").unwrap();
+        assert_ne!(image_md5(&setup.up_face.as_ref().unwrap().face), old_up_md5);
+        assert!(setup.down_face.is_none());
     }
 
     #[test]
-    fn default_page_is_loaded_and_all_buttons_need_rendering() {
+    fn set_named_button_faces_of_unknown_button_fails() {
         // Setup
-        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &get_full_config(false)).unwrap();
 
         // Act
-        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let result = state.set_named_button_faces(
+            &String::from("unknown"),
+            FaceValues::default(),
+            Some(FaceValues::default()),
+        );
 
         // Test
-        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 15);
+        assert!(matches!(result, Err(Error::ButtonNotFound(_))));
+    }
+
+    /// The scripts of a list of handlers
+    fn handler_scripts<'a>(handlers: impl IntoIterator<Item = &'a EventHandler>) -> Vec<String> {
+        handlers.into_iter().map(|h| h.script.clone()).collect()
     }
 
     #[test]
-    fn after_button_press_face_is_returned_for_rendering() {
+    fn peeking_returns_the_handlers_without_changing_the_button() {
         // Setup
-        let config = get_full_config(false);
+        let mut config = get_full_config(false);
+        config.pages[0].buttons[0].button =
+            config::ButtonOrButtonName::ButtonName("named_button0".to_string());
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.set_rendered_and_get_rendering_faces();
 
         // Act
-        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
-        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 15);
-        state.on_button_pressed(0);
+        let peeked_down = state.peek_button_handler(4, &PressState::Down).unwrap();
+        let peeked_up = state.peek_button_handler(4, &PressState::Up).unwrap();
 
         // Test
-        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 1);
+        assert!(state.buttons_needing_rendering().is_empty());
+        assert!(!state.buttons[4].is_pressed());
+        assert_eq!(peeked_down.button_name, "named_button0");
+        let pressed = handler_scripts(state.on_button_pressed(4));
+        assert_eq!(handler_scripts(&peeked_down.handlers), pressed);
+        let released = handler_scripts(state.on_button_released(4));
+        assert_eq!(handler_scripts(&peeked_up.handlers), released);
+        assert_eq!(pressed, vec!["on_named_button0_down"]);
     }
 
     #[test]
-    fn after_button_release_face_is_returned_for_rendering() {
+    fn peeking_a_missing_button_returns_none() {
         // Setup
-        let config = get_full_config(false);
+        let state = AppState::from_config(&StreamDeckType::Orig, &get_full_config(false)).unwrap();
 
         // Act
+        let peeked = state.peek_button_handler(100, &PressState::Down);
+
+        // Test
+        assert!(peeked.is_none());
+    }
+
+    /// Adds the named button "mode" with three cycle faces (red, green and blue),
+    /// shown at index 4 by "page0".
+    fn add_cycle_face_button(config: &mut config::Config) {
+        config.buttons.as_mut().unwrap().push(
+            serde_yaml::from_str(
+                "\
+name: mode
+cycle_faces:
+  - color: '#FF0000'
+  - color: '#00FF00'
+  - color: '#0000FF'
+",
+            )
+            .unwrap(),
+        );
+        config.pages[0].buttons[0].button =
+            config::ButtonOrButtonName::ButtonName("mode".to_string());
+    }
+
+    #[test]
+    fn cycling_selects_the_faces_in_order_and_wraps() {
+        // Setup
+        let mut config = get_full_config(false);
+        add_cycle_face_button(&mut config);
         let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
-        state.on_button_pressed(0);
         state.set_rendered_and_get_rendering_faces();
-        state.on_button_released(0);
+        let cycle_md5s: Vec<md5::Digest> = state.named_buttons["mode"]
+            .cycle_faces
+            .iter()
+            .map(|face| image_md5(&face.face))
+            .collect();
+        let name = String::from("mode");
+
+        // Act
+        let mut indexes = Vec::new();
+        let mut rendered = Vec::new();
+        for _ in 0..3 {
+            indexes.push(state.cycle_button_face(&name).unwrap());
+            let faces = state.set_rendered_and_get_rendering_faces();
+            assert_eq!(faces.len(), 1);
+            assert_eq!(faces[0].0, 4);
+            rendered.push(image_md5(&faces[0].1.face));
+        }
 
         // Test
-        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 1);
+        assert_eq!(indexes, vec![1, 2, 0]);
+        assert_eq!(rendered, vec![cycle_md5s[1], cycle_md5s[2], cycle_md5s[0]]);
+        assert_ne!(cycle_md5s[0], cycle_md5s[1]);
+        assert_ne!(cycle_md5s[1], cycle_md5s[2]);
     }
 
     #[test]
-    fn button_press_and_release_results_in_no_need_for_rendering() {
+    fn cycling_a_button_without_cycle_faces_fails() {
         // Setup
-        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &get_full_config(false)).unwrap();
 
         // Act
-        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
-        state.set_rendered_and_get_rendering_faces();
-        state.on_button_pressed(0);
-        state.on_button_released(0);
+        let result = state.cycle_button_face(&String::from("named_button0"));
 
         // Test
-        assert_eq!(state.set_rendered_and_get_rendering_faces().len(), 0);
+        assert!(matches!(result, Err(Error::NoCycleFaces(_))));
     }
 
-    // Get the md5 sum of an image
-    fn image_md5(i: &RgbImage) -> md5::Digest {
-        md5::compute(i.as_raw())
+    #[test]
+    fn unused_named_button_is_unreferenced() {
+        // Setup
+        let mut config = get_full_config(false);
+        for i in 0..4 {
+            config.pages[0].buttons[i].button =
+                config::ButtonOrButtonName::ButtonName(format!("named_button{}", i));
+        }
+        let state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+
+        // Act
+        let unreferenced = state.unreferenced_named_buttons();
+
+        // Test
+        assert_eq!(unreferenced, vec![String::from("named_button4")]);
     }
 
     #[test]
-    fn page_loading_results_in_face_for_new_button_returned_for_rendering() {
+    fn undrawn_faces_are_the_faces_of_not_loaded_pages() {
         // Setup
         let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let undrawn_before_loading = state.undrawn_faces().len();
 
         // Act
-        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
-        state.set_rendered_and_get_rendering_faces();
-        state.load_page(&"page1".to_string()).unwrap();
+        state.load_page(&String::from("page1")).unwrap();
 
         // Test
-        let face_md5s = HashSet::<md5::Digest, RandomState>::from_iter(
-            state
-                .set_rendered_and_get_rendering_faces()
-                .iter()
-                .map(|f| image_md5(&f.1.face)),
-        );
-        assert_eq!(face_md5s.len(), 15);
-        for index in 0..15 {
-            assert!(face_md5s.contains(&image_md5(
-                &state
-                    .named_buttons
-                    .get(format!("page1_button{index}").as_str())
-                    .unwrap()
-                    .up_face
-                    .as_ref()
-                    .unwrap()
-                    .face
-            )))
+        // The up faces of the buttons of page1 and page2
+        assert_eq!(undrawn_before_loading, 30);
+        let undrawn = state.undrawn_faces();
+        assert_eq!(undrawn.len(), 15);
+        for button_id in 0..15 {
+            let setup = &state.named_buttons[&format!("page2_button{}", button_id)];
+            assert!(setup
+                .face_arcs()
+                .all(|face| undrawn.iter().any(|f| Arc::ptr_eq(f, face))));
         }
     }
 
     #[test]
-    fn page_loading_and_unloading_results_in_face_for_empty_needing_rendering() {
+    fn simulated_press_increments_the_press_counter() {
         // Setup
         let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let metrics = state.metrics();
 
         // Act
-        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
-        state.unload_page(&"page0".to_string()).unwrap();
-        state.set_rendered_and_get_rendering_faces();
-        state.load_page(&"page1".to_string()).unwrap();
-        state.set_rendered_and_get_rendering_faces();
-        state.unload_page(&"page1".to_string()).unwrap();
+        state.on_button_pressed(3);
+        state.on_button_released(3);
+        state.on_button_pressed(3);
 
         // Test
-        let rendering_faces = state.set_rendered_and_get_rendering_faces();
-        let face_md5s = HashSet::<md5::Digest, RandomState>::from_iter(
-            rendering_faces.iter().map(|f| image_md5(&f.1.face)),
-        );
-        assert_eq!(rendering_faces.len(), 15);
-        assert_eq!(face_md5s.len(), 1);
-        assert!(face_md5s.contains(&image_md5(
-            &state
-                .named_buttons
-                .get("empty".to_string().as_str())
-                .unwrap()
-                .up_face
-                .as_ref()
-                .unwrap()
-                .face
-        )));
+        assert_eq!(metrics.button_presses(3), 2);
+        assert_eq!(metrics.button_presses(4), 0);
     }
 
     #[test]
-    fn page_unloading_over_other_page_results_in_other_page_visible() {
+    fn page_loads_and_unloads_are_counted() {
         // Setup
         let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        let loads_before = state.metrics().exposition();
 
         // Act
-        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
-        state.set_rendered_and_get_rendering_faces();
-        state.load_page(&"page1".to_string()).unwrap();
-        state.load_page(&"page2".to_string()).unwrap();
-        state.set_rendered_and_get_rendering_faces();
-        state.unload_page(&"page2".to_string()).unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+        state.unload_page(&String::from("page1")).unwrap();
+        let _ = state.load_page(&String::from("unknown_page"));
 
         // Test
-        let rendering_faces = state.set_rendered_and_get_rendering_faces();
-        let face_md5s = HashSet::<md5::Digest, RandomState>::from_iter(
-            rendering_faces.iter().map(|f| image_md5(&f.1.face)),
-        );
-        assert_eq!(rendering_faces.len(), 15);
-        assert_eq!(face_md5s.len(), 15);
-        for index in 0..15 {
-            assert!(face_md5s.contains(&image_md5(
-                &state
-                    .named_buttons
-                    .get(format!("page1_button{index}").as_str())
-                    .unwrap()
-                    .up_face
-                    .as_ref()
-                    .unwrap()
-                    .face
-            )))
-        }
+        assert!(loads_before.contains("streamdeck_page_loads_total 1\n"));
+        let exposition = state.metrics().exposition();
+        assert!(exposition.contains("streamdeck_page_loads_total 2\n"));
+        assert!(exposition.contains("streamdeck_page_unloads_total 1\n"));
+    }
+
+    /// Config with the named button "shared" shown by "isolated" (index 4, isolating its named
+    /// buttons) and "other" (index 3), both loaded with "isolated" on top.
+    fn config_with_isolated_page() -> config::Config {
+        serde_yaml::from_str(
+            "\
+buttons:
+- name: shared
+  up_face:
+    label: shared
+pages:
+- name: isolated
+  isolate_named_buttons: true
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button: shared
+- name: other
+  buttons:
+  - position:
+      row: 0
+      col: 1
+    button: shared
+default_pages:
+- other
+- isolated
+",
+        )
+        .unwrap()
+    }
+
+    /// The md5 of the faces rendered on the buttons, by button id
+    fn rendered_md5s(state: &mut AppState) -> HashMap<u8, md5::Digest> {
+        state
+            .set_rendered_and_get_rendering_faces()
+            .into_iter()
+            .map(|(id, face)| (id, image_md5(&face.face)))
+            .collect()
     }
 
     #[test]
-    fn not_existing_page_loading_results_in_error() {
+    fn changing_a_page_copy_does_not_change_other_pages() {
         // Setup
-        let config = get_full_config(false);
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config_with_isolated_page()).unwrap();
+        let before = rendered_md5s(&mut state);
 
         // Act
-        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
-        let result = state.load_page(&String::from("unkown_page"));
+        state
+            .set_named_button_faces(
+                &String::from("shared"),
+                FaceValues {
+                    label: Some(String::from("changed")),
+                    ..FaceValues::default()
+                },
+                None,
+            )
+            .unwrap();
+        let after = rendered_md5s(&mut state);
 
         // Test
-        assert!(result.is_err());
+        assert_eq!(before[&4], before[&3]);
+        assert_eq!(after.keys().collect::<Vec<&u8>>(), vec![&4]);
+        assert_ne!(after[&4], before[&4]);
+        assert!(state.named_button_names().iter().all(|name| !name.starts_with(PAGE_COPY_PREFIX)));
+    }
+
+    /// The config with an isolated page, the shared named button latches
+    fn config_with_latch_on_isolated_page() -> config::Config {
+        let mut config = config_with_isolated_page();
+        config.buttons.as_mut().unwrap()[0].latch = Some(config::LatchConfig {
+            face: None,
+            down_handler: None,
+        });
+        config
     }
 
     #[test]
-    fn load_page_on_window() {
+    fn latch_on_an_isolated_page_is_kept_for_the_named_button() {
         // Setup
-        let config = get_full_config(false);
+        let mut state =
+            AppState::from_config(&StreamDeckType::Orig, &config_with_latch_on_isolated_page()).unwrap();
 
         // Act
-        let mut state = AppState::from_config(&StreamDeckType::Orig, &config).unwrap();
+        state.on_button_pressed(4);
+        state.on_button_released(4);
+        let latched_on_isolated_page = state.buttons[4].is_latched();
+        state.unload_page(&String::from("isolated")).unwrap();
+
+        // Test
+        assert!(latched_on_isolated_page);
+        assert!(state.button_latch(&String::from("shared")));
+        assert!(state.buttons[3].is_latched());
+    }
+
+    #[test]
+    fn latch_set_by_name_is_shown_on_an_isolated_page() {
+        // Setup
+        let mut state =
+            AppState::from_config(&StreamDeckType::Orig, &config_with_latch_on_isolated_page()).unwrap();
+
+        // Act
+        state.set_button_latch(&String::from("shared"), true).unwrap();
+
+        // Test
+        assert!(state.buttons[4].is_latched());
+    }
+
+    #[test]
+    fn unloading_an_isolating_page_restores_the_original() {
+        // Setup
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config_with_isolated_page()).unwrap();
+        let original = rendered_md5s(&mut state)[&3];
         state
-            .on_foreground_window(&WindowInformation {
-                title: String::from("This is a title for loading page2_title page"),
-                executable: String::from("/usr/bin/page2_exec"),
-                class_name: String::from("Some class we don't care about"),
-            })
+            .set_named_button_faces(
+                &String::from("shared"),
+                FaceValues {
+                    label: Some(String::from("changed")),
+                    ..FaceValues::default()
+                },
+                None,
+            )
+            .unwrap();
+        rendered_md5s(&mut state);
+
+        // Act
+        state.unload_page(&String::from("isolated")).unwrap();
+        state.load_page(&String::from("isolated")).unwrap();
+        let reloaded = rendered_md5s(&mut state);
+
+        // Test
+        assert_eq!(reloaded[&4], original);
+        assert_eq!(state.page_copies[&String::from("isolated")], vec![String::from("shared")]);
+    }
+
+    #[test]
+    fn without_isolating_page_changes_affect_all_pages() {
+        // Setup
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config_with_isolated_page()).unwrap();
+        rendered_md5s(&mut state);
+        state.unload_page(&String::from("isolated")).unwrap();
+        state.load_page(&String::from("other")).unwrap();
+        rendered_md5s(&mut state);
+
+        // Act
+        state
+            .set_named_button_faces(
+                &String::from("shared"),
+                FaceValues {
+                    label: Some(String::from("changed")),
+                    ..FaceValues::default()
+                },
+                None,
+            )
             .unwrap();
 
+        // Test
+        assert!(state.page_copies.is_empty());
+        assert_eq!(state.buttons_showing(&String::from("shared")), vec![3]);
+        assert_eq!(rendered_md5s(&mut state).keys().collect::<Vec<&u8>>(), vec![&3]);
+    }
+
+    /// The full config with the profiles "second" (page1 and page2) and "empty" (no pages)
+    fn config_with_profiles() -> config::Config {
+        let mut config = get_full_config(false);
+        config.profiles = Some(serde_yaml::from_str(
+            "\
+second:
+  default_pages: [page1, page2]
+empty:
+  default_pages: []
+",
+        )
+        .unwrap());
+        config
+    }
+
+    #[test]
+    fn activating_a_profile_loads_exactly_its_pages() {
+        // Setup
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config_with_profiles()).unwrap();
+        state.load_page(&String::from("page1")).unwrap();
+        state.take_queued_handlers();
+
+        // Act
+        state.activate_profile(&String::from("second")).unwrap();
+
         // Test
         assert_eq!(
-            state.on_button_pressed(0).unwrap().script,
-            "on_page2_button4_down"
+            state.get_page_stack(),
+            vec![String::from("page1"), String::from("page2")]
+        );
+        assert_eq!(state.button_name(4), Some(String::from("page2_button0")));
+        assert!(state.undo_page_operation());
+        assert_eq!(
+            state.get_page_stack(),
+            vec![String::from("page0"), String::from("page1")]
         );
     }
+
+    #[test]
+    fn activating_an_empty_profile_unloads_all_pages() {
+        // Setup
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config_with_profiles()).unwrap();
+
+        // Act
+        state.activate_profile(&String::from("empty")).unwrap();
+
+        // Test
+        assert!(state.get_page_stack().is_empty());
+        assert_eq!(state.button_name(4), Some(String::from("empty")));
+    }
+
+    #[test]
+    fn activating_an_unknown_profile_fails() {
+        // Setup
+        let mut state = AppState::from_config(&StreamDeckType::Orig, &config_with_profiles()).unwrap();
+
+        // Act
+        let result = state.activate_profile(&String::from("unknown"));
+
+        // Test
+        assert!(matches!(result, Err(Error::ProfileNotFound(name)) if name == "unknown"));
+        assert_eq!(state.get_page_stack(), vec![String::from("page0")]);
+    }
+
+    #[test]
+    fn profile_with_missing_page_is_rejected() {
+        // Setup
+        let mut config = get_full_config(false);
+        config.profiles = Some(serde_yaml::from_str("work:\n  default_pages: [page1, missing]").unwrap());
+
+        // Act
+        let result = AppState::from_config(&StreamDeckType::Orig, &config);
+
+        // Test
+        assert!(matches!(
+            result,
+            Err(Error::PagesNotFound(names)) if names == vec![String::from("missing")]
+        ));
+    }
 }
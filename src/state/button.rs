@@ -1,8 +1,11 @@
 use super::error::Error;
 use crate::config;
-use crate::state::button_face::ButtonFace;
+use crate::state::button_face::{ButtonFace, FaceCache, PressScale};
 use crate::state::defaults::Defaults;
 use crate::state::event_handler::EventHandler;
+use crate::state::foreground_window_condition::ForegroundWindowCondition;
+use crate::state::variables::is_truthy;
+use crate::foreground_window::WindowInformation;
 use std::collections::HashMap;
 use std::sync::Arc;
 use streamdeck_hid_rs::StreamDeckType;
@@ -11,11 +14,194 @@ use streamdeck_hid_rs::StreamDeckType;
 /// This is not the state of a button, but the setup.
 /// This setup can be applied to any button. But it is not
 /// the state of concrete button, it is part of the state (see [ButtonState]).
+#[derive(Clone)]
 pub struct ButtonSetup {
-    pub up_face: Option<ButtonFace>,
-    pub down_face: Option<ButtonFace>,
+    pub up_face: Option<Arc<ButtonFace>>,
+    pub down_face: Option<Arc<ButtonFace>>,
     pub up_handler: Option<EventHandler>,
     pub down_handler: Option<EventHandler>,
+    /// Handlers executed in order after the [up_handler]
+    pub up_handlers: Vec<EventHandler>,
+    /// Handlers executed in order after the [down_handler]
+    pub down_handlers: Vec<EventHandler>,
+    /// Page loaded when the button is pressed. While the page is loaded,
+    /// the button is highlighted (shows its down face).
+    pub load_page: Option<String>,
+    /// Faces replacing the up face, while their condition matches the foreground window
+    pub conditional_faces: Vec<ConditionalFace>,
+    /// Index of the conditional face currently replacing the up face
+    pub active_conditional_face: Option<usize>,
+    /// Faces replacing the up face (and the conditional faces), while their variable is true
+    pub variable_faces: Vec<VariableFace>,
+    /// Index of the variable face currently replacing the up face
+    pub active_variable_face: Option<usize>,
+    /// Faces replacing the up face one after the other (if not empty)
+    pub cycle_faces: Vec<Arc<ButtonFace>>,
+    /// Index of the cycle face currently replacing the up face
+    pub cycle_index: usize,
+    /// Disabled buttons are drawn dimmed and execute no handlers
+    pub enabled: bool,
+    /// Dimmed version of the up face, shown while the button is disabled
+    pub dimmed_face: Option<ButtonFace>,
+    /// Shrinking of the up face while pressed, if the button has no down face
+    pub press_scale: Option<PressScale>,
+    /// Shrunk version of the up face, shown while the button is pressed (see [press_scale])
+    pub pressed_face: Option<ButtonFace>,
+    /// Up face while the layer modifier button is held down
+    pub layer_face: Option<Arc<ButtonFace>>,
+    /// Handlers while the layer modifier button is held down
+    pub layer_up_handler: Option<EventHandler>,
+    pub layer_down_handler: Option<EventHandler>,
+    /// Handler executed, when the button changes to the [trigger] state
+    pub action: Option<EventHandler>,
+    pub trigger: PressState,
+    /// The down handlers are executed when the button is released, the up handlers when it is pressed
+    pub invert_press: bool,
+    /// Face and handler of the latched state, if the button is latching
+    pub latch: Option<Latch>,
+    /// Group of buttons, of which only one is selected
+    pub group: Option<String>,
+    /// Description from the config, for the status
+    pub description: Option<String>,
+    /// Tags from the config, for the status
+    pub tags: Vec<String>,
+}
+
+/// A face, used while the condition matches the foreground window.
+#[derive(Clone)]
+pub struct ConditionalFace {
+    pub condition: ForegroundWindowCondition,
+    pub face: Arc<ButtonFace>,
+}
+
+impl ConditionalFace {
+    pub fn from_config(
+        device_type: &streamdeck_hid_rs::StreamDeckType,
+        config: &config::ConditionalFaceConfig,
+        defaults: &Defaults,
+        face_cache: &mut FaceCache,
+    ) -> Result<ConditionalFace, Error> {
+        Ok(ConditionalFace {
+            condition: ForegroundWindowCondition::from_config(&config.condition)?,
+            face: face_cache.get_or_create(device_type, &config.face, defaults)?,
+        })
+    }
+}
+
+/// A face, used while a variable set by the scripts is true.
+#[derive(Clone)]
+pub struct VariableFace {
+    pub variable: String,
+    pub face: Arc<ButtonFace>,
+}
+
+impl VariableFace {
+    pub fn from_config(
+        device_type: &streamdeck_hid_rs::StreamDeckType,
+        config: &config::VariableFaceConfig,
+        defaults: &Defaults,
+        face_cache: &mut FaceCache,
+    ) -> Result<VariableFace, Error> {
+        Ok(VariableFace {
+            variable: config.when.clone(),
+            face: face_cache.get_or_create(device_type, &config.face, defaults)?,
+        })
+    }
+}
+
+/// The latched state of a latching button.
+#[derive(Clone)]
+pub struct Latch {
+    /// Up face while the button is latched
+    pub face: Option<Arc<ButtonFace>>,
+    /// Executed instead of the down handler, on the press that latches the button
+    pub down_handler: Option<EventHandler>,
+}
+
+impl Latch {
+    pub fn from_config(
+        device_type: &streamdeck_hid_rs::StreamDeckType,
+        config: &config::LatchConfig,
+        defaults: &Defaults,
+        face_cache: &mut FaceCache,
+    ) -> Result<Latch, Error> {
+        Ok(Latch {
+            face: match &config.face {
+                None => None,
+                Some(f) => Some(face_cache.get_or_create(device_type, f, defaults)?),
+            },
+            down_handler: match &config.down_handler {
+                None => None,
+                Some(e) => Some(EventHandler::from_config(e)?),
+            },
+        })
+    }
+}
+
+/// Create the up and down handler of a layer.
+fn layer_handlers_from_config(
+    config: &Option<config::LayerHandlersConfig>,
+) -> Result<(Option<EventHandler>, Option<EventHandler>), Error> {
+    let config = match config {
+        None => return Ok((None, None)),
+        Some(config) => config,
+    };
+    let up_handler = match &config.up_handler {
+        None => None,
+        Some(e) => Some(EventHandler::from_config(e)?),
+    };
+    let down_handler = match &config.down_handler {
+        None => None,
+        Some(e) => Some(EventHandler::from_config(e)?),
+    };
+    Ok((up_handler, down_handler))
+}
+
+/// Create the chained handlers of a button edge.
+fn handler_chain_from_config(
+    config: &Option<Vec<config::EventHandlerConfig>>,
+) -> Result<Vec<EventHandler>, Error> {
+    config
+        .iter()
+        .flatten()
+        .map(EventHandler::from_config)
+        .collect()
+}
+
+/// Select the conditional face for a foreground window.
+///
+/// # Arguments
+///
+/// faces - The conditional faces to select from.
+/// window - The foreground window.
+///
+/// # Return
+///
+/// The index of the first face whose condition matches, None if no condition matches.
+pub fn select_conditional_face(
+    faces: &[ConditionalFace],
+    window: &WindowInformation,
+) -> Option<usize> {
+    faces.iter().position(|f| f.condition.matches(window))
+}
+
+/// Select the face to use for the variables.
+///
+/// # Arguments
+///
+/// faces - The variable faces of a button.
+/// variables - The variables set by the scripts.
+///
+/// # Return
+///
+/// The index of the first face whose variable is true, None if no variable is true.
+pub fn select_variable_face(
+    faces: &[VariableFace],
+    variables: &HashMap<String, serde_json::Value>,
+) -> Option<usize> {
+    faces
+        .iter()
+        .position(|f| variables.get(&f.variable).map_or(false, is_truthy))
 }
 
 impl ButtonSetup {
@@ -25,6 +211,7 @@ impl ButtonSetup {
     ///
     /// device_type - The type of Streamdeck for which this [ButtonSetup] is created.
     /// config - The config to create the [ButtonSetup] from.
+    /// face_cache - Faces already created, identical faces are shared.
     ///
     /// # Return
     ///
@@ -33,15 +220,16 @@ impl ButtonSetup {
         device_type: &streamdeck_hid_rs::StreamDeckType,
         config: &config::ButtonConfigOptionalName,
         defaults: &Defaults,
+        face_cache: &mut FaceCache,
     ) -> Result<ButtonSetup, Error> {
         // Create the members
         let up_face = match &config.up_face {
             None => None,
-            Some(f) => Some(ButtonFace::from_config(device_type, f, defaults)?),
+            Some(f) => Some(face_cache.get_or_create(device_type, f, defaults)?),
         };
         let down_face = match &config.down_face {
             None => None,
-            Some(f) => Some(ButtonFace::from_config(device_type, f, defaults)?),
+            Some(f) => Some(face_cache.get_or_create(device_type, f, defaults)?),
         };
         let up_handler = match &config.up_handler {
             None => None,
@@ -51,12 +239,52 @@ impl ButtonSetup {
             None => None,
             Some(e) => Some(EventHandler::from_config(e)?),
         };
-        Ok(ButtonSetup {
+        let up_handlers = handler_chain_from_config(&config.up_handlers)?;
+        let down_handlers = handler_chain_from_config(&config.down_handlers)?;
+        let layer_face = match &config.layer_face {
+            None => None,
+            Some(f) => Some(face_cache.get_or_create(device_type, f, defaults)?),
+        };
+        let (layer_up_handler, layer_down_handler) = layer_handlers_from_config(&config.layer_handlers)?;
+        let action = match &config.action {
+            None => None,
+            Some(e) => Some(EventHandler::from_config(e)?),
+        };
+        let latch = match &config.latch {
+            None => None,
+            Some(l) => Some(Latch::from_config(device_type, l, defaults, face_cache)?),
+        };
+        let mut setup = ButtonSetup {
             up_face,
             down_face,
             up_handler,
             down_handler,
-        })
+            load_page: config.load_page.clone(),
+            conditional_faces: Vec::new(),
+            active_conditional_face: None,
+            enabled: true,
+            dimmed_face: None,
+            press_scale: defaults.press_scale,
+            pressed_face: None,
+            layer_face,
+            layer_up_handler,
+            layer_down_handler,
+            action,
+            trigger: PressState::from_trigger_config(&config.trigger),
+            invert_press: config.invert_press == Some(true),
+            latch,
+            group: config.group.clone(),
+            description: config.description.clone(),
+            tags: config.tags.clone().unwrap_or_default(),
+            variable_faces: Vec::new(),
+            active_variable_face: None,
+            up_handlers,
+            down_handlers,
+            cycle_faces: Vec::new(),
+            cycle_index: 0,
+        };
+        setup.set_enabled(config.enabled != Some(false));
+        Ok(setup)
     }
 
     /// Create Button Setup from configuration.
@@ -65,6 +293,7 @@ impl ButtonSetup {
     ///
     /// device_type - The type of Streamdeck for which this [ButtonSetup] is created.
     /// config - The config to create the [ButtonSetup] from.
+    /// face_cache - Faces already created, identical faces are shared.
     ///
     /// # Return
     ///
@@ -73,15 +302,16 @@ impl ButtonSetup {
         device_type: &streamdeck_hid_rs::StreamDeckType,
         config: &config::ButtonConfigWithName,
         defaults: &Defaults,
+        face_cache: &mut FaceCache,
     ) -> Result<ButtonSetup, Error> {
         // Create the members
         let up_face = match &config.up_face {
             None => None,
-            Some(f) => Some(ButtonFace::from_config(device_type, f, defaults)?),
+            Some(f) => Some(face_cache.get_or_create(device_type, f, defaults)?),
         };
         let down_face = match &config.down_face {
             None => None,
-            Some(f) => Some(ButtonFace::from_config(device_type, f, defaults)?),
+            Some(f) => Some(face_cache.get_or_create(device_type, f, defaults)?),
         };
         let up_handler = match &config.up_handler {
             None => None,
@@ -91,12 +321,273 @@ impl ButtonSetup {
             None => None,
             Some(e) => Some(EventHandler::from_config(e)?),
         };
-        Ok(ButtonSetup {
+        let up_handlers = handler_chain_from_config(&config.up_handlers)?;
+        let down_handlers = handler_chain_from_config(&config.down_handlers)?;
+        let layer_face = match &config.layer_face {
+            None => None,
+            Some(f) => Some(face_cache.get_or_create(device_type, f, defaults)?),
+        };
+        let (layer_up_handler, layer_down_handler) = layer_handlers_from_config(&config.layer_handlers)?;
+        let action = match &config.action {
+            None => None,
+            Some(e) => Some(EventHandler::from_config(e)?),
+        };
+        let latch = match &config.latch {
+            None => None,
+            Some(l) => Some(Latch::from_config(device_type, l, defaults, face_cache)?),
+        };
+        let conditional_faces = match &config.on_app_faces {
+            None => Vec::new(),
+            Some(faces) => faces
+                .iter()
+                .map(|f| ConditionalFace::from_config(device_type, f, defaults, face_cache))
+                .collect::<Result<Vec<_>, Error>>()?,
+        };
+        let variable_faces = match &config.when_faces {
+            None => Vec::new(),
+            Some(faces) => faces
+                .iter()
+                .map(|f| VariableFace::from_config(device_type, f, defaults, face_cache))
+                .collect::<Result<Vec<_>, Error>>()?,
+        };
+        let cycle_faces = match &config.cycle_faces {
+            None => Vec::new(),
+            Some(faces) => faces
+                .iter()
+                .map(|f| face_cache.get_or_create(device_type, f, defaults))
+                .collect::<Result<Vec<_>, Error>>()?,
+        };
+        let mut setup = ButtonSetup {
             up_face,
             down_face,
             up_handler,
             down_handler,
-        })
+            load_page: config.load_page.clone(),
+            conditional_faces,
+            active_conditional_face: None,
+            variable_faces,
+            active_variable_face: None,
+            enabled: true,
+            dimmed_face: None,
+            press_scale: defaults.press_scale,
+            pressed_face: None,
+            layer_face,
+            layer_up_handler,
+            layer_down_handler,
+            action,
+            trigger: PressState::from_trigger_config(&config.trigger),
+            invert_press: config.invert_press == Some(true),
+            latch,
+            group: config.group.clone(),
+            description: config.description.clone(),
+            tags: config.tags.clone().unwrap_or_default(),
+            up_handlers,
+            down_handlers,
+            cycle_faces,
+            cycle_index: 0,
+        };
+        setup.set_enabled(config.enabled != Some(false));
+        Ok(setup)
+    }
+
+    /// Setup of a toast, shown on a button for a while.
+    ///
+    /// # Arguments
+    ///
+    /// face - The face of the toast.
+    /// covered - The setup of the button, the toast is shown on. Its handlers are kept.
+    pub fn for_toast(face: ButtonFace, covered: Option<&ButtonSetup>) -> ButtonSetup {
+        ButtonSetup {
+            up_face: Some(Arc::new(face)),
+            down_face: None,
+            up_handler: covered.and_then(|c| c.up_handler.clone()),
+            down_handler: covered.and_then(|c| c.down_handler.clone()),
+            up_handlers: covered.map(|c| c.up_handlers.clone()).unwrap_or_default(),
+            down_handlers: covered.map(|c| c.down_handlers.clone()).unwrap_or_default(),
+            load_page: None,
+            conditional_faces: Vec::new(),
+            active_conditional_face: None,
+            enabled: true,
+            dimmed_face: None,
+            layer_face: None,
+            layer_up_handler: None,
+            layer_down_handler: None,
+            action: None,
+            trigger: PressState::Down,
+            latch: None,
+            group: None,
+            description: None,
+            tags: Vec::new(),
+            press_scale: None,
+            pressed_face: None,
+            variable_faces: Vec::new(),
+            active_variable_face: None,
+            cycle_faces: Vec::new(),
+            cycle_index: 0,
+            invert_press: covered.map_or(false, |c| c.invert_press),
+        }
+    }
+
+    /// The handlers for a press state change, taking the layer into account.
+    ///
+    /// # Arguments
+    ///
+    /// press_state - The new press state of the button.
+    /// layer_active - Whether the layer modifier button is held down.
+    ///
+    /// # Return
+    ///
+    /// The layer handler if the layer is active and the button has one,
+    /// otherwise the normal handler followed by the chained handlers.
+    pub fn handler_chain(&self, press_state: &PressState, layer_active: bool) -> Vec<&EventHandler> {
+        let (handler, chain, layer_handler) = match press_state {
+            PressState::Down => (&self.down_handler, &self.down_handlers, &self.layer_down_handler),
+            PressState::Up => (&self.up_handler, &self.up_handlers, &self.layer_up_handler),
+        };
+        match (layer_active, layer_handler) {
+            (true, Some(layer_handler)) => vec![layer_handler],
+            _ => handler.iter().chain(chain.iter()).collect(),
+        }
+    }
+
+    /// All handlers for a press state change: The (layer or latch) handler or the handler chain,
+    /// and the action, if the action is triggered by this change.
+    ///
+    /// # Arguments
+    ///
+    /// press_state - The new press state of the button.
+    /// layer_active - Whether the layer modifier button is held down.
+    /// latched - Whether the button is latched (after the change).
+    pub fn handlers(
+        &self,
+        press_state: &PressState,
+        layer_active: bool,
+        latched: bool,
+    ) -> Vec<&EventHandler> {
+        let action = match &self.action {
+            Some(action) if self.trigger == *press_state => Some(action),
+            _ => None,
+        };
+        let handlers = match (&self.latch, press_state, latched) {
+            (Some(latch), PressState::Down, true) => latch.down_handler.iter().collect(),
+            _ => self.handler_chain(press_state, layer_active),
+        };
+        handlers.into_iter().chain(action).collect()
+    }
+
+    /// The up face, taking the layer and the latched state into account.
+    pub fn up_face_for_layer(&self, layer_active: bool, latched: bool) -> Option<&ButtonFace> {
+        let latch_face = self.latch.as_ref().and_then(|l| l.face.as_deref());
+        match (layer_active, self.layer_face.as_deref(), latched, latch_face) {
+            (true, Some(layer_face), _, _) => Some(layer_face),
+            (_, _, true, Some(latch_face)) => Some(latch_face),
+            _ => self.active_up_face(),
+        }
+    }
+
+    /// Enables or disables the button.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.update_derived_faces();
+    }
+
+    /// Creates the dimmed and the pressed face again from the current up face,
+    /// must be called whenever the up face changes.
+    pub fn update_derived_faces(&mut self) {
+        self.dimmed_face = if self.enabled {
+            None
+        } else {
+            self.active_up_face().map(|face| face.dimmed())
+        };
+        self.pressed_face = match (&self.press_scale, &self.down_face) {
+            (Some(press_scale), None) => self.active_up_face().map(|face| face.pressed(press_scale)),
+            _ => None,
+        };
+    }
+
+    /// The up face, or the variable, conditional or cycle face replacing it.
+    pub fn active_up_face(&self) -> Option<&ButtonFace> {
+        match (self.active_variable_face, self.active_conditional_face) {
+            (Some(index), _) => Some(&self.variable_faces[index].face),
+            (None, Some(index)) => Some(&self.conditional_faces[index].face),
+            (None, None) => match self.cycle_faces.get(self.cycle_index) {
+                Some(face) => Some(face.as_ref()),
+                None => self.up_face.as_deref(),
+            },
+        }
+    }
+
+    /// Advance to the next cycle face, after the last one the first face follows again.
+    ///
+    /// # Return
+    ///
+    /// The index of the new cycle face, None if the button has no cycle faces.
+    pub fn cycle_face(&mut self) -> Option<usize> {
+        if self.cycle_faces.is_empty() {
+            return None;
+        }
+        self.cycle_index = (self.cycle_index + 1) % self.cycle_faces.len();
+        self.update_derived_faces();
+        Some(self.cycle_index)
+    }
+
+    /// All faces of the setup (including the conditional, the variable, the cycle, the layer and the latch faces).
+    pub fn faces(&self) -> impl Iterator<Item = &ButtonFace> {
+        self.face_arcs().map(|face| face.as_ref())
+    }
+
+    /// All faces of the setup, like [ButtonSetup::faces], as shared pointers.
+    pub fn face_arcs(&self) -> impl Iterator<Item = &Arc<ButtonFace>> {
+        [&self.up_face, &self.down_face, &self.layer_face]
+            .into_iter()
+            .flatten()
+            .chain(self.conditional_faces.iter().map(|f| &f.face))
+            .chain(self.variable_faces.iter().map(|f| &f.face))
+            .chain(self.cycle_faces.iter())
+            .chain(self.latch.iter().filter_map(|l| l.face.as_ref()))
+    }
+
+    /// All faces of the setup (including the conditional, the variable, the cycle, the layer and the latch faces), mutable.
+    ///
+    /// Faces may be shared with other buttons, use [Arc::make_mut] to change them.
+    pub fn faces_mut(&mut self) -> impl Iterator<Item = &mut Arc<ButtonFace>> {
+        [&mut self.up_face, &mut self.down_face, &mut self.layer_face]
+            .into_iter()
+            .flatten()
+            .chain(self.conditional_faces.iter_mut().map(|f| &mut f.face))
+            .chain(self.variable_faces.iter_mut().map(|f| &mut f.face))
+            .chain(self.cycle_faces.iter_mut())
+            .chain(self.latch.iter_mut().filter_map(|l| l.face.as_mut()))
+    }
+
+    /// Select the conditional face for a new foreground window.
+    ///
+    /// # Return
+    ///
+    /// true, if the face changed and the button has to be rendered again.
+    pub fn on_foreground_window(&mut self, window: &WindowInformation) -> bool {
+        let selected = select_conditional_face(&self.conditional_faces, window);
+        let changed = selected != self.active_conditional_face;
+        self.active_conditional_face = selected;
+        if changed {
+            self.update_derived_faces();
+        }
+        changed
+    }
+
+    /// Select the variable face after a variable changed.
+    ///
+    /// # Return
+    ///
+    /// true, if the face changed and the button has to be rendered again.
+    pub fn on_variables_changed(&mut self, variables: &HashMap<String, serde_json::Value>) -> bool {
+        let selected = select_variable_face(&self.variable_faces, variables);
+        let changed = selected != self.active_variable_face;
+        self.active_variable_face = selected;
+        if changed {
+            self.update_derived_faces();
+        }
+        changed
     }
 }
 
@@ -107,12 +598,28 @@ pub enum PressState {
     Up,
 }
 
+impl PressState {
+    /// The press state, on which the action of a button is triggered.
+    pub fn from_trigger_config(config: &Option<config::TriggerConfig>) -> PressState {
+        match config {
+            Some(config::TriggerConfig::Up) => PressState::Up,
+            Some(config::TriggerConfig::Down) | None => PressState::Down,
+        }
+    }
+}
+
 /// The state of a button!
 pub struct ButtonState {
     button_name: String,
     press_state: PressState,
+    // Highlighted buttons are rendered as if they were pressed
+    highlighted: bool,
+    // Whether the layer modifier button is held down
+    layer_active: bool,
+    // Whether a latching button is latched
+    latched: bool,
     // And how it is rendered. Basically, if this is not the same
-    // as the press_state the button is not correctly rendered
+    // as the displayed state the button is not correctly rendered
     render_state: Option<PressState>,
 }
 
@@ -121,6 +628,9 @@ impl ButtonState {
         ButtonState {
             button_name,
             press_state: PressState::Up,
+            highlighted: false,
+            layer_active: false,
+            latched: false,
             render_state: None,
         }
     }
@@ -129,46 +639,145 @@ impl ButtonState {
         ButtonState {
             button_name: String::from("empty"),
             press_state: PressState::Up,
+            highlighted: false,
+            layer_active: false,
+            latched: false,
             render_state: None,
         }
     }
 
+    /// Whether the button is currently pressed down
+    pub fn is_pressed(&self) -> bool {
+        self.press_state == PressState::Down
+    }
+
     /// Set, that it needs rendering
     pub fn set_needs_rendering(&mut self) {
         self.render_state = None;
     }
 
+    /// The press state, whose handlers are executed when the button changes to a press state.
+    ///
+    /// With `invert_press`, pressing the button executes the handlers of releasing it and the
+    /// other way round.
+    fn handled_press_state(setup: Option<&ButtonSetup>, press_state: &PressState) -> PressState {
+        match (setup.map_or(false, |s| s.invert_press), press_state) {
+            (true, PressState::Down) => PressState::Up,
+            (true, PressState::Up) => PressState::Down,
+            (false, press_state) => press_state.clone(),
+        }
+    }
+
+    /// The handlers, that changing to a press state would execute, without changing the button.
+    ///
+    /// # Arguments
+    ///
+    /// named_buttons - The named buttons, the setup of the button is taken from.
+    /// press_state - The new press state.
+    pub fn peek_handlers<'a>(
+        &self,
+        named_buttons: &'a HashMap<String, ButtonSetup>,
+        press_state: &PressState,
+    ) -> Vec<&'a EventHandler> {
+        let setup = self.get_setup(named_buttons);
+        let press_state = &ButtonState::handled_press_state(setup, press_state);
+        // Every press toggles latching buttons
+        let latched = match press_state {
+            PressState::Down if setup.map_or(false, |s| s.latch.is_some()) => !self.latched,
+            _ => self.latched,
+        };
+        setup
+            .map(|s| s.handlers(press_state, self.layer_active, latched))
+            .unwrap_or_default()
+    }
+
     /// Sets the press state of the button
     pub fn set_pressed<'a>(
         &mut self,
         named_buttons: &'a HashMap<String, ButtonSetup>,
-    ) -> Option<&'a EventHandler> {
+    ) -> Vec<&'a EventHandler> {
+        let handlers = self.peek_handlers(named_buttons, &PressState::Down);
         self.press_state = PressState::Down;
-        self.get_setup(named_buttons)
-            .and_then(|s| s.down_handler.as_ref())
+        self.toggle_latch(named_buttons, &PressState::Down);
+        handlers
     }
 
     /// Sets the press state of the button
     pub fn set_released<'a>(
         &mut self,
         named_buttons: &'a HashMap<String, ButtonSetup>,
-    ) -> Option<&'a EventHandler> {
+    ) -> Vec<&'a EventHandler> {
+        let handlers = self.peek_handlers(named_buttons, &PressState::Up);
         self.press_state = PressState::Up;
-        self.get_setup(named_buttons)
-            .and_then(|s| s.up_handler.as_ref())
+        self.toggle_latch(named_buttons, &PressState::Up);
+        handlers
+    }
+
+    /// Every press toggles latching buttons (with `invert_press`, every release).
+    fn toggle_latch(&mut self, named_buttons: &HashMap<String, ButtonSetup>, press_state: &PressState) {
+        let setup = self.get_setup(named_buttons);
+        if setup.map_or(false, |s| s.latch.is_some())
+            && ButtonState::handled_press_state(setup, press_state) == PressState::Down
+        {
+            self.set_latched(!self.latched);
+        }
+    }
+
+    /// Whether the button is highlighted (rendered with its down face)
+    #[cfg(test)]
+    pub fn is_highlighted(&self) -> bool {
+        self.highlighted
+    }
+
+    /// Sets whether the button is highlighted (rendered with its down face)
+    pub fn set_highlighted(&mut self, highlighted: bool) {
+        self.highlighted = highlighted;
+    }
+
+    /// Sets whether the layer modifier button is held down,
+    /// the button needs rendering if this changes.
+    pub fn set_layer_active(&mut self, layer_active: bool) {
+        if self.layer_active != layer_active {
+            self.layer_active = layer_active;
+            self.set_needs_rendering();
+        }
+    }
+
+    /// Whether the button is latched
+    pub fn is_latched(&self) -> bool {
+        self.latched
+    }
+
+    /// Sets whether the button is latched,
+    /// the button needs rendering if this changes.
+    pub fn set_latched(&mut self, latched: bool) {
+        if self.latched != latched {
+            self.latched = latched;
+            self.set_needs_rendering();
+        }
+    }
+
+    /// The state in which the button is displayed, highlighted buttons
+    /// are displayed as pressed.
+    fn displayed_state(&self) -> PressState {
+        if self.highlighted {
+            PressState::Down
+        } else {
+            self.press_state.clone()
+        }
     }
 
     /// Returns whether the button needs rendering
     pub fn needs_rendering(&self) -> bool {
         if let Some(rs) = &self.render_state {
-            return *rs != self.press_state;
+            return *rs != self.displayed_state();
         }
         true
     }
 
     /// Get the ButtonSetup, either from the internal setup
     /// or from the list of global setups
-    fn get_setup<'a>(
+    pub fn get_setup<'a>(
         &self,
         named_buttons: &'a HashMap<String, ButtonSetup>,
     ) -> Option<&'a ButtonSetup> {
@@ -191,16 +800,23 @@ impl ButtonState {
         named_buttons: &'a HashMap<String, ButtonSetup>,
     ) -> Option<&'a ButtonFace> {
         if self.needs_rendering() {
-            self.render_state = Some(self.press_state.clone());
+            let displayed_state = self.displayed_state();
+            self.render_state = Some(displayed_state.clone());
             let setup = self.get_setup(named_buttons)?;
-            match self.press_state {
-                PressState::Up => match setup.up_face {
-                    None => setup.down_face.as_ref(),
-                    Some(_) => setup.up_face.as_ref(),
+            if !setup.enabled {
+                return setup.dimmed_face.as_ref();
+            }
+            match displayed_state {
+                PressState::Up => match setup.up_face_for_layer(self.layer_active, self.latched) {
+                    None => setup.down_face.as_deref(),
+                    Some(face) => Some(face),
                 },
-                PressState::Down => match setup.down_face {
-                    None => setup.up_face.as_ref(),
-                    Some(_) => setup.down_face.as_ref(),
+                PressState::Down => match (&setup.down_face, &setup.pressed_face) {
+                    (Some(_), _) => setup.down_face.as_deref(),
+                    (None, Some(pressed_face)) if !self.layer_active && !self.latched => {
+                        Some(pressed_face)
+                    }
+                    (None, _) => setup.up_face_for_layer(self.layer_active, self.latched),
                 },
             }
         } else {
@@ -208,6 +824,11 @@ impl ButtonState {
         }
     }
 
+    /// The name of the named button on this button
+    pub fn button_name(&self) -> &String {
+        &self.button_name
+    }
+
     /// Tests the button name
     pub fn uses_button(&self, name: &String) -> bool {
         self.button_name.eq(name)
@@ -218,6 +839,7 @@ impl ButtonState {
 mod tests {
     use super::*;
     use crate::config::ButtonConfigOptionalName;
+    use crate::state::event_handler::{HandlerSource, ScriptLanguage};
 
     #[test]
     fn per_default_the_button_needs_rendering() {
@@ -240,6 +862,29 @@ mod tests {
             down_face: None,
             up_handler: None,
             down_handler: None,
+            load_page: None,
+            conditional_faces: Vec::new(),
+            active_conditional_face: None,
+            enabled: true,
+            dimmed_face: None,
+            layer_face: None,
+            layer_up_handler: None,
+            layer_down_handler: None,
+            action: None,
+            trigger: PressState::Down,
+            latch: None,
+            group: None,
+            description: None,
+            tags: Vec::new(),
+            press_scale: None,
+            pressed_face: None,
+            variable_faces: Vec::new(),
+            active_variable_face: None,
+            up_handlers: Vec::new(),
+            down_handlers: Vec::new(),
+            cycle_faces: Vec::new(),
+            cycle_index: 0,
+            invert_press: false,
         });
         named_buttons.insert(String::from("button"), setup.clone());
 
@@ -264,6 +909,29 @@ mod tests {
                 down_face: None,
                 up_handler: None,
                 down_handler: None,
+                load_page: None,
+                conditional_faces: Vec::new(),
+                active_conditional_face: None,
+                enabled: true,
+                dimmed_face: None,
+                layer_face: None,
+                layer_up_handler: None,
+                layer_down_handler: None,
+                action: None,
+                trigger: PressState::Down,
+                latch: None,
+                group: None,
+                description: None,
+                tags: Vec::new(),
+                press_scale: None,
+                pressed_face: None,
+                variable_faces: Vec::new(),
+                active_variable_face: None,
+                up_handlers: Vec::new(),
+                down_handlers: Vec::new(),
+                cycle_faces: Vec::new(),
+                cycle_index: 0,
+                invert_press: false,
             }),
         );
 
@@ -286,6 +954,29 @@ mod tests {
                 down_face: None,
                 up_handler: None,
                 down_handler: None,
+                load_page: None,
+                conditional_faces: Vec::new(),
+                active_conditional_face: None,
+                enabled: true,
+                dimmed_face: None,
+                layer_face: None,
+                layer_up_handler: None,
+                layer_down_handler: None,
+                action: None,
+                trigger: PressState::Down,
+                latch: None,
+                group: None,
+                description: None,
+                tags: Vec::new(),
+                press_scale: None,
+                pressed_face: None,
+                variable_faces: Vec::new(),
+                active_variable_face: None,
+                up_handlers: Vec::new(),
+                down_handlers: Vec::new(),
+                cycle_faces: Vec::new(),
+                cycle_index: 0,
+                invert_press: false,
             }),
         );
 
@@ -309,6 +1000,29 @@ mod tests {
                 down_face: None,
                 up_handler: None,
                 down_handler: None,
+                load_page: None,
+                conditional_faces: Vec::new(),
+                active_conditional_face: None,
+                enabled: true,
+                dimmed_face: None,
+                layer_face: None,
+                layer_up_handler: None,
+                layer_down_handler: None,
+                action: None,
+                trigger: PressState::Down,
+                latch: None,
+                group: None,
+                description: None,
+                tags: Vec::new(),
+                press_scale: None,
+                pressed_face: None,
+                variable_faces: Vec::new(),
+                active_variable_face: None,
+                up_handlers: Vec::new(),
+                down_handlers: Vec::new(),
+                cycle_faces: Vec::new(),
+                cycle_index: 0,
+                invert_press: false,
             }),
         );
 
@@ -332,4 +1046,295 @@ mod tests {
         // Test
         assert!(state.needs_rendering());
     }
+
+    #[test]
+    fn highlighted_button_needs_rendering_until_rendered() {
+        // Setup
+        let mut state = ButtonState::new("button".to_string());
+        let named_buttons = HashMap::new();
+        state.set_rendered_and_get_face_for_rendering(&named_buttons);
+
+        // Act
+        state.set_highlighted(true);
+
+        // Test
+        assert!(state.needs_rendering());
+        state.set_rendered_and_get_face_for_rendering(&named_buttons);
+        assert!(!state.needs_rendering());
+        // Pressing a highlighted button does not change its looks
+        state.set_pressed(&named_buttons);
+        assert!(!state.needs_rendering());
+    }
+
+    /// Conditional face (with an empty face) for windows with the given executable
+    fn conditional_face(executable: &str) -> ConditionalFace {
+        ConditionalFace {
+            condition: ForegroundWindowCondition::from_config(
+                &config::ForegroundWindowConditionConfig {
+                    title: None,
+                    executable: Some(String::from(executable)),
+                    class_name: None,
+                    workspace: None,
+                    title_equals: None,
+                    title_starts_with: None,
+                    title_contains: None,
+                },
+            )
+            .unwrap(),
+            face: Arc::new(ButtonFace::empty(StreamDeckType::Orig)),
+        }
+    }
+
+    #[test]
+    fn first_matching_conditional_face_is_selected() {
+        // Setup
+        let faces = vec![
+            conditional_face(".*editor.*"),
+            conditional_face(".*firefox.*"),
+            conditional_face(".*"),
+        ];
+        let window = WindowInformation {
+            title: String::from("title"),
+            executable: String::from("/usr/bin/firefox"),
+            class_name: String::from("class"),
+            workspace: None,
+        };
+
+        // Act
+        let selected = select_conditional_face(&faces, &window);
+
+        // Test
+        assert_eq!(selected, Some(1));
+    }
+
+    #[test]
+    fn no_conditional_face_is_selected_without_match() {
+        // Setup
+        let faces = vec![conditional_face(".*editor.*")];
+        let window = WindowInformation {
+            title: String::from("title"),
+            executable: String::from("/usr/bin/firefox"),
+            class_name: String::from("class"),
+            workspace: None,
+        };
+
+        // Act
+        let selected = select_conditional_face(&faces, &window);
+
+        // Test
+        assert_eq!(selected, None);
+    }
+
+    /// Setup with a down handler and a layer down handler
+    fn setup_with_layer_handler() -> ButtonSetup {
+        ButtonSetup {
+            up_face: None,
+            down_face: None,
+            up_handler: Some(EventHandler {
+                script: String::from("up"),
+                pythonpath: Vec::new(),
+                source: HandlerSource::Inline,
+                language: ScriptLanguage::Python,
+            }),
+            down_handler: Some(EventHandler {
+                script: String::from("down"),
+                pythonpath: Vec::new(),
+                source: HandlerSource::Inline,
+                language: ScriptLanguage::Python,
+            }),
+            load_page: None,
+            conditional_faces: Vec::new(),
+            active_conditional_face: None,
+            enabled: true,
+            dimmed_face: None,
+            layer_face: None,
+            layer_up_handler: None,
+            layer_down_handler: Some(EventHandler {
+                script: String::from("layer_down"),
+                pythonpath: Vec::new(),
+                source: HandlerSource::Inline,
+                language: ScriptLanguage::Python,
+            }),
+            action: None,
+            trigger: PressState::Down,
+            latch: None,
+            group: None,
+            description: None,
+            tags: Vec::new(),
+            press_scale: None,
+            pressed_face: None,
+            variable_faces: Vec::new(),
+            active_variable_face: None,
+            up_handlers: Vec::new(),
+            down_handlers: Vec::new(),
+            cycle_faces: Vec::new(),
+            cycle_index: 0,
+            invert_press: false,
+        }
+    }
+
+    #[test]
+    fn layer_handler_is_used_while_layer_is_active() {
+        // Setup
+        let setup = setup_with_layer_handler();
+
+        // Act
+        let handlers = setup.handler_chain(&PressState::Down, true);
+
+        // Test
+        assert_eq!(scripts(handlers), vec!["layer_down"]);
+    }
+
+    #[test]
+    fn normal_handler_is_used_while_layer_is_inactive() {
+        // Setup
+        let setup = setup_with_layer_handler();
+
+        // Act
+        let handlers = setup.handler_chain(&PressState::Down, false);
+
+        // Test
+        assert_eq!(scripts(handlers), vec!["down"]);
+    }
+
+    #[test]
+    fn normal_handler_is_used_without_layer_handler() {
+        // Setup
+        let setup = setup_with_layer_handler();
+
+        // Act
+        let handlers = setup.handler_chain(&PressState::Up, true);
+
+        // Test
+        assert_eq!(scripts(handlers), vec!["up"]);
+    }
+
+    /// Setup with up and down handlers and an action with the given trigger
+    fn setup_with_action(trigger: PressState) -> ButtonSetup {
+        let mut setup = setup_with_layer_handler();
+        setup.layer_down_handler = None;
+        setup.action = Some(EventHandler {
+            script: String::from("action"),
+            pythonpath: Vec::new(),
+            source: HandlerSource::Inline,
+            language: ScriptLanguage::Python,
+        });
+        setup.trigger = trigger;
+        setup
+    }
+
+    /// The scripts of the handlers
+    fn scripts(handlers: Vec<&EventHandler>) -> Vec<String> {
+        handlers.iter().map(|h| h.script.clone()).collect()
+    }
+
+    #[test]
+    fn action_with_down_trigger_is_executed_on_press() {
+        // Setup
+        let mut named_buttons = HashMap::new();
+        named_buttons.insert(String::from("button"), setup_with_action(PressState::Down));
+        let mut state = ButtonState::new(String::from("button"));
+
+        // Act
+        let pressed = scripts(state.set_pressed(&named_buttons));
+        let released = scripts(state.set_released(&named_buttons));
+
+        // Test
+        assert_eq!(pressed, vec!["down", "action"]);
+        assert_eq!(released, vec!["up"]);
+    }
+
+    #[test]
+    fn action_with_up_trigger_is_executed_on_release() {
+        // Setup
+        let mut named_buttons = HashMap::new();
+        named_buttons.insert(String::from("button"), setup_with_action(PressState::Up));
+        let mut state = ButtonState::new(String::from("button"));
+
+        // Act
+        let pressed = scripts(state.set_pressed(&named_buttons));
+        let released = scripts(state.set_released(&named_buttons));
+
+        // Test
+        assert_eq!(pressed, vec!["down"]);
+        assert_eq!(released, vec!["up", "action"]);
+    }
+
+    #[test]
+    fn trigger_defaults_to_down() {
+        assert!(PressState::from_trigger_config(&None) == PressState::Down);
+        assert!(
+            PressState::from_trigger_config(&Some(config::TriggerConfig::Up)) == PressState::Up
+        );
+    }
+
+    #[test]
+    fn chained_handlers_are_executed_in_order() {
+        // Setup
+        let mut setup = setup_with_action(PressState::Down);
+        setup.down_handlers = vec!["first", "second"]
+            .into_iter()
+            .map(|script| EventHandler {
+                script: String::from(script),
+                pythonpath: Vec::new(),
+                source: HandlerSource::Inline,
+                language: ScriptLanguage::Python,
+            })
+            .collect();
+        let mut named_buttons = HashMap::new();
+        named_buttons.insert(String::from("button"), setup);
+        let mut state = ButtonState::new(String::from("button"));
+
+        // Act
+        let pressed = scripts(state.set_pressed(&named_buttons));
+        let released = scripts(state.set_released(&named_buttons));
+
+        // Test
+        assert_eq!(pressed, vec!["down", "first", "second", "action"]);
+        assert_eq!(released, vec!["up"]);
+    }
+
+    #[test]
+    fn inverted_press_executes_the_down_handler_on_release() {
+        // Setup
+        let mut setup = setup_with_action(PressState::Down);
+        setup.invert_press = true;
+        let mut named_buttons = HashMap::new();
+        named_buttons.insert(String::from("button"), setup);
+        let mut state = ButtonState::new(String::from("button"));
+
+        // Act
+        let pressed = scripts(state.set_pressed(&named_buttons));
+        let pressed_state = state.is_pressed();
+        let released = scripts(state.set_released(&named_buttons));
+
+        // Test
+        assert_eq!(pressed, vec!["up"]);
+        assert!(pressed_state);
+        assert_eq!(released, vec!["down", "action"]);
+        assert!(!state.is_pressed());
+    }
+
+    #[test]
+    fn inverted_latching_button_toggles_on_release() {
+        // Setup
+        let mut setup = setup_with_action(PressState::Down);
+        setup.invert_press = true;
+        setup.latch = Some(Latch {
+            face: None,
+            down_handler: None,
+        });
+        let mut named_buttons = HashMap::new();
+        named_buttons.insert(String::from("button"), setup);
+        let mut state = ButtonState::new(String::from("button"));
+
+        // Act
+        state.set_pressed(&named_buttons);
+        let latched_while_pressed = state.is_latched();
+        state.set_released(&named_buttons);
+
+        // Test
+        assert!(!latched_while_pressed);
+        assert!(state.is_latched());
+    }
 }
@@ -1,18 +1,119 @@
 use super::error::Error;
+use super::fonts::{visual_order, Fonts};
 use super::Defaults;
 use crate::config;
 use crate::config::LabelConfig;
 use image::{Pixel, Rgba};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Color of a text.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TextColor {
+    /// Always the same color
+    Fixed(Rgba<u8>),
+    /// Black or white, whatever contrasts better with the background behind the text
+    Auto,
+}
+
+impl TextColor {
+    pub fn from_config(config: &config::ColorConfig) -> Result<TextColor, Error> {
+        if config.is_auto() {
+            Ok(TextColor::Auto)
+        } else {
+            Ok(TextColor::Fixed(
+                config.to_image_rgba_color().map_err(Error::ConfigError)?,
+            ))
+        }
+    }
+}
 
 /// Colored text, used in the button face
+#[derive(Clone)]
 struct ColoredText {
-    color: Option<Rgba<u8>>,
+    color: Option<TextColor>,
     text: String,
+    /// Font size in pixels, replacing the size derived from the height of the face
+    size: Option<f32>,
+    /// Explicit position, replacing the position of the label
+    at: Option<TextAt>,
+    /// Fonts of the text, replacing the fonts of the defaults
+    font: Option<Arc<Fonts>>,
+}
+
+/// Text placed with its anchor at pixel coordinates of the face
+#[derive(Clone, Copy)]
+struct TextAt {
+    x: i32,
+    y: i32,
+    /// Fraction of the text width left of the coordinates (0.0 left, 0.5 center, 1.0 right)
+    anchor_x: f32,
+    /// Fraction of the text height above the coordinates (0.0 top, 0.5 center, 1.0 bottom)
+    anchor_y: f32,
+}
+
+impl TextAt {
+    fn from_config(config: &config::LabelAtConfig) -> TextAt {
+        use crate::config::LabelAnchorConfig::*;
+        let (anchor_x, anchor_y) = match config.anchor.unwrap_or(Center) {
+            TopLeft => (0.0, 0.0),
+            Top => (0.5, 0.0),
+            TopRight => (1.0, 0.0),
+            Left => (0.0, 0.5),
+            Center => (0.5, 0.5),
+            Right => (1.0, 0.5),
+            BottomLeft => (0.0, 1.0),
+            Bottom => (0.5, 1.0),
+            BottomRight => (1.0, 1.0),
+        };
+        TextAt {
+            x: config.x,
+            y: config.y,
+            anchor_x,
+            anchor_y,
+        }
+    }
+}
+
+/// Color blended over the face with the given opacity
+#[derive(Clone)]
+struct Overlay {
+    color: Rgba<u8>,
+    opacity: f32,
+}
+
+/// Space between the border of the face and the image
+#[derive(Debug, PartialEq, Clone)]
+enum Padding {
+    Pixels(u32),
+    /// Percent of the width (left and right) or height (top and bottom) of the face
+    Percent(f32),
+}
+
+/// Color of the image, that is made transparent
+#[derive(Clone)]
+struct ColorKey {
+    color: Rgba<u8>,
+    /// Maximal difference of each color channel
+    tolerance: u8,
+}
+
+/// A cell of a sprite sheet, drawn instead of a whole image file
+#[derive(Clone)]
+struct Sprite {
+    file: String,
+    cols: u32,
+    rows: u32,
+    index: u32,
 }
 
 /// Face (picture) to be printed on a button.
 ///
 /// The face is pre-rendered into an image.
+#[derive(Clone)]
 pub struct ButtonFace {
     device_type: streamdeck_hid_rs::StreamDeckType,
     pub face: image::RgbImage,
@@ -21,6 +122,17 @@ pub struct ButtonFace {
     label: Option<ColoredText>,
     sublabel: Option<ColoredText>,
     superlabel: Option<ColoredText>,
+    lines: Vec<(ColoredText, TextPosition)>,
+    overlay: Option<Overlay>,
+    sprite: Option<Sprite>,
+    /// Scale the image with nearest-neighbor filtering, keeping pixel art sharp
+    pixelated: bool,
+    /// Space between the border of the face and the image
+    padding: Option<Padding>,
+    /// Pixels of the image with this color (within the tolerance) are transparent
+    color_key: Option<ColorKey>,
+    /// False until the face has been drawn into [face]
+    drawn: bool,
 }
 
 impl ButtonFace {
@@ -31,14 +143,52 @@ impl ButtonFace {
         face_config: &config::ButtonFaceConfig,
         defaults: &Defaults,
     ) -> Result<ButtonFace, Error> {
+        let mut button = ButtonFace::from_config_deferred(device_type, face_config)?;
+        button.draw_face(defaults)?;
+        Ok(button)
+    }
+
+    /// Create a [ButtonFace] from the configuration, without drawing it.
+    ///
+    /// The face has to be drawn (see [ButtonFace::redraw]) before it is shown.
+    /// Errors in the config values are still reported, but errors while drawing
+    /// (for example missing image files) only when the face is drawn.
+    pub fn from_config_deferred(
+        device_type: &streamdeck_hid_rs::StreamDeckType,
+        face_config: &config::ButtonFaceConfig,
+    ) -> Result<ButtonFace, Error> {
+        let (color, file) = match &face_config.background {
+            None => (
+                match &face_config.color {
+                    None => None,
+                    Some(c) => Some(c.to_image_rgba_color().map_err(Error::ConfigError)?),
+                },
+                face_config.file.clone(),
+            ),
+            Some(_) if face_config.color.is_some() || face_config.file.is_some() => {
+                return Err(Error::ConfigParserError(String::from(
+                    "background can not be used together with color or file in a face",
+                )));
+            }
+            Some(config::BackgroundConfig::RGB(c)) => {
+                (Some(Rgba([c.red, c.green, c.blue, 0xFF])), None)
+            }
+            Some(config::BackgroundConfig::ColorOrFile(text)) if text.starts_with('#') => (
+                Some(config::hex_string_to_rgba_color(text).map_err(Error::ConfigError)?),
+                None,
+            ),
+            Some(config::BackgroundConfig::ColorOrFile(text)) => (None, Some(text.clone())),
+        };
+        if file.is_some() && face_config.sprite.is_some() {
+            return Err(Error::ConfigParserError(String::from(
+                "file and sprite can not be used together in a face",
+            )));
+        }
         let face = image::RgbImage::new(0, 0);
-        let mut button = ButtonFace {
+        let button = ButtonFace {
             face,
-            color: match &face_config.color {
-                None => None,
-                Some(c) => Some(c.to_image_rgba_color().map_err(Error::ConfigError)?),
-            },
-            file: face_config.file.clone(),
+            color,
+            file,
             label: match &face_config.label {
                 None => None,
                 Some(label_config) => Some(ColoredText::from_config(label_config)?),
@@ -52,8 +202,37 @@ impl ButtonFace {
                 None => None,
                 Some(label_config) => Some(ColoredText::from_config(label_config)?),
             },
+            lines: match &face_config.lines {
+                None => Vec::new(),
+                Some(lines) => lines
+                    .iter()
+                    .map(ColoredText::line_from_config)
+                    .collect::<Result<Vec<_>, Error>>()?,
+            },
+            overlay: match &face_config.overlay {
+                None => None,
+                Some(overlay_config) => Some(Overlay::from_config(overlay_config)?),
+            },
+            sprite: match &face_config.sprite {
+                None => None,
+                Some(sprite_config) => Some(Sprite::from_config(sprite_config)?),
+            },
+            pixelated: face_config.pixelated == Some(true),
+            padding: match &face_config.padding {
+                None => None,
+                Some(padding_config) => Some(Padding::from_config(padding_config)?),
+            },
+            color_key: match &face_config.color_key {
+                None => None,
+                Some(color_config) => Some(ColorKey {
+                    color: color_config.to_image_rgba_color().map_err(Error::ConfigError)?,
+                    tolerance: face_config
+                        .color_key_tolerance
+                        .unwrap_or(DEFAULT_COLOR_KEY_TOLERANCE),
+                }),
+            },
+            drawn: false,
         };
-        button.draw_face(defaults)?;
         Ok(button)
     }
 
@@ -65,10 +244,27 @@ impl ButtonFace {
             file: None,
             label: None,
             sublabel: None,
-            superlabel: None
+            superlabel: None,
+            lines: Vec::new(),
+            overlay: None,
+            sprite: None,
+            pixelated: false,
+            padding: None,
+            color_key: None,
+            drawn: true,
         }
     }
 
+    /// A face showing the given image, instead of drawing it from the face values.
+    pub fn from_image(
+        device_type: streamdeck_hid_rs::StreamDeckType,
+        image: image::RgbImage,
+    ) -> ButtonFace {
+        let mut face = ButtonFace::empty(device_type);
+        face.face = image;
+        face
+    }
+
     /// Updates the face with new values
     pub fn update_values(&mut self,
                   color: Option<Rgba<u8>>,
@@ -86,23 +282,116 @@ impl ButtonFace {
         if file.is_some() {
             self.file = file;
         }
-        if label.is_some() || labelcolor.is_some() {
-            self.label.as_mut().map(|mut l| l.update_values(label, labelcolor));
-        }
-        if sublabel.is_some() || sublabelcolor.is_some() {
-            self.label.as_mut().map(|mut l| l.update_values(sublabel, sublabelcolor));
-        }
-        if superlabel.is_some() || superlabelcolor.is_some() {
-            self.label.as_mut().map(|mut l| l.update_values(superlabel, superlabelcolor));
+        ColoredText::update_or_create(&mut self.label, label, labelcolor);
+        ColoredText::update_or_create(&mut self.sublabel, sublabel, sublabelcolor);
+        ColoredText::update_or_create(&mut self.superlabel, superlabel, superlabelcolor);
+        self.draw_face(defaults)
+    }
+
+    /// A copy of the face with the text as label, for toasts.
+    ///
+    /// # Arguments
+    ///
+    /// text - The text of the toast, replacing the label.
+    /// color - Blended over the face, if given.
+    /// defaults - Defaults for drawing the face.
+    ///
+    /// # Return
+    ///
+    /// The drawn face, or an error if it could not be drawn.
+    pub fn toasted(
+        &self,
+        text: &str,
+        color: Option<Rgba<u8>>,
+        defaults: &Defaults,
+    ) -> Result<ButtonFace, Error> {
+        let mut face = self.clone();
+        face.label = Some(ColoredText {
+            color: None,
+            text: text.to_string(),
+            size: None,
+            at: None,
+            font: None,
+        });
+        if let Some(color) = color {
+            face.overlay = Some(Overlay {
+                color,
+                opacity: TOAST_OVERLAY_OPACITY,
+            });
         }
+        face.draw_face(defaults)?;
+        Ok(face)
+    }
+
+    /// A copy of the face with a darkened image, for example for disabled buttons.
+    pub fn dimmed(&self) -> ButtonFace {
+        let mut dimmed = ButtonFace::empty(self.device_type.clone());
+        dimmed.face = darken(&self.face, DIMMED_BRIGHTNESS);
+        dimmed
+    }
+
+    /// A copy of the face shrunk onto the background, for a "pushed in" look of pressed buttons.
+    pub fn pressed(&self, press_scale: &PressScale) -> ButtonFace {
+        let mut pressed = ButtonFace::empty(self.device_type.clone());
+        pressed.face = shrink(&self.face, press_scale.scale, press_scale.background);
+        pressed
+    }
+
+    /// The local image files drawn on the face (the image and the sprite sheet, if they are no URLs)
+    pub fn files(&self) -> Vec<&String> {
+        self.file
+            .iter()
+            .chain(self.sprite.as_ref().map(|s| &s.file))
+            .filter(|file| !is_url(file))
+            .collect()
+    }
+
+    /// Tests whether the face draws the given image file
+    pub fn uses_file(&self, path: &Path) -> bool {
+        self.files()
+            .iter()
+            .any(|file| is_same_file(Path::new(file), path))
+    }
+
+    /// Whether drawing the face loads an image (a file, a URL or a sprite sheet), which may take a while.
+    pub fn loads_image(&self) -> bool {
+        self.file.is_some() || self.sprite.is_some()
+    }
+
+    /// A stand-in for the face, filled with its background color, shown until the face is drawn.
+    pub fn placeholder(&self, defaults: &Defaults) -> ButtonFace {
+        let (width, height) = self.device_type.button_image_size();
+        let color = self.color.unwrap_or(defaults.background_color);
+        let mut placeholder = ButtonFace::empty(self.device_type.clone());
+        placeholder.face = image::RgbImage::from_pixel(width, height, color.to_rgb());
+        placeholder
+    }
+
+    /// Draws the face again, for example because the image file changed
+    pub fn redraw(&mut self, defaults: &Defaults) -> Result<(), Error> {
         self.draw_face(defaults)
     }
 
+    /// Whether the face has been drawn, faces created with [ButtonFace::from_config_deferred] are not.
+    pub fn is_drawn(&self) -> bool {
+        self.drawn
+    }
+
     /// Draws the face from the other values
     fn draw_face(&mut self, defaults: &Defaults) -> Result<(), Error> {
         // Start by creating the face (as rgba image
         // because we want to write rgba data on it).
         let (width, height) = self.device_type.button_image_size();
+        if width == 0 || height == 0 {
+            // Devices without displays (like pedals) show no faces
+            self.face = image::RgbImage::new(0, 0);
+            self.drawn = true;
+            return Ok(());
+        }
+        // Draw at a multiple of the size, the face is downsampled at the end
+        let supersample = defaults.supersample;
+        let (native_width, native_height) = (width, height);
+        let (width, height) = (width * supersample, height * supersample);
         let mut face = image::RgbaImage::new(width, height);
 
         // Get the background color
@@ -115,70 +404,532 @@ impl ButtonFace {
             back_color,
         );
 
+        // Load the image (the whole file or a cell of a sprite sheet)
+        let top_image = match (&self.file, &self.sprite) {
+            (Some(path), _) => Some(open_image(path)?),
+            (None, Some(sprite)) => Some(sprite.crop(&open_image(&sprite.file)?)),
+            (None, None) => None,
+        };
+
         // Draw the image!
-        if let Some(path) = &self.file {
-            let top_image = image::io::Reader::open(path)
-                .map_err(Error::ImageOpeningError)?
-                .decode()
-                .map_err(Error::ImageEncodingError)?;
-            let top_image = image::imageops::resize(
+        if let Some(top_image) = top_image {
+            let top_image = match &self.color_key {
+                None => top_image,
+                Some(color_key) => image::DynamicImage::ImageRgba8(color_key.apply(&top_image)),
+            };
+            let (inset_x, inset_y) = match &self.padding {
+                None => (0, 0),
+                Some(padding) => (
+                    padding.inset(native_width) * supersample,
+                    padding.inset(native_height) * supersample,
+                ),
+            };
+            let top_image = resize_image(
                 &top_image,
-                width,
-                height,
-                image::imageops::FilterType::Lanczos3,
+                width - 2 * inset_x,
+                height - 2 * inset_y,
+                self.pixelated,
             );
-            image::imageops::overlay(&mut face, &top_image, 0, 0);
+            image::imageops::overlay(&mut face, &top_image, inset_x as i64, inset_y as i64);
+        }
+
+        // Blend the overlay color over everything drawn so far
+        if let Some(overlay) = &self.overlay {
+            overlay.draw(&mut face);
         }
 
         // Convert to rgb image
         self.face = image::DynamicImage::ImageRgba8(face).to_rgb8();
+        self.drawn = true;
 
         // Draw the text on it
         if let Some(label) = &self.label {
-            label.draw(&mut self.face, TextPosition::Center, &defaults.label_color);
+            label.draw(&mut self.face, TextPosition::Center, &defaults.label_color, defaults);
         }
         if let Some(sublabel) = &self.sublabel {
-            sublabel.draw(&mut self.face, TextPosition::Sub, &defaults.sublabel_color);
+            sublabel.draw(&mut self.face, TextPosition::Sub, &defaults.sublabel_color, defaults);
         }
         if let Some(superlabel) = &self.superlabel {
             superlabel.draw(
                 &mut self.face,
                 TextPosition::Super,
                 &defaults.superlabel_color,
+                defaults,
+            );
+        }
+        for (line, position) in &self.lines {
+            line.draw(&mut self.face, *position, &defaults.label_color, defaults);
+        }
+
+        // Downsample to the size of the buttons
+        if supersample > 1 {
+            self.face = image::imageops::resize(
+                &self.face,
+                native_width,
+                native_height,
+                image::imageops::FilterType::Triangle,
             );
         }
         Ok(())
     }
 }
 
+/// Faces created from the config, identical face configs share one face.
+///
+/// Many buttons use the same face (for example the same color without label),
+/// caching them saves drawing the face and keeping it in memory more than once.
+#[derive(Default)]
+pub struct FaceCache {
+    /// The faces by the debug representation of their config (which contains every config value)
+    /// and the modification time and size of their image files
+    faces: HashMap<String, Arc<ButtonFace>>,
+    /// Create new faces without drawing them
+    deferred: bool,
+}
+
+impl FaceCache {
+    pub fn new() -> FaceCache {
+        FaceCache::default()
+    }
+
+    /// Sets whether new faces are created without drawing them (see [ButtonFace::from_config_deferred]).
+    pub fn set_deferred(&mut self, deferred: bool) {
+        self.deferred = deferred;
+    }
+
+    /// Get the face for a face config, it is only created if no identical config was seen before.
+    ///
+    /// # Arguments
+    ///
+    /// device_type - The type of the device, the face is for.
+    /// face_config - The config of the face.
+    /// defaults - The defaults for values missing in the config.
+    ///
+    /// # Return
+    ///
+    /// The face (shared with all other faces with the same config), or the error creating it.
+    pub fn get_or_create(
+        &mut self,
+        device_type: &streamdeck_hid_rs::StreamDeckType,
+        face_config: &config::ButtonFaceConfig,
+        defaults: &Defaults,
+    ) -> Result<Arc<ButtonFace>, Error> {
+        // Faces of image files, that changed since, are not the same
+        let key = format!("{:?} {:?}", face_config, config_file_stamps(face_config));
+        if let Some(face) = self.faces.get(&key) {
+            if self.deferred || face.is_drawn() {
+                return Ok(face.clone());
+            }
+        }
+        let face = Arc::new(if self.deferred {
+            ButtonFace::from_config_deferred(device_type, face_config)?
+        } else {
+            ButtonFace::from_config(device_type, face_config, defaults)?
+        });
+        self.faces.insert(key, face.clone());
+        Ok(face)
+    }
+}
+
+impl Sprite {
+    pub fn from_config(config: &config::SpriteConfig) -> Result<Sprite, Error> {
+        if config.index >= config.cols * config.rows {
+            return Err(Error::InvalidSpriteIndex(config.index));
+        }
+        Ok(Sprite {
+            file: config.file.clone(),
+            cols: config.cols,
+            rows: config.rows,
+            index: config.index,
+        })
+    }
+
+    /// Crop the cell out of the sprite sheet.
+    fn crop(&self, sheet: &image::DynamicImage) -> image::DynamicImage {
+        crop_sprite_cell(sheet, self.cols, self.rows, self.index)
+    }
+}
+
+impl ColorKey {
+    /// Make the pixels of the image with the key color transparent.
+    fn apply(&self, image: &image::DynamicImage) -> image::RgbaImage {
+        let mut result = image.to_rgba8();
+        for pixel in result.pixels_mut() {
+            let matches = (0..3).all(|channel| {
+                (pixel.0[channel] as i32 - self.color.0[channel] as i32).abs()
+                    <= self.tolerance as i32
+            });
+            if matches {
+                pixel.0[3] = 0;
+            }
+        }
+        result
+    }
+}
+
+impl Padding {
+    pub fn from_config(config: &config::PaddingConfig) -> Result<Padding, Error> {
+        match config {
+            config::PaddingConfig::Pixels(pixels) => Ok(Padding::Pixels(*pixels)),
+            config::PaddingConfig::Percent(text) => {
+                let percent = text
+                    .trim()
+                    .strip_suffix('%')
+                    .and_then(|number| number.trim().parse::<f32>().ok())
+                    .ok_or_else(|| Error::ConfigParserError(text.clone()))?;
+                // Padding on both sides, so it can be at most half of the face
+                if !(0.0..50.0).contains(&percent) {
+                    return Err(Error::ConfigParserError(text.clone()));
+                }
+                Ok(Padding::Percent(percent))
+            }
+        }
+    }
+
+    /// Size of the padding on each side of the given length (width or height) of the face.
+    ///
+    /// The padding is limited, so that at least one pixel is left for the image.
+    fn inset(&self, length: u32) -> u32 {
+        let inset = match self {
+            Padding::Pixels(pixels) => *pixels,
+            Padding::Percent(percent) => (length as f32 * percent / 100.0).round() as u32,
+        };
+        std::cmp::min(inset, length.saturating_sub(1) / 2)
+    }
+}
+
+impl Overlay {
+    pub fn from_config(config: &config::OverlayConfig) -> Result<Overlay, Error> {
+        if !(0.0..=1.0).contains(&config.opacity) {
+            return Err(Error::InvalidOpacity(config.opacity));
+        }
+        Ok(Overlay {
+            color: config
+                .color
+                .to_image_rgba_color()
+                .map_err(Error::ConfigError)?,
+            opacity: config.opacity,
+        })
+    }
+
+    /// Blend the overlay color over every pixel of the image.
+    fn draw(&self, image: &mut image::RgbaImage) {
+        for pixel in image.pixels_mut() {
+            for channel in 0..3 {
+                pixel.0[channel] = (pixel.0[channel] as f32 * (1.0 - self.opacity)
+                    + self.color.0[channel] as f32 * self.opacity)
+                    .round() as u8;
+            }
+        }
+    }
+}
+
+/// Shrinking of the up face, shown while a button without down face is pressed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PressScale {
+    /// Size of the pressed face, relative to the button (0.0 - 1.0)
+    pub scale: f32,
+    /// Color of the margins around the shrunk face
+    pub background: Rgba<u8>,
+}
+
+/// Brightness of dimmed faces, relative to the original face
+const DIMMED_BRIGHTNESS: f32 = 0.4;
+
+/// Opacity of the color blended over faces showing a toast.
+const TOAST_OVERLAY_OPACITY: f32 = 0.7;
+
+/// Tolerance of the color key, if none is given in the config.
+const DEFAULT_COLOR_KEY_TOLERANCE: u8 = 16;
+
 // Helper functions
 
+/// Darken an image, by multiplying all colors with the given brightness (0.0 - 1.0).
+fn darken(image: &image::RgbImage, brightness: f32) -> image::RgbImage {
+    let mut result = image.clone();
+    for pixel in result.pixels_mut() {
+        for channel in pixel.0.iter_mut() {
+            *channel = (*channel as f32 * brightness).round() as u8;
+        }
+    }
+    result
+}
+
+/// Shrink an image by the scale (0.0 - 1.0) and center it on the background color.
+fn shrink(image: &image::RgbImage, scale: f32, background: Rgba<u8>) -> image::RgbImage {
+    let (width, height) = image.dimensions();
+    let mut result = image::RgbImage::from_pixel(width, height, background.to_rgb());
+    if width == 0 || height == 0 {
+        return result;
+    }
+    let scaled_width = ((width as f32 * scale).round() as u32).clamp(1, width);
+    let scaled_height = ((height as f32 * scale).round() as u32).clamp(1, height);
+    let scaled = image::imageops::resize(
+        image,
+        scaled_width,
+        scaled_height,
+        image::imageops::FilterType::Triangle,
+    );
+    image::imageops::overlay(
+        &mut result,
+        &scaled,
+        ((width - scaled_width) / 2) as i64,
+        ((height - scaled_height) / 2) as i64,
+    );
+    result
+}
+
+/// Timeout for downloading images from URLs.
+const IMAGE_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Images downloaded from URLs (by URL), so that every URL is only fetched once.
+static DOWNLOADED_IMAGES: Mutex<Option<HashMap<String, Vec<u8>>>> = Mutex::new(None);
+
+/// Whether an image path is a http(s) URL.
+fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Download the (encoded) image from an URL, or return it from the cache.
+///
+/// The cache is not locked during the download, so that a slow server does not block
+/// the rendering of other faces.
+fn download_image(url: &str) -> Result<Vec<u8>, Error> {
+    if let Some(bytes) = DOWNLOADED_IMAGES
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|cache| cache.get(url))
+    {
+        return Ok(bytes.clone());
+    }
+    let response = ureq::AgentBuilder::new()
+        .timeout(IMAGE_DOWNLOAD_TIMEOUT)
+        .build()
+        .get(url)
+        .call()
+        .map_err(|e| Error::ImageDownloadError(format!("{}: {}", url, e)))?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .map_err(|e| Error::ImageDownloadError(format!("{}: {}", url, e)))?;
+    DOWNLOADED_IMAGES
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(url.to_string(), bytes.clone());
+    Ok(bytes)
+}
+
+/// Forget all downloaded images, so that their URLs are fetched again when they are used next.
+pub fn forget_downloaded_images() {
+    *DOWNLOADED_IMAGES.lock().unwrap() = None;
+}
+
+/// Modification time and size of a local image file, None for URLs and files that can not be read.
+fn file_stamp(path: &str) -> Option<(SystemTime, u64)> {
+    if is_url(path) {
+        return None;
+    }
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// The [file_stamp] of every image file of a face config (the file or background and the sprite sheet).
+fn config_file_stamps(config: &config::ButtonFaceConfig) -> Vec<Option<(SystemTime, u64)>> {
+    let background_file = match &config.background {
+        Some(config::BackgroundConfig::ColorOrFile(text)) if !text.starts_with('#') => Some(text),
+        _ => None,
+    };
+    config
+        .file
+        .iter()
+        .chain(background_file)
+        .chain(config.sprite.as_ref().map(|s| &s.file))
+        .map(|file| file_stamp(file))
+        .collect()
+}
+
+/// Open and decode an image file (or download it, if the path is a http(s) URL).
+fn open_image(path: &str) -> Result<image::DynamicImage, Error> {
+    if is_url(path) {
+        return image::load_from_memory(&download_image(path)?).map_err(Error::ImageEncodingError);
+    }
+    image::io::Reader::open(path)
+        .map_err(Error::ImageOpeningError)?
+        .decode()
+        .map_err(Error::ImageEncodingError)
+}
+
+/// Scale an image to the given size.
+///
+/// Pixelated images are scaled with nearest-neighbor filtering (keeping the pixels sharp),
+/// all others with Lanczos3.
+fn resize_image(
+    image: &image::DynamicImage,
+    width: u32,
+    height: u32,
+    pixelated: bool,
+) -> image::RgbaImage {
+    let filter = if pixelated {
+        image::imageops::FilterType::Nearest
+    } else {
+        image::imageops::FilterType::Lanczos3
+    };
+    image::imageops::resize(image, width, height, filter)
+}
+
+/// Crop a cell out of a sprite sheet with the given number of columns and rows.
+///
+/// The cells are counted row by row, starting at the top left.
+fn crop_sprite_cell(
+    sheet: &image::DynamicImage,
+    cols: u32,
+    rows: u32,
+    index: u32,
+) -> image::DynamicImage {
+    let cell_width = sheet.width() / cols;
+    let cell_height = sheet.height() / rows;
+    sheet.crop_imm(
+        (index % cols) * cell_width,
+        (index / cols) * cell_height,
+        cell_width,
+        cell_height,
+    )
+}
+
+/// Test whether two paths point to the same file.
+///
+/// If the paths can not be resolved, they are compared directly.
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
 /// Find the text scale, so that the given text fits into
 /// the given image with.
+///
+/// The scale is clamped to `min_scale` and `max_scale`. If the text does not
+/// fit with the minimal scale, it is truncated and ends with an ellipsis.
+///
+/// # Return
+///
+/// The (possibly truncated) text, the scale and the width and height of the text.
 fn find_text_scale(
     text: &str,
-    font: &rusttype::Font,
+    fonts: &Fonts,
     image_width: u32,
     default_scale: f32,
-) -> (rusttype::Scale, i32, i32) {
+    min_scale: f32,
+    max_scale: f32,
+) -> (String, rusttype::Scale, i32, i32) {
     let max_width = image_width as f32 * 0.9;
 
-    let scale = rusttype::Scale::uniform(default_scale);
+    let scale = rusttype::Scale::uniform(default_scale.clamp(min_scale, max_scale));
 
-    let (w, h) = imageproc::drawing::text_size(scale, font, text);
+    let (w, h) = fonts.text_size(scale, text);
+    if w as f32 <= max_width {
+        return (String::from(text), scale, w, h);
+    }
+    let mut scale =
+        rusttype::Scale::uniform((scale.y * max_width / (w as f32)).clamp(min_scale, max_scale));
+    let (mut w, mut h) = fonts.text_size(scale, text);
+    // Glyphs are placed on whole pixels, so the width does not shrink exactly with the scale
+    while w as f32 > max_width && scale.y > min_scale {
+        scale = rusttype::Scale::uniform((scale.y * 0.95).max(min_scale));
+        (w, h) = fonts.text_size(scale, text);
+    }
     if w as f32 <= max_width {
-        return (scale, w, h);
+        return (String::from(text), scale, w, h);
+    }
+
+    // Still too wide with the minimal scale, remove characters until it fits
+    let mut chars: Vec<char> = text.chars().collect();
+    loop {
+        chars.pop();
+        let truncated: String = chars.iter().chain(['…'].iter()).collect();
+        let (w, h) = fonts.text_size(scale, truncated.as_str());
+        if w as f32 <= max_width || chars.is_empty() {
+            return (truncated, scale, w, h);
+        }
+    }
+}
+
+/// Measure a label, that is shrunk to fit on a button like the labels of faces.
+///
+/// The text is measured with the fonts of the defaults, for example for external layout tools.
+///
+/// # Arguments
+///
+/// text - The text of the label.
+/// defaults - The defaults, providing the fonts.
+/// image_width - Width of the button image, the text may use 90% of it.
+/// base_scale - Font size (in pixels), if the text fits with it.
+///
+/// # Return
+///
+/// The width and the height of the text (in pixels) and the font size it is drawn with.
+pub fn measure_label(
+    text: &str,
+    defaults: &Defaults,
+    image_width: u32,
+    base_scale: f32,
+) -> (u32, u32, f32) {
+    let (_, scale, w, h) = find_text_scale(
+        text,
+        &defaults.fonts,
+        image_width,
+        base_scale,
+        0.0,
+        f32::INFINITY,
+    );
+    (w.max(0) as u32, h.max(0) as u32, scale.y)
+}
+
+/// Average relative luminance (0.0 - 255.0) of a region of the image.
+///
+/// The region is clipped to the image. If nothing remains, black (0.0) is assumed.
+fn average_luminance(image: &image::RgbImage, x: i32, y: i32, width: i32, height: i32) -> f32 {
+    let x_start = x.clamp(0, image.width() as i32) as u32;
+    let y_start = y.clamp(0, image.height() as i32) as u32;
+    let x_end = (x + width).clamp(0, image.width() as i32) as u32;
+    let y_end = (y + height).clamp(0, image.height() as i32) as u32;
+
+    let mut sum = 0.0;
+    let mut count = 0;
+    for py in y_start..y_end {
+        for px in x_start..x_end {
+            let [r, g, b] = image.get_pixel(px, py).0;
+            sum += 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return 0.0;
+    }
+    sum / count as f32
+}
+
+/// Black or white, whatever contrasts better with the given luminance.
+fn contrast_color(luminance: f32) -> Rgba<u8> {
+    if luminance > 127.5 {
+        Rgba([0, 0, 0, 255])
+    } else {
+        Rgba([255, 255, 255, 255])
     }
-    let scale = rusttype::Scale::uniform(default_scale * max_width / (w as f32));
-    let (w, h) = imageproc::drawing::text_size(scale, font, text);
-    (scale, w, h)
 }
 
 /// Possible positions of text.
+#[derive(Clone, Copy)]
 enum TextPosition {
     Center,
     Sub,
     Super,
+    /// Free position, as fraction of the button height (0.0 top, 1.0 bottom)
+    Line(f32),
 }
 
 impl ColoredText {
@@ -187,15 +938,57 @@ impl ColoredText {
             LabelConfig::JustText(text) => Ok(ColoredText {
                 color: None,
                 text: text.clone(),
+                size: None,
+                at: None,
+                font: None,
             }),
-            LabelConfig::WithColor(config) => Ok(ColoredText {
+            LabelConfig::WithColor(config) => {
+                if let Some(size) = config.size {
+                    if !(size > 0.0) {
+                        return Err(Error::ConfigParserError(format!(
+                            "size of label \"{}\" must be positive, got {}",
+                            config.text, size
+                        )));
+                    }
+                }
+                Ok(ColoredText {
+                    color: match &config.color {
+                        None => None,
+                        Some(c) => Some(TextColor::from_config(c)?),
+                    },
+                    text: config.text.clone(),
+                    size: config.size,
+                    at: config.at.as_ref().map(TextAt::from_config),
+                    font: match &config.font {
+                        None => None,
+                        Some(file) => Some(Fonts::for_label(file)?),
+                    },
+                })
+            }
+        }
+    }
+
+    /// Create a line of text together with its position.
+    fn line_from_config(config: &config::LineConfig) -> Result<(ColoredText, TextPosition), Error> {
+        if !(0.0..=1.0).contains(&config.y) {
+            return Err(Error::ConfigParserError(format!(
+                "y of line \"{}\" must be between 0.0 and 1.0, got {}",
+                config.text, config.y
+            )));
+        }
+        Ok((
+            ColoredText {
                 color: match &config.color {
                     None => None,
-                    Some(c) => Some(c.to_image_rgba_color().map_err(Error::ConfigError)?),
+                    Some(c) => Some(TextColor::from_config(c)?),
                 },
                 text: config.text.clone(),
-            }),
-        }
+                size: None,
+                at: None,
+                font: None,
+            },
+            TextPosition::Line(config.y),
+        ))
     }
 
     pub fn update_values(&mut self, label: Option<String>, color: Option<Rgba<u8>>) {
@@ -203,51 +996,92 @@ impl ColoredText {
             self.text = label_text;
         }
         if let Some(label_color) = color {
-            self.color = Some(label_color);
+            self.color = Some(TextColor::Fixed(label_color));
+        }
+    }
+
+    /// Update the values of a text, creating the text if it does not exist yet.
+    ///
+    /// A missing text is only created, if the new text is given.
+    pub fn update_or_create(
+        text: &mut Option<ColoredText>,
+        label: Option<String>,
+        color: Option<Rgba<u8>>,
+    ) {
+        match (text.as_mut(), label) {
+            (Some(text), label) => text.update_values(label, color),
+            (None, Some(label)) => {
+                *text = Some(ColoredText {
+                    color: color.map(TextColor::Fixed),
+                    text: label,
+                    size: None,
+                    at: None,
+                    font: None,
+                })
+            }
+            (None, None) => {}
         }
     }
 
     /// Draw the positioned text on the button face.
+    ///
+    /// The face is drawn supersampled, configured font sizes are scaled accordingly (and by the
+    /// font scale of the device).
     fn draw(
         &self,
         image: &mut image::RgbImage,
         position: TextPosition,
-        default_color: &image::Rgba<u8>,
+        default_color: &TextColor,
+        defaults: &Defaults,
     ) {
-        // Font data
-        let font_data: &[u8] = include_bytes!("../../assets/DejaVuSans.ttf");
-        let font = rusttype::Font::try_from_vec(Vec::from(font_data)).unwrap();
-
-        // Find the color, defaulting to the default color
-        let color = self.color.as_ref().unwrap_or(default_color);
-
-        let text = &self.text;
-
-        let (scale, w, h) = find_text_scale(
-            text.as_str(),
-            &font,
+        let supersample = defaults.supersample as f32;
+        let font_scale = defaults.font_scale * supersample;
+        let fonts = self.font.as_deref().unwrap_or(&defaults.fonts);
+        let (text, scale, w, h) = find_text_scale(
+            self.text.as_str(),
+            fonts,
             image.width(),
-            image.height() as f32
-                / match position {
-                    TextPosition::Center => 1.1,
-                    _ => 4.0,
-                },
+            self.size.map(|size| size * font_scale).unwrap_or(
+                image.height() as f32
+                    / match position {
+                        TextPosition::Center => 1.1,
+                        _ => 4.0,
+                    },
+            ),
+            defaults.min_font_scale * font_scale,
+            defaults.max_font_scale * font_scale,
         );
 
-        let baseline = match position {
-            TextPosition::Center => image.height() as f32 / 2.0,
-            TextPosition::Sub => image.height() as f32 * 4.0 / 5.0,
-            TextPosition::Super => image.height() as f32 / 5.0,
-        } as i32;
+        let (x, y) = match self.at {
+            // The coordinates are in pixels of the button, the face is drawn supersampled
+            Some(at) => (
+                (at.x as f32 * supersample - w as f32 * at.anchor_x) as i32,
+                (at.y as f32 * supersample - h as f32 * at.anchor_y) as i32,
+            ),
+            None => {
+                let baseline = match position {
+                    TextPosition::Center => image.height() as f32 / 2.0,
+                    TextPosition::Sub => image.height() as f32 * 4.0 / 5.0,
+                    TextPosition::Super => image.height() as f32 / 5.0,
+                    TextPosition::Line(y) => image.height() as f32 * y,
+                } as i32;
+                ((image.width() as i32 - w) / 2, baseline - h / 2)
+            }
+        };
+
+        // Find the color, defaulting to the default color
+        let color = match self.color.as_ref().unwrap_or(default_color) {
+            TextColor::Fixed(color) => *color,
+            TextColor::Auto => contrast_color(average_luminance(image, x, y, w, h)),
+        };
 
-        imageproc::drawing::draw_text_mut(
+        fonts.draw_text_mut(
             image,
             color.to_rgb(),
-            (image.width() as i32 - w) / 2,
-            baseline - h / 2,
+            x,
+            y,
             scale,
-            &font,
-            text.as_str(),
+            visual_order(&text).as_str(),
         );
     }
 }
@@ -256,6 +1090,7 @@ impl ColoredText {
 mod tests {
     use super::*;
     use crate::config::LabelConfigWithColor;
+    use crate::state::golden::assert_face_matches_golden;
     use imageproc::assert_pixels_eq;
     use streamdeck_hid_rs::StreamDeckType;
 
@@ -285,8 +1120,16 @@ mod tests {
                 label: None,
                 sublabel: None,
                 superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
             },
-            &Defaults::from_config(&None).unwrap(),
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
         )
         .unwrap();
 
@@ -314,8 +1157,16 @@ mod tests {
                 label: None,
                 sublabel: None,
                 superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
             },
-            &Defaults::from_config(&None).unwrap(),
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
         )
         .unwrap();
 
@@ -343,8 +1194,16 @@ mod tests {
                 label: None,
                 sublabel: None,
                 superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
             },
-            &Defaults::from_config(&None).unwrap(),
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
         )
         .unwrap();
 
@@ -381,10 +1240,21 @@ mod tests {
                 sublabel: Some(config::LabelConfig::WithColor(LabelConfigWithColor {
                     color: Some(config::ColorConfig::HEXString(String::from("#FFFF00"))),
                     text: String::from("AAAA"),
+                    size: None,
+                    at: None,
+                    font: None,
                 })),
                 superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
             },
-            &Defaults::from_config(&None).unwrap(),
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
         )
         .unwrap();
 
@@ -429,9 +1299,20 @@ mod tests {
                 superlabel: Some(config::LabelConfig::WithColor(LabelConfigWithColor {
                     color: Some(config::ColorConfig::HEXString(String::from("#FFFF00"))),
                     text: String::from("AAAA"),
+                    size: None,
+                    at: None,
+                    font: None,
                 })),
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
             },
-            &Defaults::from_config(&None).unwrap(),
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
         )
         .unwrap();
 
@@ -466,4 +1347,1070 @@ mod tests {
             5
         )
     }
+
+    #[test]
+    fn half_red_overlay_over_white_is_pink() {
+        // Setup
+
+        // Act
+        let face = ButtonFace::from_config(
+            &streamdeck_hid_rs::StreamDeckType::Orig,
+            &config::ButtonFaceConfig {
+                color: Some(config::ColorConfig::HEXString(String::from("#FFFFFF"))),
+                file: None,
+                label: None,
+                sublabel: None,
+                superlabel: None,
+                overlay: Some(config::OverlayConfig {
+                    color: config::ColorConfig::HEXString(String::from("#FF0000")),
+                    opacity: 0.5,
+                }),
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
+            },
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        let pink_image = image::RgbImage::from_pixel(
+            face.face.width(),
+            face.face.height(),
+            image::Rgb([255, 128, 128]),
+        );
+        assert_pixels_eq!(face.face, pink_image);
+    }
+
+    #[test]
+    fn overlay_opacity_out_of_range_is_an_error() {
+        // Setup
+
+        // Act
+        let result = ButtonFace::from_config(
+            &streamdeck_hid_rs::StreamDeckType::Orig,
+            &config::ButtonFaceConfig {
+                color: None,
+                file: None,
+                label: None,
+                sublabel: None,
+                superlabel: None,
+                overlay: Some(config::OverlayConfig {
+                    color: config::ColorConfig::HEXString(String::from("#FF0000")),
+                    opacity: 1.5,
+                }),
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
+            },
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        );
+
+        // Test
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn auto_color_over_dark_background_is_white() {
+        // Setup
+        let image = image::RgbImage::from_pixel(72, 72, image::Rgb([20, 20, 40]));
+
+        // Act
+        let color = contrast_color(average_luminance(&image, 10, 10, 50, 20));
+
+        // Test
+        assert_eq!(color, Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn auto_color_over_light_background_is_black() {
+        // Setup
+        let image = image::RgbImage::from_pixel(72, 72, image::Rgb([230, 230, 200]));
+
+        // Act
+        let color = contrast_color(average_luminance(&image, 10, 10, 50, 20));
+
+        // Test
+        assert_eq!(color, Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn luminance_is_only_sampled_in_the_region() {
+        // Setup
+        let mut image = image::RgbImage::from_pixel(72, 72, image::Rgb([0, 0, 0]));
+        imageproc::drawing::draw_filled_rect_mut(
+            &mut image,
+            imageproc::rect::Rect::at(0, 36).of_size(72, 36),
+            image::Rgb([255, 255, 255]),
+        );
+
+        // Act
+        let top = average_luminance(&image, 0, 0, 72, 36);
+        let bottom = average_luminance(&image, 0, 36, 72, 36);
+        let outside = average_luminance(&image, 100, 100, 10, 10);
+
+        // Test
+        assert_eq!(top, 0.0);
+        more_asserts::assert_gt!(bottom, 254.0);
+        assert_eq!(outside, 0.0);
+    }
+
+    #[test]
+    fn auto_label_color_over_white_background_draws_black_text() {
+        // Setup
+
+        // Act
+        let face = ButtonFace::from_config(
+            &streamdeck_hid_rs::StreamDeckType::Orig,
+            &config::ButtonFaceConfig {
+                color: Some(config::ColorConfig::HEXString(String::from("#FFFFFF"))),
+                file: None,
+                label: Some(config::LabelConfig::WithColor(LabelConfigWithColor {
+                    color: Some(config::ColorConfig::HEXString(String::from("auto"))),
+                    text: String::from("AAAA"),
+                    size: None,
+                    at: None,
+                    font: None,
+                })),
+                sublabel: None,
+                superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
+            },
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        more_asserts::assert_gt!(
+            count_color_occurrences(&face.face, &image::Rgb([0, 0, 0])),
+            5
+        )
+    }
+
+    /// Sprite sheet with 4 columns and 2 rows of 10x10 pixels cells,
+    /// cell i has the color (i, 0, 0).
+    fn sprite_sheet() -> image::DynamicImage {
+        image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(40, 20, |x, y| {
+            image::Rgb([(x / 10 + (y / 10) * 4) as u8, 0, 0])
+        }))
+    }
+
+    #[test]
+    fn sprite_first_cell_is_cropped() {
+        // Setup
+        let sheet = sprite_sheet();
+
+        // Act
+        let cell = crop_sprite_cell(&sheet, 4, 2, 0).to_rgb8();
+
+        // Test
+        assert_pixels_eq!(
+            cell,
+            image::RgbImage::from_pixel(10, 10, image::Rgb([0, 0, 0]))
+        );
+    }
+
+    #[test]
+    fn sprite_last_cell_is_cropped() {
+        // Setup
+        let sheet = sprite_sheet();
+
+        // Act
+        let cell = crop_sprite_cell(&sheet, 4, 2, 7).to_rgb8();
+
+        // Test
+        assert_pixels_eq!(
+            cell,
+            image::RgbImage::from_pixel(10, 10, image::Rgb([7, 0, 0]))
+        );
+    }
+
+    #[test]
+    fn sprite_index_out_of_range_is_an_error() {
+        // Setup
+        let config = config::SpriteConfig {
+            file: String::from("sheet.png"),
+            cols: 4,
+            rows: 2,
+            index: 8,
+        };
+
+        // Act
+        let result = Sprite::from_config(&config);
+
+        // Test
+        assert!(matches!(result, Err(Error::InvalidSpriteIndex(8))));
+    }
+
+    /// Line of text for tests
+    fn line_config(color: &str, y: f32) -> config::LineConfig {
+        config::LineConfig {
+            text: String::from("AAAA"),
+            color: Some(config::ColorConfig::HEXString(String::from(color))),
+            y,
+        }
+    }
+
+    #[test]
+    fn four_lines_are_drawn_at_their_positions() {
+        // Setup
+        let colors = [
+            image::Rgb([255, 0, 0]),
+            image::Rgb([0, 255, 0]),
+            image::Rgb([0, 0, 255]),
+            image::Rgb([255, 255, 0]),
+        ];
+
+        // Act
+        let mut face = ButtonFace::from_config(
+            &streamdeck_hid_rs::StreamDeckType::Orig,
+            &config::ButtonFaceConfig {
+                color: Some(config::ColorConfig::HEXString(String::from("#000000"))),
+                file: None,
+                label: None,
+                sublabel: None,
+                superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: Some(vec![
+                    line_config("#FF0000", 0.125),
+                    line_config("#00FF00", 0.375),
+                    line_config("#0000FF", 0.625),
+                    line_config("#FFFF00", 0.875),
+                ]),
+                pixelated: None,
+                padding: None,
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
+            },
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        // Every line is drawn in its own quarter of the face, and nowhere else
+        let (width, height) = (face.face.width(), face.face.height());
+        for (index, color) in colors.iter().enumerate() {
+            let quarter = image::imageops::crop(
+                &mut face.face,
+                0,
+                index as u32 * height / 4,
+                width,
+                height / 4,
+            )
+            .to_image();
+            let in_quarter = count_color_occurrences(&quarter, color);
+            more_asserts::assert_gt!(in_quarter, 5);
+            assert_eq!(count_color_occurrences(&face.face, color), in_quarter);
+        }
+    }
+
+    #[test]
+    fn line_outside_of_face_is_an_error() {
+        // Setup
+        let config = line_config("#FF0000", 1.5);
+
+        // Act
+        let result = ColoredText::line_from_config(&config);
+
+        // Test
+        assert!(matches!(result, Err(Error::ConfigParserError(_))));
+    }
+
+    /// The fonts used for the labels
+    fn label_font() -> Fonts {
+        Fonts::new(&[], None).unwrap()
+    }
+
+    #[test]
+    fn text_scale_is_clamped_to_max_scale() {
+        // Setup
+        let font = label_font();
+
+        // Act
+        let (text, scale, _, _) = find_text_scale("A", &font, 72, 65.0, 0.0, 20.0);
+
+        // Test
+        assert_eq!(text, "A");
+        assert_eq!(scale.y, 20.0);
+    }
+
+    #[test]
+    fn text_scale_is_clamped_to_min_scale() {
+        // Setup
+        let font = label_font();
+
+        // Act
+        let (_, scale, _, _) = find_text_scale("A", &font, 72, 5.0, 10.0, 100.0);
+
+        // Test
+        assert_eq!(scale.y, 10.0);
+    }
+
+    #[test]
+    fn long_text_is_shrunk_but_not_truncated_without_min_scale() {
+        // Setup
+        let font = label_font();
+        let long_text = "This is a long label";
+
+        // Act
+        let (text, scale, w, _) = find_text_scale(long_text, &font, 72, 65.0, 0.0, f32::INFINITY);
+
+        // Test
+        assert_eq!(text, long_text);
+        more_asserts::assert_lt!(scale.y, 65.0);
+        more_asserts::assert_le!(w as f32, 72.0 * 0.9);
+    }
+
+    #[test]
+    fn too_long_text_is_truncated_with_ellipsis_at_min_scale() {
+        // Setup
+        let font = label_font();
+        let long_text = "This is a long label";
+
+        // Act
+        let (text, scale, w, _) = find_text_scale(long_text, &font, 72, 65.0, 15.0, f32::INFINITY);
+
+        // Test
+        assert_eq!(scale.y, 15.0);
+        assert!(text.ends_with('…'));
+        more_asserts::assert_lt!(text.chars().count(), long_text.chars().count());
+        assert!(long_text.starts_with(text.trim_end_matches('…')));
+        more_asserts::assert_le!(w as f32, 72.0 * 0.9);
+    }
+
+    #[test]
+    fn darkened_image_has_scaled_colors() {
+        // Setup
+        let image = image::RgbImage::from_pixel(10, 10, image::Rgb([200, 100, 0]));
+
+        // Act
+        let darkened = darken(&image, 0.5);
+
+        // Test
+        assert_pixels_eq!(
+            darkened,
+            image::RgbImage::from_pixel(10, 10, image::Rgb([100, 50, 0]))
+        );
+    }
+
+    /// Checkerboard of 4x4 pixels, alternating black and white
+    fn checkerboard() -> image::DynamicImage {
+        image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(4, 4, |x, y| {
+            if (x + y) % 2 == 0 {
+                image::Rgba([0, 0, 0, 255])
+            } else {
+                image::Rgba([255, 255, 255, 255])
+            }
+        }))
+    }
+
+    #[test]
+    fn pixelated_upscale_reproduces_source_pixels() {
+        // Setup
+        let source = checkerboard();
+
+        // Act
+        let scaled = resize_image(&source, 8, 8, true);
+
+        // Test
+        let expected = image::RgbaImage::from_fn(8, 8, |x, y| source.to_rgba8()[(x / 2, y / 2)]);
+        assert_pixels_eq!(scaled, expected);
+    }
+
+    #[test]
+    fn default_upscale_blurs_source_pixels() {
+        // Setup
+        let source = checkerboard();
+
+        // Act
+        let scaled = resize_image(&source, 8, 8, false);
+
+        // Test
+        let expected = image::RgbaImage::from_fn(8, 8, |x, y| source.to_rgba8()[(x / 2, y / 2)]);
+        assert_ne!(scaled, expected);
+    }
+
+    #[test]
+    fn padded_image_is_centered_in_the_face() {
+        // Setup
+        let image_dir = std::env::temp_dir().join("streamdeck_controller_padding_test");
+        std::fs::create_dir_all(&image_dir).unwrap();
+        let image_path = image_dir.join("blue.png");
+        image::RgbImage::from_pixel(10, 10, image::Rgb([0, 0, 255]))
+            .save(&image_path)
+            .unwrap();
+
+        // Act
+        let face = ButtonFace::from_config(
+            &streamdeck_hid_rs::StreamDeckType::Orig,
+            &config::ButtonFaceConfig {
+                color: Some(config::ColorConfig::HEXString(String::from("#FF0000"))),
+                file: Some(image_path.to_str().unwrap().to_string()),
+                label: None,
+                sublabel: None,
+                superlabel: None,
+                overlay: None,
+                sprite: None,
+                lines: None,
+                pixelated: None,
+                padding: Some(config::PaddingConfig::Percent(String::from("25%"))),
+                background: None,
+                color_key: None,
+                color_key_tolerance: None,
+            },
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        let (width, height) = (face.face.width(), face.face.height());
+        let (inset_x, inset_y) = (width / 4, height / 4);
+        for (x, y, pixel) in face.face.enumerate_pixels() {
+            let inside = (inset_x..width - inset_x).contains(&x)
+                && (inset_y..height - inset_y).contains(&y);
+            if inside {
+                assert_eq!(*pixel, image::Rgb([0, 0, 255]), "at ({}, {})", x, y);
+            } else {
+                assert_eq!(*pixel, image::Rgb([255, 0, 0]), "at ({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn padding_of_half_the_face_is_an_error() {
+        // Setup
+        let config = config::PaddingConfig::Percent(String::from("50%"));
+
+        // Act
+        let result = Padding::from_config(&config);
+
+        // Test
+        assert!(matches!(result, Err(Error::ConfigParserError(_))));
+    }
+
+    #[test]
+    fn pixel_padding_is_limited_to_the_face() {
+        // Setup
+        let padding = Padding::from_config(&config::PaddingConfig::Pixels(100)).unwrap();
+
+        // Act
+        let inset = padding.inset(72);
+
+        // Test
+        assert_eq!(inset, 35);
+    }
+
+    /// Face config with only the background set
+    fn background_face_config(background: config::BackgroundConfig) -> config::ButtonFaceConfig {
+        config::ButtonFaceConfig {
+            background: Some(background),
+            color: None,
+            file: None,
+            label: None,
+            sublabel: None,
+            superlabel: None,
+            overlay: None,
+            sprite: None,
+            lines: None,
+            pixelated: None,
+            padding: None,
+            color_key: None,
+            color_key_tolerance: None,
+        }
+    }
+
+    #[test]
+    fn background_color_fills_the_face() {
+        // Setup
+        let config =
+            background_face_config(config::BackgroundConfig::ColorOrFile(String::from("#FF0000")));
+
+        // Act
+        let face = ButtonFace::from_config(
+            &streamdeck_hid_rs::StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        let red_image = image::RgbImage::from_pixel(
+            face.face.width(),
+            face.face.height(),
+            image::Rgb([255, 0, 0]),
+        );
+        assert_pixels_eq!(face.face, red_image);
+    }
+
+    #[test]
+    fn background_file_is_drawn_as_image() {
+        // Setup
+        let config = background_face_config(config::BackgroundConfig::ColorOrFile(String::from(
+            "./src/state/test_image_st_orig.png",
+        )));
+
+        // Act
+        let face = ButtonFace::from_config(
+            &streamdeck_hid_rs::StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        assert_eq!(
+            face.files(),
+            vec![&String::from("./src/state/test_image_st_orig.png")]
+        );
+        assert_eq!(face.color, None);
+    }
+
+    #[test]
+    fn background_together_with_color_is_an_error() {
+        // Setup
+        let mut config =
+            background_face_config(config::BackgroundConfig::ColorOrFile(String::from("icon.png")));
+        config.color = Some(config::ColorConfig::HEXString(String::from("#FF0000")));
+
+        // Act
+        let result = ButtonFace::from_config(
+            &streamdeck_hid_rs::StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        );
+
+        // Test
+        assert!(matches!(result, Err(Error::ConfigParserError(_))));
+    }
+
+    /// Serves the PNG encoded image on a local port, returns the URL and the number of requests.
+    fn serve_image(
+        image: image::RgbImage,
+    ) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/icon.png", listener.local_addr().unwrap());
+        let requests = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let thread_requests = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).unwrap();
+                thread_requests.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    png.len()
+                );
+                std::io::Write::write_all(&mut stream, header.as_bytes()).unwrap();
+                std::io::Write::write_all(&mut stream, &png).unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    #[test]
+    fn image_url_is_downloaded_once() {
+        // Setup
+        let (url, requests) =
+            serve_image(image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 255])));
+        let config = background_face_config(config::BackgroundConfig::ColorOrFile(url));
+        let defaults = Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap();
+
+        // Act
+        let face = ButtonFace::from_config(&StreamDeckType::Orig, &config, &defaults).unwrap();
+        ButtonFace::from_config(&StreamDeckType::Orig, &config, &defaults).unwrap();
+
+        // Test
+        let blue_image = image::RgbImage::from_pixel(
+            face.face.width(),
+            face.face.height(),
+            image::Rgb([0, 0, 255]),
+        );
+        assert_pixels_eq!(face.face, blue_image);
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(face.files().is_empty());
+    }
+
+    #[test]
+    fn forgotten_image_urls_are_downloaded_again() {
+        // Setup
+        let (url, requests) =
+            serve_image(image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 255])));
+        download_image(&url).unwrap();
+
+        // Act
+        forget_downloaded_images();
+        download_image(&url).unwrap();
+
+        // Test
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn unreachable_image_url_is_a_download_error() {
+        // Setup
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/icon.png", listener.local_addr().unwrap());
+        drop(listener);
+
+        // Act
+        let result = download_image(&url);
+
+        // Test
+        assert!(matches!(result, Err(Error::ImageDownloadError(message)) if message.contains(&url)));
+    }
+
+    #[test]
+    fn color_key_pixels_show_the_background() {
+        // Setup
+        let image_dir = std::env::temp_dir().join("streamdeck_controller_color_key_test");
+        std::fs::create_dir_all(&image_dir).unwrap();
+        let image_path = image_dir.join("green_screen.png");
+        image::RgbImage::from_fn(4, 4, |x, _| {
+            if x < 2 {
+                image::Rgb([10, 250, 5])
+            } else {
+                image::Rgb([0, 0, 255])
+            }
+        })
+        .save(&image_path)
+        .unwrap();
+        let mut config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            image_path.to_str().unwrap().to_string(),
+        ));
+        config.color_key = Some(config::ColorConfig::HEXString(String::from("#00FF00")));
+        config.pixelated = Some(true);
+        let defaults = Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap();
+
+        // Act
+        let face = ButtonFace::from_config(&StreamDeckType::Orig, &config, &defaults).unwrap();
+
+        // Test
+        let (width, height) = (face.face.width(), face.face.height());
+        let background = defaults.background_color.to_rgb();
+        assert_eq!(face.face[(0, 0)], background);
+        assert_eq!(face.face[(width / 2 - 1, height - 1)], background);
+        assert_eq!(face.face[(width / 2, 0)], image::Rgb([0, 0, 255]));
+        assert_eq!(face.face[(width - 1, height - 1)], image::Rgb([0, 0, 255]));
+    }
+
+    #[test]
+    fn colors_outside_of_the_tolerance_are_kept() {
+        // Setup
+        let color_key = ColorKey {
+            color: Rgba([0, 255, 0, 255]),
+            tolerance: 4,
+        };
+        let image = image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                image::Rgb([4, 251, 0])
+            } else {
+                image::Rgb([5, 255, 0])
+            }
+        }));
+
+        // Act
+        let keyed = color_key.apply(&image);
+
+        // Test
+        assert_eq!(keyed[(0, 0)].0[3], 0);
+        assert_eq!(keyed[(1, 0)].0[3], 255);
+    }
+
+    /// Face with a white "Hi" superlabel of the given size on black background
+    fn sized_label_face(size: Option<f32>) -> ButtonFace {
+        let mut config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("#000000"),
+        ));
+        config.superlabel = Some(config::LabelConfig::WithColor(LabelConfigWithColor {
+            color: Some(config::ColorConfig::HEXString(String::from("#FFFFFF"))),
+            text: String::from("Hi"),
+            size,
+            at: None,
+            font: None,
+        }));
+        ButtonFace::from_config(
+            &StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap()
+    }
+
+    /// Number of rows of the image containing bright (text) pixels
+    fn text_height(image: &image::RgbImage) -> usize {
+        image
+            .rows()
+            .filter(|row| row.clone().any(|pixel| pixel[0] > 128))
+            .count()
+    }
+
+    #[test]
+    fn larger_label_size_draws_taller_glyphs() {
+        // Setup
+        let default_face = sized_label_face(None);
+
+        // Act
+        let large_face = sized_label_face(Some(36.0));
+
+        // Test
+        more_asserts::assert_gt!(text_height(&large_face.face), text_height(&default_face.face));
+    }
+
+    #[test]
+    fn label_size_must_be_positive() {
+        // Setup
+        let mut config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("#000000"),
+        ));
+        config.label = Some(config::LabelConfig::WithColor(LabelConfigWithColor {
+            color: None,
+            text: String::from("Hi"),
+            size: Some(0.0),
+            at: None,
+            font: None,
+        }));
+
+        // Act
+        let result = ButtonFace::from_config(
+            &StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        );
+
+        // Test
+        assert!(matches!(result, Err(Error::ConfigParserError(_))));
+    }
+
+    #[test]
+    fn color_face_matches_golden_image() {
+        // Setup
+        let config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("#FF0000"),
+        ));
+
+        // Act
+        let face = ButtonFace::from_config(
+            &StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        assert_face_matches_golden(&face, "./src/state/golden/color_only.png");
+    }
+
+    #[test]
+    fn label_face_matches_golden_image() {
+        // Setup
+        let mut config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("#0000FF"),
+        ));
+        config.label = Some(config::LabelConfig::JustText(String::from("Label")));
+
+        // Act
+        let face = ButtonFace::from_config(
+            &StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        assert_face_matches_golden(&face, "./src/state/golden/label.png");
+    }
+
+    #[test]
+    fn image_with_sublabel_face_matches_golden_image() {
+        // Setup
+        let mut config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("./src/state/test_image_st_orig.png"),
+        ));
+        config.sublabel = Some(config::LabelConfig::WithColor(LabelConfigWithColor {
+            color: Some(config::ColorConfig::HEXString(String::from("#FFFF00"))),
+            text: String::from("Sub"),
+            size: None,
+            at: None,
+            font: None,
+        }));
+
+        // Act
+        let face = ButtonFace::from_config(
+            &StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        assert_face_matches_golden(&face, "./src/state/golden/image_with_sublabel.png");
+    }
+
+    #[test]
+    fn supersampled_face_has_the_button_size() {
+        // Setup
+        let mut config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("#000000"),
+        ));
+        config.label = Some(config::LabelConfig::JustText(String::from("Wx")));
+        let mut defaults = Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap();
+        defaults.supersample = 3;
+
+        // Act
+        let face = ButtonFace::from_config(&StreamDeckType::Orig, &config, &defaults).unwrap();
+
+        // Test
+        assert_eq!(
+            face.face.dimensions(),
+            StreamDeckType::Orig.button_image_size()
+        );
+    }
+
+    #[test]
+    fn supersampled_label_has_smoother_edges() {
+        // Setup
+        let mut config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("#000000"),
+        ));
+        config.label = Some(config::LabelConfig::JustText(String::from("Wx")));
+        let mut supersampled_defaults = Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap();
+        supersampled_defaults.supersample = 4;
+
+        // Act
+        let native =
+            ButtonFace::from_config(&StreamDeckType::Orig, &config, &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap())
+                .unwrap();
+        let supersampled =
+            ButtonFace::from_config(&StreamDeckType::Orig, &config, &supersampled_defaults).unwrap();
+
+        // Test
+        let white = image::Rgb([255, 255, 255]);
+        assert!(
+            count_color_occurrences(&supersampled.face, &white)
+                < count_color_occurrences(&native.face, &white)
+        );
+    }
+
+    #[test]
+    fn pressed_face_is_surrounded_by_background_margins() {
+        // Setup
+        let config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("#FF0000"),
+        ));
+        let face = ButtonFace::from_config(
+            &StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+        let press_scale = PressScale {
+            scale: 0.8,
+            background: Rgba([0, 0, 255, 255]),
+        };
+
+        // Act
+        let pressed = face.pressed(&press_scale);
+
+        // Test
+        let (width, height) = StreamDeckType::Orig.button_image_size();
+        let margin_x = (width - (width as f32 * 0.8).round() as u32) / 2;
+        let margin_y = (height - (height as f32 * 0.8).round() as u32) / 2;
+        let (red, blue) = (image::Rgb([255, 0, 0]), image::Rgb([0, 0, 255]));
+        assert_eq!(pressed.face.dimensions(), (width, height));
+        assert_eq!(*pressed.face.get_pixel(margin_x - 1, height / 2), blue);
+        assert_eq!(*pressed.face.get_pixel(margin_x, height / 2), red);
+        assert_eq!(*pressed.face.get_pixel(width - margin_x - 1, height / 2), red);
+        assert_eq!(*pressed.face.get_pixel(width - margin_x, height / 2), blue);
+        assert_eq!(*pressed.face.get_pixel(width / 2, margin_y - 1), blue);
+        assert_eq!(*pressed.face.get_pixel(width / 2, margin_y), red);
+        assert_eq!(*pressed.face.get_pixel(width / 2, height - margin_y), blue);
+    }
+
+    #[test]
+    fn changed_image_file_is_decoded_again() {
+        // Setup
+        let image_dir = std::env::temp_dir().join("streamdeck_controller_changed_file_test");
+        std::fs::create_dir_all(&image_dir).unwrap();
+        let image_path = image_dir.join("image.png");
+        let image_file = image_path.to_str().unwrap().to_string();
+        image::RgbImage::from_pixel(2, 2, image::Rgb([255, 0, 0]))
+            .save(&image_path)
+            .unwrap();
+        let config = background_face_config(config::BackgroundConfig::ColorOrFile(image_file.clone()));
+        let defaults = Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap();
+        let mut cache = FaceCache::new();
+        let first = cache
+            .get_or_create(&StreamDeckType::Orig, &config, &defaults)
+            .unwrap();
+
+        // Act
+        image::RgbImage::from_pixel(3, 3, image::Rgb([0, 0, 255]))
+            .save(&image_path)
+            .unwrap();
+        let second = cache
+            .get_or_create(&StreamDeckType::Orig, &config, &defaults)
+            .unwrap();
+        let mut updated = ButtonFace::clone(&first);
+        updated
+            .update_values(None, Some(image_file), None, None, None, None, None, None, &defaults)
+            .unwrap();
+
+        // Test
+        let (width, height) = StreamDeckType::Orig.button_image_size();
+        let (red, blue) = (image::Rgb([255, 0, 0]), image::Rgb([0, 0, 255]));
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_eq!(*first.face.get_pixel(width / 2, height / 2), red);
+        assert_eq!(*second.face.get_pixel(width / 2, height / 2), blue);
+        assert_eq!(*updated.face.get_pixel(width / 2, height / 2), blue);
+        std::fs::remove_dir_all(&image_dir).unwrap();
+    }
+
+    #[test]
+    fn measured_short_label_keeps_the_base_scale() {
+        // Setup
+        let defaults = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
+
+        // Act
+        let (w, h, scale) = measure_label("A", &defaults, 72, 20.0);
+
+        // Test
+        assert_eq!(scale, 20.0);
+        more_asserts::assert_gt!(w, 0);
+        more_asserts::assert_gt!(h, 0);
+    }
+
+    #[test]
+    fn measured_long_label_is_shrunk_to_fit() {
+        // Setup
+        let defaults = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
+
+        // Act
+        let (w, _, scale) = measure_label("This is a long label", &defaults, 72, 65.0);
+
+        // Test
+        more_asserts::assert_lt!(scale, 65.0);
+        more_asserts::assert_le!(w as f32, 72.0 * 0.9);
+    }
+
+    /// Face with a small white "12" label at the given coordinates on black background
+    fn label_at_face(x: i32, y: i32, anchor: config::LabelAnchorConfig) -> ButtonFace {
+        let mut config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("#000000"),
+        ));
+        config.label = Some(config::LabelConfig::WithColor(LabelConfigWithColor {
+            color: Some(config::ColorConfig::HEXString(String::from("#FFFFFF"))),
+            text: String::from("12"),
+            size: Some(12.0),
+            at: Some(config::LabelAtConfig {
+                x,
+                y,
+                anchor: Some(anchor),
+            }),
+            font: None,
+        }));
+        ButtonFace::from_config(
+            &StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &streamdeck_hid_rs::StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap()
+    }
+
+    /// Smallest and largest x and y of the bright (text) pixels of the image
+    fn text_bounds(image: &image::RgbImage) -> (u32, u32, u32, u32) {
+        let bright: Vec<(u32, u32)> = image
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel[0] > 128)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        assert!(!bright.is_empty());
+        (
+            bright.iter().map(|(x, _)| *x).min().unwrap(),
+            bright.iter().map(|(_, y)| *y).min().unwrap(),
+            bright.iter().map(|(x, _)| *x).max().unwrap(),
+            bright.iter().map(|(_, y)| *y).max().unwrap(),
+        )
+    }
+
+    #[test]
+    fn label_is_drawn_right_of_and_below_top_left_anchor() {
+        // Act
+        let face = label_at_face(4, 6, config::LabelAnchorConfig::TopLeft);
+
+        // Test
+        let (min_x, min_y, max_x, max_y) = text_bounds(&face.face);
+        more_asserts::assert_ge!(min_x, 4);
+        more_asserts::assert_ge!(min_y, 6);
+        more_asserts::assert_lt!(max_x, 36);
+        more_asserts::assert_lt!(max_y, 24);
+    }
+
+    #[test]
+    fn label_is_drawn_left_of_and_above_bottom_right_anchor() {
+        // Act
+        let face = label_at_face(66, 68, config::LabelAnchorConfig::BottomRight);
+
+        // Test
+        let (min_x, min_y, max_x, max_y) = text_bounds(&face.face);
+        more_asserts::assert_gt!(min_x, 36);
+        more_asserts::assert_gt!(min_y, 50);
+        more_asserts::assert_le!(max_x, 66);
+        more_asserts::assert_le!(max_y, 68);
+    }
+
+    #[test]
+    fn labels_are_drawn_with_their_own_fonts() {
+        // Setup
+        let label = |font: Option<&str>| {
+            Some(config::LabelConfig::WithColor(LabelConfigWithColor {
+                color: Some(config::ColorConfig::HEXString(String::from("#FFFFFF"))),
+                text: String::from("ii"),
+                size: None,
+                at: None,
+                font: font.map(String::from),
+            }))
+        };
+        let mut config = background_face_config(config::BackgroundConfig::ColorOrFile(
+            String::from("#000000"),
+        ));
+        config.superlabel = label(Some("./src/state/test_box_font.ttf"));
+        config.sublabel = label(None);
+
+        // Act
+        let face = ButtonFace::from_config(
+            &StreamDeckType::Orig,
+            &config,
+            &Defaults::from_config(&None, &StreamDeckType::Orig).unwrap(),
+        )
+        .unwrap();
+
+        // Test
+        let top = image::imageops::crop_imm(&face.face, 0, 0, 72, 36).to_image();
+        let bottom = image::imageops::crop_imm(&face.face, 0, 36, 72, 36).to_image();
+        let (top_min_x, _, top_max_x, _) = text_bounds(&top);
+        let (bottom_min_x, _, bottom_max_x, _) = text_bounds(&bottom);
+        more_asserts::assert_gt!(top_max_x - top_min_x, 2 * (bottom_max_x - bottom_min_x));
+    }
 }
@@ -9,7 +9,7 @@ use streamdeck_hid_rs::StreamDeckType;
 /// Position on the Streamdeck (for row or col).
 ///
 /// Allowing defining position as a distance from a border (left, right, top bottom).
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum PositionFromBorder {
     FromStart(u8),
     FromEnd(u8),
@@ -35,10 +35,40 @@ impl PositionFromBorder {
             PositionFromBorder::FromStart(index as u8)
         }
     }
+
+    /// The position counted from the start.
+    ///
+    /// # Arguments
+    ///
+    /// length - The number of rows or columns of the device.
+    fn from_start(&self, length: i32) -> i32 {
+        match self {
+            PositionFromBorder::FromStart(position) => *position as i32,
+            PositionFromBorder::FromEnd(position) => length - (*position as i32 + 1),
+        }
+    }
+}
+
+/// Direction in which columns are counted, when converting positions to button indexes.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ColumnOrder {
+    /// Column 0 is the leftmost column
+    LeftToRight,
+    /// Column 0 is the rightmost column (the default)
+    RightToLeft,
+}
+
+impl ColumnOrder {
+    pub fn from_config(config: &Option<config::ColumnOrderConfig>) -> ColumnOrder {
+        match config {
+            Some(config::ColumnOrderConfig::Ltr) => ColumnOrder::LeftToRight,
+            Some(config::ColumnOrderConfig::Rtl) | None => ColumnOrder::RightToLeft,
+        }
+    }
 }
 
 /// Position of a button
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct ButtonPosition {
     pub col: PositionFromBorder,
     pub row: PositionFromBorder,
@@ -50,11 +80,18 @@ impl ButtonPosition {
     /// # Arguments
     ///
     /// config - The config to create the position from.
+    /// device_type - The type of the device, grid labels (like "A1") are validated against.
+    /// column_order - The direction in which columns are counted, grid labels always count
+    ///                columns from the left.
     ///
     /// # Return
     ///
     /// The button position
-    pub fn from_config(config: &config::ButtonPositionConfig) -> Result<ButtonPosition, Error> {
+    pub fn from_config(
+        config: &config::ButtonPositionConfig,
+        device_type: &StreamDeckType,
+        column_order: &ColumnOrder,
+    ) -> Result<ButtonPosition, Error> {
         match config {
             ButtonPositionConfig::ButtonPositionTupleConfig(text) => {
                 let parser = Regex::new(r"^\(\W*(\d+)\W*,\W*(\d+)\W*\)$").unwrap();
@@ -73,26 +110,152 @@ impl ButtonPosition {
                         ),
                     });
                 }
-                Err(ConfigParserError(text.clone()))
+                ButtonPosition::from_grid_label(text, device_type, column_order)
             }
             ButtonPositionConfig::ButtonPositionObjectConfig(object) => Ok(ButtonPosition {
                 col: PositionFromBorder::from_array_index(object.col),
                 row: PositionFromBorder::from_array_index(object.row),
             }),
+            // Only the page knows the position of the other button
+            ButtonPositionConfig::ButtonPositionRelativeConfig(relative) => Err(ConfigParserError(
+                format!("position relative to {} outside of a page", relative.relative_to),
+            )),
+            // A range stands for its first position (for example when other buttons are relative to it)
+            ButtonPositionConfig::ButtonPositionRangeConfig(range) => {
+                Ok(ButtonPosition::range_from_config(range, device_type)?.remove(0))
+            }
+        }
+    }
+
+    /// Create all button positions of a range from the config.
+    ///
+    /// # Arguments
+    ///
+    /// config - The range of rows and columns.
+    /// device_type - The type of the device, the range must be on.
+    ///
+    /// # Return
+    ///
+    /// The positions in the range, row by row. An error if the range can not be parsed,
+    /// is empty or is not on the device.
+    pub fn range_from_config(
+        config: &config::ButtonPositionRange,
+        device_type: &StreamDeckType,
+    ) -> Result<Vec<ButtonPosition>, Error> {
+        let (device_rows, device_cols) = device_type.num_buttons();
+        let (first_row, last_row) = range_bounds(&config.rows, device_rows as i32)?;
+        let (first_col, last_col) = range_bounds(&config.cols, device_cols as i32)?;
+        let mut positions = Vec::new();
+        for row in first_row..=last_row {
+            for col in first_col..=last_col {
+                positions.push(ButtonPosition {
+                    col: PositionFromBorder::FromStart(col as u8),
+                    row: PositionFromBorder::FromStart(row as u8),
+                });
+            }
+        }
+        Ok(positions)
+    }
+
+    /// The position moved by the given number of rows and columns.
+    ///
+    /// # Arguments
+    ///
+    /// drow - Number of rows to move down (negative for up).
+    /// dcol - Number of columns to move (in the direction in which columns are counted).
+    /// device_type - The type of the device, the position must stay on.
+    ///
+    /// # Return
+    ///
+    /// The moved position, or an error if it is outside of the device.
+    pub fn offset(
+        &self,
+        drow: i32,
+        dcol: i32,
+        device_type: &StreamDeckType,
+    ) -> Result<ButtonPosition, Error> {
+        let (device_rows, device_cols) = device_type.num_buttons();
+        let row = self.row.from_start(device_rows as i32) + drow;
+        let col = self.col.from_start(device_cols as i32) + dcol;
+        if row < 0 || row >= device_rows as i32 || col < 0 || col >= device_cols as i32 {
+            return Err(ConfigParserError(format!(
+                "position moved by ({}, {}) is outside of the device",
+                drow, dcol
+            )));
+        }
+        Ok(ButtonPosition {
+            col: PositionFromBorder::FromStart(col as u8),
+            row: PositionFromBorder::FromStart(row as u8),
+        })
+    }
+
+    /// Create a button position from a grid label like "A1".
+    ///
+    /// The letter is the column (starting with "A" for the leftmost column, like in a
+    /// spreadsheet) and the number is the row (starting with 1 for the top row).
+    ///
+    /// # Arguments
+    ///
+    /// text - The grid label.
+    /// device_type - The type of the device, the label must be on.
+    /// column_order - The direction in which columns are counted by the position.
+    ///
+    /// # Return
+    ///
+    /// The button position, or an error if the label can not be parsed or is not on the device.
+    fn from_grid_label(
+        text: &String,
+        device_type: &StreamDeckType,
+        column_order: &ColumnOrder,
+    ) -> Result<ButtonPosition, Error> {
+        let parser = Regex::new(r"^\s*([A-Za-z])(\d+)\s*$").unwrap();
+        let captures = parser
+            .captures(text)
+            .ok_or_else(|| ConfigParserError(text.clone()))?;
+        let col = (captures[1].to_ascii_uppercase().as_bytes()[0] - b'A') as usize;
+        let row = usize::from_str(&captures[2]).map_err(|_| ConfigParserError(text.clone()))?;
+        let (device_rows, device_cols) = device_type.num_buttons();
+        if col >= device_cols as usize || row < 1 || row > device_rows as usize {
+            return Err(ConfigParserError(text.clone()));
         }
+        // In right to left order, the columns from the left are the columns from the end
+        let col = match column_order {
+            ColumnOrder::LeftToRight => PositionFromBorder::FromStart(col as u8),
+            ColumnOrder::RightToLeft => PositionFromBorder::FromEnd(col as u8),
+        };
+        Ok(ButtonPosition {
+            col,
+            row: PositionFromBorder::FromStart((row - 1) as u8),
+        })
     }
 
-    pub fn to_button_index(&self, device_type: &StreamDeckType) -> usize {
+    /// Convert the position to the index of the button on the device.
+    ///
+    /// # Arguments
+    ///
+    /// device_type - The type of the device, the index is for.
+    /// column_order - Whether the columns of the position are counted from the left or the right.
+    ///
+    /// # Return
+    ///
+    /// The button index (clipped to the buttons of the device).
+    pub fn to_button_index(&self, device_type: &StreamDeckType, column_order: &ColumnOrder) -> usize {
         let (device_rows, device_cols) = device_type.num_buttons();
         // Convert to row and col without "FromEnd"
         let row = match self.row {
             PositionFromBorder::FromStart(row) => row as i32,
             PositionFromBorder::FromEnd(neg_row) => device_rows as i32 - (neg_row + 1) as i32,
         };
-        // Invert col, because the buttons are counted from right to left
-        let col = match self.col {
-            PositionFromBorder::FromStart(col) => device_cols as i32 - (col + 1) as i32,
-            PositionFromBorder::FromEnd(neg_col) => neg_col as i32,
+        // Convert col, in right to left order it is inverted
+        let col = match (column_order, &self.col) {
+            (ColumnOrder::LeftToRight, PositionFromBorder::FromStart(col)) => *col as i32,
+            (ColumnOrder::LeftToRight, PositionFromBorder::FromEnd(neg_col)) => {
+                device_cols as i32 - (neg_col + 1) as i32
+            }
+            (ColumnOrder::RightToLeft, PositionFromBorder::FromStart(col)) => {
+                device_cols as i32 - (col + 1) as i32
+            }
+            (ColumnOrder::RightToLeft, PositionFromBorder::FromEnd(neg_col)) => *neg_col as i32,
         };
         // Clip row and col
         let row = std::cmp::min(device_rows as i32 - 1, std::cmp::max(0, row));
@@ -102,6 +265,43 @@ impl ButtonPosition {
     }
 }
 
+/// The first and last index (counted from the start) of a range of rows or columns.
+///
+/// # Arguments
+///
+/// config - The range, negative indexes are counted from the end.
+/// length - The number of rows or columns of the device.
+///
+/// # Return
+///
+/// The inclusive bounds, or an error if the range can not be parsed, is empty or is outside of
+/// the device.
+fn range_bounds(config: &config::PositionRangeConfig, length: i32) -> Result<(i32, i32), Error> {
+    let (first, last, text) = match config {
+        config::PositionRangeConfig::Index(index) => (*index, *index, index.to_string()),
+        config::PositionRangeConfig::Range(text) => {
+            let parser = Regex::new(r"^\s*(-?\d+)\s*\.\.\s*(-?\d+)\s*$").unwrap();
+            let captures = parser
+                .captures(text)
+                .ok_or_else(|| ConfigParserError(text.clone()))?;
+            let first = i32::from_str(&captures[1]).map_err(|_| ConfigParserError(text.clone()))?;
+            let last = i32::from_str(&captures[2]).map_err(|_| ConfigParserError(text.clone()))?;
+            (first, last, text.clone())
+        }
+    };
+    let from_start = |index: i32| {
+        if index < -length || index >= length {
+            return Err(ConfigParserError(text.clone()));
+        }
+        Ok(PositionFromBorder::from_array_index(index).from_start(length))
+    };
+    let (first, last) = (from_start(first)?, from_start(last)?);
+    if first > last {
+        return Err(ConfigParserError(text));
+    }
+    Ok((first, last))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,10 +346,12 @@ mod tests {
                     row: 0,
                     col: -1,
                 }),
+                &device_type,
+                &ColumnOrder::RightToLeft,
             )
             .unwrap();
             // Act
-            let index = position.to_button_index(&device_type);
+            let index = position.to_button_index(&device_type, &ColumnOrder::RightToLeft);
             // Test
             assert_eq!(index, 0);
         }
@@ -160,14 +362,16 @@ mod tests {
         for device_type in StreamDeckType::ALL {
             // Setup
             let position = ButtonPosition::from_config(
-                &&config::ButtonPositionConfig::ButtonPositionObjectConfig(ButtonPositionObject {
+                &config::ButtonPositionConfig::ButtonPositionObjectConfig(ButtonPositionObject {
                     row: -1,
                     col: 0,
                 }),
+                &device_type,
+                &ColumnOrder::RightToLeft,
             )
             .unwrap();
             // Act
-            let index = position.to_button_index(&device_type);
+            let index = position.to_button_index(&device_type, &ColumnOrder::RightToLeft);
             // Test
             assert_eq!(index, device_type.total_num_buttons() - 1);
         }
@@ -178,14 +382,16 @@ mod tests {
         for device_type in StreamDeckType::ALL {
             // Setup
             let position = ButtonPosition::from_config(
-                &&config::ButtonPositionConfig::ButtonPositionObjectConfig(ButtonPositionObject {
+                &config::ButtonPositionConfig::ButtonPositionObjectConfig(ButtonPositionObject {
                     row: 0,
                     col: 0,
                 }),
+                &device_type,
+                &ColumnOrder::RightToLeft,
             )
             .unwrap();
             // Act
-            let index = position.to_button_index(&device_type);
+            let index = position.to_button_index(&device_type, &ColumnOrder::RightToLeft);
             // Test
             assert_eq!(index, device_type.num_buttons().1 as usize - 1);
         }
@@ -196,14 +402,16 @@ mod tests {
         for device_type in StreamDeckType::ALL {
             // Setup
             let position = ButtonPosition::from_config(
-                &&config::ButtonPositionConfig::ButtonPositionObjectConfig(ButtonPositionObject {
+                &config::ButtonPositionConfig::ButtonPositionObjectConfig(ButtonPositionObject {
                     row: -1,
                     col: -1,
                 }),
+                &device_type,
+                &ColumnOrder::RightToLeft,
             )
             .unwrap();
             // Act
-            let index = position.to_button_index(&device_type);
+            let index = position.to_button_index(&device_type, &ColumnOrder::RightToLeft);
             // Test
             assert_eq!(
                 index,
@@ -211,4 +419,147 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn col_zero_is_leftmost_in_left_to_right_order() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let position = ButtonPosition::from_config(
+                &config::ButtonPositionConfig::ButtonPositionObjectConfig(ButtonPositionObject {
+                    row: 0,
+                    col: 0,
+                }),
+                &device_type,
+                &ColumnOrder::RightToLeft,
+            )
+            .unwrap();
+            // Act
+            let index = position.to_button_index(&device_type, &ColumnOrder::LeftToRight);
+            // Test
+            assert_eq!(index, 0);
+        }
+    }
+
+    #[test]
+    fn col_zero_is_rightmost_in_right_to_left_order() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let position = ButtonPosition::from_config(
+                &config::ButtonPositionConfig::ButtonPositionObjectConfig(ButtonPositionObject {
+                    row: 0,
+                    col: 0,
+                }),
+                &device_type,
+                &ColumnOrder::RightToLeft,
+            )
+            .unwrap();
+            // Act
+            let index = position.to_button_index(&device_type, &ColumnOrder::RightToLeft);
+            // Test
+            assert_eq!(index, device_type.num_buttons().1 as usize - 1);
+        }
+    }
+
+    #[test]
+    fn grid_label_a1_is_top_left() {
+        for column_order in [ColumnOrder::LeftToRight, ColumnOrder::RightToLeft] {
+            // Setup
+            let config =
+                config::ButtonPositionConfig::ButtonPositionTupleConfig(String::from("A1"));
+            // Act
+            let position =
+                ButtonPosition::from_config(&config, &StreamDeckType::Orig, &column_order)
+                    .unwrap();
+            // Test
+            assert_eq!(position.to_button_index(&StreamDeckType::Orig, &column_order), 0);
+        }
+    }
+
+    #[test]
+    fn grid_label_columns_are_counted_from_the_left_in_right_to_left_order() {
+        // Setup
+        let config = config::ButtonPositionConfig::ButtonPositionTupleConfig(String::from("B1"));
+        // Act
+        let position =
+            ButtonPosition::from_config(&config, &StreamDeckType::Orig, &ColumnOrder::RightToLeft)
+                .unwrap();
+        // Test
+        assert_eq!(position.to_button_index(&StreamDeckType::Orig, &ColumnOrder::RightToLeft), 1);
+    }
+
+    #[test]
+    fn grid_label_c3_is_third_col_and_row() {
+        for column_order in [ColumnOrder::LeftToRight, ColumnOrder::RightToLeft] {
+            // Setup
+            let config =
+                config::ButtonPositionConfig::ButtonPositionTupleConfig(String::from("c3"));
+            // Act
+            let position =
+                ButtonPosition::from_config(&config, &StreamDeckType::Orig, &column_order)
+                    .unwrap();
+            // Test
+            assert_eq!(position.to_button_index(&StreamDeckType::Orig, &column_order), 12);
+        }
+    }
+
+    #[test]
+    fn grid_label_out_of_range_is_rejected() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let config =
+                config::ButtonPositionConfig::ButtonPositionTupleConfig(String::from("Z9"));
+            // Act
+            let result =
+                ButtonPosition::from_config(&config, &device_type, &ColumnOrder::RightToLeft);
+            // Test
+            assert!(matches!(result, Err(ConfigParserError(text)) if text == "Z9"));
+        }
+    }
+
+    #[test]
+    fn grid_label_row_zero_is_rejected() {
+        // Setup
+        let config = config::ButtonPositionConfig::ButtonPositionTupleConfig(String::from("A0"));
+        // Act
+        let result =
+            ButtonPosition::from_config(&config, &StreamDeckType::Orig, &ColumnOrder::RightToLeft);
+        // Test
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn offset_from_end_position() {
+        // Setup
+        let position = ButtonPosition {
+            col: PositionFromBorder::FromEnd(0),
+            row: PositionFromBorder::FromStart(0),
+        };
+
+        // Act
+        let moved = position.offset(1, -1, &StreamDeckType::Orig).unwrap();
+
+        // Test
+        assert_eq!(
+            moved,
+            ButtonPosition {
+                col: PositionFromBorder::FromStart(3),
+                row: PositionFromBorder::FromStart(1),
+            }
+        );
+    }
+
+    #[test]
+    fn offset_outside_of_the_device_is_rejected() {
+        // Setup
+        let position = ButtonPosition {
+            col: PositionFromBorder::FromStart(0),
+            row: PositionFromBorder::FromStart(0),
+        };
+
+        // Act
+        let result = position.offset(-1, 0, &StreamDeckType::Orig);
+
+        // Test
+        assert!(matches!(result, Err(ConfigParserError(_))));
+    }
 }
@@ -1,21 +1,60 @@
+use super::button_face::{PressScale, TextColor};
+use super::button_position::ColumnOrder;
 use super::error::Error;
+use super::fonts::Fonts;
 use crate::config;
+use streamdeck_hid_rs::StreamDeckType;
+
+/// Height (in pixels) of the buttons of the original Stream Deck, font sizes are given for this height.
+const REFERENCE_BUTTON_HEIGHT: f32 = 72.0;
 
 /// Defaults, that fill missing values
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Defaults {
     pub background_color: image::Rgba<u8>,
-    pub label_color: image::Rgba<u8>,
-    pub superlabel_color: image::Rgba<u8>,
-    pub sublabel_color: image::Rgba<u8>,
+    pub label_color: TextColor,
+    pub superlabel_color: TextColor,
+    pub sublabel_color: TextColor,
+    pub column_order: ColumnOrder,
+    pub min_font_scale: f32,
+    pub max_font_scale: f32,
+    /// Factor for all configured font sizes, so texts take the same part of the buttons on all devices
+    pub font_scale: f32,
+    /// Fonts for drawing labels, the bundled font and the fallback fonts
+    pub fonts: Fonts,
+    /// Faces are drawn at this multiple of the button size and downsampled (1 is off)
+    pub supersample: u32,
+    /// Shrinking of the up face while buttons without down face are pressed (None is off)
+    pub press_scale: Option<PressScale>,
 }
 
 impl Defaults {
-    pub fn from_config(config: &Option<config::DefaultsConfig>) -> Result<Defaults, Error> {
+    /// Create the defaults from the config.
+    ///
+    /// # Arguments
+    ///
+    /// config - The defaults section of the config.
+    /// device_type - The type of the device, the font scale is derived from the size of its buttons.
+    ///
+    /// # Return
+    ///
+    /// The defaults, or an error if a value of the config is invalid.
+    pub fn from_config(
+        config: &Option<config::DefaultsConfig>,
+        device_type: &StreamDeckType,
+    ) -> Result<Defaults, Error> {
         let mut background_color = image::Rgba([0, 0, 0, 255]);
-        let mut label_color = image::Rgba([255, 255, 255, 255]);
-        let mut superlabel_color = image::Rgba([255, 255, 0, 255]);
-        let mut sublabel_color = image::Rgba([0, 255, 255, 255]);
+        let mut label_color = TextColor::Fixed(image::Rgba([255, 255, 255, 255]));
+        let mut superlabel_color = TextColor::Fixed(image::Rgba([255, 255, 0, 255]));
+        let mut sublabel_color = TextColor::Fixed(image::Rgba([0, 255, 255, 255]));
+        let mut column_order = ColumnOrder::RightToLeft;
+        let mut min_font_scale = 0.0;
+        let mut max_font_scale = f32::INFINITY;
+        let mut fallback_fonts = Vec::new();
+        let mut emoji_font = None;
+        let mut supersample = 1;
+        let mut press_scale = None;
+        let mut font_scale = device_font_scale(device_type);
 
         if let Some(config) = config {
             background_color = match &config.background_color {
@@ -24,16 +63,54 @@ impl Defaults {
             };
             label_color = match &config.label_color {
                 None => label_color,
-                Some(c) => c.to_image_rgba_color().map_err(Error::ConfigError)?,
+                Some(c) => TextColor::from_config(c)?,
             };
             superlabel_color = match &config.superlabel_color {
                 None => superlabel_color,
-                Some(c) => c.to_image_rgba_color().map_err(Error::ConfigError)?,
+                Some(c) => TextColor::from_config(c)?,
             };
             sublabel_color = match &config.sublabel_color {
                 None => sublabel_color,
-                Some(c) => c.to_image_rgba_color().map_err(Error::ConfigError)?,
+                Some(c) => TextColor::from_config(c)?,
             };
+            column_order = ColumnOrder::from_config(&config.column_order);
+            min_font_scale = config.min_font_scale.unwrap_or(min_font_scale);
+            max_font_scale = config.max_font_scale.unwrap_or(max_font_scale);
+            fallback_fonts = config.fallback_fonts.clone().unwrap_or(fallback_fonts);
+            emoji_font = config.emoji_font.clone();
+            supersample = config.supersample.unwrap_or(supersample);
+            press_scale = config.press_scale;
+            font_scale = config.font_scale.unwrap_or(font_scale);
+        }
+
+        if !(min_font_scale >= 0.0 && min_font_scale <= max_font_scale) {
+            return Err(Error::ConfigParserError(format!(
+                "invalid font scales, min_font_scale ({}) must be positive and at most max_font_scale ({})",
+                min_font_scale, max_font_scale
+            )));
+        }
+
+        if !(1..=4).contains(&supersample) {
+            return Err(Error::ConfigParserError(format!(
+                "invalid supersample {}, must be 1, 2, 3 or 4",
+                supersample
+            )));
+        }
+
+        if !(font_scale > 0.0) {
+            return Err(Error::ConfigParserError(format!(
+                "invalid font_scale {}, must be greater than 0",
+                font_scale
+            )));
+        }
+
+        if let Some(scale) = press_scale {
+            if !(scale > 0.0 && scale <= 1.0) {
+                return Err(Error::ConfigParserError(format!(
+                    "invalid press_scale {}, must be greater than 0 and at most 1",
+                    scale
+                )));
+            }
         }
 
         Ok(Defaults {
@@ -41,10 +118,27 @@ impl Defaults {
             superlabel_color,
             sublabel_color,
             label_color,
+            column_order,
+            min_font_scale,
+            max_font_scale,
+            font_scale,
+            fonts: Fonts::new(&fallback_fonts, emoji_font.as_deref())?,
+            supersample,
+            press_scale: press_scale.map(|scale| PressScale {
+                scale,
+                background: background_color,
+            }),
         })
     }
 }
 
+/// The font scale of a device, its button height relative to the buttons of the original Stream Deck.
+///
+/// All device types have displays, so the derived scale passes the check of `font_scale`.
+fn device_font_scale(device_type: &StreamDeckType) -> f32 {
+    device_type.button_image_size().1 as f32 / REFERENCE_BUTTON_HEIGHT
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -57,17 +151,34 @@ mod tests {
             label_color: None,
             superlabel_color: None,
             sublabel_color: None,
+            column_order: None,
+            min_font_scale: None,
+            max_font_scale: None,
+            fallback_fonts: None,
+            supersample: None,
+            press_scale: None,
+            emoji_font: None,
+            font_scale: None,
         });
 
         // Act
-        let defaults = Defaults::from_config(&config).unwrap();
-        let defaults_from_none = Defaults::from_config(&None).unwrap();
+        let defaults = Defaults::from_config(&config, &StreamDeckType::Orig).unwrap();
+        let defaults_from_none = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
 
         // Test
         assert_eq!(defaults.background_color, image::Rgba([0, 0, 0, 255]));
-        assert_eq!(defaults.label_color, image::Rgba([255, 255, 255, 255]));
-        assert_eq!(defaults.superlabel_color, image::Rgba([255, 255, 0, 255]));
-        assert_eq!(defaults.sublabel_color, image::Rgba([0, 255, 255, 255]));
+        assert_eq!(
+            defaults.label_color,
+            TextColor::Fixed(image::Rgba([255, 255, 255, 255]))
+        );
+        assert_eq!(
+            defaults.superlabel_color,
+            TextColor::Fixed(image::Rgba([255, 255, 0, 255]))
+        );
+        assert_eq!(
+            defaults.sublabel_color,
+            TextColor::Fixed(image::Rgba([0, 255, 255, 255]))
+        );
 
         assert_eq!(
             defaults_from_none.background_color,
@@ -75,15 +186,164 @@ mod tests {
         );
         assert_eq!(
             defaults_from_none.label_color,
-            image::Rgba([255, 255, 255, 255])
+            TextColor::Fixed(image::Rgba([255, 255, 255, 255]))
         );
         assert_eq!(
             defaults_from_none.superlabel_color,
-            image::Rgba([255, 255, 0, 255])
+            TextColor::Fixed(image::Rgba([255, 255, 0, 255]))
         );
         assert_eq!(
             defaults_from_none.sublabel_color,
-            image::Rgba([0, 255, 255, 255])
+            TextColor::Fixed(image::Rgba([0, 255, 255, 255]))
         );
+        assert_eq!(defaults.column_order, ColumnOrder::RightToLeft);
+        assert_eq!(defaults_from_none.column_order, ColumnOrder::RightToLeft);
+    }
+
+    #[test]
+    fn auto_label_color() {
+        // Setup
+        let config = Some(config::DefaultsConfig {
+            background_color: None,
+            label_color: Some(config::ColorConfig::HEXString(String::from("auto"))),
+            superlabel_color: None,
+            sublabel_color: None,
+            column_order: None,
+            min_font_scale: None,
+            max_font_scale: None,
+            fallback_fonts: None,
+            supersample: None,
+            press_scale: None,
+            emoji_font: None,
+            font_scale: None,
+        });
+
+        // Act
+        let defaults = Defaults::from_config(&config, &StreamDeckType::Orig).unwrap();
+
+        // Test
+        assert_eq!(defaults.label_color, TextColor::Auto);
+    }
+
+    #[test]
+    fn min_font_scale_above_max_font_scale_is_an_error() {
+        // Setup
+        let config = Some(config::DefaultsConfig {
+            background_color: None,
+            label_color: None,
+            superlabel_color: None,
+            sublabel_color: None,
+            column_order: None,
+            min_font_scale: Some(20.0),
+            max_font_scale: Some(10.0),
+            fallback_fonts: None,
+            supersample: None,
+            press_scale: None,
+            emoji_font: None,
+            font_scale: None,
+        });
+
+        // Act
+        let result = Defaults::from_config(&config, &StreamDeckType::Orig);
+
+        // Test
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn supersample_above_four_is_an_error() {
+        // Setup
+        let config = Some(config::DefaultsConfig {
+            background_color: None,
+            label_color: None,
+            superlabel_color: None,
+            sublabel_color: None,
+            column_order: None,
+            min_font_scale: None,
+            max_font_scale: None,
+            fallback_fonts: None,
+            supersample: Some(5),
+            press_scale: None,
+            emoji_font: None,
+            font_scale: None,
+        });
+
+        // Act
+        let result = Defaults::from_config(&config, &StreamDeckType::Orig);
+
+        // Test
+        assert!(matches!(result, Err(Error::ConfigParserError(_))));
+    }
+
+    #[test]
+    fn press_scale_above_one_is_an_error() {
+        // Setup
+        let config = Some(config::DefaultsConfig {
+            background_color: None,
+            label_color: None,
+            superlabel_color: None,
+            sublabel_color: None,
+            column_order: None,
+            min_font_scale: None,
+            max_font_scale: None,
+            fallback_fonts: None,
+            supersample: None,
+            press_scale: Some(1.5),
+            emoji_font: None,
+            font_scale: None,
+        });
+
+        // Act
+        let result = Defaults::from_config(&config, &StreamDeckType::Orig);
+
+        // Test
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn font_scale_is_derived_from_the_button_size() {
+        // Act
+        let orig = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
+        let xl = Defaults::from_config(&None, &StreamDeckType::Xl).unwrap();
+
+        // Test
+        assert_eq!(orig.font_scale, 1.0);
+        assert_eq!(xl.font_scale, 96.0 / 72.0);
+        assert!(xl.font_scale > orig.font_scale);
+    }
+
+    #[test]
+    fn derived_font_scale_is_valid_for_all_device_types() {
+        for device_type in StreamDeckType::ALL {
+            // Act
+            let defaults = Defaults::from_config(&None, &device_type).unwrap();
+
+            // Test
+            assert!(defaults.font_scale > 0.0);
+        }
+    }
+
+    #[test]
+    fn configured_font_scale_replaces_the_derived_one() {
+        // Setup
+        let config: config::DefaultsConfig = serde_yaml::from_str("font_scale: 1.5").unwrap();
+
+        // Act
+        let orig = Defaults::from_config(&Some(config), &StreamDeckType::Orig).unwrap();
+
+        // Test
+        assert_eq!(orig.font_scale, 1.5);
+    }
+
+    #[test]
+    fn fails_with_zero_font_scale() {
+        // Setup
+        let config: config::DefaultsConfig = serde_yaml::from_str("font_scale: 0").unwrap();
+
+        // Act
+        let result = Defaults::from_config(&Some(config), &StreamDeckType::Xl);
+
+        // Test
+        assert!(matches!(result, Err(Error::ConfigParserError(_))));
     }
 }
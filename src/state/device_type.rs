@@ -0,0 +1,294 @@
+use super::error::Error;
+use crate::config;
+use streamdeck_hid_rs::StreamDeckType;
+
+/// Names of the device types, as they are used in the config.
+const DEVICE_TYPE_NAMES: [(&str, StreamDeckType); 5] = [
+    ("orig", StreamDeckType::Orig),
+    ("orig_v2", StreamDeckType::OrigV2),
+    ("mini", StreamDeckType::Mini),
+    ("xl", StreamDeckType::Xl),
+    ("mk2", StreamDeckType::Mk2),
+];
+
+/// Get the device type from its name in the config.
+///
+/// Instead of a name, the layout of the buttons can be given as "<rows>x<columns>" (for example "4x8"),
+/// the device type is then guessed with [device_type_for_layout].
+///
+/// # Arguments
+///
+/// name - The name of the device type (for example "orig", "xl" or "mini") or the layout of its buttons.
+///
+/// # Return
+///
+/// The device type, or an error if the name is unknown or no device type has the layout.
+pub fn device_type_from_name(name: &str) -> Result<StreamDeckType, Error> {
+    let by_name = DEVICE_TYPE_NAMES
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, device_type)| device_type.clone());
+    let by_layout = || {
+        let (rows, cols) = name.to_ascii_lowercase().split_once('x').and_then(|(rows, cols)| {
+            Some((rows.trim().parse::<usize>().ok()?, cols.trim().parse::<usize>().ok()?))
+        })?;
+        device_type_for_layout(rows, cols)
+    };
+    by_name
+        .or_else(by_layout)
+        .ok_or_else(|| Error::UnknownDeviceType(name.to_string()))
+}
+
+/// Guess the device type from the layout of its buttons.
+///
+/// Several device types have the same layout (like "orig", "orig_v2" and "mk2"), the first one
+/// in the order of [DEVICE_TYPE_NAMES] is returned for them. Their faces are drawn the same way.
+///
+/// # Arguments
+///
+/// rows - The number of rows of buttons.
+/// cols - The number of columns of buttons.
+///
+/// # Return
+///
+/// The device type, or None if no device type has the layout.
+pub fn device_type_for_layout(rows: usize, cols: usize) -> Option<StreamDeckType> {
+    DEVICE_TYPE_NAMES
+        .iter()
+        .map(|(_, device_type)| device_type)
+        .find(|device_type| {
+            let (device_rows, device_cols) = device_type.num_buttons();
+            device_rows as usize == rows && device_cols as usize == cols
+        })
+        .cloned()
+}
+
+/// Get the name of a device type, as it is used in the config.
+pub fn device_type_name(device_type: &StreamDeckType) -> &'static str {
+    DEVICE_TYPE_NAMES
+        .iter()
+        .find(|(_, t)| t == device_type)
+        .map(|(name, _)| *name)
+        .expect("every device type is in DEVICE_TYPE_NAMES")
+}
+
+/// Whether the buttons of a device type have displays.
+///
+/// Pedal-type devices have keys but no screens (their button image size is zero),
+/// faces can not be rendered on them.
+pub fn has_display(device_type: &StreamDeckType) -> bool {
+    let (width, height) = device_type.button_image_size();
+    width > 0 && height > 0
+}
+
+/// Number of touch points of a device type, next to its buttons.
+///
+/// None of the device types of streamdeck-hid-rs has touch points, the Stream Deck Neo
+/// (with two touch points) is not known to it yet.
+pub fn num_touch_points(device_type: &StreamDeckType) -> usize {
+    match device_type {
+        StreamDeckType::Orig
+        | StreamDeckType::OrigV2
+        | StreamDeckType::Mini
+        | StreamDeckType::Xl
+        | StreamDeckType::Mk2 => 0,
+    }
+}
+
+/// Check that the connected device has all touch points given in the config.
+///
+/// # Arguments
+///
+/// configured - The touch points from the config, if any.
+/// connected - The type of the connected device.
+///
+/// # Return
+///
+/// () if the device has at least as many touch points as configured, Error otherwise
+/// (the handlers of the missing touch points can never be executed).
+pub fn check_touch_points(
+    configured: &Option<Vec<config::TouchPointConfig>>,
+    connected: &StreamDeckType,
+) -> Result<(), Error> {
+    let configured = configured.as_ref().map_or(0, |configured| configured.len());
+    let available = num_touch_points(connected);
+    if configured > available {
+        return Err(Error::ConfigParserError(format!(
+            "{} touch points configured, but the {} device has {}",
+            configured,
+            device_type_name(connected),
+            available
+        )));
+    }
+    Ok(())
+}
+
+/// Check that the connected device is of the type given in the config.
+///
+/// # Arguments
+///
+/// configured - The name of the device type from the config, if any.
+/// connected - The type of the connected device.
+///
+/// # Return
+///
+/// () if no type is configured or the types are the same (for a configured layout, if the
+/// layouts are the same), Error if the types differ or the configured type is unknown.
+pub fn check_device_type(
+    configured: &Option<String>,
+    connected: &StreamDeckType,
+) -> Result<(), Error> {
+    let (configured, is_layout) = match configured {
+        None => return Ok(()),
+        Some(configured) => (
+            device_type_from_name(configured)?,
+            !DEVICE_TYPE_NAMES.iter().any(|(n, _)| n.eq_ignore_ascii_case(configured)),
+        ),
+    };
+    let matches = if is_layout {
+        configured.num_buttons() == connected.num_buttons()
+    } else {
+        configured == *connected
+    };
+    if !matches {
+        return Err(Error::DeviceTypeMismatch(
+            device_type_name(&configured).to_string(),
+            device_type_name(connected).to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_map_to_device_types() {
+        assert!(matches!(
+            device_type_from_name("orig"),
+            Ok(StreamDeckType::Orig)
+        ));
+        assert!(matches!(
+            device_type_from_name("orig_v2"),
+            Ok(StreamDeckType::OrigV2)
+        ));
+        assert!(matches!(
+            device_type_from_name("mini"),
+            Ok(StreamDeckType::Mini)
+        ));
+        assert!(matches!(device_type_from_name("xl"), Ok(StreamDeckType::Xl)));
+        assert!(matches!(device_type_from_name("mk2"), Ok(StreamDeckType::Mk2)));
+    }
+
+    #[test]
+    fn names_are_case_insensitive() {
+        assert!(matches!(device_type_from_name("XL"), Ok(StreamDeckType::Xl)));
+    }
+
+    #[test]
+    fn unknown_name_is_an_error() {
+        assert!(matches!(
+            device_type_from_name("plus"),
+            Err(Error::UnknownDeviceType(name)) if name == "plus"
+        ));
+    }
+
+    #[test]
+    fn every_device_type_has_a_name() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let name = device_type_name(&device_type);
+
+            // Act
+            let result = device_type_from_name(name).unwrap();
+
+            // Test
+            assert_eq!(device_type_name(&result), name);
+        }
+    }
+
+    #[test]
+    fn devices_with_screens_have_displays() {
+        for device_type in StreamDeckType::ALL {
+            assert!(has_display(&device_type));
+        }
+    }
+
+    #[test]
+    fn touch_points_on_devices_without_them_are_detected() {
+        // Setup
+        let configured = Some(serde_yaml::from_str("[{}]").unwrap());
+
+        // Act
+        let result = check_touch_points(&configured, &StreamDeckType::Xl);
+
+        // Test
+        assert!(matches!(result, Err(Error::ConfigParserError(message)) if message.contains("xl")));
+        assert!(check_touch_points(&None, &StreamDeckType::Xl).is_ok());
+    }
+
+    #[test]
+    fn mismatched_device_types_are_detected() {
+        // Setup
+        let configured = Some(String::from("xl"));
+
+        // Act
+        let result = check_device_type(&configured, &StreamDeckType::Mini);
+
+        // Test
+        assert!(matches!(
+            result,
+            Err(Error::DeviceTypeMismatch(configured, connected))
+                if configured == "xl" && connected == "mini"
+        ));
+    }
+
+    #[test]
+    fn same_device_type_is_accepted() {
+        assert!(check_device_type(&Some(String::from("XL")), &StreamDeckType::Xl).is_ok());
+    }
+
+    #[test]
+    fn missing_device_type_is_accepted() {
+        assert!(check_device_type(&None, &StreamDeckType::Orig).is_ok());
+    }
+
+    #[test]
+    fn layouts_map_to_device_types() {
+        assert!(matches!(device_type_for_layout(3, 5), Some(StreamDeckType::Orig)));
+        assert!(matches!(device_type_for_layout(2, 3), Some(StreamDeckType::Mini)));
+        assert!(matches!(device_type_for_layout(4, 8), Some(StreamDeckType::Xl)));
+        assert!(device_type_for_layout(4, 4).is_none());
+    }
+
+    #[test]
+    fn every_device_type_is_found_by_its_layout() {
+        for device_type in StreamDeckType::ALL {
+            // Setup
+            let (rows, cols) = device_type.num_buttons();
+
+            // Act
+            let result = device_type_for_layout(rows as usize, cols as usize).unwrap();
+
+            // Test
+            assert_eq!(result.num_buttons(), device_type.num_buttons());
+        }
+    }
+
+    #[test]
+    fn layout_can_be_given_instead_of_the_name() {
+        assert!(matches!(device_type_from_name("4x8"), Ok(StreamDeckType::Xl)));
+        assert!(matches!(device_type_from_name("2X3"), Ok(StreamDeckType::Mini)));
+        assert!(matches!(
+            device_type_from_name("4x4"),
+            Err(Error::UnknownDeviceType(name)) if name == "4x4"
+        ));
+    }
+
+    #[test]
+    fn configured_layout_accepts_all_device_types_with_it() {
+        assert!(check_device_type(&Some(String::from("3x5")), &StreamDeckType::Mk2).is_ok());
+        assert!(check_device_type(&Some(String::from("3x5")), &StreamDeckType::Xl).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use image::ImageError;
+use std::fmt;
 use std::io;
 
 /// Possible errors that can occur during
@@ -8,11 +9,222 @@ use std::io;
 pub enum Error {
     ImageOpeningError(io::Error),
     ImageEncodingError(ImageError),
+    /// Downloading an image from an URL failed
+    ImageDownloadError(String),
     ConfigError(crate::config::Error),
     ConfigParserError(String),
     PageNotFound(String),
+    /// There is no profile with this name
+    ProfileNotFound(String),
+    /// Pages referenced by the config (for example as default pages), that do not exist
+    PagesNotFound(Vec<String>),
     ButtonNotFound(String),
     LoadScriptFailed(std::io::Error),
     DuplicateNamedButton(String),
-    RegexError(regex::Error),
+    /// A regex of a field (first) of the config with its pattern (second) is invalid
+    RegexError(String, String, regex::Error),
+    InvalidOpacity(f32),
+    InvalidSpriteIndex(u32),
+    UnknownDeviceType(String),
+    /// The configured device type (first) differs from the connected one (second)
+    DeviceTypeMismatch(String, String),
+    /// The size (width, height) of an image does not match the size of the buttons
+    InvalidImageSize(u32, u32),
+    /// The length of an image buffer does not match its size
+    InvalidImageBufferLength(usize),
+    /// A font file could not be loaded
+    FontLoadingError(String),
+    /// The named button (first) is not part of the button group (second)
+    ButtonNotInGroup(String, String),
+    /// There is no button with this index on the device
+    InvalidButtonIndex(usize),
+    /// Positions relative to the named button depend on each other in a cycle
+    CyclicRelativePosition(String),
+    /// Writing an image to the file (first) failed
+    ImageSavingError(String, ImageError),
+    /// The named button has no cycle faces
+    NoCycleFaces(String),
+    /// The shebang line of a script selects an unknown language
+    UnknownScriptLanguage(String),
+    /// The name of a named button starts with a prefix, that is reserved for internal buttons
+    ReservedButtonName(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ImageOpeningError(e) => write!(f, "unable to open image: {}", e),
+            Error::ImageEncodingError(e) => write!(f, "unable to decode image: {}", e),
+            Error::ImageDownloadError(message) => write!(f, "unable to download image: {}", message),
+            Error::ConfigError(e) => write!(f, "invalid config: {}", e),
+            Error::ConfigParserError(text) => write!(f, "unable to parse config value: {}", text),
+            Error::PageNotFound(name) => write!(f, "page not found: {}", name),
+            Error::ProfileNotFound(name) => write!(f, "profile not found: {}", name),
+            Error::PagesNotFound(names) => write!(f, "pages not found: {}", names.join(", ")),
+            Error::ButtonNotFound(name) => write!(f, "button not found: {}", name),
+            Error::LoadScriptFailed(e) => write!(f, "unable to load script: {}", e),
+            Error::DuplicateNamedButton(name) => {
+                write!(f, "named button is defined more than once: {}", name)
+            }
+            Error::RegexError(field, pattern, e) => write!(
+                f,
+                "invalid regular expression for {} '{}': {}",
+                field, pattern, e
+            ),
+            Error::InvalidOpacity(opacity) => {
+                write!(f, "opacity {} is not between 0.0 and 1.0", opacity)
+            }
+            Error::InvalidSpriteIndex(index) => {
+                write!(f, "sprite index {} is outside of the sprite sheet", index)
+            }
+            Error::UnknownDeviceType(name) => write!(f, "unknown device type: {}", name),
+            Error::DeviceTypeMismatch(configured, connected) => write!(
+                f,
+                "configured device type {} differs from the connected device type {}",
+                configured, connected
+            ),
+            Error::InvalidImageSize(width, height) => write!(
+                f,
+                "image size {}x{} does not match the button image size",
+                width, height
+            ),
+            Error::InvalidImageBufferLength(length) => write!(
+                f,
+                "image buffer length {} does not match width * height * 3",
+                length
+            ),
+            Error::FontLoadingError(message) => write!(f, "unable to load font: {}", message),
+            Error::ButtonNotInGroup(button, group) => {
+                write!(f, "button {} is not in group {}", button, group)
+            }
+            Error::InvalidButtonIndex(index) => {
+                write!(f, "there is no button with index {} on the device", index)
+            }
+            Error::CyclicRelativePosition(name) => {
+                write!(f, "position relative to {} refers to itself", name)
+            }
+            Error::ImageSavingError(path, e) => write!(f, "unable to save image {}: {}", path, e),
+            Error::NoCycleFaces(name) => write!(f, "button {} has no cycle_faces", name),
+            Error::UnknownScriptLanguage(shebang) => {
+                write!(f, "unknown script language in shebang: {}", shebang)
+            }
+            Error::ReservedButtonName(name) => {
+                write!(f, "named button {} starts with a reserved prefix", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::ImageOpeningError(e) => Some(e),
+            Error::ImageEncodingError(e) => Some(e),
+            Error::ConfigError(e) => Some(e),
+            Error::LoadScriptFailed(e) => Some(e),
+            Error::RegexError(_, _, e) => Some(e),
+            Error::ImageSavingError(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    /// An io error with the given message
+    fn io_error(message: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::Other, message)
+    }
+
+    #[test]
+    fn messages_contain_their_payload() {
+        // Setup
+        let errors = vec![
+            (Error::ImageOpeningError(io_error("payload")), "payload"),
+            (
+                Error::ImageEncodingError(ImageError::IoError(io_error("payload"))),
+                "payload",
+            ),
+            (
+                Error::ConfigError(crate::config::Error::InvalidColorHexString(String::from(
+                    "#XYZ",
+                ))),
+                "#XYZ",
+            ),
+            (Error::ImageDownloadError(String::from("http://host/a.png")), "http://host/a.png"),
+            (Error::ConfigParserError(String::from("(1, x)")), "(1, x)"),
+            (Error::PageNotFound(String::from("page1")), "page1"),
+            (Error::ProfileNotFound(String::from("work")), "work"),
+            (
+                Error::PagesNotFound(vec![String::from("page1"), String::from("page2")]),
+                "page1, page2",
+            ),
+            (Error::ButtonNotFound(String::from("button1")), "button1"),
+            (Error::LoadScriptFailed(io_error("payload")), "payload"),
+            (Error::DuplicateNamedButton(String::from("button1")), "button1"),
+            (
+                Error::RegexError(
+                    String::from("title"),
+                    String::from("(abc"),
+                    regex::Regex::new("(abc").unwrap_err(),
+                ),
+                "'(abc'",
+            ),
+            (Error::InvalidOpacity(1.5), "1.5"),
+            (Error::InvalidSpriteIndex(8), "8"),
+            (Error::UnknownDeviceType(String::from("plus")), "plus"),
+            (
+                Error::DeviceTypeMismatch(String::from("xl"), String::from("mini")),
+                "mini",
+            ),
+            (Error::InvalidImageSize(10, 20), "10x20"),
+            (Error::InvalidImageBufferLength(17), "17"),
+            (Error::FontLoadingError(String::from("font.ttf")), "font.ttf"),
+            (
+                Error::ButtonNotInGroup(String::from("button1"), String::from("group1")),
+                "group1",
+            ),
+            (Error::InvalidButtonIndex(42), "42"),
+            (Error::CyclicRelativePosition(String::from("button1")), "button1"),
+            (
+                Error::ImageSavingError(
+                    String::from("faces/button1.png"),
+                    ImageError::IoError(io_error("payload")),
+                ),
+                "faces/button1.png",
+            ),
+            (Error::NoCycleFaces(String::from("button1")), "button1"),
+            (Error::UnknownScriptLanguage(String::from("#!ruby")), "#!ruby"),
+            (Error::ReservedButtonName(String::from("toast:0")), "toast:0"),
+        ];
+
+        for (error, payload) in errors {
+            // Act
+            let message = error.to_string();
+
+            // Test
+            assert!(!message.is_empty());
+            assert!(message.contains(payload), "{} in {}", payload, message);
+        }
+    }
+
+    #[test]
+    fn wrapped_errors_are_the_source() {
+        // Setup
+        let error = Error::LoadScriptFailed(io_error("payload"));
+
+        // Act
+        let source = error.source();
+
+        // Test
+        assert_eq!(source.unwrap().to_string(), "payload");
+    }
+
+    #[test]
+    fn errors_without_wrapped_error_have_no_source() {
+        assert!(Error::PageNotFound(String::from("page1")).source().is_none());
+    }
 }
@@ -1,25 +1,217 @@
 use super::error::Error;
 use crate::config;
 use crate::config::EventHandlerConfig;
+use std::fmt;
 use std::fs;
+use std::path::PathBuf;
+
+/// Where the script of an event handler comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandlerSource {
+    /// Given as `code` in the config
+    Inline,
+    /// Loaded from the `file` with this path
+    File(PathBuf),
+}
+
+impl fmt::Display for HandlerSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandlerSource::Inline => write!(f, "inline"),
+            HandlerSource::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Language of the script of an event handler
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScriptLanguage {
+    Python,
+    Lua,
+}
+
+impl fmt::Display for ScriptLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptLanguage::Python => write!(f, "python"),
+            ScriptLanguage::Lua => write!(f, "lua"),
+        }
+    }
+}
+
+impl ScriptLanguage {
+    /// Detect the language of a script from its shebang line (like `#!python` or `#!/usr/bin/env lua`).
+    ///
+    /// # Return
+    ///
+    /// The language, python if the script has no shebang line, or an error if the shebang selects
+    /// an unknown language.
+    pub fn detect(script: &str) -> Result<ScriptLanguage, Error> {
+        let shebang = match script.lines().next() {
+            Some(line) if line.starts_with("#!") => line.trim(),
+            _ => return Ok(ScriptLanguage::Python),
+        };
+        // The interpreter is the last word, maybe with a path and a version (like python3)
+        let interpreter = shebang[2..]
+            .split_whitespace()
+            .last()
+            .and_then(|word| word.rsplit('/').next())
+            .unwrap_or_default();
+        if interpreter.starts_with("python") {
+            Ok(ScriptLanguage::Python)
+        } else if interpreter.starts_with("lua") {
+            Ok(ScriptLanguage::Lua)
+        } else {
+            Err(Error::UnknownScriptLanguage(shebang.to_string()))
+        }
+    }
+}
 
 /// Event handler, that are executed when an event occurs
-///
-/// For now its just dummy ...
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EventHandler {
     pub script: String,
+    /// Where the script comes from, for logs and the status
+    pub source: HandlerSource,
+    /// Absolute directories prepended to the python path while the script runs
+    pub pythonpath: Vec<PathBuf>,
+    /// The language of the script, selected by its shebang line
+    pub language: ScriptLanguage,
 }
 
 impl EventHandler {
     pub fn from_config(config: &config::EventHandlerConfig) -> Result<EventHandler, Error> {
-        Ok(match config {
+        let mut handler = match config {
             EventHandlerConfig::AsCode { code } => EventHandler {
                 script: code.clone(),
+                source: HandlerSource::Inline,
+                pythonpath: Vec::new(),
+                language: ScriptLanguage::Python,
             },
-            EventHandlerConfig::AsFile { file } => EventHandler {
+            EventHandlerConfig::AsFile { file, pythonpath } => EventHandler {
                 script: fs::read_to_string(&file).map_err(Error::LoadScriptFailed)?,
+                source: HandlerSource::File(PathBuf::from(file)),
+                pythonpath: match pythonpath {
+                    None => Vec::new(),
+                    // Relative to the config directory, which is the current directory
+                    // while the config is loaded
+                    Some(dirs) => {
+                        let current_dir = std::env::current_dir().map_err(Error::LoadScriptFailed)?;
+                        dirs.iter().map(|dir| current_dir.join(dir)).collect()
+                    }
+                },
+                language: ScriptLanguage::Python,
             },
-        })
+        };
+        handler.language = ScriptLanguage::detect(&handler.script)?;
+        Ok(handler)
+    }
+}
+
+/// Context in which an event handler is executed.
+///
+/// It is made available to the script as variables.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventContext {
+    /// Id of the button that caused the event
+    pub button_id: Option<usize>,
+    /// Name of the button that caused the event
+    pub button_name: Option<String>,
+    /// The event, "down" or "up" for buttons
+    pub event: Option<&'static str>,
+}
+
+impl EventContext {
+    /// Context for a button being pressed or released
+    pub fn for_button(button_id: usize, button_name: String, event: &'static str) -> EventContext {
+        EventContext {
+            button_id: Some(button_id),
+            button_name: Some(button_name),
+            event: Some(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_handler_is_inline() {
+        // Setup
+        let config = EventHandlerConfig::AsCode {
+            code: String::from("print('hello')"),
+        };
+
+        // Act
+        let handler = EventHandler::from_config(&config).unwrap();
+
+        // Test
+        assert_eq!(handler.source, HandlerSource::Inline);
+        assert_eq!(handler.source.to_string(), "inline");
+    }
+
+    #[test]
+    fn file_handler_reports_its_path() {
+        // Setup
+        let file = std::env::temp_dir().join("streamdeck_controller_handler_source_test.py");
+        fs::write(&file, "print('hello')").unwrap();
+        let config = EventHandlerConfig::AsFile {
+            file: file.to_string_lossy().to_string(),
+            pythonpath: None,
+        };
+
+        // Act
+        let handler = EventHandler::from_config(&config).unwrap();
+
+        // Test
+        assert_eq!(handler.source, HandlerSource::File(file.clone()));
+        assert_eq!(handler.source.to_string(), file.display().to_string());
+        assert_eq!(handler.script, "print('hello')");
+    }
+
+    #[test]
+    fn script_without_shebang_is_python() {
+        // Act
+        let language = ScriptLanguage::detect("print('hello')\n#!lua");
+
+        // Test
+        assert_eq!(language.unwrap(), ScriptLanguage::Python);
+    }
+
+    #[test]
+    fn python_shebangs_select_python() {
+        // Act
+        let short = ScriptLanguage::detect("#!python\nprint('hello')");
+        let env = ScriptLanguage::detect("#!/usr/bin/env python3\nprint('hello')");
+
+        // Test
+        assert_eq!(short.unwrap(), ScriptLanguage::Python);
+        assert_eq!(env.unwrap(), ScriptLanguage::Python);
+    }
+
+    #[test]
+    fn lua_shebangs_select_lua() {
+        // Act
+        let short = ScriptLanguage::detect("#!lua\nprint('hello')");
+        let path = ScriptLanguage::detect("#!/usr/bin/lua5.4\nprint('hello')");
+
+        // Test
+        assert_eq!(short.unwrap(), ScriptLanguage::Lua);
+        assert_eq!(path.unwrap(), ScriptLanguage::Lua);
+    }
+
+    #[test]
+    fn unknown_shebang_is_an_error() {
+        // Setup
+        let config = EventHandlerConfig::AsCode {
+            code: String::from("#!ruby\nputs 'hello'"),
+        };
+
+        // Act
+        let result = EventHandler::from_config(&config);
+
+        // Test
+        assert!(matches!(result, Err(Error::UnknownScriptLanguage(shebang)) if shebang == "#!ruby"));
     }
 }
@@ -0,0 +1,293 @@
+use super::app_state::{AppState, PendingFace};
+use super::button_face::ButtonFace;
+use super::error::Error;
+use log::{debug, warn};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+/// Draws the faces of pages, that have not been loaded yet, in the background.
+///
+/// The faces are drawn without holding the lock of the state, so that the rendering is not
+/// blocked. Loading a page afterwards finds its faces already drawn.
+pub struct FaceWarmer {
+    stop: Arc<AtomicBool>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl FaceWarmer {
+    /// Start drawing the undrawn faces of the state in a background thread.
+    pub fn start(app_state: &Arc<RwLock<AppState>>) -> FaceWarmer {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let app_state = app_state.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let warmed = warm_faces(&app_state, &stop);
+                debug!("{} faces drawn in the background", warmed);
+            })
+        };
+        FaceWarmer { stop, thread }
+    }
+
+    /// Stop drawing faces (after the face currently drawn) and wait for the thread to finish.
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.thread.join();
+    }
+}
+
+/// Draw the undrawn faces of the state, until all are drawn or [stop] is set.
+///
+/// Faces, that can not be drawn (for example because of missing image files), are left undrawn,
+/// the error is reported when their page is loaded.
+///
+/// # Return
+///
+/// The number of faces drawn and installed in the state.
+fn warm_faces(app_state: &Arc<RwLock<AppState>>, stop: &AtomicBool) -> usize {
+    let (faces, defaults) = {
+        let app_state = app_state.read().unwrap();
+        (app_state.undrawn_faces(), app_state.defaults().clone())
+    };
+    let mut warmed = 0;
+    for face in faces {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let mut drawn = ButtonFace::clone(&face);
+        if let Err(e) = drawn.redraw(&defaults) {
+            debug!("face not drawn in the background: {}", e);
+            continue;
+        }
+        if app_state
+            .write()
+            .unwrap()
+            .install_drawn_face(&face, Arc::new(drawn))
+            > 0
+        {
+            warmed += 1;
+        }
+    }
+    warmed
+}
+
+/// Draw the faces shown with a loading placeholder in a background thread, replacing the placeholders.
+///
+/// # Arguments
+///
+/// app_state - The state with the pending faces.
+/// done - Called after the faces have been installed, for example to render them.
+pub fn load_pending_faces(app_state: &Arc<RwLock<AppState>>, done: impl FnOnce() + Send + 'static) {
+    let (pending, defaults) = {
+        let mut app_state = app_state.write().unwrap();
+        let pending = app_state.take_pending_faces();
+        if pending.is_empty() {
+            return;
+        }
+        (pending, app_state.defaults().clone())
+    };
+    let app_state = app_state.clone();
+    thread::spawn(move || {
+        install_pending_faces(&app_state, pending, |face| {
+            let mut drawn = face.clone();
+            drawn.redraw(&defaults)?;
+            Ok(drawn)
+        });
+        done();
+    });
+}
+
+/// Draw the pending faces and replace their placeholders in the state.
+///
+/// Faces, that can not be drawn, keep their placeholder and the error is logged.
+///
+/// # Arguments
+///
+/// app_state - The state, the placeholders are replaced in.
+/// pending - The faces to draw.
+/// draw - Draws a face (the state is not locked while it runs).
+///
+/// # Return
+///
+/// The number of faces drawn and installed in the state.
+fn install_pending_faces(
+    app_state: &Arc<RwLock<AppState>>,
+    pending: Vec<PendingFace>,
+    draw: impl Fn(&ButtonFace) -> Result<ButtonFace, Error>,
+) -> usize {
+    let mut installed = 0;
+    for pending_face in pending {
+        match draw(&pending_face.face) {
+            Ok(drawn) => {
+                if app_state
+                    .write()
+                    .unwrap()
+                    .install_drawn_face(&pending_face.placeholder, Arc::new(drawn))
+                    > 0
+                {
+                    installed += 1;
+                }
+            }
+            Err(e) => warn!("unable to draw face: {}", e),
+        }
+    }
+    installed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::defaults::Defaults;
+    use streamdeck_hid_rs::StreamDeckType;
+
+    /// State with a default page and a second page, with one button each
+    fn create_state() -> Arc<RwLock<AppState>> {
+        let config: crate::config::Config = serde_yaml::from_str(
+            "\
+pages:
+- name: page1
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button:
+      up_face:
+        label: one
+- name: page2
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button:
+      up_face:
+        label: two
+      down_face:
+        label: pressed
+default_pages:
+- page1
+",
+        )
+        .unwrap();
+        Arc::new(RwLock::new(
+            AppState::from_config(&StreamDeckType::Orig, &config).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn faces_of_not_loaded_pages_are_warmed() {
+        // Setup
+        let app_state = create_state();
+
+        // Act
+        let warmed = warm_faces(&app_state, &AtomicBool::new(false));
+
+        // Test
+        assert_eq!(warmed, 2);
+        assert!(app_state.read().unwrap().undrawn_faces().is_empty());
+    }
+
+    #[test]
+    fn stopped_warming_draws_nothing() {
+        // Setup
+        let app_state = create_state();
+
+        // Act
+        let warmed = warm_faces(&app_state, &AtomicBool::new(true));
+
+        // Test
+        assert_eq!(warmed, 0);
+        assert_eq!(app_state.read().unwrap().undrawn_faces().len(), 2);
+    }
+
+    /// State with loading placeholders and a second page showing an image, at index 4
+    fn create_image_page_state() -> Arc<RwLock<AppState>> {
+        let config: crate::config::Config = serde_yaml::from_str(
+            "\
+loading_placeholder: true
+pages:
+- name: page1
+  buttons: []
+- name: page2
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button:
+      up_face:
+        color: '#FF0000'
+        file: slow_image.png
+default_pages:
+- page1
+",
+        )
+        .unwrap();
+        Arc::new(RwLock::new(
+            AppState::from_config(&StreamDeckType::Orig, &config).unwrap(),
+        ))
+    }
+
+    /// The color of the center of the face rendered on a button, after rendering all buttons
+    fn rendered_center_color(app_state: &Arc<RwLock<AppState>>, button_id: u8) -> Option<image::Rgb<u8>> {
+        let mut app_state = app_state.write().unwrap();
+        app_state
+            .set_rendered_and_get_rendering_faces()
+            .into_iter()
+            .find(|(id, _)| *id == button_id)
+            .map(|(_, face)| {
+                let center = face.face.width() / 2;
+                *face.face.get_pixel(center, center)
+            })
+    }
+
+    #[test]
+    fn placeholder_is_shown_until_the_face_is_drawn() {
+        // Setup
+        let app_state = create_image_page_state();
+        rendered_center_color(&app_state, 4);
+        app_state
+            .write()
+            .unwrap()
+            .load_page(&String::from("page2"))
+            .unwrap();
+        let placeholder_color = rendered_center_color(&app_state, 4);
+        let pending = app_state.write().unwrap().take_pending_faces();
+
+        // Act
+        // The fake decoder draws the image in green
+        let installed = install_pending_faces(&app_state, pending, |face| {
+            let mut drawn = face.clone();
+            drawn.face = image::RgbImage::from_pixel(72, 72, image::Rgb([0, 255, 0]));
+            Ok(drawn)
+        });
+
+        // Test
+        assert_eq!(placeholder_color, Some(image::Rgb([255, 0, 0])));
+        assert_eq!(installed, 1);
+        assert_eq!(rendered_center_color(&app_state, 4), Some(image::Rgb([0, 255, 0])));
+    }
+
+    #[test]
+    fn placeholder_stays_if_the_face_can_not_be_drawn() {
+        // Setup
+        let app_state = create_image_page_state();
+        app_state
+            .write()
+            .unwrap()
+            .load_page(&String::from("page2"))
+            .unwrap();
+        rendered_center_color(&app_state, 4);
+        let pending = app_state.write().unwrap().take_pending_faces();
+
+        // Act
+        let installed = install_pending_faces(&app_state, pending, |face| {
+            let mut drawn = face.clone();
+            drawn.redraw(&Defaults::from_config(&None, &StreamDeckType::Orig).unwrap())?;
+            Ok(drawn)
+        });
+
+        // Test
+        assert_eq!(installed, 0);
+        assert_eq!(rendered_center_color(&app_state, 4), None);
+    }
+}
@@ -0,0 +1,562 @@
+use super::error::Error;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// The fonts used to draw texts on the faces.
+///
+/// The bundled DejaVuSans font comes first, followed by the configured fallback fonts.
+/// Every character is drawn with the first font containing a glyph for it, so that
+/// for example CJK texts can be drawn with a fallback font.
+/// If an emoji font is configured, emoji are drawn with its colored bitmap glyphs instead.
+#[derive(Clone)]
+pub struct Fonts {
+    fonts: Vec<rusttype::Font<'static>>,
+    /// Data of the color emoji font, parsed when drawing
+    emoji: Option<Vec<u8>>,
+}
+
+/// Fonts of labels with their own font (by font file), so that every file is only loaded once.
+static LABEL_FONTS: Mutex<Option<HashMap<String, Arc<Fonts>>>> = Mutex::new(None);
+
+/// Load a font file (TrueType or OpenType).
+fn load_font(file: &str) -> Result<rusttype::Font<'static>, Error> {
+    let data =
+        std::fs::read(file).map_err(|e| Error::FontLoadingError(format!("{}: {}", file, e)))?;
+    rusttype::Font::try_from_vec(data)
+        .ok_or_else(|| Error::FontLoadingError(format!("{}: not a font file", file)))
+}
+
+/// A run of characters drawn with the same font.
+enum Run<'a> {
+    Text(&'a rusttype::Font<'static>, String),
+    Emoji(String),
+}
+
+impl fmt::Debug for Fonts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fonts")
+            .field("num_fonts", &self.fonts.len())
+            .field("has_emoji_font", &self.emoji.is_some())
+            .finish()
+    }
+}
+
+impl Fonts {
+    /// Load the fonts.
+    ///
+    /// # Arguments
+    ///
+    /// fallback_files - The font files (TrueType or OpenType), used for characters missing in the bundled font.
+    /// emoji_file - A color emoji font file with bitmap glyphs (sbix or CBDT), used for emoji.
+    ///
+    /// # Return
+    ///
+    /// The fonts, or an error if a font could not be loaded.
+    pub fn new(fallback_files: &[String], emoji_file: Option<&str>) -> Result<Fonts, Error> {
+        let font_data: &[u8] = include_bytes!("../../assets/DejaVuSans.ttf");
+        let mut fonts = vec![rusttype::Font::try_from_vec(Vec::from(font_data)).unwrap()];
+        for file in fallback_files {
+            fonts.push(load_font(file)?);
+        }
+        let emoji = match emoji_file {
+            None => None,
+            Some(file) => {
+                let data = std::fs::read(file)
+                    .map_err(|e| Error::FontLoadingError(format!("{}: {}", file, e)))?;
+                ttf_parser::Face::from_slice(&data, 0)
+                    .map_err(|e| Error::FontLoadingError(format!("{}: {}", file, e)))?;
+                Some(data)
+            }
+        };
+        Ok(Fonts { fonts, emoji })
+    }
+
+    /// Load the fonts of a label with its own font, or return them from the cache.
+    ///
+    /// The font of the file comes first, followed by the bundled font for the characters missing in it.
+    ///
+    /// # Arguments
+    ///
+    /// file - The font file (TrueType or OpenType) of the label.
+    ///
+    /// # Return
+    ///
+    /// The fonts (shared by all labels with the same file), or an error if the font could not be loaded.
+    pub fn for_label(file: &str) -> Result<Arc<Fonts>, Error> {
+        let mut cache = LABEL_FONTS.lock().unwrap();
+        let cache = cache.get_or_insert_with(HashMap::new);
+        if let Some(fonts) = cache.get(file) {
+            return Ok(fonts.clone());
+        }
+        let mut fonts = Fonts::new(&[], None)?;
+        fonts.fonts.insert(0, load_font(file)?);
+        let fonts = Arc::new(fonts);
+        cache.insert(file.to_string(), fonts.clone());
+        Ok(fonts)
+    }
+
+    /// Split a text into runs of characters, that are drawn with the same font.
+    fn runs(&self, text: &str) -> Vec<Run> {
+        let emoji_face = self.emoji_face();
+        let num_fonts = self.fonts.len();
+        let is_emoji = |c: char| match &emoji_face {
+            Some(face) => {
+                is_emoji_joiner(c) || (is_emoji_codepoint(c) && has_emoji_glyph(face, c))
+            }
+            None => false,
+        };
+        split_into_runs(text, num_fonts + 1, |index, c| {
+            if index == num_fonts {
+                is_emoji(c)
+            } else {
+                !is_emoji(c) && self.fonts[index].glyph(c).id().0 != 0
+            }
+        })
+        .into_iter()
+        .map(|(index, run)| {
+            if index == num_fonts {
+                Run::Emoji(run)
+            } else {
+                Run::Text(&self.fonts[index], run)
+            }
+        })
+        .collect()
+    }
+
+    /// The parsed emoji font, if one is configured.
+    fn emoji_face(&self) -> Option<ttf_parser::Face> {
+        self.emoji
+            .as_ref()
+            .and_then(|data| ttf_parser::Face::from_slice(data, 0).ok())
+    }
+
+    /// Width and height of a text.
+    pub fn text_size(&self, scale: rusttype::Scale, text: &str) -> (i32, i32) {
+        self.runs(text)
+            .iter()
+            .map(|run| match run {
+                Run::Text(font, run) => imageproc::drawing::text_size(scale, *font, run),
+                Run::Emoji(run) => emoji_run_size(scale, run),
+            })
+            .fold((0, 0), |(w, h), (run_w, run_h)| (w + run_w, h.max(run_h)))
+    }
+
+    /// Draw a text (in visual order, see [visual_order]) on an image.
+    pub fn draw_text_mut(
+        &self,
+        image: &mut image::RgbImage,
+        color: image::Rgb<u8>,
+        x: i32,
+        y: i32,
+        scale: rusttype::Scale,
+        text: &str,
+    ) {
+        let mut x = x;
+        for run in self.runs(text) {
+            match run {
+                Run::Text(font, run) => {
+                    imageproc::drawing::draw_text_mut(image, color, x, y, scale, font, &run);
+                    x += imageproc::drawing::text_size(scale, font, &run).0;
+                }
+                Run::Emoji(run) => {
+                    let size = emoji_size(scale);
+                    if let Some(face) = self.emoji_face() {
+                        for c in run.chars().filter(|c| !is_emoji_joiner(*c)) {
+                            if let Some(glyph) = emoji_glyph(&face, c, size) {
+                                overlay_emoji(image, &glyph, x, y);
+                            }
+                            x += size as i32;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether a character is in one of the unicode emoji blocks.
+fn is_emoji_codepoint(c: char) -> bool {
+    matches!(c as u32, 0x1F000..=0x1FAFF | 0x2600..=0x27BF)
+}
+
+/// Whether a character only modifies the surrounding emoji (variation selector, zero width joiner).
+///
+/// They are not drawn.
+fn is_emoji_joiner(c: char) -> bool {
+    c == '\u{FE0F}' || c == '\u{200D}'
+}
+
+/// Width and height (in pixels) of a single emoji.
+fn emoji_size(scale: rusttype::Scale) -> u32 {
+    scale.y.round().max(1.0) as u32
+}
+
+/// Width and height of a run of emoji, every emoji is a square with the height of the font scale.
+fn emoji_run_size(scale: rusttype::Scale, run: &str) -> (i32, i32) {
+    let size = emoji_size(scale) as i32;
+    let count = run.chars().filter(|c| !is_emoji_joiner(*c)).count() as i32;
+    (count * size, size)
+}
+
+/// Whether the emoji font has a bitmap glyph for a character.
+fn has_emoji_glyph(face: &ttf_parser::Face, c: char) -> bool {
+    face.glyph_index(c)
+        .and_then(|id| face.glyph_raster_image(id, u16::MAX))
+        .is_some()
+}
+
+/// Decode the colored bitmap of an emoji, scaled to the given size.
+///
+/// # Arguments
+///
+/// face - The emoji font.
+/// c - The emoji.
+/// size - Width and height of the result in pixels.
+///
+/// # Return
+///
+/// The bitmap, or None if the font has no bitmap glyph for the emoji.
+fn emoji_glyph(face: &ttf_parser::Face, c: char, size: u32) -> Option<image::RgbaImage> {
+    let id = face.glyph_index(c)?;
+    let raster = face.glyph_raster_image(id, size.min(u16::MAX as u32) as u16)?;
+    let bitmap = image::load_from_memory(raster.data).ok()?.into_rgba8();
+    Some(image::imageops::resize(
+        &bitmap,
+        size,
+        size,
+        image::imageops::FilterType::Triangle,
+    ))
+}
+
+/// Alpha blend an emoji bitmap onto an image, with its top left corner at (x, y).
+fn overlay_emoji(image: &mut image::RgbImage, glyph: &image::RgbaImage, x: i32, y: i32) {
+    for (gx, gy, pixel) in glyph.enumerate_pixels() {
+        let (px, py) = (x + gx as i32, y + gy as i32);
+        if px < 0 || py < 0 || px >= image.width() as i32 || py >= image.height() as i32 {
+            continue;
+        }
+        let alpha = pixel[3] as u32;
+        let target = image.get_pixel_mut(px as u32, py as u32);
+        for i in 0..3 {
+            target[i] = ((pixel[i] as u32 * alpha + target[i] as u32 * (255 - alpha)) / 255) as u8;
+        }
+    }
+}
+
+/// Split a text into runs of characters with the same font.
+///
+/// # Arguments
+///
+/// text - The text to split.
+/// num_fonts - The number of fonts to choose from.
+/// has_glyph - Whether the font with the given index has a glyph for the character.
+///
+/// # Return
+///
+/// The runs with the index of their font. Characters missing in all fonts use the first font.
+fn split_into_runs(
+    text: &str,
+    num_fonts: usize,
+    has_glyph: impl Fn(usize, char) -> bool,
+) -> Vec<(usize, String)> {
+    let mut runs: Vec<(usize, String)> = Vec::new();
+    for c in text.chars() {
+        let index = (0..num_fonts).find(|i| has_glyph(*i, c)).unwrap_or(0);
+        match runs.last_mut() {
+            Some((last_index, run)) if *last_index == index => run.push(c),
+            _ => runs.push((index, c.to_string())),
+        }
+    }
+    runs
+}
+
+/// Direction of a character
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Direction {
+    LeftToRight,
+    RightToLeft,
+    /// Spaces, punctuation and so on, they take the direction of their surrounding
+    Neutral,
+}
+
+/// Direction of a character, right to left for Hebrew and Arabic letters.
+///
+/// Arabic-Indic digits are numbers, they keep their order like other digits.
+fn direction(c: char) -> Direction {
+    match c as u32 {
+        0x0660..=0x0669 | 0x06F0..=0x06F9 => Direction::LeftToRight,
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF => Direction::RightToLeft,
+        _ if c.is_alphanumeric() => Direction::LeftToRight,
+        _ => Direction::Neutral,
+    }
+}
+
+/// The mirrored form of brackets, as drawn in right to left runs.
+fn mirrored(c: char) -> char {
+    match c {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        c => c,
+    }
+}
+
+/// Reorder a text from logical (reading) order to visual order (from left to right).
+///
+/// This is a simplified version of the unicode bidirectional algorithm: Right to left
+/// runs (Hebrew, Arabic) are reversed (with mirrored brackets), and if the text starts
+/// with a right to left character, the order of the runs is reversed as well. Numbers
+/// and latin words keep their order. Texts without right to left characters are not
+/// changed. Explicit direction marks and embeddings are not supported, and Arabic
+/// letters are not shaped (they are drawn in their isolated form).
+pub fn visual_order(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let directions: Vec<Direction> = chars.iter().map(|c| direction(*c)).collect();
+    if !directions.contains(&Direction::RightToLeft) {
+        return String::from(text);
+    }
+    let base = *directions
+        .iter()
+        .find(|d| **d != Direction::Neutral)
+        .unwrap_or(&Direction::LeftToRight);
+
+    // Neutral characters between characters of the same direction take that direction,
+    // all others the base direction
+    let resolved: Vec<Direction> = (0..chars.len())
+        .map(|i| {
+            if directions[i] != Direction::Neutral {
+                return directions[i];
+            }
+            let before = directions[..i].iter().rev().find(|d| **d != Direction::Neutral);
+            let after = directions[i + 1..].iter().find(|d| **d != Direction::Neutral);
+            match (before, after) {
+                (Some(before), Some(after)) if before == after => *before,
+                _ => base,
+            }
+        })
+        .collect();
+
+    // Collect the runs of the same direction
+    let mut runs: Vec<(Direction, Vec<char>)> = Vec::new();
+    for (c, d) in chars.iter().zip(resolved.iter()) {
+        match runs.last_mut() {
+            Some((last_direction, run)) if last_direction == d => run.push(*c),
+            _ => runs.push((*d, vec![*c])),
+        }
+    }
+    if base == Direction::RightToLeft {
+        runs.reverse();
+    }
+    runs.into_iter()
+        .flat_map(|(d, mut run)| {
+            if d == Direction::RightToLeft {
+                run.reverse();
+                run = run.into_iter().map(mirrored).collect();
+            }
+            run
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latin_text_is_not_reordered() {
+        assert_eq!(visual_order("Hello, World!"), "Hello, World!");
+    }
+
+    #[test]
+    fn hebrew_text_is_reversed() {
+        // Setup
+        let text = "שלום עולם";
+
+        // Act
+        let visual = visual_order(text);
+
+        // Test
+        assert_eq!(visual, "םלוע םולש");
+    }
+
+    #[test]
+    fn numbers_in_hebrew_text_keep_their_order() {
+        // Setup
+        let text = "חדר 42";
+
+        // Act
+        let visual = visual_order(text);
+
+        // Test
+        assert_eq!(visual, "42 רדח");
+    }
+
+    #[test]
+    fn hebrew_word_in_latin_text_is_reversed_in_place() {
+        // Setup
+        let text = "Say שלום now";
+
+        // Act
+        let visual = visual_order(text);
+
+        // Test
+        assert_eq!(visual, "Say םולש now");
+    }
+
+    #[test]
+    fn numbers_and_latin_words_in_arabic_text_keep_their_order() {
+        // Setup
+        // "price 42 USD"
+        let text = "سعر 42 USD";
+
+        // Act
+        let visual = visual_order(text);
+
+        // Test
+        assert_eq!(visual, "42 USD رعس");
+    }
+
+    #[test]
+    fn arabic_indic_digits_keep_their_order() {
+        // Setup
+        // "room 42" with Arabic-Indic digits
+        let text = "غرفة ٤٢";
+
+        // Act
+        let visual = visual_order(text);
+
+        // Test
+        assert_eq!(visual, "٤٢ ةفرغ");
+    }
+
+    #[test]
+    fn brackets_in_right_to_left_text_are_mirrored() {
+        // Setup
+        let text = "(שלום)";
+
+        // Act
+        let visual = visual_order(text);
+
+        // Test
+        assert_eq!(visual, "(םולש)");
+    }
+
+    #[test]
+    fn characters_use_the_first_font_with_a_glyph() {
+        // Setup
+        // Font 0 has latin characters, font 1 has everything
+        let has_glyph = |index: usize, c: char| index == 1 || c.is_ascii();
+
+        // Act
+        let runs = split_into_runs("Hi 漢字!", 2, has_glyph);
+
+        // Test
+        assert_eq!(
+            runs,
+            vec![
+                (0, String::from("Hi ")),
+                (1, String::from("漢字")),
+                (0, String::from("!"))
+            ]
+        );
+    }
+
+    #[test]
+    fn characters_without_glyph_use_the_first_font() {
+        // Act
+        let runs = split_into_runs("a漢", 2, |_, c| c.is_ascii());
+
+        // Test
+        assert_eq!(runs, vec![(0, String::from("a漢"))]);
+    }
+
+    #[test]
+    fn text_is_drawn_with_the_bundled_font() {
+        // Setup
+        let fonts = Fonts::new(&[], None).unwrap();
+        let mut image = image::RgbImage::new(72, 72);
+
+        // Act
+        fonts.draw_text_mut(
+            &mut image,
+            image::Rgb([255, 255, 255]),
+            4,
+            20,
+            rusttype::Scale::uniform(20.0),
+            "Hi",
+        );
+
+        // Test
+        assert!(image.pixels().any(|p| p[0] > 0));
+    }
+
+    #[test]
+    fn missing_fallback_font_is_an_error() {
+        // Act
+        let result = Fonts::new(&[String::from("./does_not_exist.ttf")], None);
+
+        // Test
+        assert!(matches!(result, Err(Error::FontLoadingError(_))));
+    }
+
+    #[test]
+    fn emoji_are_drawn_with_the_colored_emoji_font() {
+        // Setup
+        let fonts = Fonts::new(&[], Some("./src/state/test_emoji_font.ttf")).unwrap();
+        let mut image = image::RgbImage::new(72, 72);
+
+        // Act
+        fonts.draw_text_mut(
+            &mut image,
+            image::Rgb([255, 255, 255]),
+            4,
+            4,
+            rusttype::Scale::uniform(32.0),
+            "😀",
+        );
+
+        // Test
+        let has_color = |r: bool, g: bool, b: bool| {
+            image
+                .pixels()
+                .any(|p| (p[0] > 200) == r && (p[1] > 200) == g && (p[2] > 200) == b)
+        };
+        assert!(has_color(true, false, false));
+        assert!(has_color(false, true, false));
+        assert!(has_color(false, false, true));
+        assert_eq!(fonts.text_size(rusttype::Scale::uniform(32.0), "😀"), (32, 32));
+    }
+
+    #[test]
+    fn label_fonts_are_loaded_once_per_file() {
+        // Act
+        let first = Fonts::for_label("./src/state/test_box_font.ttf").unwrap();
+        let second = Fonts::for_label("./src/state/test_box_font.ttf").unwrap();
+        let missing = Fonts::for_label("./does_not_exist.ttf");
+
+        // Test
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(matches!(missing, Err(Error::FontLoadingError(_))));
+    }
+
+    #[test]
+    fn label_font_comes_before_the_bundled_font() {
+        // Setup
+        let label_fonts = Fonts::for_label("./src/state/test_box_font.ttf").unwrap();
+        let bundled_fonts = Fonts::new(&[], None).unwrap();
+        let scale = rusttype::Scale::uniform(20.0);
+
+        // Act
+        let label_size = label_fonts.text_size(scale, "ii");
+        let bundled_size = bundled_fonts.text_size(scale, "ii");
+
+        // Test
+        more_asserts::assert_gt!(label_size.0, 2 * bundled_size.0);
+    }
+}
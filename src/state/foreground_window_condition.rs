@@ -1,43 +1,75 @@
-use crate::config::ForegroundWindowConditionConfig;
+use crate::config::{ForegroundWindowConditionConfig, PageConditionConfig};
 use crate::foreground_window::WindowInformation;
 use crate::state::error::Error;
 
+/// Compile the regex of a field of a condition.
+///
+/// # Arguments
+///
+/// field - The name of the field in the config, for the error message.
+/// pattern - The pattern of the regex, if it is given in the config.
+///
+/// # Return
+///
+/// The compiled regex, or an error mentioning the field and the pattern.
+fn compile_regex(field: &str, pattern: &Option<String>) -> Result<Option<regex::Regex>, Error> {
+    match pattern {
+        None => Ok(None),
+        Some(pattern) => regex::Regex::new(pattern.as_str())
+            .map(Some)
+            .map_err(|e| Error::RegexError(field.to_string(), pattern.clone(), e)),
+    }
+}
+
+/// Compile the title regex of a condition, from the regex or one of the literal title matchers.
+///
+/// # Return
+///
+/// The compiled regex, or an error if the regex is invalid or several title matchers are given.
+fn compile_title_regex(config: &ForegroundWindowConditionConfig) -> Result<Option<regex::Regex>, Error> {
+    let literal = [
+        (&config.title_equals, "^", "$"),
+        (&config.title_starts_with, "^", ""),
+        (&config.title_contains, "", ""),
+    ]
+    .into_iter()
+    .filter_map(|(text, prefix, suffix)| {
+        text.as_ref()
+            .map(|text| format!("{}{}{}", prefix, regex::escape(text), suffix))
+    })
+    .collect::<Vec<String>>();
+    match (&config.title, literal.as_slice()) {
+        (title, []) => compile_regex("title", title),
+        (None, [pattern]) => compile_regex("title", &Some(pattern.clone())),
+        _ => Err(Error::ConfigParserError(String::from(
+            "only one of title, title_equals, title_starts_with and title_contains can be given",
+        ))),
+    }
+}
+
 /// Condition for actions based on foreground window
+#[derive(Clone)]
 pub struct ForegroundWindowCondition {
     pub title: Option<regex::Regex>,
     pub executable: Option<regex::Regex>,
     pub class_name: Option<regex::Regex>,
+    pub workspace: Option<u32>,
 }
 
 impl ForegroundWindowCondition {
     pub fn from_config(
         config: &ForegroundWindowConditionConfig,
     ) -> Result<ForegroundWindowCondition, Error> {
-        let title = match &config.title {
-            None => None,
-            Some(title) => Some(regex::Regex::new(title.as_str()).map_err(Error::RegexError)?),
-        };
-        let executable = match &config.executable {
-            None => None,
-            Some(executable) => {
-                Some(regex::Regex::new(executable.as_str()).map_err(Error::RegexError)?)
-            }
-        };
-        let class_name = match &config.class_name {
-            None => None,
-            Some(class_name) => {
-                Some(regex::Regex::new(class_name.as_str()).map_err(Error::RegexError)?)
-            }
-        };
         Ok(ForegroundWindowCondition {
-            title,
-            executable,
-            class_name,
+            title: compile_title_regex(config)?,
+            executable: compile_regex("executable", &config.executable)?,
+            class_name: compile_regex("class_name", &config.class_name)?,
+            workspace: config.workspace,
         })
     }
 
-    /// Test whether the conditions is given by matching the title
-    /// and the executable.
+    /// Test whether the conditions is given by matching the title,
+    /// the executable, the class name and the workspace.
     pub fn matches(&self, window: &WindowInformation) -> bool {
         let title_matches = if let Some(title_re) = &self.title {
             title_re.is_match(window.title.as_str())
@@ -54,7 +86,52 @@ impl ForegroundWindowCondition {
         } else {
             true
         };
-        title_matches && exec_matches && class_matches
+        let workspace_matches = if let Some(workspace) = self.workspace {
+            window.workspace == Some(workspace)
+        } else {
+            true
+        };
+        title_matches && exec_matches && class_matches && workspace_matches
+    }
+}
+
+/// Condition for loading a page, a window condition or a group of conditions
+pub enum PageCondition {
+    All(Vec<PageCondition>),
+    Any(Vec<PageCondition>),
+    Window(ForegroundWindowCondition),
+}
+
+impl PageCondition {
+    pub fn from_config(config: &PageConditionConfig) -> Result<PageCondition, Error> {
+        Ok(match config {
+            PageConditionConfig::All(group) => PageCondition::All(
+                group
+                    .all
+                    .iter()
+                    .map(PageCondition::from_config)
+                    .collect::<Result<_, _>>()?,
+            ),
+            PageConditionConfig::Any(group) => PageCondition::Any(
+                group
+                    .any
+                    .iter()
+                    .map(PageCondition::from_config)
+                    .collect::<Result<_, _>>()?,
+            ),
+            PageConditionConfig::Window(c) => {
+                PageCondition::Window(ForegroundWindowCondition::from_config(c)?)
+            }
+        })
+    }
+
+    /// Test whether the condition is given, groups are evaluated recursively.
+    pub fn matches(&self, window: &WindowInformation) -> bool {
+        match self {
+            PageCondition::All(conditions) => conditions.iter().all(|c| c.matches(window)),
+            PageCondition::Any(conditions) => conditions.iter().any(|c| c.matches(window)),
+            PageCondition::Window(condition) => condition.matches(window),
+        }
     }
 }
 
@@ -70,6 +147,10 @@ mod tests {
             title: Some(".*title.*".to_string()),
             executable: Some(".*exec.*".to_string()),
             class_name: Some(".*class.*".to_string()),
+            workspace: None,
+            title_equals: None,
+            title_starts_with: None,
+            title_contains: None,
         };
 
         // Act
@@ -80,6 +161,7 @@ mod tests {
             title: String::from("Some title here"),
             executable: String::from("Some executable here"),
             class_name: String::from("Some class here"),
+            workspace: None,
         }));
     }
 
@@ -90,6 +172,10 @@ mod tests {
             title: Some(".*title.*".to_string()),
             executable: Some(".*exec.*".to_string()),
             class_name: Some(".*class.*".to_string()),
+            workspace: None,
+            title_equals: None,
+            title_starts_with: None,
+            title_contains: None,
         };
 
         // Act
@@ -100,16 +186,19 @@ mod tests {
             title: String::from("No match"),
             executable: String::from("Some executable here"),
             class_name: String::from("Some class here"),
+            workspace: None,
         }));
         assert!(!object.matches(&WindowInformation {
             title: String::from("Some title here"),
             executable: String::from("No match"),
-            class_name: String::from("Some class here")
+            class_name: String::from("Some class here"),
+            workspace: None,
         }));
         assert!(!object.matches(&WindowInformation {
             title: String::from("Some title here"),
             executable: String::from("Some executable here"),
-            class_name: String::from("No match")
+            class_name: String::from("No match"),
+            workspace: None,
         }));
     }
 
@@ -120,6 +209,10 @@ mod tests {
             title: Some(".*title.*".to_string()),
             executable: None,
             class_name: None,
+            workspace: None,
+            title_equals: None,
+            title_starts_with: None,
+            title_contains: None,
         };
 
         // Act
@@ -129,12 +222,14 @@ mod tests {
         assert!(!object.matches(&WindowInformation {
             title: String::from("No match"),
             executable: String::from("Some executable here"),
-            class_name: String::from("No match")
+            class_name: String::from("No match"),
+            workspace: None,
         }));
         assert!(object.matches(&WindowInformation {
             title: String::from("Some title here"),
             executable: String::from("Some executable here"),
-            class_name: String::from("No match")
+            class_name: String::from("No match"),
+            workspace: None,
         }));
     }
 
@@ -145,6 +240,10 @@ mod tests {
             title: None,
             executable: Some(".*exec.*".to_string()),
             class_name: None,
+            workspace: None,
+            title_equals: None,
+            title_starts_with: None,
+            title_contains: None,
         };
 
         // Act
@@ -154,12 +253,14 @@ mod tests {
         assert!(object.matches(&WindowInformation {
             title: String::from("No match"),
             executable: String::from("Some executable here"),
-            class_name: String::from("Some class here")
+            class_name: String::from("Some class here"),
+            workspace: None,
         }));
         assert!(!object.matches(&WindowInformation {
             title: String::from("Some title here"),
             executable: String::from("No match"),
-            class_name: String::from("Some class here")
+            class_name: String::from("Some class here"),
+            workspace: None,
         }));
     }
 
@@ -170,6 +271,10 @@ mod tests {
             title: None,
             executable: None,
             class_name: Some(".*class.*".to_string()),
+            workspace: None,
+            title_equals: None,
+            title_starts_with: None,
+            title_contains: None,
         };
 
         // Act
@@ -179,12 +284,183 @@ mod tests {
         assert!(object.matches(&WindowInformation {
             title: String::from("No match"),
             executable: String::from("No match"),
-            class_name: String::from("Some class here")
+            class_name: String::from("Some class here"),
+            workspace: None,
         }));
         assert!(!object.matches(&WindowInformation {
             title: String::from("No match"),
             executable: String::from("No match"),
-            class_name: String::from("No match")
+            class_name: String::from("No match"),
+            workspace: None,
         }));
     }
+
+    #[test]
+    fn test_with_only_workspace() {
+        // Setup
+        let config = crate::config::ForegroundWindowConditionConfig {
+            title: None,
+            executable: None,
+            class_name: None,
+            workspace: Some(2),
+            title_equals: None,
+            title_starts_with: None,
+            title_contains: None,
+        };
+
+        // Act
+        let object = ForegroundWindowCondition::from_config(&config).unwrap();
+
+        // Test
+        assert!(object.matches(&WindowInformation {
+            title: String::from("No match"),
+            executable: String::from("No match"),
+            class_name: String::from("No match"),
+            workspace: Some(2),
+        }));
+        assert!(!object.matches(&WindowInformation {
+            title: String::from("No match"),
+            executable: String::from("No match"),
+            class_name: String::from("No match"),
+            workspace: Some(1),
+        }));
+        assert!(!object.matches(&WindowInformation {
+            title: String::from("No match"),
+            executable: String::from("No match"),
+            class_name: String::from("No match"),
+            workspace: None,
+        }));
+    }
+
+    #[test]
+    fn test_without_workspace_matches_any_workspace() {
+        // Setup
+        let config = crate::config::ForegroundWindowConditionConfig {
+            title: Some(".*title.*".to_string()),
+            executable: None,
+            class_name: None,
+            workspace: None,
+            title_equals: None,
+            title_starts_with: None,
+            title_contains: None,
+        };
+
+        // Act
+        let object = ForegroundWindowCondition::from_config(&config).unwrap();
+
+        // Test
+        assert!(object.matches(&WindowInformation {
+            title: String::from("Some title here"),
+            executable: String::from("No match"),
+            class_name: String::from("No match"),
+            workspace: Some(3),
+        }));
+    }
+
+    #[test]
+    fn invalid_title_regex_error_mentions_field_and_pattern() {
+        // Setup
+        let config = crate::config::ForegroundWindowConditionConfig {
+            title: Some("(unclosed".to_string()),
+            executable: Some(".*exec.*".to_string()),
+            class_name: None,
+            workspace: None,
+            title_equals: None,
+            title_starts_with: None,
+            title_contains: None,
+        };
+
+        // Act
+        let result = ForegroundWindowCondition::from_config(&config);
+
+        // Test
+        let message = match result {
+            Err(e @ Error::RegexError(..)) => e.to_string(),
+            _ => panic!("expected a regex error"),
+        };
+        assert!(message.contains("title"));
+        assert!(message.contains("(unclosed"));
+    }
+
+    #[test]
+    fn invalid_class_name_regex_error_names_the_field() {
+        // Setup
+        let config = crate::config::ForegroundWindowConditionConfig {
+            title: None,
+            executable: None,
+            class_name: Some("[a-".to_string()),
+            workspace: None,
+            title_equals: None,
+            title_starts_with: None,
+            title_contains: None,
+        };
+
+        // Act
+        let result = ForegroundWindowCondition::from_config(&config);
+
+        // Test
+        assert!(matches!(
+            result,
+            Err(Error::RegexError(field, pattern, _)) if field == "class_name" && pattern == "[a-"
+        ));
+    }
+
+    /// Condition from the yaml of its config
+    fn condition_from_yaml(yaml: &str) -> Result<ForegroundWindowCondition, Error> {
+        let config: ForegroundWindowConditionConfig = serde_yaml::from_str(yaml).unwrap();
+        ForegroundWindowCondition::from_config(&config)
+    }
+
+    /// Window with the given title
+    fn window_titled(title: &str) -> WindowInformation {
+        WindowInformation {
+            title: String::from(title),
+            executable: String::from("exec"),
+            class_name: String::from("class"),
+            workspace: None,
+        }
+    }
+
+    #[test]
+    fn title_equals_matches_the_whole_title_literally() {
+        // Act
+        let condition = condition_from_yaml("title_equals: 'Song (live).mp3'").unwrap();
+
+        // Test
+        assert!(condition.matches(&window_titled("Song (live).mp3")));
+        assert!(!condition.matches(&window_titled("Song (live).mp3 - Player")));
+        assert!(!condition.matches(&window_titled("Song (live)xmp3")));
+    }
+
+    #[test]
+    fn title_starts_with_matches_the_start_literally() {
+        // Act
+        let condition = condition_from_yaml("title_starts_with: '[draft]'").unwrap();
+
+        // Test
+        assert!(condition.matches(&window_titled("[draft] notes")));
+        assert!(!condition.matches(&window_titled("notes [draft]")));
+        assert!(!condition.matches(&window_titled("d notes")));
+    }
+
+    #[test]
+    fn title_contains_matches_anywhere_literally() {
+        // Act
+        let condition = condition_from_yaml("title_contains: '*.rs'").unwrap();
+
+        // Test
+        assert!(condition.matches(&window_titled("editor - *.rs files")));
+        assert!(!condition.matches(&window_titled("editor - main.rs")));
+    }
+
+    #[test]
+    fn literal_title_matchers_can_not_be_combined_with_the_regex() {
+        // Act
+        let with_regex = condition_from_yaml("title: '.*'\ntitle_contains: abc");
+        let two_literals = condition_from_yaml("title_equals: abc\ntitle_starts_with: a");
+
+        // Test
+        assert!(matches!(with_regex, Err(Error::ConfigParserError(_))));
+        assert!(matches!(two_literals, Err(Error::ConfigParserError(_))));
+    }
 }
@@ -0,0 +1,131 @@
+//! Compares rendered faces to stored golden images, to catch regressions in the rendering.
+
+use super::button_face::ButtonFace;
+use std::path::Path;
+
+/// Maximal difference of a color channel, for two pixels to be considered equal
+const CHANNEL_TOLERANCE: u8 = 8;
+
+/// Maximal fraction of the pixels, that may differ (for example at the anti-aliased edges of text)
+const MAX_DIFFERING_PIXELS: f32 = 0.01;
+
+/// If this environment variable is set, the rendered faces are written as new golden images
+const UPDATE_GOLDEN_IMAGES: &str = "UPDATE_GOLDEN_IMAGES";
+
+/// Count the pixels, that differ between two images.
+///
+/// # Arguments
+///
+/// a - The first image.
+/// b - The second image.
+/// tolerance - Maximal difference of each color channel, for the pixels to be considered equal.
+///
+/// # Return
+///
+/// The number of differing pixels, None if the images differ in size.
+pub fn count_differing_pixels(
+    a: &image::RgbImage,
+    b: &image::RgbImage,
+    tolerance: u8,
+) -> Option<usize> {
+    if a.dimensions() != b.dimensions() {
+        return None;
+    }
+    Some(
+        a.pixels()
+            .zip(b.pixels())
+            .filter(|(pa, pb)| {
+                pa.0.iter()
+                    .zip(pb.0.iter())
+                    .any(|(ca, cb)| ca.abs_diff(*cb) > tolerance)
+            })
+            .count(),
+    )
+}
+
+/// Assert, that a face looks like the golden image (within a tolerance).
+///
+/// A missing golden image fails the test. Set the `UPDATE_GOLDEN_IMAGES` environment
+/// variable to record new golden images, or to replace them after an intended change
+/// of the rendering.
+///
+/// # Arguments
+///
+/// face - The rendered face.
+/// path - Path of the golden image (a PNG file).
+pub fn assert_face_matches_golden(face: &ButtonFace, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_IMAGES).is_some() {
+        face.face.save(path).unwrap();
+        eprintln!("wrote golden image {}", path.display());
+        return;
+    }
+    assert!(
+        path.exists(),
+        "golden image {} is missing, record it by running the test with {} set",
+        path.display(),
+        UPDATE_GOLDEN_IMAGES
+    );
+    let golden = image::open(path).unwrap().to_rgb8();
+    let differing = count_differing_pixels(&face.face, &golden, CHANNEL_TOLERANCE)
+        .unwrap_or_else(|| {
+            panic!(
+                "face size {:?} differs from golden image {} size {:?}",
+                face.face.dimensions(),
+                path.display(),
+                golden.dimensions()
+            )
+        });
+    let max_differing = (golden.width() * golden.height()) as f32 * MAX_DIFFERING_PIXELS;
+    assert!(
+        differing as f32 <= max_differing,
+        "{} pixels of the face differ from golden image {}",
+        differing,
+        path.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_images_do_not_differ() {
+        // Setup
+        let image = image::RgbImage::from_pixel(4, 4, image::Rgb([10, 20, 30]));
+
+        // Act
+        let differing = count_differing_pixels(&image, &image.clone(), 0);
+
+        // Test
+        assert_eq!(differing, Some(0));
+    }
+
+    #[test]
+    fn differences_within_the_tolerance_are_ignored() {
+        // Setup
+        let a = image::RgbImage::from_pixel(4, 4, image::Rgb([10, 20, 30]));
+        let mut b = a.clone();
+        b.put_pixel(0, 0, image::Rgb([14, 20, 30]));
+        b.put_pixel(1, 0, image::Rgb([10, 40, 30]));
+
+        // Act
+        let differing = count_differing_pixels(&a, &b, 4);
+
+        // Test
+        assert_eq!(differing, Some(1));
+    }
+
+    #[test]
+    fn images_of_different_size_can_not_be_compared() {
+        // Setup
+        let a = image::RgbImage::new(4, 4);
+        let b = image::RgbImage::new(4, 5);
+
+        // Act
+        let differing = count_differing_pixels(&a, &b, 0);
+
+        // Test
+        assert_eq!(differing, None);
+    }
+}
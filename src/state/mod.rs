@@ -1,12 +1,25 @@
 mod app_state;
 pub use app_state::*;
 mod button;
+pub use button::PressState;
 mod button_face;
+pub use button_face::measure_label;
 mod button_position;
 mod error;
 mod event_handler;
+mod face_warmer;
+pub use face_warmer::{load_pending_faces, FaceWarmer};
 pub use event_handler::*;
 mod defaults;
+mod device_type;
+pub use device_type::*;
+mod fonts;
 mod foreground_window_condition;
+#[cfg(test)]
+mod golden;
 mod page;
+mod safe_mode;
+pub use safe_mode::*;
+mod touch_point;
+mod variables;
 use defaults::*;
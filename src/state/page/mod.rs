@@ -4,9 +4,13 @@ use positioned_button_setup::*;
 
 use super::error::Error;
 use crate::config;
+use crate::foreground_window::WindowInformation;
 use crate::state::button::ButtonSetup;
+use crate::state::button_face::FaceCache;
+use crate::state::button_position::{ButtonPosition, ColumnOrder};
 use crate::state::defaults::Defaults;
-use crate::state::foreground_window_condition::ForegroundWindowCondition;
+use crate::state::event_handler::EventHandler;
+use crate::state::foreground_window_condition::PageCondition;
 use std::collections::HashMap;
 use std::sync::Arc;
 use streamdeck_hid_rs::StreamDeckType;
@@ -14,8 +18,21 @@ use streamdeck_hid_rs::StreamDeckType;
 /// A page, that can be loaded!
 pub struct Page {
     pub buttons: Vec<PositionedButtonSetup>,
-    pub on_foreground_window: Vec<ForegroundWindowCondition>,
+    /// The page is loaded if any of these conditions matches the foreground window
+    pub on_foreground_window: Vec<PageCondition>,
     pub unload_if_not_loaded: bool,
+    /// Executed after the page has been loaded
+    pub on_load: Option<Arc<EventHandler>>,
+    /// Executed after the page has been unloaded
+    pub on_unload: Option<Arc<EventHandler>>,
+    /// Executed for every press and release of a button of the page
+    pub on_any_button: Option<Arc<EventHandler>>,
+    /// Description from the config, for the status
+    pub description: Option<String>,
+    /// Tags from the config, for the status
+    pub tags: Vec<String>,
+    /// The page gets its own copies of its named buttons, while it is loaded
+    pub isolate_named_buttons: bool,
 }
 
 impl Page {
@@ -25,6 +42,7 @@ impl Page {
         device_type: &StreamDeckType,
         config: &config::PageConfig,
         defaults: &Defaults,
+        face_cache: &mut FaceCache,
     ) -> Result<(Page, HashMap<String, ButtonSetup>), Error> {
         let mut buttons = Vec::new();
         let mut named_buttons = HashMap::new();
@@ -35,43 +53,83 @@ impl Page {
                 let mut l = Vec::new();
                 unload_if_not_loaded = configs.remove == Some(true);
                 for c in &configs.conditions {
-                    l.push(ForegroundWindowCondition::from_config(c)?);
+                    l.push(PageCondition::from_config(c)?);
                 }
                 l
             }
         };
 
-        for button_config in &config.buttons {
+        let positions = resolve_positions(device_type, &defaults.column_order, &config.buttons)?;
+        for (button_config, positions) in config.buttons.iter().zip(positions) {
+            let mut positions = positions.into_iter();
+            let first_position = positions.next().expect("a button has at least one position");
             let (button, named_button) = PositionedButtonSetup::from_config_with_named_button(
                 &config.name,
                 device_type,
                 button_config,
+                first_position,
                 defaults,
+                face_cache,
             )?;
+            let button_name = button.button_name.clone();
             buttons.push(button);
+            // The other positions of a range share the button of the first position
+            for position in positions {
+                buttons.push(PositionedButtonSetup {
+                    position,
+                    button_name: button_name.clone(),
+                });
+            }
             if let Some((name, named_button)) = named_button {
                 named_buttons.insert(name, named_button);
             }
         }
 
+        let on_load = match &config.on_load {
+            None => None,
+            Some(c) => Some(Arc::new(EventHandler::from_config(c)?)),
+        };
+        let on_unload = match &config.on_unload {
+            None => None,
+            Some(c) => Some(Arc::new(EventHandler::from_config(c)?)),
+        };
+        let on_any_button = match &config.on_any_button {
+            None => None,
+            Some(c) => Some(Arc::new(EventHandler::from_config(c)?)),
+        };
+
         Ok((
             Page {
                 on_foreground_window,
                 buttons,
                 unload_if_not_loaded,
+                on_load,
+                on_unload,
+                on_any_button,
+                description: config.description.clone(),
+                tags: config.tags.clone().unwrap_or_default(),
+                isolate_named_buttons: config.isolate_named_buttons == Some(true),
             },
             named_buttons,
         ))
     }
 
+    /// Whether any of the conditions of the page matches the foreground window.
+    pub fn matches_window(&self, window: &WindowInformation) -> bool {
+        self.on_foreground_window
+            .iter()
+            .any(|condition| condition.matches(window))
+    }
+
     /// Get button at position, if it exists
     pub fn get_button(
         &self,
         device_type: &StreamDeckType,
+        column_order: &ColumnOrder,
         button_index: usize,
     ) -> Option<&PositionedButtonSetup> {
         for button in &self.buttons {
-            if button.position.to_button_index(device_type) == button_index {
+            if button.position.to_button_index(device_type, column_order) == button_index {
                 return Some(button);
             }
         }
@@ -79,6 +137,98 @@ impl Page {
     }
 }
 
+/// The name of the named button of a button config, if it has one.
+fn button_config_name(config: &config::PageButtonConfig) -> Option<&String> {
+    match &config.button {
+        config::ButtonOrButtonName::ButtonName(name) => Some(name),
+        config::ButtonOrButtonName::Button(setup) => setup.name.as_ref(),
+    }
+}
+
+/// Resolve the positions of the buttons of a page.
+///
+/// Positions relative to another button are resolved after the position of that button.
+/// A range is expanded to all of its positions.
+///
+/// # Arguments
+///
+/// device_type - The type of the device, the positions are on.
+/// column_order - The direction in which columns are counted.
+/// buttons - The configs of the buttons of the page.
+///
+/// # Return
+///
+/// The positions of every button (in the order of the buttons), or an error if a position is
+/// invalid, refers to a button not on the page or the relative positions form a cycle.
+fn resolve_positions(
+    device_type: &StreamDeckType,
+    column_order: &ColumnOrder,
+    buttons: &[config::PageButtonConfig],
+) -> Result<Vec<Vec<ButtonPosition>>, Error> {
+    let mut positions: Vec<Option<ButtonPosition>> = buttons.iter().map(|_| None).collect();
+    let mut result = Vec::new();
+    for index in 0..buttons.len() {
+        let position = resolve_position(
+            device_type,
+            column_order,
+            buttons,
+            index,
+            &mut positions,
+            &mut Vec::new(),
+        )?;
+        result.push(match &buttons[index].position {
+            config::ButtonPositionConfig::ButtonPositionRangeConfig(range) => {
+                ButtonPosition::range_from_config(range, device_type)?
+            }
+            _ => vec![position],
+        });
+    }
+    Ok(result)
+}
+
+/// Resolve the position of one button, see [resolve_positions].
+///
+/// # Arguments
+///
+/// resolving - The buttons, whose relative positions are being resolved (to detect cycles).
+fn resolve_position(
+    device_type: &StreamDeckType,
+    column_order: &ColumnOrder,
+    buttons: &[config::PageButtonConfig],
+    index: usize,
+    positions: &mut Vec<Option<ButtonPosition>>,
+    resolving: &mut Vec<usize>,
+) -> Result<ButtonPosition, Error> {
+    if let Some(position) = &positions[index] {
+        return Ok(position.clone());
+    }
+    let position = match &buttons[index].position {
+        config::ButtonPositionConfig::ButtonPositionRelativeConfig(relative) => {
+            if resolving.contains(&index) {
+                return Err(Error::CyclicRelativePosition(relative.relative_to.clone()));
+            }
+            let anchor = buttons
+                .iter()
+                .position(|b| button_config_name(b) == Some(&relative.relative_to))
+                .ok_or_else(|| Error::ButtonNotFound(relative.relative_to.clone()))?;
+            resolving.push(index);
+            let anchor_position = resolve_position(
+                device_type,
+                column_order,
+                buttons,
+                anchor,
+                positions,
+                resolving,
+            )?;
+            resolving.pop();
+            anchor_position.offset(relative.drow, relative.dcol, device_type)?
+        }
+        position => ButtonPosition::from_config(position, device_type, column_order)?,
+    };
+    positions[index] = Some(position.clone());
+    Ok(position)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,6 +252,19 @@ mod tests {
                         down_face: None,
                         up_handler: None,
                         down_handler: None,
+                        load_page: None,
+                        enabled: None,
+                        layer_face: None,
+                        layer_handlers: None,
+                        action: None,
+                        trigger: None,
+                        latch: None,
+                        group: None,
+                        description: None,
+                        tags: None,
+                        up_handlers: None,
+                        down_handlers: None,
+                        invert_press: None,
                     }),
                 },
                 config::PageButtonConfig {
@@ -111,12 +274,23 @@ mod tests {
                     button: config::ButtonOrButtonName::ButtonName(String::from("named_button")),
                 },
             ]),
+            on_load: None,
+            on_unload: None,
+            on_any_button: None,
+            description: None,
+            tags: None,
+            isolate_named_buttons: None,
         };
-        let defaults = Defaults::from_config(&None).unwrap();
+        let defaults = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
 
         // Act
         let (page, named_buttons) =
-            Page::from_config_with_named_buttons(&StreamDeckType::Orig, &config, &defaults)
+            Page::from_config_with_named_buttons(
+                &StreamDeckType::Orig,
+                &config,
+                &defaults,
+                &mut FaceCache::new(),
+            )
                 .unwrap();
 
         // Result
@@ -140,18 +314,220 @@ mod tests {
                     down_face: None,
                     up_handler: None,
                     down_handler: None,
+                    load_page: None,
+                    enabled: None,
+                    layer_face: None,
+                    layer_handlers: None,
+                    action: None,
+                    trigger: None,
+                    latch: None,
+                    group: None,
+                    description: None,
+                    tags: None,
+                    up_handlers: None,
+                    down_handlers: None,
+                    invert_press: None,
                 }),
             }]),
+            on_load: None,
+            on_unload: None,
+            on_any_button: None,
+            description: None,
+            tags: None,
+            isolate_named_buttons: None,
         };
-        let defaults = Defaults::from_config(&None).unwrap();
+        let defaults = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
 
         // Act
         let (page, named_buttons) =
-            Page::from_config_with_named_buttons(&StreamDeckType::Orig, &config, &defaults)
+            Page::from_config_with_named_buttons(
+                &StreamDeckType::Orig,
+                &config,
+                &defaults,
+                &mut FaceCache::new(),
+            )
                 .unwrap();
 
         // Result
         assert_eq!(named_buttons.len(), 1);
         assert_eq!(page.buttons.len(), 1);
     }
+
+    /// A page config with buttons referring to named buttons
+    fn page_config_with_positions(
+        positions: Vec<(config::ButtonPositionConfig, &str)>,
+    ) -> config::PageConfig {
+        config::PageConfig {
+            name: String::from("page1"),
+            on_app: None,
+            buttons: positions
+                .into_iter()
+                .map(|(position, name)| config::PageButtonConfig {
+                    position,
+                    button: config::ButtonOrButtonName::ButtonName(String::from(name)),
+                })
+                .collect(),
+            on_load: None,
+            on_unload: None,
+            on_any_button: None,
+            description: None,
+            tags: None,
+            isolate_named_buttons: None,
+        }
+    }
+
+    /// A position relative to a named button
+    fn relative(relative_to: &str, drow: i32, dcol: i32) -> config::ButtonPositionConfig {
+        config::ButtonPositionConfig::ButtonPositionRelativeConfig(
+            config::ButtonPositionRelative {
+                relative_to: String::from(relative_to),
+                drow,
+                dcol,
+            },
+        )
+    }
+
+    #[test]
+    fn relative_button_is_placed_next_to_its_anchor() {
+        // Setup
+        let config = page_config_with_positions(vec![
+            (relative("anchor", 1, 2), "relative"),
+            (
+                config::ButtonPositionConfig::ButtonPositionObjectConfig(ButtonPositionObject {
+                    row: 0,
+                    col: 1,
+                }),
+                "anchor",
+            ),
+        ]);
+        let defaults = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
+
+        // Act
+        let (page, _) = Page::from_config_with_named_buttons(
+            &StreamDeckType::Orig,
+            &config,
+            &defaults,
+            &mut FaceCache::new(),
+        )
+        .unwrap();
+
+        // Test
+        // Row 1, column 3 (counted from the left)
+        let button = page
+            .get_button(&StreamDeckType::Orig, &ColumnOrder::LeftToRight, 8)
+            .unwrap();
+        assert_eq!(button.button_name, "relative");
+    }
+
+    #[test]
+    fn self_referential_relative_position_is_an_error() {
+        // Setup
+        let config = page_config_with_positions(vec![(relative("itself", 0, 1), "itself")]);
+        let defaults = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
+
+        // Act
+        let result = Page::from_config_with_named_buttons(
+            &StreamDeckType::Orig,
+            &config,
+            &defaults,
+            &mut FaceCache::new(),
+        );
+
+        // Test
+        assert!(matches!(result, Err(Error::CyclicRelativePosition(name)) if name == "itself"));
+    }
+
+    #[test]
+    fn range_is_expanded_to_all_its_positions() {
+        // Setup
+        let config = page_config_with_positions(vec![(
+            config::ButtonPositionConfig::ButtonPositionRangeConfig(config::ButtonPositionRange {
+                rows: config::PositionRangeConfig::Range(String::from("0..1")),
+                cols: config::PositionRangeConfig::Range(String::from("-2..-1")),
+            }),
+            "ranged",
+        )]);
+        let defaults = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
+
+        // Act
+        let (page, _) = Page::from_config_with_named_buttons(
+            &StreamDeckType::Orig,
+            &config,
+            &defaults,
+            &mut FaceCache::new(),
+        )
+        .unwrap();
+
+        // Test
+        let mut indices: Vec<usize> = page
+            .buttons
+            .iter()
+            .map(|button| {
+                button
+                    .position
+                    .to_button_index(&StreamDeckType::Orig, &ColumnOrder::LeftToRight)
+            })
+            .collect();
+        indices.sort();
+        assert_eq!(indices, vec![3, 4, 8, 9]);
+        assert!(page.buttons.iter().all(|button| button.button_name == "ranged"));
+    }
+
+    #[test]
+    fn unnamed_button_in_a_range_is_shared() {
+        // Setup
+        let config: config::PageConfig = serde_yaml::from_str(
+            "\
+name: page1
+buttons:
+- position:
+    rows: 1
+    cols: 0..4
+  button:
+    up_face:
+      color: '#FF0000'
+",
+        )
+        .unwrap();
+        let defaults = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
+
+        // Act
+        let (page, named_buttons) = Page::from_config_with_named_buttons(
+            &StreamDeckType::Orig,
+            &config,
+            &defaults,
+            &mut FaceCache::new(),
+        )
+        .unwrap();
+
+        // Test
+        assert_eq!(page.buttons.len(), 5);
+        assert_eq!(named_buttons.len(), 1);
+        let name = named_buttons.keys().next().unwrap();
+        assert!(page.buttons.iter().all(|button| &button.button_name == name));
+    }
+
+    #[test]
+    fn range_outside_of_the_device_is_an_error() {
+        // Setup
+        let config = page_config_with_positions(vec![(
+            config::ButtonPositionConfig::ButtonPositionRangeConfig(config::ButtonPositionRange {
+                rows: config::PositionRangeConfig::Index(0),
+                cols: config::PositionRangeConfig::Range(String::from("2..5")),
+            }),
+            "ranged",
+        )]);
+        let defaults = Defaults::from_config(&None, &StreamDeckType::Orig).unwrap();
+
+        // Act
+        let result = Page::from_config_with_named_buttons(
+            &StreamDeckType::Orig,
+            &config,
+            &defaults,
+            &mut FaceCache::new(),
+        );
+
+        // Test
+        assert!(matches!(result, Err(Error::ConfigParserError(text)) if text == "2..5"));
+    }
 }
@@ -1,6 +1,7 @@
 use super::super::button_position::ButtonPosition;
 use crate::config;
 use crate::state::button::ButtonSetup;
+use crate::state::button_face::FaceCache;
 use crate::state::defaults::Defaults;
 use crate::state::error::Error;
 use std::sync::Arc;
@@ -23,7 +24,7 @@ impl PositionedButtonSetup {
     ///
     /// # Arguments
     ///
-    /// The config to create the object from.
+    /// The config to create the object from, and the position of the button (resolved by the page).
     ///
     /// # Result
     ///
@@ -34,9 +35,10 @@ impl PositionedButtonSetup {
         page_name: &String,
         device_type: &StreamDeckType,
         config: &config::PageButtonConfig,
+        position: ButtonPosition,
         defaults: &Defaults,
+        face_cache: &mut FaceCache,
     ) -> Result<(PositionedButtonSetup, Option<(String, ButtonSetup)>), Error> {
-        let position = ButtonPosition::from_config(&config.position)?;
         // Create a button or just a name
         match &config.button {
             ButtonOrButtonName::ButtonName(button_name) => {
@@ -44,12 +46,12 @@ impl PositionedButtonSetup {
             },
             ButtonOrButtonName::Button(setup) => {
                 // Set the name
-                let button_name = setup.name.clone().unwrap_or_else(|| format!("page_{}_button_{}", page_name, position.to_button_index(device_type)));
+                let button_name = setup.name.clone().unwrap_or_else(|| format!("page_{}_button_{}", page_name, position.to_button_index(device_type, &defaults.column_order)));
                 Ok(
                     (
                         PositionedButtonSetup { position, button_name: button_name.clone() },
                         Some(
-                            (button_name, ButtonSetup::from_optional_name_config(device_type, setup, defaults)?)
+                            (button_name, ButtonSetup::from_optional_name_config(device_type, setup, defaults, face_cache)?)
                         )
                     )
                 )
@@ -68,6 +70,7 @@ impl PositionedButtonSetup {
 mod tests {
     use super::*;
     use crate::config::ButtonPositionObject;
+    use crate::state::button_position::ColumnOrder;
 
     #[test]
     fn test_from_config_with_named_button() {
@@ -84,7 +87,14 @@ mod tests {
             &"test_page".to_string(),
             &StreamDeckType::Orig,
             &config,
-            &Defaults::from_config(&None).unwrap(),
+            ButtonPosition::from_config(
+                &config.position,
+                &StreamDeckType::Orig,
+                &ColumnOrder::RightToLeft,
+            )
+            .unwrap(),
+            &Defaults::from_config(&None, &StreamDeckType::Orig).unwrap(),
+            &mut FaceCache::new(),
         )
         .unwrap();
 
@@ -0,0 +1,110 @@
+use super::app_state::AppState;
+use crate::config;
+use log::error;
+use streamdeck_hid_rs::StreamDeckType;
+
+/// The built-in config of the safe mode, a single page showing an error indicator on button 0.
+const SAFE_MODE_CONFIG: &str = "\
+positions_left_to_right: true
+pages:
+- name: safe_mode
+  buttons:
+  - position:
+      row: 0
+      col: 0
+    button:
+      name: safe_mode_error
+      up_face:
+        color: '#FF0000'
+        label: Config error
+        sublabel: see log
+default_pages:
+- safe_mode
+";
+
+/// The built-in config of the safe mode.
+///
+/// It shows a red error indicator on button 0 and nothing else.
+pub fn safe_mode_config() -> config::Config {
+    serde_yaml::from_str(SAFE_MODE_CONFIG).expect("the safe mode config is valid")
+}
+
+/// Create the app state from the config, or the safe mode state if that fails.
+///
+/// # Arguments
+///
+/// device_type - The type of the connected device.
+/// config - The loaded config, or the error that occurred while loading it.
+///
+/// # Return
+///
+/// The config and the app state created from it. If the config could not be loaded or is invalid,
+/// the error is logged, and the safe mode config and state are returned instead.
+pub fn app_state_or_safe_mode(
+    device_type: &StreamDeckType,
+    config: Result<config::Config, String>,
+) -> (config::Config, AppState) {
+    let result = config.and_then(|config| match AppState::from_config(device_type, &config) {
+        Ok(app_state) => Ok((config, app_state)),
+        Err(e) => Err(e.to_string()),
+    });
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            error!("unable to load the config, starting in safe mode: {}", e);
+            let config = safe_mode_config();
+            let app_state = AppState::from_config(device_type, &config)
+                .expect("the safe mode config is valid");
+            (config, app_state)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_config_starts_the_safe_mode() {
+        // Setup
+        let config: config::Config =
+            serde_yaml::from_str("pages: []\ndefault_pages:\n- does_not_exist\n").unwrap();
+
+        // Act
+        let (config, app_state) = app_state_or_safe_mode(&StreamDeckType::Orig, Ok(config));
+
+        // Test
+        assert_eq!(config, safe_mode_config());
+        assert_eq!(app_state.get_page_stack(), vec![String::from("safe_mode")]);
+        assert_eq!(
+            app_state.visible_buttons()[0],
+            (0, String::from("safe_mode_error"))
+        );
+    }
+
+    #[test]
+    fn unreadable_config_starts_the_safe_mode() {
+        // Act
+        let (_, app_state) =
+            app_state_or_safe_mode(&StreamDeckType::Xl, Err(String::from("invalid yaml")));
+
+        // Test
+        assert_eq!(
+            app_state.visible_buttons()[0],
+            (0, String::from("safe_mode_error"))
+        );
+    }
+
+    #[test]
+    fn valid_config_is_used() {
+        // Setup
+        let config: config::Config = serde_yaml::from_str("pages: []").unwrap();
+
+        // Act
+        let (config, app_state) = app_state_or_safe_mode(&StreamDeckType::Orig, Ok(config));
+
+        // Test
+        assert_eq!(config.pages, vec![]);
+        assert!(app_state.get_page_stack().is_empty());
+    }
+}
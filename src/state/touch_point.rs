@@ -0,0 +1,62 @@
+use super::error::Error;
+use super::event_handler::EventHandler;
+use crate::config;
+
+/// Number of touch points of the Stream Deck Neo, the device with the most touch points.
+pub const MAX_TOUCH_POINTS: usize = 2;
+
+/// Handlers of a touch point next to the buttons.
+pub struct TouchPoint {
+    pub down_handler: Option<EventHandler>,
+    pub up_handler: Option<EventHandler>,
+}
+
+impl TouchPoint {
+    /// Create the touch point from the config.
+    ///
+    /// # Arguments
+    ///
+    /// config - The config of the touch point.
+    ///
+    /// # Return
+    ///
+    /// The touch point, or an error if a handler could not be created.
+    pub fn from_config(config: &config::TouchPointConfig) -> Result<TouchPoint, Error> {
+        Ok(TouchPoint {
+            down_handler: match &config.down_handler {
+                None => None,
+                Some(e) => Some(EventHandler::from_config(e)?),
+            },
+            up_handler: match &config.up_handler {
+                None => None,
+                Some(e) => Some(EventHandler::from_config(e)?),
+            },
+        })
+    }
+
+    /// Create the touch points from the config, the index in the list is the index of the touch point.
+    ///
+    /// # Arguments
+    ///
+    /// configs - The configs of the touch points, if any.
+    ///
+    /// # Return
+    ///
+    /// The touch points, or an error if there are more than [MAX_TOUCH_POINTS] or a handler could not be created.
+    pub fn all_from_config(
+        configs: &Option<Vec<config::TouchPointConfig>>,
+    ) -> Result<Vec<TouchPoint>, Error> {
+        let configs = match configs {
+            None => return Ok(Vec::new()),
+            Some(configs) => configs,
+        };
+        if configs.len() > MAX_TOUCH_POINTS {
+            return Err(Error::ConfigParserError(format!(
+                "{} touch points configured, devices have at most {}",
+                configs.len(),
+                MAX_TOUCH_POINTS
+            )));
+        }
+        configs.iter().map(TouchPoint::from_config).collect()
+    }
+}
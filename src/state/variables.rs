@@ -0,0 +1,46 @@
+use serde_json::Value;
+
+/// Whether a variable set by the scripts counts as true, like in python.
+///
+/// None (null), false, 0, empty strings, empty lists and empty objects are false, everything else is true.
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn values_are_truthy_like_in_python() {
+        // Setup
+        let values = vec![
+            (json!(null), false),
+            (json!(false), false),
+            (json!(true), true),
+            (json!(0), false),
+            (json!(0.0), false),
+            (json!(-1), true),
+            (json!(""), false),
+            (json!("no"), true),
+            (json!([]), false),
+            (json!([0]), true),
+        ];
+
+        for (value, expected) in values {
+            // Act
+            let truthy = is_truthy(&value);
+
+            // Test
+            assert_eq!(truthy, expected, "{}", value);
+        }
+    }
+}